@@ -16,5 +16,17 @@ pub mod mini_lsm_wrapper {
     pub use mini_lsm_mvcc::*;
 }
 
+#[allow(dead_code)]
+pub fn init_logger() {
+    // print the engine's log messages at `info` level and above to stdout, unless `RUST_LOG`
+    // asks for another level
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format_target(false)
+        .format_timestamp(None)
+        .format_level(false)
+        .target(env_logger::Target::Stdout)
+        .try_init();
+}
+
 #[allow(dead_code)]
 fn main() {}
@@ -327,7 +327,7 @@ impl LsmStorageInner {
             l1_sstables: l1_sstables.clone(),
         };
 
-        println!("force full compaction: {:?}", compaction_task);
+        log::info!("force full compaction: {:?}", compaction_task);
 
         let sstables = self.compact(&compaction_task)?;
         let mut ids = Vec::with_capacity(sstables.len());
@@ -365,7 +365,7 @@ impl LsmStorageInner {
             std::fs::remove_file(self.path_of_sst(*sst))?;
         }
 
-        println!("force full compaction done, new SSTs: {:?}", ids);
+        log::info!("force full compaction done, new SSTs: {:?}", ids);
 
         Ok(())
     }
@@ -382,7 +382,7 @@ impl LsmStorageInner {
             return Ok(());
         };
         self.dump_structure();
-        println!("running compaction task: {:?}", task);
+        log::debug!("running compaction task: {:?}", task);
         let sstables = self.compact(&task)?;
         let output = sstables.iter().map(|x| x.sst_id()).collect::<Vec<_>>();
         let ssts_to_remove = {
@@ -412,7 +412,7 @@ impl LsmStorageInner {
                 .add_record(&state_lock, ManifestRecord::Compaction(task, new_sst_ids))?;
             ssts_to_remove
         };
-        println!(
+        log::info!(
             "compaction finished: {} files removed, {} files added, output={:?}",
             ssts_to_remove.len(),
             output.len(),
@@ -440,7 +440,7 @@ impl LsmStorageInner {
                 loop {
                     crossbeam_channel::select! {
                         recv(ticker) -> _ => if let Err(e) = this.trigger_compaction() {
-                            eprintln!("compaction failed: {}", e);
+                            log::error!("compaction failed: {}", e);
                         },
                         recv(rx) -> _ => return
                     }
@@ -473,7 +473,7 @@ impl LsmStorageInner {
             loop {
                 crossbeam_channel::select! {
                     recv(ticker) -> _ => if let Err(e) = this.trigger_flush() {
-                        eprintln!("flush failed: {}", e);
+                        log::error!("flush failed: {}", e);
                     },
                     recv(rx) -> _ => return
                 }
@@ -110,7 +110,7 @@ impl LeveledCompactionController {
 
         // Flush L0 SST is the top priority
         if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger {
-            println!("flush L0 SST to base level {}", base_level);
+            log::info!("flush L0 SST to base level {}", base_level);
             return Some(LeveledCompactionTask {
                 upper_level: None,
                 upper_level_sst_ids: snapshot.l0_sstables.clone(),
@@ -135,7 +135,7 @@ impl LeveledCompactionController {
 
         let priority = priorities.first();
         if let Some((_, level)) = priority {
-            println!(
+            log::info!(
                 "target level sizes: {:?}, real level sizes: {:?}, base_level: {}",
                 target_level_size
                     .iter()
@@ -150,7 +150,7 @@ impl LeveledCompactionController {
 
             let level = *level;
             let selected_sst = snapshot.levels[level - 1].1.iter().min().copied().unwrap(); // select the oldest sst to compact
-            println!(
+            log::info!(
                 "compaction triggered by priority: {level} out of {:?}, select {selected_sst} for compaction",
                 priorities
             );
@@ -59,7 +59,7 @@ impl SimpleLeveledCompactionController {
 
         // check level0_file_num_compaction_trigger for compaction of L0 to L1
         if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger {
-            println!(
+            log::info!(
                 "compaction triggered at level 0 because L0 has {} SSTs >= {}",
                 snapshot.l0_sstables.len(),
                 self.options.level0_file_num_compaction_trigger
@@ -83,9 +83,11 @@ impl SimpleLeveledCompactionController {
             let lower_level = i + 1;
             let size_ratio = level_sizes[lower_level] as f64 / level_sizes[i] as f64;
             if size_ratio < self.options.size_ratio_percent as f64 / 100.0 {
-                println!(
+                log::info!(
                     "compaction triggered at level {} and {} with size ratio {}",
-                    i, lower_level, size_ratio
+                    i,
+                    lower_level,
+                    size_ratio
                 );
                 return Some(SimpleLeveledCompactionTask {
                     upper_level: if i == 0 { None } else { Some(i) },
@@ -61,7 +61,7 @@ impl TieredCompactionController {
         let space_amp_ratio =
             (size as f64) / (snapshot.levels.last().unwrap().1.len() as f64) * 100.0;
         if space_amp_ratio >= self.options.max_size_amplification_percent as f64 {
-            println!(
+            log::info!(
                 "compaction triggered by space amplification ratio: {}",
                 space_amp_ratio
             );
@@ -78,7 +78,7 @@ impl TieredCompactionController {
             let next_level_size = snapshot.levels[id + 1].1.len();
             let current_size_ratio = next_level_size as f64 / size as f64;
             if current_size_ratio > size_ratio_trigger && id + 1 >= self.options.min_merge_width {
-                println!(
+                log::info!(
                     "compaction triggered by size ratio: {} > {}",
                     current_size_ratio * 100.0,
                     size_ratio_trigger * 100.0
@@ -100,7 +100,7 @@ impl TieredCompactionController {
             .levels
             .len()
             .min(self.options.max_merge_width.unwrap_or(usize::MAX));
-        println!("compaction triggered by reducing sorted runs");
+        log::info!("compaction triggered by reducing sorted runs");
         Some(TieredCompactionTask {
             tiers: snapshot
                 .levels
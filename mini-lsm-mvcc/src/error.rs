@@ -0,0 +1 @@
+../../mini-lsm/src/error.rs
\ No newline at end of file
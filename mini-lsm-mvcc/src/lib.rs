@@ -15,6 +15,7 @@
 pub mod block;
 pub mod compact;
 pub mod debug;
+pub mod error;
 pub mod iterators;
 pub mod key;
 pub mod lsm_iterator;
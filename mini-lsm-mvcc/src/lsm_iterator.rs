@@ -21,11 +21,12 @@ use crate::iterators::StorageIterator;
 use crate::iterators::concat_iterator::SstConcatIterator;
 use crate::iterators::merge_iterator::MergeIterator;
 use crate::iterators::two_merge_iterator::TwoMergeIterator;
+use crate::key::KeySlice;
 use crate::mem_table::MemTableIterator;
 use crate::table::SsTableIterator;
 
 /// Represents the internal type for an LSM iterator. This type will be changed across the course for multiple times.
-type LsmIteratorInner = TwoMergeIterator<
+pub(crate) type LsmIteratorInner = TwoMergeIterator<
     TwoMergeIterator<MergeIterator<MemTableIterator>, MergeIterator<SsTableIterator>>,
     MergeIterator<SstConcatIterator>,
 >;
@@ -125,6 +126,74 @@ impl StorageIterator for LsmIterator {
     }
 }
 
+/// An iterator over every version of the keys in a range, in ascending key order and descending
+/// timestamp order within a key. Unlike [`LsmIterator`], versions are not collapsed, and deletes
+/// are yielded as empty values. Versions newer than `read_ts` are skipped.
+pub struct AllVersionsIterator {
+    inner: LsmIteratorInner,
+    end_bound: Bound<Bytes>,
+    is_valid: bool,
+    read_ts: u64,
+}
+
+impl AllVersionsIterator {
+    pub(crate) fn new(
+        iter: LsmIteratorInner,
+        end_bound: Bound<Bytes>,
+        read_ts: u64,
+    ) -> Result<Self> {
+        let mut iter = Self {
+            inner: iter,
+            end_bound,
+            is_valid: false,
+            read_ts,
+        };
+        iter.move_to_visible()?;
+        Ok(iter)
+    }
+
+    /// Skip the versions newer than `read_ts`, stopping at the end bound.
+    fn move_to_visible(&mut self) -> Result<()> {
+        loop {
+            self.is_valid = self.inner.is_valid()
+                && match self.end_bound.as_ref() {
+                    Bound::Unbounded => true,
+                    Bound::Included(key) => self.inner.key().key_ref() <= key.as_ref(),
+                    Bound::Excluded(key) => self.inner.key().key_ref() < key.as_ref(),
+                };
+            if !self.is_valid || self.inner.key().ts() <= self.read_ts {
+                return Ok(());
+            }
+            self.inner.next()?;
+        }
+    }
+}
+
+impl StorageIterator for AllVersionsIterator {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        self.inner.key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.inner.value()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.inner.next()?;
+        self.move_to_visible()
+    }
+
+    fn num_active_iterators(&self) -> usize {
+        self.inner.num_active_iterators()
+    }
+}
+
 /// A wrapper around existing iterator, will prevent users from calling `next` when the iterator is
 /// invalid. If an iterator is already invalid, `next` does not do anything. If `next` returns an error,
 /// `is_valid` should return false, and `next` should always return an error.
@@ -171,11 +240,11 @@ impl<I: StorageIterator> StorageIterator for FusedIterator<I> {
         if self.has_errored {
             bail!("the iterator is tainted");
         }
-        if self.iter.is_valid() {
-            if let Err(e) = self.iter.next() {
-                self.has_errored = true;
-                return Err(e);
-            }
+        if self.iter.is_valid()
+            && let Err(e) = self.iter.next()
+        {
+            self.has_errored = true;
+            return Err(e);
         }
         Ok(())
     }
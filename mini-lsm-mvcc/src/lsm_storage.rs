@@ -33,7 +33,7 @@ use crate::iterators::concat_iterator::SstConcatIterator;
 use crate::iterators::merge_iterator::MergeIterator;
 use crate::iterators::two_merge_iterator::TwoMergeIterator;
 use crate::key::{self, KeySlice};
-use crate::lsm_iterator::{FusedIterator, LsmIterator};
+use crate::lsm_iterator::{AllVersionsIterator, FusedIterator, LsmIterator, LsmIteratorInner};
 use crate::manifest::{Manifest, ManifestRecord};
 use crate::mem_table::{MemTable, map_bound, map_key_bound_plus_ts};
 use crate::mvcc::LsmMvccInner;
@@ -271,6 +271,10 @@ impl MiniLsm {
         self.inner.get(key)
     }
 
+    pub fn get_all_versions(&self, key: &[u8]) -> Result<Vec<(u64, Option<Bytes>)>> {
+        self.inner.get_all_versions(key)
+    }
+
     pub fn write_batch<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<()> {
         self.inner.write_batch(batch)
     }
@@ -295,6 +299,14 @@ impl MiniLsm {
         self.inner.scan(lower, upper)
     }
 
+    pub fn scan_all_versions(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<AllVersionsIterator>> {
+        self.inner.scan_all_versions(lower, upper)
+    }
+
     /// Only call this in test cases due to race conditions
     pub fn force_flush(&self) -> Result<()> {
         if !self.inner.state.read().memtable.is_empty() {
@@ -410,7 +422,7 @@ impl LsmStorageInner {
                 state.sstables.insert(table_id, Arc::new(sst));
                 sst_cnt += 1;
             }
-            println!("{} SSTs opened", sst_cnt);
+            log::info!("{} SSTs opened", sst_cnt);
 
             next_sst_id += 1;
 
@@ -446,7 +458,7 @@ impl LsmStorageInner {
                         wal_cnt += 1;
                     }
                 }
-                println!("{} WALs recovered", wal_cnt);
+                log::info!("{} WALs recovered", wal_cnt);
                 state.memtable = Arc::new(MemTable::create_with_wal(
                     next_sst_id,
                     Self::path_of_wal_static(path, next_sst_id),
@@ -491,7 +503,8 @@ impl LsmStorageInner {
         txn.get(key)
     }
 
-    pub(crate) fn get_with_ts(&self, key: &[u8], read_ts: u64) -> Result<Option<Bytes>> {
+    /// Create an iterator over all sources positioned at the latest version of `key`.
+    fn create_point_iter(&self, key: &[u8]) -> Result<LsmIteratorInner> {
         let snapshot = {
             let guard = self.state.read();
             Arc::clone(&guard)
@@ -555,14 +568,14 @@ impl LsmStorageInner {
             level_iters.push(Box::new(level_iter));
         }
 
-        let iter = LsmIterator::new(
-            TwoMergeIterator::create(
-                TwoMergeIterator::create(memtable_iter, l0_iter)?,
-                MergeIterator::create(level_iters),
-            )?,
-            Bound::Unbounded,
-            read_ts,
-        )?;
+        TwoMergeIterator::create(
+            TwoMergeIterator::create(memtable_iter, l0_iter)?,
+            MergeIterator::create(level_iters),
+        )
+    }
+
+    pub(crate) fn get_with_ts(&self, key: &[u8], read_ts: u64) -> Result<Option<Bytes>> {
+        let iter = LsmIterator::new(self.create_point_iter(key)?, Bound::Unbounded, read_ts)?;
 
         if iter.is_valid() && iter.key() == key && !iter.value().is_empty() {
             return Ok(Some(Bytes::copy_from_slice(iter.value())));
@@ -570,6 +583,23 @@ impl LsmStorageInner {
         Ok(None)
     }
 
+    /// Get every version of `key` that has not been garbage collected yet, as `(ts, value)` pairs
+    /// in ascending order of timestamps, where `None` stands for a delete.
+    pub fn get_all_versions(&self, key: &[u8]) -> Result<Vec<(u64, Option<Bytes>)>> {
+        let mut iter = self.create_point_iter(key)?;
+        let mut versions = Vec::new();
+        while iter.is_valid() && iter.key().key_ref() == key {
+            let value = iter.value();
+            versions.push((
+                iter.key().ts(),
+                (!value.is_empty()).then(|| Bytes::copy_from_slice(value)),
+            ));
+            iter.next()?;
+        }
+        versions.reverse();
+        Ok(versions)
+    }
+
     pub fn write_batch_inner<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<u64> {
         let _lck = self.mvcc().write_lock.lock();
         let ts = self.mvcc().latest_commit_ts() + 1;
@@ -761,7 +791,7 @@ impl LsmStorageInner {
                 // In tiered compaction, create a new tier
                 snapshot.levels.insert(0, (sst_id, vec![sst_id]));
             }
-            println!("flushed {}.sst with size={}", sst_id, sst.table_size());
+            log::info!("flushed {}.sst with size={}", sst_id, sst.table_size());
             snapshot.sstables.insert(sst_id, sst);
             // Update the snapshot.
             *guard = Arc::new(snapshot);
@@ -784,11 +814,7 @@ impl LsmStorageInner {
     }
 
     /// Create an iterator over a range of keys.
-    pub fn scan<'a>(
-        self: &'a Arc<Self>,
-        lower: Bound<&[u8]>,
-        upper: Bound<&[u8]>,
-    ) -> Result<TxnIterator> {
+    pub fn scan(self: &Arc<Self>, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<TxnIterator> {
         let txn = self.mvcc().new_txn(self.clone(), self.options.serializable);
         txn.scan(lower, upper)
     }
@@ -799,6 +825,39 @@ impl LsmStorageInner {
         upper: Bound<&[u8]>,
         read_ts: u64,
     ) -> Result<FusedIterator<LsmIterator>> {
+        let iter = self.create_range_iter(lower, upper, read_ts)?;
+        Ok(FusedIterator::new(LsmIterator::new(
+            iter,
+            map_bound(upper),
+            read_ts,
+        )?))
+    }
+
+    /// Create an iterator over every version of the keys in a range that is committed by now,
+    /// including deletes, e.g. to feed a replication stream. Versions already garbage collected by
+    /// compaction are not included.
+    pub fn scan_all_versions(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<AllVersionsIterator>> {
+        let read_ts = self.mvcc().latest_commit_ts();
+        let iter = self.create_range_iter(lower, upper, read_ts)?;
+        Ok(FusedIterator::new(AllVersionsIterator::new(
+            iter,
+            map_bound(upper),
+            read_ts,
+        )?))
+    }
+
+    /// Merge the memtables and SSTs positioned at the first key within `lower`, without skipping
+    /// any version.
+    fn create_range_iter(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        read_ts: u64,
+    ) -> Result<LsmIteratorInner> {
         let snapshot = {
             let guard = self.state.read();
             Arc::clone(&guard)
@@ -882,12 +941,6 @@ impl LsmStorageInner {
         }
 
         let iter = TwoMergeIterator::create(memtable_iter, l0_iter)?;
-        let iter = TwoMergeIterator::create(iter, MergeIterator::create(level_iters))?;
-
-        Ok(FusedIterator::new(LsmIterator::new(
-            iter,
-            map_bound(upper),
-            read_ts,
-        )?))
+        TwoMergeIterator::create(iter, MergeIterator::create(level_iters))
     }
 }
@@ -28,6 +28,7 @@ use ouroboros::self_referencing;
 use parking_lot::Mutex;
 
 use crate::{
+    error::LsmError,
     iterators::{StorageIterator, two_merge_iterator::TwoMergeIterator},
     lsm_iterator::{FusedIterator, LsmIterator},
     lsm_storage::{LsmStorageInner, WriteBatchRecord},
@@ -121,16 +122,17 @@ impl Transaction {
         if let Some(guard) = &self.key_hashes {
             let guard = guard.lock();
             let (write_set, read_set) = &*guard;
-            println!(
+            log::debug!(
                 "commit txn: write_set: {:?}, read_set: {:?}",
-                write_set, read_set
+                write_set,
+                read_set
             );
             if !write_set.is_empty() {
                 let committed_txns = self.inner.mvcc().committed_txns.lock();
                 for (_, txn_data) in committed_txns.range((self.read_ts + 1)..) {
                     for key_hash in read_set {
                         if txn_data.key_hashes.contains(key_hash) {
-                            bail!("serializable check failed");
+                            bail!(LsmError::TxnConflict);
                         }
                     }
                 }
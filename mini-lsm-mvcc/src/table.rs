@@ -163,7 +163,9 @@ impl SsTable {
         Self::open(0, None, file)
     }
 
-    /// Open SSTable from a file.
+    /// Open SSTable from a file. Every key in the file carries a timestamp; SSTs written by the
+    /// engine without MVCC store bare keys and cannot be opened, so a directory created by it has
+    /// to be exported and re-imported instead of being reused.
     pub fn open(id: usize, block_cache: Option<Arc<BlockCache>>, file: FileObject) -> Result<Self> {
         let len = file.size();
         let raw_bloom_offset = file.read(len - 4, 4)?;
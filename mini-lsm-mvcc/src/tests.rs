@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod all_versions;
+mod gc_versions;
 mod harness;
+mod read_ts;
+mod scan_all_versions;
+mod sst_max_ts;
+mod txn_conflict;
 mod week1_day1;
 mod week1_day2;
 mod week1_day3;
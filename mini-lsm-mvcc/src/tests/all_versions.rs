@@ -0,0 +1,55 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_get_all_versions() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    let mut expected = Vec::new();
+    storage.put(b"a", b"1").unwrap();
+    expected.push((
+        storage.inner.mvcc().latest_commit_ts(),
+        Some(Bytes::from_static(b"1")),
+    ));
+    storage.put(b"a", b"2").unwrap();
+    expected.push((
+        storage.inner.mvcc().latest_commit_ts(),
+        Some(Bytes::from_static(b"2")),
+    ));
+    storage.put(b"b", b"1").unwrap();
+    storage.force_flush().unwrap();
+    storage.put(b"a", b"3").unwrap();
+    expected.push((
+        storage.inner.mvcc().latest_commit_ts(),
+        Some(Bytes::from_static(b"3")),
+    ));
+    storage.delete(b"a").unwrap();
+    expected.push((storage.inner.mvcc().latest_commit_ts(), None));
+
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert_eq!(storage.get_all_versions(b"a").unwrap(), expected);
+    assert_eq!(storage.get_all_versions(b"c").unwrap(), vec![]);
+}
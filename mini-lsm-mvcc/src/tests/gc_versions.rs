@@ -0,0 +1,79 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::construct_merge_iterator_over_storage;
+use crate::{
+    compact::CompactionOptions,
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+/// All versions stored in the SSTs, as (key, ts, value).
+fn sst_versions(storage: &MiniLsm) -> Vec<(Bytes, u64, Bytes)> {
+    let mut iter = construct_merge_iterator_over_storage(&storage.inner.state.read());
+    let mut versions = Vec::new();
+    while iter.is_valid() {
+        versions.push((
+            Bytes::copy_from_slice(iter.key().key_ref()),
+            iter.key().ts(),
+            Bytes::copy_from_slice(iter.value()),
+        ));
+        iter.next().unwrap();
+    }
+    versions
+}
+
+#[test]
+fn test_gc_versions_below_watermark() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    let mut txn = None;
+    for round in 0..5 {
+        for key in 0..10 {
+            storage
+                .put(
+                    format!("key_{key}").as_bytes(),
+                    format!("{round}").as_bytes(),
+                )
+                .unwrap();
+        }
+        if round == 2 {
+            txn = Some(storage.new_txn().unwrap());
+        }
+        storage.force_flush().unwrap();
+    }
+    let txn = txn.unwrap();
+    assert_eq!(storage.inner.mvcc().watermark(), txn.read_ts);
+
+    // The versions read by the open transaction and the newer ones are kept.
+    storage.force_full_compaction().unwrap();
+    let versions = sst_versions(&storage);
+    assert_eq!(versions.len(), 10 * 3);
+    assert!(versions.iter().all(|(_, ts, _)| *ts >= txn.read_ts - 9));
+    assert_eq!(txn.get(b"key_0").unwrap(), Some(Bytes::from("2")));
+
+    drop(txn);
+    assert_eq!(
+        storage.inner.mvcc().watermark(),
+        storage.inner.mvcc().latest_commit_ts()
+    );
+    storage.force_full_compaction().unwrap();
+    let versions = sst_versions(&storage);
+    assert_eq!(versions.len(), 10);
+    assert!(versions.iter().all(|(_, _, value)| value == "4"));
+}
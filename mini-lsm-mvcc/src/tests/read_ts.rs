@@ -0,0 +1,59 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_versions_by_read_ts() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    let ts1 = storage.inner.mvcc().latest_commit_ts();
+    let txn = storage.new_txn().unwrap();
+    storage.put(b"a", b"2").unwrap();
+    let ts2 = storage.inner.mvcc().latest_commit_ts();
+    assert!(ts2 > ts1);
+
+    let check = |storage: &MiniLsm| {
+        assert_eq!(storage.inner.get_with_ts(b"a", ts1 - 1).unwrap(), None);
+        assert_eq!(
+            storage.inner.get_with_ts(b"a", ts1).unwrap(),
+            Some(Bytes::from_static(b"1"))
+        );
+        assert_eq!(
+            storage.inner.get_with_ts(b"a", ts2).unwrap(),
+            Some(Bytes::from_static(b"2"))
+        );
+    };
+    check(&storage);
+    assert_eq!(txn.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"2")));
+
+    // Both versions survive a flush and a restart, and the clock continues after them.
+    storage.force_flush().unwrap();
+    check(&storage);
+    drop(txn);
+    storage.close().unwrap();
+    drop(storage);
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    check(&storage);
+    assert_eq!(storage.inner.mvcc().latest_commit_ts(), ts2);
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+fn collect_versions(
+    storage: &MiniLsm,
+    lower: Bound<&[u8]>,
+    upper: Bound<&[u8]>,
+) -> Vec<(Vec<u8>, u64, Vec<u8>)> {
+    let mut iter = storage.scan_all_versions(lower, upper).unwrap();
+    let mut versions = Vec::new();
+    while iter.is_valid() {
+        versions.push((
+            iter.key().key_ref().to_vec(),
+            iter.key().ts(),
+            iter.value().to_vec(),
+        ));
+        iter.next().unwrap();
+    }
+    versions
+}
+
+#[test]
+fn test_scan_all_versions() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    let ts1 = storage.inner.mvcc().latest_commit_ts();
+    storage.put(b"b", b"1").unwrap();
+    let ts2 = storage.inner.mvcc().latest_commit_ts();
+    storage.force_flush().unwrap();
+    storage.put(b"a", b"2").unwrap();
+    let ts3 = storage.inner.mvcc().latest_commit_ts();
+    storage.delete(b"b").unwrap();
+    let ts4 = storage.inner.mvcc().latest_commit_ts();
+    storage.put(b"c", b"1").unwrap();
+    let ts5 = storage.inner.mvcc().latest_commit_ts();
+    assert_ne!(ts1, ts3);
+
+    let all = vec![
+        (b"a".to_vec(), ts3, b"2".to_vec()),
+        (b"a".to_vec(), ts1, b"1".to_vec()),
+        (b"b".to_vec(), ts4, b"".to_vec()),
+        (b"b".to_vec(), ts2, b"1".to_vec()),
+        (b"c".to_vec(), ts5, b"1".to_vec()),
+    ];
+    assert_eq!(
+        collect_versions(&storage, Bound::Unbounded, Bound::Unbounded),
+        all
+    );
+    assert_eq!(
+        collect_versions(&storage, Bound::Excluded(b"a"), Bound::Included(b"b")),
+        all[2..4]
+    );
+    assert_eq!(
+        collect_versions(&storage, Bound::Included(b"a"), Bound::Excluded(b"b")),
+        all[..2]
+    );
+
+    // A scan only sees the versions committed when it was created.
+    let mut iter = storage
+        .scan_all_versions(Bound::Included(b"d"), Bound::Unbounded)
+        .unwrap();
+    storage.put(b"d", b"1").unwrap();
+    assert!(!iter.is_valid());
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    key::KeySlice,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+    table::{FileObject, SsTable, SsTableBuilder},
+};
+
+#[test]
+fn test_sst_max_ts_persisted() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let mut builder = SsTableBuilder::new(16);
+    builder.add(KeySlice::for_testing_from_slice_with_ts(b"11", 3), b"11");
+    builder.add(KeySlice::for_testing_from_slice_with_ts(b"22", 7), b"22");
+    builder.add(KeySlice::for_testing_from_slice_with_ts(b"33", 5), b"33");
+    assert_eq!(builder.build_for_test(&path).unwrap().max_ts(), 7);
+    let sst = SsTable::open(1, None, FileObject::open(&path).unwrap()).unwrap();
+    assert_eq!(sst.max_ts(), 7);
+}
+
+#[test]
+fn test_recovery_advances_commit_ts() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for i in 0..10 {
+        storage
+            .put(format!("key_{i}").as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    let commit_ts = storage.inner.mvcc().latest_commit_ts();
+    storage.close().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert_eq!(storage.inner.mvcc().latest_commit_ts(), commit_ts);
+    storage.put(b"key_0", b"new_value").unwrap();
+    assert!(storage.inner.mvcc().latest_commit_ts() > commit_ts);
+    assert_eq!(&storage.get(b"key_0").unwrap().unwrap()[..], b"new_value");
+}
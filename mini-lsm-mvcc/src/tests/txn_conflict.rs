@@ -0,0 +1,78 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    error::LsmError,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+    mvcc::txn::Transaction,
+};
+
+fn open_serializable(dir: &tempfile::TempDir) -> std::sync::Arc<MiniLsm> {
+    let mut options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    options.serializable = true;
+    MiniLsm::open(dir, options).unwrap()
+}
+
+fn increment(txn: &Transaction, key: &[u8]) {
+    let value = txn.get(key).unwrap().unwrap();
+    let value = std::str::from_utf8(&value).unwrap().parse::<u64>().unwrap() + 1;
+    txn.put(key, value.to_string().as_bytes());
+}
+
+#[test]
+fn test_read_modify_write_conflict() {
+    let dir = tempdir().unwrap();
+    let storage = open_serializable(&dir);
+    storage.put(b"counter", b"0").unwrap();
+
+    let txn1 = storage.new_txn().unwrap();
+    let txn2 = storage.new_txn().unwrap();
+    increment(&txn1, b"counter");
+    increment(&txn2, b"counter");
+    // The buffered write is visible to the transaction only.
+    assert_eq!(txn1.get(b"counter").unwrap(), Some(Bytes::from("1")));
+    assert_eq!(storage.get(b"counter").unwrap(), Some(Bytes::from("0")));
+
+    txn1.commit().unwrap();
+    let err = txn2.commit().unwrap_err();
+    assert_eq!(err.downcast_ref::<LsmError>(), Some(&LsmError::TxnConflict));
+    assert_eq!(storage.get(b"counter").unwrap(), Some(Bytes::from("1")));
+
+    // Retrying the aborted transaction from a new snapshot succeeds.
+    let txn3 = storage.new_txn().unwrap();
+    increment(&txn3, b"counter");
+    txn3.commit().unwrap();
+    assert_eq!(storage.get(b"counter").unwrap(), Some(Bytes::from("2")));
+}
+
+#[test]
+fn test_disjoint_read_modify_write() {
+    let dir = tempdir().unwrap();
+    let storage = open_serializable(&dir);
+    storage.put(b"counter1", b"0").unwrap();
+    storage.put(b"counter2", b"0").unwrap();
+
+    let txn1 = storage.new_txn().unwrap();
+    let txn2 = storage.new_txn().unwrap();
+    increment(&txn1, b"counter1");
+    increment(&txn2, b"counter2");
+    txn1.commit().unwrap();
+    txn2.commit().unwrap();
+    assert_eq!(storage.get(b"counter1").unwrap(), Some(Bytes::from("1")));
+    assert_eq!(storage.get(b"counter2").unwrap(), Some(Bytes::from("1")));
+}
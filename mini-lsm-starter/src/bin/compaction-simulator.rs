@@ -278,6 +278,7 @@ fn generate_random_split(
 }
 
 fn main() {
+    wrapper::init_logger();
     let args = Args::parse();
     match args {
         Args::Simple {
@@ -327,7 +327,10 @@ impl ReplBuilder {
 }
 
 fn main() -> Result<()> {
+    wrapper::init_logger();
     let args = Args::parse();
+    // The starter code and the reference solutions do not share the same set of options.
+    #[allow(clippy::needless_update)]
     let lsm = MiniLsm::open(
         args.path,
         LsmStorageOptions {
@@ -361,6 +364,7 @@ fn main() -> Result<()> {
             },
             enable_wal: args.enable_wal,
             serializable: args.serializable,
+            ..LsmStorageOptions::default_for_week1_test()
         },
     )?;
 
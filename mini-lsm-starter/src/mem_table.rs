@@ -15,7 +15,6 @@
 #![allow(unused_variables)] // TODO(you): remove this lint after implementing this mod
 #![allow(dead_code)] // TODO(you): remove this lint after implementing this mod
 
-use std::clone;
 use std::ops::Bound;
 use std::path::Path;
 use std::sync::Arc;
@@ -172,7 +171,7 @@ impl StorageIterator for MemTableIterator {
         unimplemented!()
     }
 
-    fn key(&self) -> KeySlice {
+    fn key(&self) -> KeySlice<'_> {
         unimplemented!()
     }
 
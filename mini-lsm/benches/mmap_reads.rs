@@ -0,0 +1,65 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare full scans of an SST read with `pread` and with mmap. Run with
+//! `cargo bench -p mini-lsm --bench mmap_reads`.
+
+use std::sync::Arc;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mini_lsm::iterators::StorageIterator;
+use mini_lsm::key::KeySlice;
+use mini_lsm::table::{FileObject, SsTable, SsTableBuilder, SsTableIterator};
+use tempfile::tempdir;
+
+const NUM_KEYS: usize = 200_000;
+
+fn full_scan(c: &mut Criterion) {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let mut builder = SsTableBuilder::new(4096);
+    let value = vec![b'v'; 100];
+    for i in 0..NUM_KEYS {
+        builder.add(
+            KeySlice::from_slice(format!("key_{:08}", i).as_bytes()),
+            &value,
+        );
+    }
+    builder.build(1, None, &path).unwrap();
+
+    let mut group = c.benchmark_group("full_scan");
+    for (name, mmap) in [("pread", false), ("mmap", true)] {
+        let file = if mmap {
+            FileObject::open_mmap(&path).unwrap()
+        } else {
+            FileObject::open(&path).unwrap()
+        };
+        let sst = Arc::new(SsTable::open(1, None, file).unwrap());
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut iter = SsTableIterator::create_and_seek_to_first(sst.clone()).unwrap();
+                let mut num_keys = 0;
+                while iter.is_valid() {
+                    num_keys += 1;
+                    iter.next().unwrap();
+                }
+                assert_eq!(num_keys, NUM_KEYS);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, full_scan);
+criterion_main!(benches);
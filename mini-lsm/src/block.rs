@@ -15,17 +15,32 @@
 mod builder;
 mod iterator;
 
+use std::sync::Arc;
+
 pub use builder::BlockBuilder;
 use bytes::{Buf, BufMut, Bytes};
 pub use iterator::BlockIterator;
 
 pub(crate) const SIZEOF_U16: usize = std::mem::size_of::<u16>();
 
+/// The longest key or value an entry can hold, as its length is encoded as a u16.
+pub const MAX_ENTRY_FIELD_LEN: usize = u16::MAX as usize;
+
+/// Every this many entries, a block stores the full key instead of the part not shared with the
+/// previous key, so that a seek only decodes the keys from the closest restart point on.
+pub const DEFAULT_RESTART_INTERVAL: usize = 16;
+
 /// A block is the smallest unit of read and caching in LSM tree. It is a collection of sorted
-/// key-value pairs.
+/// key-value pairs, each encoded as
+/// `| shared prefix len (u16) | rest key len (u16) | rest key | value len (u16) | value |`, where
+/// the shared prefix is taken from the previous key, or is empty at the restart points.
 pub struct Block {
     pub(crate) data: Vec<u8>,
     pub(crate) offsets: Vec<u16>,
+    /// The number of entries between restart points. 0 for the blocks of SSTs written before
+    /// restart points were introduced, where the shared prefix is always taken from the first key
+    /// of the block.
+    pub(crate) restart_interval: usize,
 }
 
 impl Block {
@@ -40,7 +55,13 @@ impl Block {
         buf.into()
     }
 
+    /// Decode a block built with [`DEFAULT_RESTART_INTERVAL`].
     pub fn decode(data: &[u8]) -> Self {
+        Self::decode_with_restart_interval(data, DEFAULT_RESTART_INTERVAL)
+    }
+
+    /// Decode a block built with the given restart interval, see [`Block::restart_interval`].
+    pub fn decode_with_restart_interval(data: &[u8], restart_interval: usize) -> Self {
         // get number of elements in the block
         let entry_offsets_len = (&data[data.len() - SIZEOF_U16..]).get_u16() as usize;
         let data_end = data.len() - SIZEOF_U16 - entry_offsets_len * SIZEOF_U16;
@@ -52,6 +73,20 @@ impl Block {
             .collect();
         // retrieve data
         let data = data[0..data_end].to_vec();
-        Self { data, offsets }
+        Self {
+            data,
+            offsets,
+            restart_interval,
+        }
+    }
+
+    /// Create an iterator positioned at the first entry of the block.
+    pub fn iter(self: &Arc<Self>) -> BlockIterator {
+        BlockIterator::create_and_seek_to_first(self.clone())
+    }
+
+    /// Get number of entries in the block.
+    pub fn num_of_entries(&self) -> usize {
+        self.offsets.len()
     }
 }
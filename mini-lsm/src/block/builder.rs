@@ -14,9 +14,10 @@
 
 use bytes::BufMut;
 
+use crate::error::LsmError;
 use crate::key::{KeySlice, KeyVec};
 
-use super::{Block, SIZEOF_U16};
+use super::{Block, DEFAULT_RESTART_INTERVAL, MAX_ENTRY_FIELD_LEN, SIZEOF_U16};
 
 /// Builds a block.
 pub struct BlockBuilder {
@@ -26,17 +27,21 @@ pub struct BlockBuilder {
     data: Vec<u8>,
     /// The expected block size.
     block_size: usize,
-    /// The first key in the block
-    first_key: KeyVec,
+    /// The number of entries between restart points.
+    restart_interval: usize,
+    /// The last key added to the block.
+    last_key: KeyVec,
+    /// The first entry rejected for exceeding [`MAX_ENTRY_FIELD_LEN`], reported by `try_build`.
+    error: Option<LsmError>,
 }
 
-fn compute_overlap(first_key: KeySlice, key: KeySlice) -> usize {
+fn compute_overlap(prev_key: KeySlice, key: KeySlice) -> usize {
     let mut i = 0;
     loop {
-        if i >= first_key.len() || i >= key.len() {
+        if i >= prev_key.len() || i >= key.len() {
             break;
         }
-        if first_key.raw_ref()[i] != key.raw_ref()[i] {
+        if prev_key.raw_ref()[i] != key.raw_ref()[i] {
             break;
         }
         i += 1;
@@ -47,11 +52,19 @@ fn compute_overlap(first_key: KeySlice, key: KeySlice) -> usize {
 impl BlockBuilder {
     /// Creates a new block builder.
     pub fn new(block_size: usize) -> Self {
+        Self::with_restart_interval(block_size, DEFAULT_RESTART_INTERVAL)
+    }
+
+    /// Creates a new block builder storing the full key of every `restart_interval`-th entry.
+    pub fn with_restart_interval(block_size: usize, restart_interval: usize) -> Self {
+        assert!(restart_interval > 0, "restart interval must be positive");
         Self {
             offsets: Vec::new(),
             data: Vec::new(),
             block_size,
-            first_key: KeyVec::new(),
+            restart_interval,
+            last_key: KeyVec::new(),
+            error: None,
         }
     }
 
@@ -60,18 +73,42 @@ impl BlockBuilder {
         // key-value pairs
     }
 
-    /// Adds a key-value pair to the block. Returns false when the block is full.
+    /// Adds a key-value pair to the block. Returns false when the block is full. A key or value
+    /// longer than [`MAX_ENTRY_FIELD_LEN`] is not added but makes `try_build` fail.
     #[must_use]
     pub fn add(&mut self, key: KeySlice, value: &[u8]) -> bool {
         assert!(!key.is_empty(), "key must not be empty");
+        // the lengths would be truncated, making the block unreadable
+        let error = if key.len() > MAX_ENTRY_FIELD_LEN {
+            Some(LsmError::KeyTooLarge {
+                len: key.len(),
+                limit: MAX_ENTRY_FIELD_LEN,
+            })
+        } else if value.len() > MAX_ENTRY_FIELD_LEN {
+            Some(LsmError::ValueTooLarge {
+                len: value.len(),
+                limit: MAX_ENTRY_FIELD_LEN,
+            })
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.error.get_or_insert(error);
+            return true;
+        }
         if self.estimated_size() + key.len() + value.len() + SIZEOF_U16 * 3 /* key_len, value_len and offset */ > self.block_size
             && !self.is_empty()
         {
             return false;
         }
+        // Store the full key at restart points.
+        let overlap = if self.offsets.len().is_multiple_of(self.restart_interval) {
+            0
+        } else {
+            compute_overlap(self.last_key.as_key_slice(), key)
+        };
         // Add the offset of the data into the offset array.
         self.offsets.push(self.data.len() as u16);
-        let overlap = compute_overlap(self.first_key.as_key_slice(), key);
         // Encode key overlap.
         self.data.put_u16(overlap as u16);
         // Encode key length.
@@ -83,9 +120,7 @@ impl BlockBuilder {
         // Encode value content.
         self.data.put(value);
 
-        if self.first_key.is_empty() {
-            self.first_key = key.to_key_vec();
-        }
+        self.last_key.set_from_slice(key);
 
         true
     }
@@ -97,12 +132,21 @@ impl BlockBuilder {
 
     /// Finalize the block.
     pub fn build(self) -> Block {
+        self.try_build().unwrap()
+    }
+
+    /// Finalize the block, failing with the first key or value `add` rejected for its size.
+    pub fn try_build(self) -> Result<Block, LsmError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
         if self.is_empty() {
             panic!("block should not be empty");
         }
-        Block {
+        Ok(Block {
             data: self.data,
             offsets: self.offsets,
-        }
+            restart_interval: self.restart_interval,
+        })
     }
 }
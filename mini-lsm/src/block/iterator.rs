@@ -33,12 +33,16 @@ pub struct BlockIterator {
     value_range: (usize, usize),
     /// the current index at the iterator position
     idx: usize,
-    /// the first key in the block
+    /// the first key in the block, which the other keys share prefixes with in blocks without
+    /// restart points
     first_key: KeyVec,
 }
 
 impl Block {
     fn get_first_key(&self) -> KeyVec {
+        if self.offsets.is_empty() {
+            return KeyVec::new();
+        }
         let mut buf = &self.data[..];
         buf.get_u16();
         let key_len = buf.get_u16();
@@ -65,6 +69,13 @@ impl BlockIterator {
         iter
     }
 
+    /// Creates a block iterator and seek to the last entry.
+    pub fn create_and_seek_to_last(block: Arc<Block>) -> Self {
+        let mut iter = Self::new(block);
+        iter.seek_to_last();
+        iter
+    }
+
     /// Creates a block iterator and seek to the first key that >= `key`.
     pub fn create_and_seek_to_key(block: Arc<Block>, key: KeySlice) -> Self {
         let mut iter = Self::new(block);
@@ -73,7 +84,7 @@ impl BlockIterator {
     }
 
     /// Returns the key of the current entry.
-    pub fn key(&self) -> KeySlice {
+    pub fn key(&self) -> KeySlice<'_> {
         debug_assert!(!self.key.is_empty(), "invalid iterator");
         self.key.as_key_slice()
     }
@@ -94,51 +105,92 @@ impl BlockIterator {
         self.seek_to(0);
     }
 
-    /// Seeks to the idx-th key in the block.
+    /// Seeks to the last key in the block, or invalidates the iterator if the block is empty.
+    pub fn seek_to_last(&mut self) {
+        self.seek_to(self.block.offsets.len().saturating_sub(1));
+    }
+
+    /// The number of entries between restart points, where every entry is a restart point in
+    /// blocks without restart points as their keys only depend on the first key.
+    fn restart_interval(&self) -> usize {
+        self.block.restart_interval.max(1)
+    }
+
+    /// Seeks to the idx-th key in the block, decoding the keys from the restart point before it,
+    /// or from the current position if it is between the two.
     fn seek_to(&mut self, idx: usize) {
         if idx >= self.block.offsets.len() {
             self.key.clear();
             self.value_range = (0, 0);
             return;
         }
-        let offset = self.block.offsets[idx] as usize;
-        self.seek_to_offset(offset);
+        let restart = idx - idx % self.restart_interval();
+        let mut current = if self.is_valid() && (restart..=idx).contains(&self.idx) {
+            self.idx
+        } else {
+            self.decode_entry(restart);
+            restart
+        };
+        while current < idx {
+            current += 1;
+            self.decode_entry(current);
+        }
         self.idx = idx;
     }
 
     /// Move to the next key in the block.
     pub fn next(&mut self) {
-        self.idx += 1;
-        self.seek_to(self.idx);
+        self.seek_to(self.idx + 1);
     }
 
-    /// Seek to the specified position and update the current `key` and `value`
-    /// Index update will be handled by caller
-    fn seek_to_offset(&mut self, offset: usize) {
+    /// Move to the previous key in the block. The iterator becomes invalid when moving before the
+    /// first key, and stays invalid afterwards.
+    pub fn prev(&mut self) {
+        if !self.is_valid() {
+            return;
+        }
+        if self.idx == 0 {
+            self.key.clear();
+            self.value_range = (0, 0);
+            return;
+        }
+        self.seek_to(self.idx - 1);
+    }
+
+    /// Decode the idx-th entry and update the current `key` and `value`. Unless the entry is a
+    /// restart point, the current key must be the one of the previous entry. Index update will be
+    /// handled by caller.
+    fn decode_entry(&mut self, idx: usize) {
+        let offset = self.block.offsets[idx] as usize;
         let mut entry = &self.block.data[offset..];
         // Since `get_u16()` will automatically move the ptr 2 bytes ahead here,
         // we don't need to manually advance it
         let overlap_len = entry.get_u16() as usize;
         let key_len = entry.get_u16() as usize;
         let key = &entry[..key_len];
-        self.key.clear();
-        self.key.append(&self.first_key.raw_ref()[..overlap_len]);
+        if self.block.restart_interval == 0 {
+            self.key.clear();
+            self.key.append(&self.first_key.raw_ref()[..overlap_len]);
+        } else {
+            self.key.truncate(overlap_len);
+        }
         self.key.append(key);
         entry.advance(key_len);
         let value_len = entry.get_u16() as usize;
         let value_offset_begin = offset + SIZEOF_U16 + SIZEOF_U16 + key_len + SIZEOF_U16;
         let value_offset_end = value_offset_begin + value_len;
         self.value_range = (value_offset_begin, value_offset_end);
-        entry.advance(value_len);
     }
 
-    /// Seek to the first key that is >= `key`.
+    /// Seek to the first key that is >= `key`. Binary searches the restart points for the last
+    /// one whose key is <= `key`, then scans forward from it.
     pub fn seek_to_key(&mut self, key: KeySlice) {
+        let interval = self.restart_interval();
         let mut low = 0;
-        let mut high = self.block.offsets.len();
+        let mut high = self.block.offsets.len().div_ceil(interval);
         while low < high {
             let mid = low + (high - low) / 2;
-            self.seek_to(mid);
+            self.seek_to(mid * interval);
             assert!(self.is_valid());
             match self.key().cmp(&key) {
                 std::cmp::Ordering::Less => low = mid + 1,
@@ -146,6 +198,10 @@ impl BlockIterator {
                 std::cmp::Ordering::Equal => return,
             }
         }
-        self.seek_to(low);
+        // All restart points before `low` have keys < `key`, so the entry is after `low - 1`.
+        self.seek_to(low.saturating_sub(1) * interval);
+        while self.is_valid() && self.key() < key {
+            self.next();
+        }
     }
 }
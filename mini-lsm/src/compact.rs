@@ -16,11 +16,16 @@ mod leveled;
 mod simple_leveled;
 mod tiered;
 
+use std::any::Any;
 use std::collections::HashSet;
+use std::ops::Bound;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
+use bytes::Bytes;
 pub use leveled::{LeveledCompactionController, LeveledCompactionOptions, LeveledCompactionTask};
 use serde::{Deserialize, Serialize};
 pub use simple_leveled::{
@@ -30,12 +35,85 @@ pub use tiered::{TieredCompactionController, TieredCompactionOptions, TieredComp
 
 use crate::iterators::StorageIterator;
 use crate::iterators::concat_iterator::SstConcatIterator;
+use crate::iterators::loser_tree_iterator::LoserTreeIterator;
 use crate::iterators::merge_iterator::MergeIterator;
 use crate::iterators::two_merge_iterator::TwoMergeIterator;
 use crate::key::KeySlice;
-use crate::lsm_storage::{LsmStorageInner, LsmStorageState};
+use crate::lsm_storage::{
+    CompactionFilter, LsmStorageInner, LsmStorageState, range_overlap, split_blocks,
+};
 use crate::manifest::ManifestRecord;
-use crate::table::{SsTable, SsTableBuilder, SsTableIterator};
+use crate::range_tombstone::{RangeTombstone, is_shadowed, shadowing};
+use crate::table::{SsTable, SsTableIterator};
+use crate::ttl::{is_expired, now_millis};
+
+/// The levels used by the running compactions, so that compactions on disjoint levels run at the
+/// same time while no compaction picks an SST another one is compacting.
+#[derive(Default)]
+pub(crate) struct RunningCompactions {
+    /// Levels read or written by a running compaction, where 0 stands for L0.
+    pub(crate) busy_levels: HashSet<usize>,
+    /// Whether a compaction that may use any level runs, i.e. a full, range or tiered compaction.
+    exclusive: bool,
+}
+
+impl RunningCompactions {
+    /// Whether a running compaction may read or write `level`, where 0 stands for L0.
+    pub(crate) fn is_busy(&self, level: usize) -> bool {
+        self.exclusive || self.busy_levels.contains(&level)
+    }
+
+    /// Mark the levels of `task` as used until the returned guard is dropped.
+    fn start<'a>(
+        &mut self,
+        storage: &'a LsmStorageInner,
+        task: &CompactionTask,
+    ) -> RunningCompactionGuard<'a> {
+        let levels = task.levels();
+        match &levels {
+            Some(levels) => self.busy_levels.extend(levels),
+            None => self.exclusive = true,
+        }
+        RunningCompactionGuard { storage, levels }
+    }
+}
+
+/// Releases the levels of a compaction when dropped, see [`RunningCompactions`].
+struct RunningCompactionGuard<'a> {
+    storage: &'a LsmStorageInner,
+    /// `None` for an exclusive compaction.
+    levels: Option<Vec<usize>>,
+}
+
+impl Drop for RunningCompactionGuard<'_> {
+    fn drop(&mut self) {
+        let mut running = self.storage.running_compactions.lock();
+        match &self.levels {
+            Some(levels) => {
+                for level in levels {
+                    running.busy_levels.remove(level);
+                }
+            }
+            None => running.exclusive = false,
+        }
+        drop(running);
+        self.storage.compaction_finished.notify_all();
+    }
+}
+
+/// Extract the message of a panic payload.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Compactions merging at least this many sorted runs use a loser tree instead of a binary heap.
+const LOSER_TREE_MIN_MERGE_WIDTH: usize = 8;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum CompactionTask {
@@ -46,17 +124,285 @@ pub enum CompactionTask {
         l0_sstables: Vec<usize>,
         l1_sstables: Vec<usize>,
     },
+    /// Compaction of the SSTs overlapping a key range, see [`LsmStorageInner::compact_range`].
+    Range {
+        l0_sstables: Vec<usize>,
+        /// The SSTs of each level (or tier) from top to bottom, as `(level, sst_ids)`.
+        levels: Vec<(usize, Vec<usize>)>,
+        /// The bottom level (or tier) the output SSTs go to.
+        output_level: usize,
+    },
 }
 
 impl CompactionTask {
     fn compact_to_bottom_level(&self) -> bool {
         match self {
-            CompactionTask::ForceFullCompaction { .. } => true,
+            CompactionTask::ForceFullCompaction { .. } | CompactionTask::Range { .. } => true,
             CompactionTask::Leveled(task) => task.is_lower_level_bottom_level,
             CompactionTask::Simple(task) => task.is_lower_level_bottom_level,
             CompactionTask::Tiered(task) => task.bottom_tier_included,
         }
     }
+
+    /// Ids of the SSTs the task reads.
+    pub fn input_sst_ids(&self) -> Vec<usize> {
+        match self {
+            CompactionTask::ForceFullCompaction {
+                l0_sstables,
+                l1_sstables,
+            } => l0_sstables.iter().chain(l1_sstables).copied().collect(),
+            CompactionTask::Range {
+                l0_sstables,
+                levels,
+                ..
+            } => l0_sstables
+                .iter()
+                .chain(levels.iter().flat_map(|(_, ssts)| ssts))
+                .copied()
+                .collect(),
+            CompactionTask::Leveled(LeveledCompactionTask {
+                upper_level_sst_ids,
+                lower_level_sst_ids,
+                ..
+            })
+            | CompactionTask::Simple(SimpleLeveledCompactionTask {
+                upper_level_sst_ids,
+                lower_level_sst_ids,
+                ..
+            }) => upper_level_sst_ids
+                .iter()
+                .chain(lower_level_sst_ids)
+                .copied()
+                .collect(),
+            CompactionTask::Tiered(task) => task
+                .tiers
+                .iter()
+                .flat_map(|(_, ssts)| ssts)
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Check that `controller` can apply the task to `state`: the task is of the kind the
+    /// controller generates, and its SSTs are still where the task expects them.
+    fn validate(&self, controller: &CompactionController, state: &LsmStorageState) -> Result<()> {
+        let level_ssts = |level: usize| {
+            state
+                .levels
+                .iter()
+                .find(|(id, _)| *id == level)
+                .map(|(_, ssts)| ssts.as_slice())
+                .ok_or_else(|| anyhow!("level {} of the compaction task not found", level))
+        };
+        let check_l0 = |ssts: &[usize]| {
+            if !ssts.iter().all(|id| state.l0_sstables.contains(id)) {
+                bail!("L0 SSTs {:?} of the compaction task not found", ssts);
+            }
+            Ok(())
+        };
+        let check_level = |level: usize, ssts: &[usize]| {
+            let level_ssts = level_ssts(level)?;
+            if !ssts.iter().all(|id| level_ssts.contains(id)) {
+                bail!(
+                    "SSTs {:?} of the compaction task not found in level {}",
+                    ssts,
+                    level
+                );
+            }
+            Ok(())
+        };
+        // the lower level SSTs of leveled tasks are replaced in place, so they must be consecutive
+        let check_run = |level: usize, ssts: &[usize]| {
+            let level_ssts = level_ssts(level)?;
+            let pos = ssts
+                .first()
+                .and_then(|first| level_ssts.iter().position(|id| id == first));
+            if !ssts.is_empty()
+                && pos.and_then(|pos| level_ssts.get(pos..pos + ssts.len())) != Some(ssts)
+            {
+                bail!(
+                    "SSTs {:?} of the compaction task are not a run of level {}",
+                    ssts,
+                    level
+                );
+            }
+            Ok(())
+        };
+        match (controller, self) {
+            (
+                _,
+                CompactionTask::ForceFullCompaction {
+                    l0_sstables,
+                    l1_sstables,
+                },
+            ) => {
+                check_l0(l0_sstables)?;
+                if state.levels.first().map(|(_, ssts)| ssts) != Some(l1_sstables) {
+                    bail!(
+                        "L1 SSTs {:?} of the compaction task do not match L1",
+                        l1_sstables
+                    );
+                }
+            }
+            (
+                _,
+                CompactionTask::Range {
+                    l0_sstables,
+                    levels,
+                    output_level,
+                },
+            ) => {
+                check_l0(l0_sstables)?;
+                for (level, ssts) in levels {
+                    check_level(*level, ssts)?;
+                }
+                level_ssts(*output_level)?;
+            }
+            (CompactionController::Leveled(_), CompactionTask::Leveled(task)) => {
+                match task.upper_level {
+                    Some(level) => check_level(level, &task.upper_level_sst_ids)?,
+                    None => check_l0(&task.upper_level_sst_ids)?,
+                }
+                check_run(task.lower_level, &task.lower_level_sst_ids)?;
+            }
+            (CompactionController::Simple(_), CompactionTask::Simple(task)) => {
+                match task.upper_level {
+                    // the whole upper level is compacted
+                    Some(level) => {
+                        if level_ssts(level)? != task.upper_level_sst_ids {
+                            bail!("SSTs of the compaction task do not match level {}", level);
+                        }
+                    }
+                    None => check_l0(&task.upper_level_sst_ids)?,
+                }
+                check_run(task.lower_level, &task.lower_level_sst_ids)?;
+            }
+            (CompactionController::Tiered(_), CompactionTask::Tiered(task)) => {
+                // Tiers are matched by id like `apply_compaction_result` does.
+                for (tier_id, ssts) in &task.tiers {
+                    match state.levels.iter().find(|(id, _)| id == tier_id) {
+                        Some((_, tier_ssts)) if tier_ssts == ssts => {}
+                        Some(_) => bail!("SSTs of tier {} changed after the task", tier_id),
+                        None => bail!("tier {} of the compaction task not found", tier_id),
+                    }
+                }
+            }
+            _ => bail!("the compaction task does not match the compaction strategy"),
+        }
+        Ok(())
+    }
+
+    /// The levels the task reads or writes, where 0 stands for L0, or `None` if it may use any
+    /// level and must not run alongside other compactions.
+    fn levels(&self) -> Option<Vec<usize>> {
+        match self {
+            CompactionTask::Leveled(LeveledCompactionTask {
+                upper_level,
+                lower_level,
+                ..
+            })
+            | CompactionTask::Simple(SimpleLeveledCompactionTask {
+                upper_level,
+                lower_level,
+                ..
+            }) => Some(vec![upper_level.unwrap_or(0), *lower_level]),
+            CompactionTask::ForceFullCompaction { .. }
+            | CompactionTask::Range { .. }
+            | CompactionTask::Tiered(_) => None,
+        }
+    }
+
+    /// The level the output SSTs go to, or `None` for tiered compaction where there are no
+    /// fixed levels.
+    fn output_level(&self) -> Option<usize> {
+        match self {
+            CompactionTask::ForceFullCompaction { .. } => Some(1),
+            CompactionTask::Range { output_level, .. } => Some(*output_level),
+            CompactionTask::Leveled(task) => Some(task.lower_level),
+            CompactionTask::Simple(task) => Some(task.lower_level),
+            CompactionTask::Tiered(_) => None,
+        }
+    }
+}
+
+/// What a compaction task would do, see [`crate::lsm_storage::MiniLsm::dry_run_compaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionPlan {
+    /// Ids of the SSTs the compaction reads.
+    pub input_sst_ids: Vec<usize>,
+    /// Total size of the input SSTs in bytes.
+    pub input_bytes: u64,
+    /// Estimated total size of the output SSTs in bytes. Only input SSTs entirely dropped by a
+    /// range tombstone or a compaction filter are known to be saved without reading them.
+    pub estimated_output_bytes: u64,
+    /// L0 SSTs after the compaction.
+    pub l0_sstables: Vec<usize>,
+    /// Levels (or tiers) after the compaction. The output SSTs are given the ids a compaction
+    /// started right away would use, and are not ordered by their keys within the level.
+    pub levels: Vec<(usize, Vec<usize>)>,
+}
+
+/// Replace the compacted L0 SSTs and L1 with the output of a full compaction.
+fn apply_full_compaction_result(
+    snapshot: &mut LsmStorageState,
+    l0_sstables: &[usize],
+    l1_sstables: &[usize],
+    output: &[usize],
+) {
+    assert_eq!(l1_sstables, snapshot.levels[0].1);
+    snapshot.levels[0].1 = output.to_vec();
+    let mut l0_sstables_map = l0_sstables.iter().copied().collect::<HashSet<_>>();
+    snapshot.l0_sstables.retain(|x| !l0_sstables_map.remove(x));
+    assert!(l0_sstables_map.is_empty());
+}
+
+/// Remove the inputs of a range compaction and add the output SSTs to the bottom level, in key
+/// order unless `in_recovery`, where the output SSTs are not opened yet and `open` sorts the levels
+/// afterwards. Tiers left empty are removed.
+fn apply_range_compaction_result(
+    snapshot: &mut LsmStorageState,
+    task: &CompactionTask,
+    output: &[usize],
+    remove_empty_levels: bool,
+    in_recovery: bool,
+) {
+    let CompactionTask::Range {
+        l0_sstables,
+        levels,
+        output_level,
+    } = task
+    else {
+        unreachable!()
+    };
+    let mut l0_sstables_map = l0_sstables.iter().copied().collect::<HashSet<_>>();
+    snapshot.l0_sstables.retain(|x| !l0_sstables_map.remove(x));
+    assert!(l0_sstables_map.is_empty());
+    for (level, ssts) in levels {
+        let Some((_, level_ssts)) = snapshot.levels.iter_mut().find(|(id, _)| id == level) else {
+            panic!("level {} of the compaction task not found", level);
+        };
+        let mut ssts_map = ssts.iter().copied().collect::<HashSet<_>>();
+        level_ssts.retain(|x| !ssts_map.remove(x));
+        assert!(ssts_map.is_empty());
+    }
+    let Some((_, level_ssts)) = snapshot
+        .levels
+        .iter_mut()
+        .find(|(id, _)| id == output_level)
+    else {
+        panic!("level {} of the compaction task not found", output_level);
+    };
+    let pos = match output.first() {
+        Some(first) if !in_recovery => {
+            let first_key = snapshot.sstables[first].first_key();
+            level_ssts.partition_point(|id| snapshot.sstables[id].first_key() < first_key)
+        }
+        _ => level_ssts.len(),
+    };
+    level_ssts.splice(pos..pos, output.iter().copied());
+    if remove_empty_levels {
+        snapshot.levels.retain(|(_, ssts)| !ssts.is_empty());
+    }
 }
 
 pub(crate) enum CompactionController {
@@ -67,14 +413,21 @@ pub(crate) enum CompactionController {
 }
 
 impl CompactionController {
-    pub fn generate_compaction_task(&self, snapshot: &LsmStorageState) -> Option<CompactionTask> {
+    /// Generate a compaction task using none of the `busy_levels`, where 0 stands for L0. Tiered
+    /// compaction has no fixed levels, so it only generates a task if no level is busy.
+    pub fn generate_compaction_task(
+        &self,
+        snapshot: &LsmStorageState,
+        busy_levels: &HashSet<usize>,
+    ) -> Option<CompactionTask> {
         match self {
             CompactionController::Leveled(ctrl) => ctrl
-                .generate_compaction_task(snapshot)
+                .generate_compaction_task_excluding(snapshot, busy_levels)
                 .map(CompactionTask::Leveled),
             CompactionController::Simple(ctrl) => ctrl
-                .generate_compaction_task(snapshot)
+                .generate_compaction_task_excluding(snapshot, busy_levels)
                 .map(CompactionTask::Simple),
+            CompactionController::Tiered(_) if !busy_levels.is_empty() => None,
             CompactionController::Tiered(ctrl) => ctrl
                 .generate_compaction_task(snapshot)
                 .map(CompactionTask::Tiered),
@@ -99,6 +452,29 @@ impl CompactionController {
             (CompactionController::Tiered(ctrl), CompactionTask::Tiered(task)) => {
                 ctrl.apply_compaction_result(snapshot, task, output)
             }
+            (
+                _,
+                CompactionTask::ForceFullCompaction {
+                    l0_sstables,
+                    l1_sstables,
+                },
+            ) => {
+                let mut snapshot = snapshot.clone();
+                apply_full_compaction_result(&mut snapshot, l0_sstables, l1_sstables, output);
+                let removed = l0_sstables.iter().chain(l1_sstables).copied().collect();
+                (snapshot, removed)
+            }
+            (_, CompactionTask::Range { .. }) => {
+                let mut snapshot = snapshot.clone();
+                apply_range_compaction_result(
+                    &mut snapshot,
+                    task,
+                    output,
+                    !self.flush_to_l0(),
+                    in_recovery,
+                );
+                (snapshot, task.input_sst_ids())
+            }
             _ => unreachable!(),
         }
     }
@@ -126,56 +502,326 @@ pub enum CompactionOptions {
     NoCompaction,
 }
 
+/// Check whether a key compacted to the bottom level is dropped by any of the filters.
+fn is_filtered(compaction_filters: &[CompactionFilter], key: &[u8]) -> bool {
+    compaction_filters
+        .iter()
+        .any(|filter| filter.drops_at_bottom_level(key))
+}
+
+/// Select the SSTs overlapping the range for [`LsmStorageInner::compact_range`], widening the
+/// range to the SSTs selected until no more SSTs overlap with it. Returns `None` if no SST
+/// overlaps the range.
+fn range_compaction_task(
+    snapshot: &LsmStorageState,
+    lower: Bound<&[u8]>,
+    upper: Bound<&[u8]>,
+) -> Option<CompactionTask> {
+    let overlaps = |lower: Bound<&[u8]>, upper: Bound<&[u8]>, id: &usize| {
+        let table = &snapshot.sstables[id];
+        range_overlap(
+            lower,
+            upper,
+            table.first_key().as_key_slice(),
+            table.last_key().as_key_slice(),
+        )
+    };
+    let (mut lower, mut upper) = (lower, upper);
+    loop {
+        let selected = snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flat_map(|(_, ssts)| ssts))
+            .filter(|id| overlaps(lower, upper, id))
+            .map(|id| &snapshot.sstables[id])
+            .collect::<Vec<_>>();
+        let first_key = selected
+            .iter()
+            .map(|table| table.first_key().raw_ref())
+            .min()?;
+        let last_key = selected
+            .iter()
+            .map(|table| table.last_key().raw_ref())
+            .max()?;
+        let widened = (Bound::Included(first_key), Bound::Included(last_key));
+        if widened == (lower, upper) {
+            break;
+        }
+        (lower, upper) = widened;
+    }
+    let l0_sstables = snapshot
+        .l0_sstables
+        .iter()
+        .copied()
+        .filter(|id| overlaps(lower, upper, id))
+        .collect();
+    let levels = snapshot
+        .levels
+        .iter()
+        .map(|(level, ssts)| {
+            let ssts = ssts
+                .iter()
+                .copied()
+                .filter(|id| overlaps(lower, upper, id))
+                .collect::<Vec<_>>();
+            (*level, ssts)
+        })
+        .filter(|(_, ssts)| !ssts.is_empty())
+        .collect();
+    Some(CompactionTask::Range {
+        l0_sstables,
+        levels,
+        output_level: snapshot.levels.last()?.0,
+    })
+}
+
+/// The SSTs of the level a range compaction outputs to.
+fn output_level_ssts<'a>(snapshot: &'a LsmStorageState, task: &CompactionTask) -> &'a [usize] {
+    let Some(output_level) = task.output_level() else {
+        return &[];
+    };
+    snapshot
+        .levels
+        .iter()
+        .find(|(level, _)| *level == output_level)
+        .map_or(&[], |(_, ssts)| ssts)
+}
+
+/// Whether the SSTs are sorted by their keys without overlapping.
+fn is_sorted_run(snapshot: &LsmStorageState, sst_ids: &[usize]) -> bool {
+    sst_ids.windows(2).all(|pair| {
+        snapshot.sstables[&pair[0]].last_key() < snapshot.sstables[&pair[1]].first_key()
+    })
+}
+
+/// Ends the inner iterator before `upper`, if any, for compacting a part of the key space.
+struct UpperBoundIterator<I> {
+    iter: I,
+    upper: Option<Bytes>,
+}
+
+impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> UpperBoundIterator<I> {
+    fn new(iter: I, upper: Option<&[u8]>) -> Self {
+        Self {
+            iter,
+            upper: upper.map(Bytes::copy_from_slice),
+        }
+    }
+}
+
+impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIterator
+    for UpperBoundIterator<I>
+{
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn value(&self) -> &[u8] {
+        self.iter.value()
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        self.iter.key()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.iter.is_valid()
+            && self
+                .upper
+                .as_ref()
+                .is_none_or(|upper| self.iter.key().raw_ref() < &upper[..])
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.iter.next()
+    }
+
+    fn num_active_iterators(&self) -> usize {
+        self.iter.num_active_iterators()
+    }
+
+    fn epoch(&self) -> usize {
+        self.iter.epoch()
+    }
+}
+
 impl LsmStorageInner {
     fn compact_generate_sst_from_iter(
         &self,
         mut iter: impl for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>,
-        compact_to_bottom_level: bool,
+        task: &CompactionTask,
+        range_tombstones: &[RangeTombstone],
     ) -> Result<Vec<Arc<SsTable>>> {
+        let compact_to_bottom_level = task.compact_to_bottom_level();
+        // Tiers are not levels, even if a range compaction has an output tier.
+        let output_level = if self.compaction_controller.flush_to_l0() {
+            task.output_level()
+        } else {
+            None
+        };
+        let block_size = self.options.block_size_for_level(output_level);
+        let compaction_filters = if compact_to_bottom_level {
+            self.compaction_filters.lock().clone()
+        } else {
+            Vec::new()
+        };
         let mut builder = None;
         let mut new_sst = Vec::new();
+        // The output SST inherits the largest epoch of the entries in it.
+        let mut epoch = 0;
 
+        let now = now_millis();
+        // The merged iterator yields a single version of each key, as the SSTs hold no timestamps,
+        // so there are no older versions to keep for readers or to collect below a watermark.
         while iter.is_valid() {
-            if builder.is_none() {
-                builder = Some(SsTableBuilder::new(self.options.block_size));
-            }
-            let builder_inner = builder.as_mut().unwrap();
-            if compact_to_bottom_level {
-                if !iter.value().is_empty() {
-                    builder_inner.add(iter.key(), iter.value());
+            let skip = (compact_to_bottom_level
+                && (iter.value().is_empty() || is_expired(iter.value(), now)))
+                || is_shadowed(range_tombstones, iter.key().raw_ref(), iter.epoch())
+                || is_filtered(&compaction_filters, iter.key().raw_ref());
+            if !skip {
+                if builder.is_none() {
+                    builder = Some(self.new_sst_builder(block_size));
                 }
-            } else {
+                let builder_inner = builder.as_mut().unwrap();
                 builder_inner.add(iter.key(), iter.value());
+                epoch = epoch.max(iter.epoch());
             }
             iter.next()?;
 
-            if builder_inner.estimated_size() >= self.options.target_sst_size {
+            if let Some(builder_inner) = &builder
+                && builder_inner.estimated_size() >= self.options.target_sst_size
+            {
                 let sst_id = self.next_sst_id();
-                let builder = builder.take().unwrap();
-                let sst = Arc::new(builder.build(
-                    sst_id,
-                    Some(self.block_cache.clone()),
-                    self.path_of_sst(sst_id),
-                )?);
-                new_sst.push(sst);
+                let mut builder = builder.take().unwrap();
+                builder.set_epoch(std::mem::take(&mut epoch));
+                let mut sst =
+                    builder.build(sst_id, self.sst_block_cache(), self.path_of_sst(sst_id))?;
+                sst.set_stats(self.stats.clone());
+                self.throttle_compaction_write(sst.table_size());
+                new_sst.push(Arc::new(sst));
             }
         }
-        if let Some(builder) = builder {
+        if let Some(mut builder) = builder {
             let sst_id = self.next_sst_id(); // lock dropped here
-            let sst = Arc::new(builder.build(
-                sst_id,
-                Some(self.block_cache.clone()),
-                self.path_of_sst(sst_id),
-            )?);
-            new_sst.push(sst);
+            builder.set_epoch(epoch);
+            let mut sst =
+                builder.build(sst_id, self.sst_block_cache(), self.path_of_sst(sst_id))?;
+            sst.set_stats(self.stats.clone());
+            self.throttle_compaction_write(sst.table_size());
+            new_sst.push(Arc::new(sst));
         }
         Ok(new_sst)
     }
 
-    fn compact(&self, task: &CompactionTask) -> Result<Vec<Arc<SsTable>>> {
-        let snapshot = {
-            let state = self.state.read();
-            state.clone()
+    /// Account for an SST written by a compaction, waiting for the rate limiter if any.
+    fn throttle_compaction_write(&self, bytes: u64) {
+        let throttled = match &self.compaction_rate_limiter {
+            Some(rate_limiter) => rate_limiter.request(bytes),
+            None => Duration::ZERO,
+        };
+        self.stats.record_compaction_write(bytes, throttled);
+    }
+
+    /// Runs the compaction task on the SSTs of `snapshot`, which must be the state the task was
+    /// generated from, so that the input SSTs are there even if the task has become obsolete.
+    ///
+    /// With `compaction_parallelism` above 1, the key space is split into disjoint ranges of
+    /// roughly equal input size, whose output SSTs are built by one thread per range.
+    ///
+    /// The keys shadowed by a range tombstone are dropped. Only the tombstones that may shadow a
+    /// key of the input SSTs are carried through the merge; dropping the tombstones themselves is
+    /// left to `remove_obsolete_range_tombstones` once the output is installed.
+    pub(crate) fn compact(
+        &self,
+        task: &CompactionTask,
+        snapshot: &LsmStorageState,
+    ) -> Result<Vec<Arc<SsTable>>> {
+        let range_tombstones = shadowing(
+            &self.range_tombstones.read(),
+            task.input_sst_ids()
+                .iter()
+                .map(|id| snapshot.sstables[id].as_ref()),
+        );
+        let range_tombstones = &range_tombstones;
+        let parallelism = self.options.compaction_parallelism;
+        let split_keys = if parallelism > 1 {
+            let blocks = task
+                .input_sst_ids()
+                .into_iter()
+                .flat_map(|id| snapshot.sstables[&id].block_ranges())
+                .map(|(first_key, _, size)| (first_key.raw_ref(), size))
+                .collect();
+            split_blocks(blocks, Bound::Unbounded, Bound::Unbounded, parallelism)
+        } else {
+            Vec::new()
+        };
+        if split_keys.is_empty() {
+            return self.compact_key_range(task, snapshot, range_tombstones, None, None);
+        }
+
+        let lower_keys = std::iter::once(None).chain(split_keys.iter().map(|key| Some(&key[..])));
+        let upper_keys = split_keys
+            .iter()
+            .map(|key| Some(&key[..]))
+            .chain(std::iter::once(None));
+        let outputs = std::thread::scope(|scope| {
+            let workers = lower_keys
+                .zip(upper_keys)
+                .map(|(lower, upper)| {
+                    scope.spawn(move || {
+                        self.compact_key_range(task, snapshot, range_tombstones, lower, upper)
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|worker| match worker.join() {
+                    Ok(result) => result,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut new_sst = Vec::new();
+        let mut result = Ok(());
+        for output in outputs {
+            match output {
+                Ok(ssts) => new_sst.extend(ssts),
+                Err(e) => result = result.and(Err(e)),
+            }
+        }
+        if let Err(e) = result {
+            // the SSTs of the other ranges are never installed
+            self.remove_sst_files(new_sst.iter().map(|sst| sst.sst_id()));
+            return Err(e);
+        }
+        Ok(new_sst)
+    }
+
+    /// Runs the compaction task on the keys within `[lower, upper)` of the input SSTs, where
+    /// `None` stands for no bound.
+    fn compact_key_range(
+        &self,
+        task: &CompactionTask,
+        snapshot: &LsmStorageState,
+        range_tombstones: &[RangeTombstone],
+        lower: Option<&[u8]>,
+        upper: Option<&[u8]>,
+    ) -> Result<Vec<Arc<SsTable>>> {
+        let sst_iter = |id: &usize| {
+            let table = snapshot.sstables[id].clone();
+            match lower {
+                Some(key) => {
+                    SsTableIterator::create_and_seek_to_key(table, KeySlice::from_slice(key))
+                }
+                None => SsTableIterator::create_and_seek_to_first(table),
+            }
+        };
+        let concat_iter = |ids: &[usize]| {
+            let ssts = ids.iter().map(|id| snapshot.sstables[id].clone()).collect();
+            match lower {
+                Some(key) => {
+                    SstConcatIterator::create_and_seek_to_key(ssts, KeySlice::from_slice(key))
+                }
+                None => SstConcatIterator::create_and_seek_to_first(ssts),
+            }
         };
         match task {
             CompactionTask::ForceFullCompaction {
@@ -184,19 +830,40 @@ impl LsmStorageInner {
             } => {
                 let mut l0_iters = Vec::with_capacity(l0_sstables.len());
                 for id in l0_sstables.iter() {
-                    l0_iters.push(Box::new(SsTableIterator::create_and_seek_to_first(
-                        snapshot.sstables.get(id).unwrap().clone(),
-                    )?));
+                    l0_iters.push(Box::new(sst_iter(id)?));
+                }
+                let iter = TwoMergeIterator::create(
+                    MergeIterator::create(l0_iters),
+                    concat_iter(l1_sstables)?,
+                )?;
+                self.compact_generate_sst_from_iter(
+                    UpperBoundIterator::new(iter, upper),
+                    task,
+                    range_tombstones,
+                )
+            }
+            CompactionTask::Range {
+                l0_sstables,
+                levels,
+                ..
+            } => {
+                let mut l0_iters = Vec::with_capacity(l0_sstables.len());
+                for id in l0_sstables.iter() {
+                    l0_iters.push(Box::new(sst_iter(id)?));
                 }
-                let mut l1_iters = Vec::with_capacity(l1_sstables.len());
-                for id in l1_sstables.iter() {
-                    l1_iters.push(snapshot.sstables.get(id).unwrap().clone());
+                let mut level_iters = Vec::with_capacity(levels.len());
+                for (_, level_sst_ids) in levels {
+                    level_iters.push(Box::new(concat_iter(level_sst_ids)?));
                 }
                 let iter = TwoMergeIterator::create(
                     MergeIterator::create(l0_iters),
-                    SstConcatIterator::create_and_seek_to_first(l1_iters)?,
+                    MergeIterator::create(level_iters),
                 )?;
-                self.compact_generate_sst_from_iter(iter, task.compact_to_bottom_level())
+                self.compact_generate_sst_from_iter(
+                    UpperBoundIterator::new(iter, upper),
+                    task,
+                    range_tombstones,
+                )
             }
             CompactionTask::Simple(SimpleLeveledCompactionTask {
                 upper_level,
@@ -213,61 +880,76 @@ impl LsmStorageInner {
                 ..
             }) => match upper_level {
                 Some(_) => {
-                    let mut upper_ssts = Vec::with_capacity(upper_level_sst_ids.len());
-                    for id in upper_level_sst_ids.iter() {
-                        upper_ssts.push(snapshot.sstables.get(id).unwrap().clone());
-                    }
-                    let upper_iter = SstConcatIterator::create_and_seek_to_first(upper_ssts)?;
-                    let mut lower_ssts = Vec::with_capacity(lower_level_sst_ids.len());
-                    for id in lower_level_sst_ids.iter() {
-                        lower_ssts.push(snapshot.sstables.get(id).unwrap().clone());
-                    }
-                    let lower_iter = SstConcatIterator::create_and_seek_to_first(lower_ssts)?;
+                    let upper_iter = concat_iter(upper_level_sst_ids)?;
+                    let lower_iter = concat_iter(lower_level_sst_ids)?;
                     self.compact_generate_sst_from_iter(
-                        TwoMergeIterator::create(upper_iter, lower_iter)?,
-                        task.compact_to_bottom_level(),
+                        UpperBoundIterator::new(
+                            TwoMergeIterator::create(upper_iter, lower_iter)?,
+                            upper,
+                        ),
+                        task,
+                        range_tombstones,
                     )
                 }
                 None => {
                     let mut upper_iters = Vec::with_capacity(upper_level_sst_ids.len());
                     for id in upper_level_sst_ids.iter() {
-                        upper_iters.push(Box::new(SsTableIterator::create_and_seek_to_first(
-                            snapshot.sstables.get(id).unwrap().clone(),
-                        )?));
+                        upper_iters.push(Box::new(sst_iter(id)?));
                     }
                     let upper_iter = MergeIterator::create(upper_iters);
-                    let mut lower_ssts = Vec::with_capacity(lower_level_sst_ids.len());
-                    for id in lower_level_sst_ids.iter() {
-                        lower_ssts.push(snapshot.sstables.get(id).unwrap().clone());
-                    }
-                    let lower_iter = SstConcatIterator::create_and_seek_to_first(lower_ssts)?;
+                    let lower_iter = concat_iter(lower_level_sst_ids)?;
                     self.compact_generate_sst_from_iter(
-                        TwoMergeIterator::create(upper_iter, lower_iter)?,
-                        task.compact_to_bottom_level(),
+                        UpperBoundIterator::new(
+                            TwoMergeIterator::create(upper_iter, lower_iter)?,
+                            upper,
+                        ),
+                        task,
+                        range_tombstones,
                     )
                 }
             },
             CompactionTask::Tiered(TieredCompactionTask { tiers, .. }) => {
                 let mut iters = Vec::with_capacity(tiers.len());
                 for (_, tier_sst_ids) in tiers {
-                    let mut ssts = Vec::with_capacity(tier_sst_ids.len());
-                    for id in tier_sst_ids.iter() {
-                        ssts.push(snapshot.sstables.get(id).unwrap().clone());
-                    }
-                    iters.push(Box::new(SstConcatIterator::create_and_seek_to_first(ssts)?));
+                    iters.push(Box::new(concat_iter(tier_sst_ids)?));
+                }
+                if iters.len() >= LOSER_TREE_MIN_MERGE_WIDTH {
+                    self.compact_generate_sst_from_iter(
+                        UpperBoundIterator::new(LoserTreeIterator::create(iters), upper),
+                        task,
+                        range_tombstones,
+                    )
+                } else {
+                    self.compact_generate_sst_from_iter(
+                        UpperBoundIterator::new(MergeIterator::create(iters), upper),
+                        task,
+                        range_tombstones,
+                    )
                 }
-                self.compact_generate_sst_from_iter(
-                    MergeIterator::create(iters),
-                    task.compact_to_bottom_level(),
-                )
             }
         }
     }
 
+    /// Remove the range tombstones that no longer cover any data older than themselves, and return
+    /// the removed ones. Must be called with the write lock of `state` held.
+    fn remove_obsolete_range_tombstones(&self, state: &LsmStorageState) -> Vec<RangeTombstone> {
+        let mut range_tombstones = self.range_tombstones.write();
+        let (obsolete, active): (Vec<_>, Vec<_>) = range_tombstones
+            .iter()
+            .cloned()
+            .partition(|tombstone| tombstone.is_obsolete(state));
+        if !obsolete.is_empty() {
+            *range_tombstones = Arc::new(active);
+        }
+        obsolete
+    }
+
     pub fn force_full_compaction(&self) -> Result<()> {
         let CompactionOptions::NoCompaction = self.options.compaction_options else {
             panic!("full compaction can only be called with compaction is not enabled")
         };
+        self.check_writable()?;
+        let _running_compaction = self.start_exclusive_compaction();
 
         let snapshot = {
             let state = self.state.read();
@@ -281,9 +963,9 @@ impl LsmStorageInner {
             l1_sstables: l1_sstables.clone(),
         };
 
-        println!("force full compaction: {:?}", compaction_task);
+        log::info!("force full compaction: {:?}", compaction_task);
 
-        let sstables = self.compact(&compaction_task)?;
+        let sstables = self.compact(&compaction_task, &snapshot)?;
         let mut ids = Vec::with_capacity(sstables.len());
 
         {
@@ -298,50 +980,221 @@ impl LsmStorageInner {
                 let result = state.sstables.insert(new_sst.sst_id(), new_sst);
                 assert!(result.is_none());
             }
-            assert_eq!(l1_sstables, state.levels[0].1);
-            state.levels[0].1.clone_from(&ids);
-            let mut l0_sstables_map = l0_sstables.iter().copied().collect::<HashSet<_>>();
-            state.l0_sstables = state
-                .l0_sstables
-                .iter()
-                .filter(|x| !l0_sstables_map.remove(x))
-                .copied()
-                .collect::<Vec<_>>();
-            assert!(l0_sstables_map.is_empty());
-            *self.state.write() = Arc::new(state);
+            apply_full_compaction_result(&mut state, &l0_sstables, &l1_sstables, &ids);
+            let mut state_guard = self.state.write();
+            self.install_sst_state(&mut state_guard, state);
+            let dropped_range_tombstones = self.remove_obsolete_range_tombstones(&state_guard);
+            drop(state_guard);
             self.sync_dir()?;
             self.manifest.as_ref().unwrap().add_record(
                 &state_lock,
                 ManifestRecord::Compaction(compaction_task, ids.clone()),
             )?;
+            if !dropped_range_tombstones.is_empty() {
+                self.manifest.as_ref().unwrap().add_record(
+                    &state_lock,
+                    ManifestRecord::DropRangeTombstones(dropped_range_tombstones),
+                )?;
+            }
+            self.maybe_compact_manifest(&state_lock)?;
         }
-        for sst in l0_sstables.iter().chain(l1_sstables.iter()) {
-            std::fs::remove_file(self.path_of_sst(*sst))?;
-        }
+        self.remove_sst_files(l0_sstables.iter().chain(l1_sstables.iter()).copied());
 
-        println!("force full compaction done, new SSTs: {:?}", ids);
+        log::info!("force full compaction done, new SSTs: {:?}", ids);
+        self.stats.record_compaction();
+        if let Some(listener) = &self.options.event_listener {
+            let removed = l0_sstables.iter().chain(&l1_sstables).copied();
+            listener.on_compaction(&removed.collect::<Vec<_>>(), &ids);
+        }
 
         Ok(())
     }
 
-    fn trigger_compaction(&self) -> Result<()> {
+    /// Compact every SST overlapping the range into the bottom level, dropping deletes, e.g. to
+    /// reclaim the space of many deleted keys in the range right away. SSTs overlapping the
+    /// selected ones are selected as well, so that the output SSTs do not overlap with the SSTs
+    /// left in the bottom level.
+    pub fn compact_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<()> {
+        self.check_writable()?;
+        let _running_compaction = self.start_exclusive_compaction();
         let snapshot = {
             let state = self.state.read();
             state.clone()
         };
-        let task = self
-            .compaction_controller
-            .generate_compaction_task(&snapshot);
-        let Some(task) = task else {
+        let Some(task) = range_compaction_task(&snapshot, lower, upper) else {
             return Ok(());
         };
+        log::info!("range compaction: {:?}", task);
+
+        let sstables = self.compact(&task, &snapshot)?;
+        let output = sstables.iter().map(|x| x.sst_id()).collect::<Vec<_>>();
+        let ssts_to_remove = {
+            let state_lock = self.state_lock.lock();
+            let mut snapshot = self.state.read().as_ref().clone();
+            for sst in sstables {
+                let result = snapshot.sstables.insert(sst.sst_id(), sst);
+                assert!(result.is_none());
+            }
+            let (mut snapshot, files_to_remove) = self
+                .compaction_controller
+                .apply_compaction_result(&snapshot, &task, &output, false);
+            // An SST ingested into the bottom level while compacting may fall between the inputs.
+            if !is_sorted_run(&snapshot, output_level_ssts(&snapshot, &task)) {
+                drop(state_lock);
+                self.remove_sst_files(output.iter().copied());
+                bail!("an SST was added into the range while compacting it");
+            }
+            let mut ssts_to_remove = Vec::with_capacity(files_to_remove.len());
+            for file_to_remove in &files_to_remove {
+                let result = snapshot.sstables.remove(file_to_remove);
+                assert!(result.is_some(), "cannot remove {}.sst", file_to_remove);
+                ssts_to_remove.push(result.unwrap());
+            }
+            let mut state = self.state.write();
+            self.install_sst_state(&mut state, snapshot);
+            let dropped_range_tombstones = self.remove_obsolete_range_tombstones(&state);
+            drop(state);
+            self.sync_dir()?;
+            self.manifest.as_ref().unwrap().add_record(
+                &state_lock,
+                ManifestRecord::Compaction(task, output.clone()),
+            )?;
+            if !dropped_range_tombstones.is_empty() {
+                self.manifest.as_ref().unwrap().add_record(
+                    &state_lock,
+                    ManifestRecord::DropRangeTombstones(dropped_range_tombstones),
+                )?;
+            }
+            self.maybe_compact_manifest(&state_lock)?;
+            ssts_to_remove
+        };
+        self.remove_sst_files(ssts_to_remove.iter().map(|sst| sst.sst_id()));
+        self.sync_dir()?;
+        log::info!("range compaction done, new SSTs: {:?}", output);
+        self.stats.record_compaction();
+        self.notify_compaction(&ssts_to_remove, &output);
+        Ok(())
+    }
+
+    /// Report what running `task` would do without building any SST. Fails if the task cannot be
+    /// applied to the current state, e.g. as its SSTs were compacted in the meantime.
+    pub fn dry_run_compaction(&self, task: &CompactionTask) -> Result<CompactionPlan> {
+        let snapshot = {
+            let state = self.state.read();
+            state.clone()
+        };
+        task.validate(&self.compaction_controller, &snapshot)?;
+        let range_tombstones = self.range_tombstones.read().clone();
+        let compaction_filters = if task.compact_to_bottom_level() {
+            self.compaction_filters.lock().clone()
+        } else {
+            Vec::new()
+        };
+        let input_sst_ids = task.input_sst_ids();
+        let mut input_bytes = 0;
+        let mut estimated_output_bytes = 0;
+        for id in &input_sst_ids {
+            let sst = &snapshot.sstables[id];
+            let (first_key, last_key) = (sst.first_key().raw_ref(), sst.last_key().raw_ref());
+            input_bytes += sst.table_size();
+            // both ends being dropped by the same range means the whole SST is
+            let dropped = range_tombstones.iter().any(|tombstone| {
+                tombstone.shadows(first_key, sst.epoch())
+                    && tombstone.shadows(last_key, sst.epoch())
+            }) || compaction_filters.iter().any(|filter| {
+                filter.drops_at_bottom_level(first_key) && filter.drops_at_bottom_level(last_key)
+            });
+            if !dropped {
+                estimated_output_bytes += sst.table_size();
+            }
+        }
+
+        let num_outputs = estimated_output_bytes.div_ceil(self.options.target_sst_size as u64);
+        let next_sst_id = self.peek_next_sst_id();
+        let output = (next_sst_id..next_sst_id + num_outputs as usize).collect::<Vec<_>>();
+        let new_snapshot = match task {
+            CompactionTask::ForceFullCompaction {
+                l0_sstables,
+                l1_sstables,
+            } => {
+                let mut new_snapshot = snapshot.as_ref().clone();
+                apply_full_compaction_result(&mut new_snapshot, l0_sstables, l1_sstables, &output);
+                new_snapshot
+            }
+            // the output SSTs do not exist, so apply the result as in recovery without sorting
+            _ => {
+                self.compaction_controller
+                    .apply_compaction_result(&snapshot, task, &output, true)
+                    .0
+            }
+        };
+        Ok(CompactionPlan {
+            input_sst_ids,
+            input_bytes,
+            estimated_output_bytes,
+            l0_sstables: new_snapshot.l0_sstables,
+            levels: new_snapshot.levels,
+        })
+    }
+
+    /// Wait until no compaction runs, and keep other compactions from starting until the returned
+    /// guard is dropped.
+    fn start_exclusive_compaction(&self) -> RunningCompactionGuard<'_> {
+        let mut running = self.running_compactions.lock();
+        while running.exclusive || !running.busy_levels.is_empty() {
+            self.compaction_finished.wait(&mut running);
+        }
+        running.exclusive = true;
+        RunningCompactionGuard {
+            storage: self,
+            levels: None,
+        }
+    }
+
+    pub(crate) fn trigger_compaction(&self) -> Result<()> {
+        self.run_one_compaction()?;
+        Ok(())
+    }
+
+    /// Runs the next compaction task the compaction controller generates, if any. Returns whether
+    /// a compaction was done.
+    pub(crate) fn run_one_compaction(&self) -> Result<bool> {
+        self.check_writable()?;
+        // The task is generated and its levels marked as used at once, so that concurrent
+        // compactions pick disjoint levels.
+        let (snapshot, task, _running_compaction) = {
+            let mut running = self.running_compactions.lock();
+            if running.exclusive {
+                return Ok(false);
+            }
+            let snapshot = {
+                let state = self.state.read();
+                state.clone()
+            };
+            let task = self
+                .compaction_controller
+                .generate_compaction_task(&snapshot, &running.busy_levels);
+            let Some(task) = task else {
+                return Ok(false);
+            };
+            let running_compaction = running.start(self, &task);
+            (snapshot, task, running_compaction)
+        };
         self.dump_structure();
-        println!("running compaction task: {:?}", task);
-        let sstables = self.compact(&task)?;
+        log::debug!("running compaction task: {:?}", task);
+        // The state lock is only taken to apply the result, so that memtables can be frozen and
+        // flushed while compacting. Only compactions remove SSTs, and no other compaction uses the
+        // levels of the task until then.
+        let sstables = self.compact(&task, &snapshot)?;
         let output = sstables.iter().map(|x| x.sst_id()).collect::<Vec<_>>();
         let ssts_to_remove = {
             let state_lock = self.state_lock.lock();
             let mut snapshot = self.state.read().as_ref().clone();
+            if let Err(e) = task.validate(&self.compaction_controller, &snapshot) {
+                drop(state_lock);
+                self.remove_sst_files(output.iter().copied());
+                return Err(e.context("the inputs of the compaction changed while compacting"));
+            }
             let mut new_sst_ids = Vec::new();
             for file_to_add in sstables {
                 new_sst_ids.push(file_to_add.sst_id());
@@ -351,6 +1204,12 @@ impl LsmStorageInner {
             let (mut snapshot, files_to_remove) = self
                 .compaction_controller
                 .apply_compaction_result(&snapshot, &task, &output, false);
+            // Flushes and ingests skip the levels compactions use, which this double checks.
+            if !is_sorted_run(&snapshot, output_level_ssts(&snapshot, &task)) {
+                drop(state_lock);
+                self.remove_sst_files(output.iter().copied());
+                bail!("an SST overlapping the output was added while compacting");
+            }
 
             let mut ssts_to_remove = Vec::with_capacity(files_to_remove.len());
             for file_to_remove in &files_to_remove {
@@ -359,61 +1218,122 @@ impl LsmStorageInner {
                 ssts_to_remove.push(result.unwrap());
             }
             let mut state = self.state.write();
-            *state = Arc::new(snapshot);
+            self.install_sst_state(&mut state, snapshot);
+            let dropped_range_tombstones = self.remove_obsolete_range_tombstones(&state);
             drop(state);
             self.sync_dir()?;
             self.manifest
                 .as_ref()
                 .unwrap()
                 .add_record(&state_lock, ManifestRecord::Compaction(task, new_sst_ids))?;
+            if !dropped_range_tombstones.is_empty() {
+                self.manifest.as_ref().unwrap().add_record(
+                    &state_lock,
+                    ManifestRecord::DropRangeTombstones(dropped_range_tombstones),
+                )?;
+            }
+            self.maybe_compact_manifest(&state_lock)?;
             ssts_to_remove
         };
-        println!(
+        log::info!(
             "compaction finished: {} files removed, {} files added, output={:?}",
             ssts_to_remove.len(),
             output.len(),
             output
         );
-        for sst in ssts_to_remove {
-            std::fs::remove_file(self.path_of_sst(sst.sst_id()))?;
-        }
+        self.remove_sst_files(ssts_to_remove.iter().map(|sst| sst.sst_id()));
         self.sync_dir()?;
+        self.stats.record_compaction();
+        self.notify_compaction(&ssts_to_remove, &output);
+
+        Ok(true)
+    }
 
+    /// Notify the event listener, if any, that a compaction replaced `removed` with `added`.
+    fn notify_compaction(&self, removed: &[Arc<SsTable>], added: &[usize]) {
+        if let Some(listener) = &self.options.event_listener {
+            let removed = removed.iter().map(|sst| sst.sst_id()).collect::<Vec<_>>();
+            listener.on_compaction(&removed, added);
+        }
+    }
+
+    /// Run one round of the work of a background thread, recording its error, if any, as the last
+    /// background error. A panic is recorded and returned as an error, upon which the thread
+    /// should stop as the state may be left inconsistent.
+    fn run_background_task(&self, name: &str, task: impl FnOnce() -> Result<()>) -> Result<()> {
+        let error = match std::panic::catch_unwind(AssertUnwindSafe(task)) {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) => format!("{} failed: {:#}", name, e),
+            Err(panic) => {
+                let error = format!("{} panicked: {}", name, panic_message(panic.as_ref()));
+                log::error!("{}, stopping the {} thread", error, name);
+                *self.background_error.lock() = Some(error.clone());
+                return Err(anyhow!(error));
+            }
+        };
+        log::error!("{}", error);
+        *self.background_error.lock() = Some(error);
         Ok(())
     }
 
-    pub(crate) fn spawn_compaction_thread(
+    /// Spawn the `max_background_compactions` compaction threads, each stopped by a message on
+    /// `rx`.
+    pub(crate) fn spawn_compaction_threads(
         self: &Arc<Self>,
         rx: crossbeam_channel::Receiver<()>,
-    ) -> Result<Option<std::thread::JoinHandle<()>>> {
+    ) -> Result<Vec<std::thread::JoinHandle<Result<()>>>> {
+        if self.options.disable_background_compaction {
+            return Ok(Vec::new());
+        }
+        let mut handles = Vec::new();
         if let CompactionOptions::Leveled(_)
         | CompactionOptions::Simple(_)
         | CompactionOptions::Tiered(_) = self.options.compaction_options
         {
-            let this = self.clone();
-            let handle = std::thread::spawn(move || {
-                let ticker = crossbeam_channel::tick(Duration::from_millis(50));
-                loop {
-                    crossbeam_channel::select! {
-                        recv(ticker) -> _ => if let Err(e) = this.trigger_compaction() {
-                            eprintln!("compaction failed: {}", e);
-                        },
-                        recv(rx) -> _ => return
+            for _ in 0..self.options.max_background_compactions {
+                let this = self.clone();
+                let rx = rx.clone();
+                handles.push(std::thread::spawn(move || {
+                    let ticker = crossbeam_channel::tick(Duration::from_millis(50));
+                    loop {
+                        crossbeam_channel::select! {
+                            recv(ticker) -> _ => this.run_background_task("compaction", || {
+                                this.trigger_compaction()
+                            })?,
+                            recv(rx) -> _ => return Ok(())
+                        }
                     }
-                }
-            });
-            return Ok(Some(handle));
+                }));
+            }
         }
-        Ok(None)
+        Ok(handles)
     }
 
-    fn trigger_flush(&self) -> Result<()> {
+    pub(crate) fn trigger_flush(&self) -> Result<()> {
         let res = {
             let state = self.state.read();
-            state.imm_memtables.len() >= self.options.num_memtable_limit
+            // Let more memtables pile up in memory if compaction is falling behind, instead of
+            // adding even more sorted runs for it to compact.
+            let pending_sorted_runs = if self.compaction_controller.flush_to_l0() {
+                state.l0_sstables.len()
+            } else {
+                state.levels.len()
+            };
+            let memtable_limit = match self.options.flush_slowdown_sorted_runs {
+                Some(trigger) if pending_sorted_runs >= trigger => {
+                    self.options.num_memtable_limit * 2
+                }
+                _ => self.options.num_memtable_limit,
+            };
+            state.imm_memtables.len() >= memtable_limit
         };
         if res {
-            self.force_flush_next_imm_memtable()?;
+            for _ in 0..self.options.flush_batch_size {
+                if self.state.read().imm_memtables.is_empty() {
+                    break;
+                }
+                self.force_flush_next_imm_memtable()?;
+            }
         }
 
         Ok(())
@@ -422,18 +1342,25 @@ impl LsmStorageInner {
     pub(crate) fn spawn_flush_thread(
         self: &Arc<Self>,
         rx: crossbeam_channel::Receiver<()>,
-    ) -> Result<Option<std::thread::JoinHandle<()>>> {
+    ) -> Result<Option<std::thread::JoinHandle<Result<()>>>> {
         let this = self.clone();
+        self.flush_thread_running.store(true, Ordering::SeqCst);
         let handle = std::thread::spawn(move || {
             let ticker = crossbeam_channel::tick(Duration::from_millis(50));
-            loop {
+            let result = loop {
                 crossbeam_channel::select! {
-                    recv(ticker) -> _ => if let Err(e) = this.trigger_flush() {
-                        eprintln!("flush failed: {}", e);
-                    },
-                    recv(rx) -> _ => return
+                    recv(ticker) -> _ => {
+                        if let Err(e) = this.run_background_task("flush", || this.trigger_flush()) {
+                            break Err(e);
+                        }
+                    }
+                    recv(rx) -> _ => break Ok(())
                 }
-            }
+            };
+            // writers stalled by `max_imm_memtables` would otherwise wait forever
+            this.flush_thread_running.store(false, Ordering::SeqCst);
+            this.notify_imm_memtables_flushed();
+            result
         });
         Ok(Some(handle))
     }
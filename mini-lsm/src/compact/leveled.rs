@@ -78,6 +78,16 @@ impl LeveledCompactionController {
     pub fn generate_compaction_task(
         &self,
         snapshot: &LsmStorageState,
+    ) -> Option<LeveledCompactionTask> {
+        self.generate_compaction_task_excluding(snapshot, &HashSet::new())
+    }
+
+    /// Generates a compaction task like `generate_compaction_task`, but neither reading nor
+    /// writing the `busy_levels` used by running compactions, where 0 stands for L0.
+    pub fn generate_compaction_task_excluding(
+        &self,
+        snapshot: &LsmStorageState,
+        busy_levels: &HashSet<usize>,
     ) -> Option<LeveledCompactionTask> {
         // step 1: compute target level size
         let mut target_level_size = (0..self.options.max_levels).map(|_| 0).collect::<Vec<_>>(); // exclude level 0
@@ -109,8 +119,11 @@ impl LeveledCompactionController {
         }
 
         // Flush L0 SST is the top priority
-        if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger {
-            println!("flush L0 SST to base level {}", base_level);
+        if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger
+            && !busy_levels.contains(&0)
+            && !busy_levels.contains(&base_level)
+        {
+            log::info!("flush L0 SST to base level {}", base_level);
             return Some(LeveledCompactionTask {
                 upper_level: None,
                 upper_level_sst_ids: snapshot.l0_sstables.clone(),
@@ -132,9 +145,11 @@ impl LeveledCompactionController {
             }
         }
         priorities.sort_by(|a, b| a.partial_cmp(b).unwrap().reverse());
-        let priority = priorities.first();
+        let priority = priorities
+            .iter()
+            .find(|(_, level)| !busy_levels.contains(level) && !busy_levels.contains(&(level + 1)));
         if let Some((_, level)) = priority {
-            println!(
+            log::info!(
                 "target level sizes: {:?}, real level sizes: {:?}, base_level: {}",
                 target_level_size
                     .iter()
@@ -149,7 +164,7 @@ impl LeveledCompactionController {
 
             let level = *level;
             let selected_sst = snapshot.levels[level - 1].1.iter().min().copied().unwrap(); // select the oldest sst to compact
-            println!(
+            log::info!(
                 "compaction triggered by priority: {level} out of {:?}, select {selected_sst} for compaction",
                 priorities
             );
@@ -13,10 +13,11 @@
 // limitations under the License.
 
 use std::collections::HashSet;
+use std::ops::Bound;
 
 use serde::{Deserialize, Serialize};
 
-use crate::lsm_storage::LsmStorageState;
+use crate::lsm_storage::{LsmStorageState, range_overlap};
 
 #[derive(Debug, Clone)]
 pub struct SimpleLeveledCompactionOptions {
@@ -44,12 +45,58 @@ impl SimpleLeveledCompactionController {
         Self { options }
     }
 
+    /// Finds the SSTs in `lower_level` overlapping the combined key range of `upper_sst_ids`. Returns
+    /// `None` if the key ranges are not known, e.g. in the compaction simulator, in which case the
+    /// whole lower level should be compacted.
+    fn find_overlapping_ssts(
+        snapshot: &LsmStorageState,
+        upper_sst_ids: &[usize],
+        lower_level: usize,
+    ) -> Option<Vec<usize>> {
+        let lower_sst_ids = &snapshot.levels[lower_level - 1].1;
+        let mut begin_key = None;
+        let mut end_key = None;
+        for id in upper_sst_ids {
+            let sst = snapshot.sstables.get(id)?;
+            if begin_key.is_none_or(|key| sst.first_key() < key) {
+                begin_key = Some(sst.first_key());
+            }
+            if end_key.is_none_or(|key| sst.last_key() > key) {
+                end_key = Some(sst.last_key());
+            }
+        }
+        let (begin_key, end_key) = (begin_key?, end_key?);
+        let mut overlap_ssts = Vec::new();
+        for id in lower_sst_ids {
+            let sst = snapshot.sstables.get(id)?;
+            if range_overlap(
+                Bound::Included(begin_key.raw_ref()),
+                Bound::Included(end_key.raw_ref()),
+                sst.first_key().as_key_slice(),
+                sst.last_key().as_key_slice(),
+            ) {
+                overlap_ssts.push(*id);
+            }
+        }
+        Some(overlap_ssts)
+    }
+
     /// Generates a compaction task.
     ///
     /// Returns `None` if no compaction needs to be scheduled. The order of SSTs in the compaction task id vector matters.
     pub fn generate_compaction_task(
         &self,
         snapshot: &LsmStorageState,
+    ) -> Option<SimpleLeveledCompactionTask> {
+        self.generate_compaction_task_excluding(snapshot, &HashSet::new())
+    }
+
+    /// Generates a compaction task like `generate_compaction_task`, but neither reading nor
+    /// writing the `busy_levels` used by running compactions, where 0 stands for L0.
+    pub fn generate_compaction_task_excluding(
+        &self,
+        snapshot: &LsmStorageState,
+        busy_levels: &HashSet<usize>,
     ) -> Option<SimpleLeveledCompactionTask> {
         if self.options.max_levels == 0 {
             return None;
@@ -62,8 +109,11 @@ impl SimpleLeveledCompactionController {
         }
 
         // check level0_file_num_compaction_trigger for compaction of L0 to L1
-        if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger {
-            println!(
+        if snapshot.l0_sstables.len() >= self.options.level0_file_num_compaction_trigger
+            && !busy_levels.contains(&0)
+            && !busy_levels.contains(&1)
+        {
+            log::info!(
                 "compaction triggered at level 0 because L0 has {} SSTs >= {}",
                 snapshot.l0_sstables.len(),
                 self.options.level0_file_num_compaction_trigger
@@ -77,25 +127,45 @@ impl SimpleLeveledCompactionController {
             });
         }
 
-        // check size_ratio_percent for compaction of other levels (>= L1)
+        // check size_ratio_percent for compaction of other levels (>= L1), picking the level
+        // furthest below the ratio
+        let target_ratio = self.options.size_ratio_percent as f64 / 100.0;
+        let mut compact_level = None;
+        let mut max_score = 1.0;
         for i in 1..self.options.max_levels {
-            let lower_level = i + 1;
-            let size_ratio = level_sizes[lower_level] as f64 / level_sizes[i] as f64;
-            if size_ratio < self.options.size_ratio_percent as f64 / 100.0 {
-                println!(
-                    "compaction triggered at level {} and {} with size ratio {}",
-                    i, lower_level, size_ratio
-                );
-                return Some(SimpleLeveledCompactionTask {
-                    upper_level: Some(i),
-                    upper_level_sst_ids: snapshot.levels[i - 1].1.clone(),
-                    lower_level,
-                    lower_level_sst_ids: snapshot.levels[lower_level - 1].1.clone(),
-                    is_lower_level_bottom_level: lower_level == self.options.max_levels,
-                });
+            if busy_levels.contains(&i) || busy_levels.contains(&(i + 1)) {
+                continue;
+            }
+            let size_ratio = level_sizes[i + 1] as f64 / level_sizes[i] as f64;
+            if size_ratio < target_ratio {
+                // infinite if the lower level is empty
+                let score = target_ratio / size_ratio;
+                if score > max_score {
+                    compact_level = Some((i, size_ratio));
+                    max_score = score;
+                }
             }
         }
-        None
+        let (upper_level, size_ratio) = compact_level?;
+        let lower_level = upper_level + 1;
+        log::info!(
+            "compaction triggered at level {} and {} with size ratio {}",
+            upper_level,
+            lower_level,
+            size_ratio
+        );
+        let upper_level_sst_ids = snapshot.levels[upper_level - 1].1.clone();
+        // only rewrite the lower-level SSTs overlapping with the upper level
+        let lower_level_sst_ids =
+            Self::find_overlapping_ssts(snapshot, &upper_level_sst_ids, lower_level)
+                .unwrap_or_else(|| snapshot.levels[lower_level - 1].1.clone());
+        Some(SimpleLeveledCompactionTask {
+            upper_level: Some(upper_level),
+            upper_level_sst_ids,
+            lower_level,
+            lower_level_sst_ids,
+            is_lower_level_bottom_level: lower_level == self.options.max_levels,
+        })
     }
 
     /// Apply the compaction result.
@@ -137,13 +207,29 @@ impl SimpleLeveledCompactionController {
             assert!(l0_ssts_compacted.is_empty());
             snapshot.l0_sstables = new_l0_sstables;
         }
+        // The lower-level SSTs of the task are a consecutive run of the level. Splice the output
+        // into their position, leaving the non-overlapping SSTs in place.
+        let lower_level_ssts = &mut snapshot.levels[task.lower_level - 1].1;
+        let pos = match task.lower_level_sst_ids.first() {
+            Some(first_id) => lower_level_ssts
+                .iter()
+                .position(|id| id == first_id)
+                .expect("sst mismatched"),
+            None => match output.first().and_then(|id| snapshot.sstables.get(id)) {
+                Some(first_sst) => lower_level_ssts.partition_point(|id| {
+                    snapshot.sstables[id].first_key() < first_sst.first_key()
+                }),
+                // SSTs are not loaded during recovery, the level is sorted after they are opened
+                None => lower_level_ssts.len(),
+            },
+        };
+        let end = pos + task.lower_level_sst_ids.len();
         assert_eq!(
             task.lower_level_sst_ids,
-            snapshot.levels[task.lower_level - 1].1,
+            lower_level_ssts[pos..end],
             "sst mismatched"
         );
-        files_to_remove.extend(&snapshot.levels[task.lower_level - 1].1);
-        snapshot.levels[task.lower_level - 1].1 = output.to_vec();
+        files_to_remove.extend(lower_level_ssts.splice(pos..end, output.iter().copied()));
         (snapshot, files_to_remove)
     }
 }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use serde::{Deserialize, Serialize};
 
@@ -35,11 +36,44 @@ pub struct TieredCompactionOptions {
 
 pub struct TieredCompactionController {
     options: TieredCompactionOptions,
+    /// Overrides `options.max_size_amplification_percent` so that it can be tuned at runtime.
+    max_size_amplification_percent: AtomicUsize,
 }
 
 impl TieredCompactionController {
     pub fn new(options: TieredCompactionOptions) -> Self {
-        Self { options }
+        Self {
+            max_size_amplification_percent: AtomicUsize::new(
+                options.max_size_amplification_percent,
+            ),
+            options,
+        }
+    }
+
+    pub fn max_size_amplification_percent(&self) -> usize {
+        self.max_size_amplification_percent.load(Ordering::Relaxed)
+    }
+
+    /// Change the space amplification ratio that triggers a full compaction. Takes effect from
+    /// the next generated task.
+    pub fn set_max_size_amplification_percent(&self, percent: usize) {
+        self.max_size_amplification_percent
+            .store(percent, Ordering::Relaxed);
+    }
+
+    /// Generate a compaction task that merges all tiers into the bottom tier regardless of the
+    /// triggers, to reclaim space on demand. Returns `None` if there is nothing to merge.
+    pub fn generate_full_compaction_task(
+        &self,
+        snapshot: &LsmStorageState,
+    ) -> Option<TieredCompactionTask> {
+        if snapshot.levels.len() < 2 {
+            return None;
+        }
+        Some(TieredCompactionTask {
+            tiers: snapshot.levels.clone(),
+            bottom_tier_included: true,
+        })
     }
 
     pub fn generate_compaction_task(
@@ -60,15 +94,12 @@ impl TieredCompactionController {
         }
         let space_amp_ratio =
             (size as f64) / (snapshot.levels.last().unwrap().1.len() as f64) * 100.0;
-        if space_amp_ratio >= self.options.max_size_amplification_percent as f64 {
-            println!(
+        if space_amp_ratio >= self.max_size_amplification_percent() as f64 {
+            log::info!(
                 "compaction triggered by space amplification ratio: {}",
                 space_amp_ratio
             );
-            return Some(TieredCompactionTask {
-                tiers: snapshot.levels.clone(),
-                bottom_tier_included: true,
-            });
+            return self.generate_full_compaction_task(snapshot);
         }
         let size_ratio_trigger = (100.0 + self.options.size_ratio as f64) / 100.0;
         // compaction triggered by size ratio
@@ -78,7 +109,7 @@ impl TieredCompactionController {
             let next_level_size = snapshot.levels[id + 1].1.len();
             let current_size_ratio = next_level_size as f64 / size as f64;
             if current_size_ratio > size_ratio_trigger && id + 1 >= self.options.min_merge_width {
-                println!(
+                log::info!(
                     "compaction triggered by size ratio: {} > {}",
                     current_size_ratio * 100.0,
                     size_ratio_trigger * 100.0
@@ -100,7 +131,7 @@ impl TieredCompactionController {
             .levels
             .len()
             .min(self.options.max_merge_width.unwrap_or(usize::MAX));
-        println!("compaction triggered by reducing sorted runs");
+        log::info!("compaction triggered by reducing sorted runs");
         Some(TieredCompactionTask {
             tiers: snapshot
                 .levels
@@ -108,7 +139,8 @@ impl TieredCompactionController {
                 .take(num_tiers_to_take)
                 .cloned()
                 .collect::<Vec<_>>(),
-            bottom_tier_included: snapshot.levels.len() >= num_tiers_to_take,
+            // Deletes can only be dropped when no older tier is left that they may shadow.
+            bottom_tier_included: num_tiers_to_take >= snapshot.levels.len(),
         })
     }
 
@@ -123,6 +155,8 @@ impl TieredCompactionController {
             "should not add l0 ssts in tiered compaction"
         );
         let mut snapshot = snapshot.clone();
+        // Tiers are matched by id, as tiers flushed after the task was generated may be added in
+        // front of the compacted ones.
         let mut tier_to_remove = task
             .tiers
             .iter()
@@ -141,13 +175,17 @@ impl TieredCompactionController {
                 levels.push((*tier_id, files.clone()));
             }
             if tier_to_remove.is_empty() && !new_tier_added {
-                // add the compacted tier to the LSM tree
+                // add the compacted tier to the LSM tree, unless everything is dropped
                 new_tier_added = true;
-                levels.push((output[0], output.to_vec()));
+                if let Some(&tier_id) = output.first() {
+                    levels.push((tier_id, output.to_vec()));
+                }
             }
         }
         if !tier_to_remove.is_empty() {
-            unreachable!("some tiers not found??");
+            let mut missing = tier_to_remove.into_keys().collect::<Vec<_>>();
+            missing.sort();
+            panic!("tiers {:?} of the compaction task not found", missing);
         }
         snapshot.levels = levels;
         (snapshot, files_to_remove)
@@ -0,0 +1,93 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use serde::Serialize;
+
+use crate::lsm_storage::{LsmStorageInner, MiniLsm};
+use crate::table::SsTable;
+
+/// The structure of the storage at some point, see [`MiniLsm::describe`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageDescription {
+    /// Number of memtables, including the active one.
+    pub num_memtables: usize,
+    /// Approximate size of all memtables in bytes.
+    pub memtables_size: usize,
+    /// Ids of the immutable memtables, the latest first.
+    pub imm_memtable_ids: Vec<usize>,
+    /// L0 SSTs, the latest first.
+    pub l0_sstables: Vec<SstDescription>,
+    /// SSTs of each level (or tier) from top to bottom, as `(level, ssts)`.
+    pub levels: Vec<(usize, Vec<SstDescription>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SstDescription {
+    pub id: usize,
+    pub first_key: Bytes,
+    pub last_key: Bytes,
+    /// Size of the SST file in bytes.
+    pub size: u64,
+}
+
+impl SstDescription {
+    fn new(sst: &SsTable) -> Self {
+        Self {
+            id: sst.sst_id(),
+            first_key: sst.first_key().clone().into_inner(),
+            last_key: sst.last_key().clone().into_inner(),
+            size: sst.table_size(),
+        }
+    }
+}
+
+impl LsmStorageInner {
+    pub fn describe(&self) -> StorageDescription {
+        let snapshot = self.state.read().clone();
+        let describe_ssts = |ids: &[usize]| {
+            ids.iter()
+                .map(|id| SstDescription::new(&snapshot.sstables[id]))
+                .collect::<Vec<_>>()
+        };
+        StorageDescription {
+            num_memtables: snapshot.imm_memtables.len() + 1,
+            memtables_size: snapshot.memtable.approximate_size()
+                + snapshot
+                    .imm_memtables
+                    .iter()
+                    .map(|memtable| memtable.approximate_size())
+                    .sum::<usize>(),
+            imm_memtable_ids: snapshot
+                .imm_memtables
+                .iter()
+                .map(|memtable| memtable.id())
+                .collect(),
+            l0_sstables: describe_ssts(&snapshot.l0_sstables),
+            levels: snapshot
+                .levels
+                .iter()
+                .map(|(level, ids)| (*level, describe_ssts(ids)))
+                .collect(),
+        }
+    }
+}
+
+impl MiniLsm {
+    /// Describe the levels and SSTs of the storage, e.g., for inspecting the layout compactions
+    /// leave behind. Can be serialized to JSON.
+    pub fn describe(&self) -> StorageDescription {
+        self.inner.describe()
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+/// Errors returned by the storage engine, so that callers can tell e.g. a corruption from an IO
+/// error or a transaction conflict. The public methods of the `mini-lsm` crate's `MiniLsm` return
+/// them directly; elsewhere they are wrapped in `anyhow::Error` and can be recovered with
+/// `downcast_ref::<LsmError>()`. This file is shared with the `mini-lsm-mvcc` crate.
+#[derive(Debug, Clone)]
+pub enum LsmError {
+    /// The bottom level has reached `max_db_size_bytes`.
+    OutOfSpace { size: u64, limit: u64 },
+    /// The key already exists and `reject_overwrites` is enabled.
+    AlreadyExists(Bytes),
+    /// The database was created with a different comparator than the one passed to `open`.
+    ComparatorMismatch { stored: String, configured: String },
+    /// Empty keys are not supported, as iterators use them to signal the end of the data.
+    EmptyKey,
+    /// The key is longer than the `limit` bytes the block and WAL encodings can represent.
+    KeyTooLarge { len: usize, limit: usize },
+    /// The value is longer than the `limit` bytes the block and WAL encodings can represent.
+    ValueTooLarge { len: usize, limit: usize },
+    /// The options passed to `open` are inconsistent.
+    InvalidOptions(String),
+    /// An invariant of the engine is violated, which indicates a bug.
+    Internal(String),
+    /// The database is opened with `open_read_only` and cannot be modified.
+    ReadOnly,
+    /// Reading or writing a file failed. The IO error is the source of this error.
+    Io(Arc<io::Error>),
+    /// A file of the database does not decode, e.g. a truncated SST or a checksum mismatch outside
+    /// of the data blocks.
+    Corruption(String),
+    /// A data block of an SST does not match its checksum.
+    BlockChecksum { sst_id: usize, block_idx: usize },
+    /// A record of the manifest passes its checksum but does not decode.
+    ManifestCorrupt(String),
+    /// A transaction conflicts with a concurrently committed one and can be retried.
+    TxnConflict,
+    /// Any other error, with the messages of its whole chain.
+    Other(String),
+}
+
+impl LsmError {
+    /// Whether the error is caused by data on disk not being what was written, in which case
+    /// retrying does not help.
+    pub fn is_corruption(&self) -> bool {
+        matches!(
+            self,
+            LsmError::Corruption(_) | LsmError::BlockChecksum { .. } | LsmError::ManifestCorrupt(_)
+        )
+    }
+}
+
+impl PartialEq for LsmError {
+    fn eq(&self, other: &Self) -> bool {
+        use LsmError::*;
+        match (self, other) {
+            (OutOfSpace { size, limit }, OutOfSpace { size: s, limit: l }) => {
+                size == s && limit == l
+            }
+            (AlreadyExists(a), AlreadyExists(b)) => a == b,
+            (KeyTooLarge { len, limit }, KeyTooLarge { len: n, limit: l })
+            | (ValueTooLarge { len, limit }, ValueTooLarge { len: n, limit: l }) => {
+                len == n && limit == l
+            }
+            (
+                ComparatorMismatch { stored, configured },
+                ComparatorMismatch {
+                    stored: s,
+                    configured: c,
+                },
+            ) => stored == s && configured == c,
+            (InvalidOptions(a), InvalidOptions(b))
+            | (Internal(a), Internal(b))
+            | (Corruption(a), Corruption(b))
+            | (ManifestCorrupt(a), ManifestCorrupt(b))
+            | (Other(a), Other(b)) => a == b,
+            (
+                BlockChecksum { sst_id, block_idx },
+                BlockChecksum {
+                    sst_id: s,
+                    block_idx: b,
+                },
+            ) => sst_id == s && block_idx == b,
+            // IO errors cannot be compared, only their kinds
+            (Io(a), Io(b)) => a.kind() == b.kind(),
+            (EmptyKey, EmptyKey) | (ReadOnly, ReadOnly) | (TxnConflict, TxnConflict) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for LsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LsmError::OutOfSpace { size, limit } => write!(
+                f,
+                "out of space: bottom level has {} bytes, limit is {} bytes",
+                size, limit
+            ),
+            LsmError::AlreadyExists(key) => write!(f, "key already exists: {:?}", key),
+            LsmError::ComparatorMismatch { stored, configured } => write!(
+                f,
+                "comparator mismatch: database was created with {:?}, opened with {:?}",
+                stored, configured
+            ),
+            LsmError::EmptyKey => write!(f, "key cannot be empty"),
+            LsmError::KeyTooLarge { len, limit } => {
+                write!(f, "key too large: {} bytes, limit is {} bytes", len, limit)
+            }
+            LsmError::ValueTooLarge { len, limit } => {
+                write!(
+                    f,
+                    "value too large: {} bytes, limit is {} bytes",
+                    len, limit
+                )
+            }
+            LsmError::InvalidOptions(msg) => write!(f, "invalid options: {}", msg),
+            LsmError::Internal(msg) => write!(f, "internal error: {}", msg),
+            LsmError::ReadOnly => write!(f, "the database is opened read-only"),
+            LsmError::Io(err) => write!(f, "io error: {}", err),
+            LsmError::Corruption(msg) | LsmError::Other(msg) => write!(f, "{}", msg),
+            LsmError::BlockChecksum { sst_id, block_idx } => write!(
+                f,
+                "block checksum mismatched: block {} of {}.sst",
+                block_idx, sst_id
+            ),
+            LsmError::ManifestCorrupt(msg) => write!(f, "corrupted manifest: {}", msg),
+            LsmError::TxnConflict => write!(f, "transaction conflict"),
+        }
+    }
+}
+
+impl std::error::Error for LsmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LsmError::Io(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LsmError {
+    fn from(err: io::Error) -> Self {
+        LsmError::Io(Arc::new(err))
+    }
+}
+
+/// Converts the errors of the internal code at the boundary of the public API. An [`LsmError`]
+/// or an IO error is recovered even below added context; anything else keeps its messages only.
+impl From<anyhow::Error> for LsmError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(err) = err.downcast_ref::<LsmError>() {
+            return err.clone();
+        }
+        match err.downcast::<io::Error>() {
+            Ok(err) => LsmError::Io(Arc::new(err)),
+            Err(err) => LsmError::Other(format!("{:#}", err)),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Callbacks for applications that need to follow the SSTs the engine creates and removes, e.g.
+//! to mirror them to a remote store.
+
+use std::fmt;
+
+/// Receives the changes to the storage state. Every callback is invoked after the manifest record
+/// of the change is durable, so the listener observes the same history a recovery would replay.
+///
+/// Callbacks are invoked from whichever thread made the change, including the flush and
+/// compaction threads, and should return quickly. They are never invoked while holding the
+/// storage state or the state lock that serializes freezes, flushes and compactions.
+pub trait LsmEventListener: Send + Sync {
+    /// An immutable memtable was flushed into the SST `sst_id`, which has the same id as the
+    /// memtable.
+    fn on_flush(&self, _sst_id: usize, _first_key: &[u8], _last_key: &[u8]) {}
+
+    /// A compaction replaced the SSTs `removed` with the SSTs `added`.
+    fn on_compaction(&self, _removed: &[usize], _added: &[usize]) {}
+
+    /// The memtable `id` was frozen and a new memtable took its place.
+    fn on_memtable_freeze(&self, _id: usize) {}
+}
+
+impl fmt::Debug for dyn LsmEventListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LsmEventListener")
+    }
+}
@@ -13,9 +13,12 @@
 // limitations under the License.
 
 pub mod concat_iterator;
+pub mod loser_tree_iterator;
 pub mod merge_iterator;
 pub mod two_merge_iterator;
 
+use crate::key::KeySlice;
+
 pub trait StorageIterator {
     type KeyType<'a>: PartialEq + Eq + PartialOrd + Ord
     where
@@ -37,4 +40,17 @@ pub trait StorageIterator {
     fn num_active_iterators(&self) -> usize {
         1
     }
+
+    /// The epoch of the memtable or SST the current entry comes from, used to decide whether the
+    /// entry is deleted by a range tombstone.
+    fn epoch(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// An iterator that can be moved to another key, before or after the current one, without being
+/// recreated.
+pub trait SeekableIterator: StorageIterator {
+    /// Move to the first key-value pair which >= `key`.
+    fn seek_to_key(&mut self, key: KeySlice) -> anyhow::Result<()>;
 }
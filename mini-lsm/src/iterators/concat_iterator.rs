@@ -21,14 +21,16 @@ use crate::{
     table::{SsTable, SsTableIterator},
 };
 
-use super::StorageIterator;
+use super::{SeekableIterator, StorageIterator};
 
 /// Concat multiple iterators ordered in key order and their key ranges do not overlap. We do not want to create the
 /// iterators when initializing this iterator to reduce the overhead of seeking.
 pub struct SstConcatIterator {
     current: Option<SsTableIterator>,
     next_sst_idx: usize,
+    /// The SSTs to iterate, in descending key order if `reverse` is set.
     sstables: Vec<Arc<SsTable>>,
+    reverse: bool,
 }
 
 impl SstConcatIterator {
@@ -50,6 +52,7 @@ impl SstConcatIterator {
                 current: None,
                 next_sst_idx: 0,
                 sstables,
+                reverse: false,
             });
         }
         let mut iter = Self {
@@ -58,6 +61,7 @@ impl SstConcatIterator {
             )?),
             next_sst_idx: 1,
             sstables,
+            reverse: false,
         };
         iter.move_until_valid()?;
         Ok(iter)
@@ -65,23 +69,50 @@ impl SstConcatIterator {
 
     pub fn create_and_seek_to_key(sstables: Vec<Arc<SsTable>>, key: KeySlice) -> Result<Self> {
         Self::check_sst_valid(&sstables);
-        let idx: usize = sstables
-            .partition_point(|table| table.first_key().as_key_slice() <= key)
-            .saturating_sub(1);
-        if idx >= sstables.len() {
-            return Ok(Self {
-                current: None,
-                next_sst_idx: sstables.len(),
-                sstables,
-            });
-        }
         let mut iter = Self {
-            current: Some(SsTableIterator::create_and_seek_to_key(
-                sstables[idx].clone(),
-                key,
-            )?),
-            next_sst_idx: idx + 1,
+            current: None,
+            next_sst_idx: 0,
+            sstables,
+            reverse: false,
+        };
+        iter.seek_to_key(key)?;
+        Ok(iter)
+    }
+
+    /// Create an iterator in descending key order, seeking to the last key-value pair.
+    pub fn create_and_seek_to_last(sstables: Vec<Arc<SsTable>>) -> Result<Self> {
+        Self::check_sst_valid(&sstables);
+        Self::create_rev(sstables, |table| {
+            Ok(SsTableIterator::create_and_seek_to_last(table)?.reversed())
+        })
+    }
+
+    /// Create an iterator in descending key order, seeking to the last key-value pair which
+    /// <= `key`.
+    pub fn create_and_seek_to_key_rev(sstables: Vec<Arc<SsTable>>, key: KeySlice) -> Result<Self> {
+        Self::check_sst_valid(&sstables);
+        let mut sstables = sstables;
+        sstables
+            .truncate(sstables.partition_point(|table| table.first_key().as_key_slice() <= key));
+        Self::create_rev(sstables, |table| {
+            SsTableIterator::create_and_seek_to_key_rev(table, key)
+        })
+    }
+
+    fn create_rev(
+        mut sstables: Vec<Arc<SsTable>>,
+        seek_first: impl FnOnce(Arc<SsTable>) -> Result<SsTableIterator>,
+    ) -> Result<Self> {
+        sstables.reverse();
+        let current = match sstables.first() {
+            Some(table) => Some(seek_first(table.clone())?),
+            None => None,
+        };
+        let mut iter = Self {
+            current,
+            next_sst_idx: 1,
             sstables,
+            reverse: true,
         };
         iter.move_until_valid()?;
         Ok(iter)
@@ -95,9 +126,12 @@ impl SstConcatIterator {
             if self.next_sst_idx >= self.sstables.len() {
                 self.current = None;
             } else {
-                self.current = Some(SsTableIterator::create_and_seek_to_first(
-                    self.sstables[self.next_sst_idx].clone(),
-                )?);
+                let table = self.sstables[self.next_sst_idx].clone();
+                self.current = Some(if self.reverse {
+                    SsTableIterator::create_and_seek_to_last(table)?.reversed()
+                } else {
+                    SsTableIterator::create_and_seek_to_first(table)?
+                });
                 self.next_sst_idx += 1;
             }
         }
@@ -108,7 +142,7 @@ impl SstConcatIterator {
 impl StorageIterator for SstConcatIterator {
     type KeyType<'a> = KeySlice<'a>;
 
-    fn key(&self) -> KeySlice {
+    fn key(&self) -> KeySlice<'_> {
         self.current.as_ref().unwrap().key()
     }
 
@@ -131,7 +165,123 @@ impl StorageIterator for SstConcatIterator {
         Ok(())
     }
 
+    /// Only the SST being iterated is active, and none once the iterator is exhausted.
+    fn num_active_iterators(&self) -> usize {
+        self.current.is_some() as usize
+    }
+
+    fn epoch(&self) -> usize {
+        self.current.as_ref().unwrap().epoch()
+    }
+}
+
+impl SeekableIterator for SstConcatIterator {
+    fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        assert!(!self.reverse, "cannot seek a descending iterator");
+        let idx = self
+            .sstables
+            .partition_point(|table| table.first_key().as_key_slice() <= key)
+            .saturating_sub(1);
+        if idx >= self.sstables.len() {
+            self.current = None;
+            self.next_sst_idx = self.sstables.len();
+            return Ok(());
+        }
+        self.current = Some(SsTableIterator::create_and_seek_to_key(
+            self.sstables[idx].clone(),
+            key,
+        )?);
+        self.next_sst_idx = idx + 1;
+        self.move_until_valid()
+    }
+}
+
+/// Yields the SSTs to concatenate in key order, or `None` when there are no more.
+pub type SstProvider = Box<dyn FnMut() -> Result<Option<Arc<SsTable>>>>;
+
+/// Like [`SstConcatIterator`], but pulls the SSTs from a provider one at a time instead of holding
+/// all of them, so that only the SST being iterated is referenced by the iterator.
+pub struct LazySstConcatIterator {
+    current: Option<SsTableIterator>,
+    provider: SstProvider,
+}
+
+impl LazySstConcatIterator {
+    pub fn create_and_seek_to_first(
+        provider: impl FnMut() -> Result<Option<Arc<SsTable>>> + 'static,
+    ) -> Result<Self> {
+        let mut iter = Self {
+            current: None,
+            provider: Box::new(provider),
+        };
+        iter.move_until_valid()?;
+        Ok(iter)
+    }
+
+    /// Skip the SSTs before `key` and seek to the first key-value pair which >= `key`.
+    pub fn create_and_seek_to_key(
+        provider: impl FnMut() -> Result<Option<Arc<SsTable>>> + 'static,
+        key: KeySlice,
+    ) -> Result<Self> {
+        let mut provider: SstProvider = Box::new(provider);
+        let mut current = None;
+        while let Some(table) = provider()? {
+            if table.last_key().as_key_slice() >= key {
+                current = Some(SsTableIterator::create_and_seek_to_key(table, key)?);
+                break;
+            }
+        }
+        let mut iter = Self { current, provider };
+        iter.move_until_valid()?;
+        Ok(iter)
+    }
+
+    fn move_until_valid(&mut self) -> Result<()> {
+        loop {
+            if let Some(iter) = &self.current
+                && iter.is_valid()
+            {
+                break;
+            }
+            // Release the finished SST before asking for the next one.
+            self.current = None;
+            match (self.provider)()? {
+                Some(table) => {
+                    self.current = Some(SsTableIterator::create_and_seek_to_first(table)?);
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageIterator for LazySstConcatIterator {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn key(&self) -> KeySlice<'_> {
+        self.current.as_ref().unwrap().key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.current.as_ref().unwrap().value()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.current.is_some()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.current.as_mut().unwrap().next()?;
+        self.move_until_valid()?;
+        Ok(())
+    }
+
     fn num_active_iterators(&self) -> usize {
-        1
+        self.current.is_some() as usize
+    }
+
+    fn epoch(&self) -> usize {
+        self.current.as_ref().unwrap().epoch()
     }
 }
@@ -0,0 +1,140 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+
+use crate::key::KeySlice;
+
+use super::StorageIterator;
+
+/// Merge multiple iterators of the same type with a loser tree. It produces the same output as
+/// [`super::merge_iterator::MergeIterator`]: if the same key occurs multiple times in some
+/// iterators, prefer the one with smaller index.
+///
+/// Each internal node of the tree keeps the loser of the match between its two subtrees, so
+/// advancing the winner only needs to replay the matches on the path from its leaf to the root,
+/// which takes about `log2(n)` comparisons instead of the `2 * log2(n)` of a binary heap. This
+/// makes it a better fit for wide merges, e.g. compacting many tiers at once.
+pub struct LoserTreeIterator<I: StorageIterator> {
+    iters: Vec<Box<I>>,
+    /// `tree[0]` is the index of the winner, `tree[1..]` are the losers of the internal nodes.
+    tree: Vec<usize>,
+    /// Buffer for the key being skipped in `next`.
+    prev_key: Vec<u8>,
+}
+
+impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> LoserTreeIterator<I> {
+    pub fn create(iters: Vec<Box<I>>) -> Self {
+        let num = iters.len();
+        let mut iter = Self {
+            iters,
+            tree: vec![0; num.max(1)],
+            prev_key: Vec::new(),
+        };
+        if num > 1 {
+            // Leaf `i` is at position `num + i`; play the matches bottom-up.
+            let mut winners = vec![0; 2 * num];
+            for (idx, winner) in winners[num..].iter_mut().enumerate() {
+                *winner = idx;
+            }
+            for node in (1..num).rev() {
+                let (left, right) = (winners[2 * node], winners[2 * node + 1]);
+                if iter.beats(left, right) {
+                    winners[node] = left;
+                    iter.tree[node] = right;
+                } else {
+                    winners[node] = right;
+                    iter.tree[node] = left;
+                }
+            }
+            iter.tree[0] = winners[1];
+        }
+        iter
+    }
+
+    /// Check whether iterator `a` should be produced before iterator `b`.
+    fn beats(&self, a: usize, b: usize) -> bool {
+        let (iter_a, iter_b) = (&self.iters[a], &self.iters[b]);
+        if !iter_a.is_valid() {
+            return false;
+        }
+        if !iter_b.is_valid() {
+            return true;
+        }
+        iter_a.key().cmp(&iter_b.key()).then(a.cmp(&b)).is_lt()
+    }
+
+    /// Replay the matches from the leaf of `idx` to the root after the iterator moved.
+    fn replay(&mut self, idx: usize) {
+        let mut winner = idx;
+        let mut node = (idx + self.iters.len()) / 2;
+        while node > 0 {
+            if self.beats(self.tree[node], winner) {
+                std::mem::swap(&mut self.tree[node], &mut winner);
+            }
+            node /= 2;
+        }
+        self.tree[0] = winner;
+    }
+
+    fn current(&self) -> &I {
+        &self.iters[self.tree[0]]
+    }
+}
+
+impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIterator
+    for LoserTreeIterator<I>
+{
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn key(&self) -> KeySlice<'_> {
+        self.current().key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.current().value()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.iters.is_empty() && self.current().is_valid()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.prev_key.clear();
+        self.prev_key
+            .extend_from_slice(self.iters[self.tree[0]].key().raw_ref());
+        // Move every iterator positioned at the current key, newest first.
+        loop {
+            let winner = self.tree[0];
+            self.iters[winner].next()?;
+            self.replay(winner);
+            if !self.is_valid() || self.key().raw_ref() != self.prev_key.as_slice() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn num_active_iterators(&self) -> usize {
+        self.iters
+            .iter()
+            .filter(|x| x.is_valid())
+            .map(|x| x.num_active_iterators())
+            .sum()
+    }
+
+    fn epoch(&self) -> usize {
+        self.current().epoch()
+    }
+}
@@ -20,9 +20,10 @@ use anyhow::Result;
 
 use crate::key::KeySlice;
 
-use super::StorageIterator;
+use super::{SeekableIterator, StorageIterator};
 
-struct HeapWrapper<I: StorageIterator>(pub usize, pub Box<I>);
+/// An iterator in the heap with its index and whether keys are merged in descending order.
+struct HeapWrapper<I: StorageIterator>(pub usize, pub Box<I>, bool);
 
 impl<I: StorageIterator> PartialEq for HeapWrapper<I> {
     fn eq(&self, other: &Self) -> bool {
@@ -40,11 +41,13 @@ impl<I: StorageIterator> PartialOrd for HeapWrapper<I> {
 
 impl<I: StorageIterator> Ord for HeapWrapper<I> {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.1
-            .key()
-            .cmp(&other.1.key())
-            .then(self.0.cmp(&other.0))
-            .reverse()
+        let key_order = self.1.key().cmp(&other.1.key());
+        let key_order = if self.2 {
+            key_order.reverse()
+        } else {
+            key_order
+        };
+        key_order.then(self.0.cmp(&other.0)).reverse()
     }
 }
 
@@ -53,38 +56,48 @@ impl<I: StorageIterator> Ord for HeapWrapper<I> {
 pub struct MergeIterator<I: StorageIterator> {
     iters: BinaryHeap<HeapWrapper<I>>,
     current: Option<HeapWrapper<I>>,
+    /// The invalid iterators, kept so that seeking backward can bring them back.
+    exhausted: Vec<HeapWrapper<I>>,
+    reverse: bool,
 }
 
 impl<I: StorageIterator> MergeIterator<I> {
     pub fn create(iters: Vec<Box<I>>) -> Self {
+        Self::create_with_order(iters, false)
+    }
+
+    /// Merge iterators yielding keys in descending order, e.g. created by `scan_rev`.
+    pub fn create_rev(iters: Vec<Box<I>>) -> Self {
+        Self::create_with_order(iters, true)
+    }
+
+    fn create_with_order(iters: Vec<Box<I>>, reverse: bool) -> Self {
         if iters.is_empty() {
             return Self {
                 iters: BinaryHeap::new(),
                 current: None,
+                exhausted: Vec::new(),
+                reverse,
             };
         }
 
         let mut heap = BinaryHeap::new();
-
-        if iters.iter().all(|x| !x.is_valid()) {
-            // All invalid, select the last one as the current.
-            let mut iters = iters;
-            return Self {
-                iters: heap,
-                current: Some(HeapWrapper(0, iters.pop().unwrap())),
-            };
-        }
-
+        let mut exhausted = Vec::new();
         for (idx, iter) in iters.into_iter().enumerate() {
             if iter.is_valid() {
-                heap.push(HeapWrapper(idx, iter));
+                heap.push(HeapWrapper(idx, iter, reverse));
+            } else {
+                exhausted.push(HeapWrapper(idx, iter, reverse));
             }
         }
 
-        let current = heap.pop().unwrap();
+        // If all are invalid, select the last one as the current.
+        let current = heap.pop().or_else(|| exhausted.pop());
         Self {
             iters: heap,
-            current: Some(current),
+            current,
+            exhausted,
+            reverse,
         }
     }
 }
@@ -94,7 +107,7 @@ impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIt
 {
     type KeyType<'a> = KeySlice<'a>;
 
-    fn key(&self) -> KeySlice {
+    fn key(&self) -> KeySlice<'_> {
         self.current.as_ref().unwrap().1.key()
     }
 
@@ -114,19 +127,20 @@ impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIt
         // Pop the item out of the heap if they have the same value.
         while let Some(mut inner_iter) = self.iters.peek_mut() {
             debug_assert!(
-                inner_iter.1.key() >= current.1.key(),
+                (inner_iter.1.key() >= current.1.key()) != self.reverse
+                    || inner_iter.1.key() == current.1.key(),
                 "heap invariant violated"
             );
             if inner_iter.1.key() == current.1.key() {
                 // Case 1: an error occurred when calling `next`.
                 if let e @ Err(_) = inner_iter.1.next() {
-                    PeekMut::pop(inner_iter);
+                    self.exhausted.push(PeekMut::pop(inner_iter));
                     return e;
                 }
 
                 // Case 2: iter is no longer valid.
                 if !inner_iter.1.is_valid() {
-                    PeekMut::pop(inner_iter);
+                    self.exhausted.push(PeekMut::pop(inner_iter));
                 }
             } else {
                 break;
@@ -138,21 +152,23 @@ impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIt
         // If the current iterator is invalid, pop it out of the heap and select the next one.
         if !current.1.is_valid() {
             if let Some(iter) = self.iters.pop() {
-                *current = iter;
+                self.exhausted.push(std::mem::replace(current, iter));
             }
             return Ok(());
         }
 
         // Otherwise, compare with heap top and swap if necessary.
-        if let Some(mut inner_iter) = self.iters.peek_mut() {
-            if *current < *inner_iter {
-                std::mem::swap(&mut *inner_iter, current);
-            }
+        if let Some(mut inner_iter) = self.iters.peek_mut()
+            && *current < *inner_iter
+        {
+            std::mem::swap(&mut *inner_iter, current);
         }
 
         Ok(())
     }
 
+    /// The sum over the iterators in the heap and the current one, unless it is exhausted.
+    /// Exhausted iterators are not counted.
     fn num_active_iterators(&self) -> usize {
         self.iters
             .iter()
@@ -161,7 +177,31 @@ impl<I: 'static + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>> StorageIt
             + self
                 .current
                 .as_ref()
+                .filter(|x| x.1.is_valid())
                 .map(|x| x.1.num_active_iterators())
                 .unwrap_or(0)
     }
+
+    fn epoch(&self) -> usize {
+        self.current.as_ref().unwrap().1.epoch()
+    }
+}
+
+impl<I: 'static + SeekableIterator + for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>>
+    SeekableIterator for MergeIterator<I>
+{
+    fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        let mut iters = std::mem::take(&mut self.exhausted);
+        iters.extend(self.current.take());
+        iters.extend(std::mem::take(&mut self.iters));
+        for iter in iters.iter_mut() {
+            iter.1.seek_to_key(key)?;
+        }
+        let (valid, exhausted): (Vec<_>, Vec<_>) =
+            iters.into_iter().partition(|iter| iter.1.is_valid());
+        self.iters = valid.into();
+        self.exhausted = exhausted;
+        self.current = self.iters.pop().or_else(|| self.exhausted.pop());
+        Ok(())
+    }
 }
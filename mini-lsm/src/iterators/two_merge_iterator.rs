@@ -14,7 +14,9 @@
 
 use anyhow::Result;
 
-use super::StorageIterator;
+use crate::key::KeySlice;
+
+use super::{SeekableIterator, StorageIterator};
 
 /// Merges two iterators of different types into one. If the two iterators have the same key, only
 /// produce the key once and prefer the entry from A.
@@ -22,6 +24,8 @@ pub struct TwoMergeIterator<A: StorageIterator, B: StorageIterator> {
     a: A,
     b: B,
     choose_a: bool,
+    /// Whether keys are merged in descending order.
+    reverse: bool,
 }
 
 impl<
@@ -29,14 +33,18 @@ impl<
     B: 'static + for<'a> StorageIterator<KeyType<'a> = A::KeyType<'a>>,
 > TwoMergeIterator<A, B>
 {
-    fn choose_a(a: &A, b: &B) -> bool {
+    fn choose_a(a: &A, b: &B, reverse: bool) -> bool {
         if !a.is_valid() {
             return false;
         }
         if !b.is_valid() {
             return true;
         }
-        a.key() < b.key()
+        if reverse {
+            a.key() > b.key()
+        } else {
+            a.key() < b.key()
+        }
     }
 
     fn skip_b(&mut self) -> Result<()> {
@@ -47,13 +55,23 @@ impl<
     }
 
     pub fn create(a: A, b: B) -> Result<Self> {
+        Self::create_with_order(a, b, false)
+    }
+
+    /// Merge two iterators yielding keys in descending order, e.g. created by `scan_rev`.
+    pub fn create_rev(a: A, b: B) -> Result<Self> {
+        Self::create_with_order(a, b, true)
+    }
+
+    fn create_with_order(a: A, b: B, reverse: bool) -> Result<Self> {
         let mut iter = Self {
             choose_a: false,
             a,
             b,
+            reverse,
         };
         iter.skip_b()?;
-        iter.choose_a = Self::choose_a(&iter.a, &iter.b);
+        iter.choose_a = Self::choose_a(&iter.a, &iter.b, reverse);
         Ok(iter)
     }
 }
@@ -96,11 +114,33 @@ impl<
             self.b.next()?;
         }
         self.skip_b()?;
-        self.choose_a = Self::choose_a(&self.a, &self.b);
+        self.choose_a = Self::choose_a(&self.a, &self.b, self.reverse);
         Ok(())
     }
 
     fn num_active_iterators(&self) -> usize {
         self.a.num_active_iterators() + self.b.num_active_iterators()
     }
+
+    fn epoch(&self) -> usize {
+        if self.choose_a {
+            self.a.epoch()
+        } else {
+            self.b.epoch()
+        }
+    }
+}
+
+impl<
+    A: 'static + SeekableIterator,
+    B: 'static + SeekableIterator + for<'a> StorageIterator<KeyType<'a> = A::KeyType<'a>>,
+> SeekableIterator for TwoMergeIterator<A, B>
+{
+    fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        self.a.seek_to_key(key)?;
+        self.b.seek_to_key(key)?;
+        self.skip_b()?;
+        self.choose_a = Self::choose_a(&self.a, &self.b, self.reverse);
+        Ok(())
+    }
 }
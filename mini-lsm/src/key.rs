@@ -57,6 +57,11 @@ impl Key<Vec<u8>> {
         self.0.clear()
     }
 
+    /// Keep only the first `len` bytes of the key.
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len)
+    }
+
     /// Append a slice to the end of the key
     pub fn append(&mut self, data: &[u8]) {
         self.0.extend(data)
@@ -68,7 +73,7 @@ impl Key<Vec<u8>> {
         self.0.extend(key_slice.0);
     }
 
-    pub fn as_key_slice(&self) -> KeySlice {
+    pub fn as_key_slice(&self) -> KeySlice<'_> {
         Key(self.0.as_slice())
     }
 
@@ -91,7 +96,7 @@ impl Key<Vec<u8>> {
 }
 
 impl Key<Bytes> {
-    pub fn as_key_slice(&self) -> KeySlice {
+    pub fn as_key_slice(&self) -> KeySlice<'_> {
         Key(&self.0)
     }
 
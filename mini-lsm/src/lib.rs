@@ -15,6 +15,9 @@
 pub mod block;
 pub mod compact;
 pub mod debug;
+pub mod describe;
+pub mod error;
+pub mod event_listener;
 pub mod iterators;
 pub mod key;
 pub mod lsm_iterator;
@@ -22,7 +25,12 @@ pub mod lsm_storage;
 pub mod manifest;
 pub mod mem_table;
 pub mod mvcc;
+pub mod pinned_ssts;
+pub mod range_tombstone;
+pub mod rate_limiter;
+pub mod stats;
 pub mod table;
+pub mod ttl;
 pub mod wal;
 
 #[cfg(test)]
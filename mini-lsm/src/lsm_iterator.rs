@@ -13,16 +13,22 @@
 // limitations under the License.
 
 use std::ops::Bound;
+use std::sync::Arc;
 
 use anyhow::{Result, bail};
 use bytes::Bytes;
 
-use crate::iterators::StorageIterator;
+use crate::error::LsmError;
 use crate::iterators::concat_iterator::SstConcatIterator;
 use crate::iterators::merge_iterator::MergeIterator;
 use crate::iterators::two_merge_iterator::TwoMergeIterator;
+use crate::iterators::{SeekableIterator, StorageIterator};
+use crate::key::KeySlice;
 use crate::mem_table::MemTableIterator;
+use crate::pinned_ssts::SstPinGuard;
+use crate::range_tombstone::{RangeTombstone, is_shadowed};
 use crate::table::SsTableIterator;
+use crate::ttl::{decode_value, live_value, now_millis};
 
 /// Represents the internal type for an LSM iterator. This type will be changed across the course for multiple times.
 type LsmIteratorInner = TwoMergeIterator<
@@ -32,37 +38,122 @@ type LsmIteratorInner = TwoMergeIterator<
 
 pub struct LsmIterator {
     inner: LsmIteratorInner,
+    /// The lower bound of a scan in ascending order, which `seek` does not move before.
+    start_bound: Bound<Bytes>,
+    /// The upper bound, or the lower bound if `reverse` is set.
     end_bound: Bound<Bytes>,
     is_valid: bool,
+    range_tombstones: Arc<Vec<RangeTombstone>>,
+    /// Whether the keys are iterated in descending order.
+    reverse: bool,
+    /// The time the scan is created at, as milliseconds since the Unix epoch. Values expired by
+    /// then are skipped.
+    now: u64,
+    /// Keeps the files of the SSTs being read from removal until the iterator is dropped.
+    pins: Option<Arc<SstPinGuard>>,
 }
 
 impl LsmIterator {
-    pub(crate) fn new(iter: LsmIteratorInner, end_bound: Bound<Bytes>) -> Result<Self> {
+    pub(crate) fn new(
+        iter: LsmIteratorInner,
+        start_bound: Bound<Bytes>,
+        end_bound: Bound<Bytes>,
+        range_tombstones: Arc<Vec<RangeTombstone>>,
+    ) -> Result<Self> {
         let mut iter = Self {
-            is_valid: iter.is_valid(),
+            is_valid: false,
             inner: iter,
+            start_bound,
             end_bound,
+            range_tombstones,
+            reverse: false,
+            now: now_millis(),
+            pins: None,
         };
+        iter.update_valid();
         iter.move_to_non_delete()?;
         Ok(iter)
     }
 
-    fn next_inner(&mut self) -> Result<()> {
-        self.inner.next()?;
+    /// Keep the SSTs pinned by `pins` from removal until the iterator is dropped.
+    pub(crate) fn with_pins(mut self, pins: Option<Arc<SstPinGuard>>) -> Self {
+        self.pins = pins;
+        self
+    }
+
+    /// Create an iterator over an inner iterator in descending key order, stopping at
+    /// `lower_bound`.
+    pub(crate) fn new_rev(
+        iter: LsmIteratorInner,
+        lower_bound: Bound<Bytes>,
+        range_tombstones: Arc<Vec<RangeTombstone>>,
+    ) -> Result<Self> {
+        let mut iter = Self {
+            is_valid: false,
+            inner: iter,
+            start_bound: Bound::Unbounded,
+            end_bound: lower_bound,
+            range_tombstones,
+            reverse: true,
+            now: now_millis(),
+            pins: None,
+        };
+        iter.update_valid();
+        iter.move_to_non_delete()?;
+        Ok(iter)
+    }
+
+    /// Move to the first key >= `key` within the bounds of the scan. The key can be before the
+    /// current position. Only supported on scans in ascending order.
+    pub fn seek(&mut self, key: &[u8]) -> Result<()> {
+        if self.reverse {
+            bail!("cannot seek a scan in descending order");
+        }
+        let target = match self.start_bound.as_ref() {
+            Bound::Included(lower) | Bound::Excluded(lower) if key <= lower.as_ref() => lower,
+            _ => key,
+        };
+        self.inner.seek_to_key(KeySlice::from_slice(target))?;
+        if let Bound::Excluded(lower) = &self.start_bound
+            && self.inner.is_valid()
+            && self.inner.key().raw_ref() == lower.as_ref()
+        {
+            self.inner.next()?;
+        }
+        self.update_valid();
+        self.move_to_non_delete()
+    }
+
+    fn update_valid(&mut self) {
         if !self.inner.is_valid() {
             self.is_valid = false;
-            return Ok(());
-        }
-        match self.end_bound.as_ref() {
-            Bound::Unbounded => {}
-            Bound::Included(key) => self.is_valid = self.inner.key().raw_ref() <= key.as_ref(),
-            Bound::Excluded(key) => self.is_valid = self.inner.key().raw_ref() < key.as_ref(),
+            return;
         }
+        let key = self.inner.key().raw_ref();
+        self.is_valid = match (self.end_bound.as_ref(), self.reverse) {
+            (Bound::Unbounded, _) => true,
+            (Bound::Included(end), false) => key <= end.as_ref(),
+            (Bound::Excluded(end), false) => key < end.as_ref(),
+            (Bound::Included(end), true) => key >= end.as_ref(),
+            (Bound::Excluded(end), true) => key > end.as_ref(),
+        };
+    }
+
+    fn next_inner(&mut self) -> Result<()> {
+        self.inner.next()?;
+        self.update_valid();
         Ok(())
     }
 
     fn move_to_non_delete(&mut self) -> Result<()> {
-        while self.is_valid() && self.inner.value().is_empty() {
+        while self.is_valid()
+            && (live_value(self.inner.value(), self.now).is_none()
+                || is_shadowed(
+                    &self.range_tombstones,
+                    self.inner.key().raw_ref(),
+                    self.inner.epoch(),
+                ))
+        {
             self.next_inner()?;
         }
         Ok(())
@@ -81,7 +172,7 @@ impl StorageIterator for LsmIterator {
     }
 
     fn value(&self) -> &[u8] {
-        self.inner.value()
+        decode_value(self.inner.value()).0
     }
 
     fn next(&mut self) -> Result<()> {
@@ -97,10 +188,13 @@ impl StorageIterator for LsmIterator {
 
 /// A wrapper around existing iterator, will prevent users from calling `next` when the iterator is
 /// invalid. If an iterator is already invalid, `next` does not do anything. If `next` returns an error,
-/// `is_valid` should return false, and `next` should always return an error.
+/// `is_valid` should return false, and `next` should always return an error. Errors are returned as
+/// [`LsmError`]s wrapped in `anyhow::Error`. The first error is kept and can be retrieved with
+/// `take_error`.
 pub struct FusedIterator<I: StorageIterator> {
     iter: I,
     has_errored: bool,
+    error: Option<LsmError>,
 }
 
 impl<I: StorageIterator> FusedIterator<I> {
@@ -108,8 +202,22 @@ impl<I: StorageIterator> FusedIterator<I> {
         Self {
             iter,
             has_errored: false,
+            error: None,
         }
     }
+
+    /// Convert the error of the inner iterator to an [`LsmError`] and keep a copy of it.
+    fn record_error(&mut self, e: anyhow::Error) -> anyhow::Error {
+        let e = LsmError::from(e);
+        self.has_errored = true;
+        self.error = Some(e.clone());
+        e.into()
+    }
+
+    /// Take the error that tainted the iterator, if any.
+    pub fn take_error(&mut self) -> Option<LsmError> {
+        self.error.take()
+    }
 }
 
 impl<I: StorageIterator> StorageIterator for FusedIterator<I> {
@@ -139,13 +247,12 @@ impl<I: StorageIterator> StorageIterator for FusedIterator<I> {
     fn next(&mut self) -> Result<()> {
         // only move when the iterator is valid and not errored
         if self.has_errored {
-            bail!("the iterator is tainted");
+            bail!(tainted_error());
         }
-        if self.iter.is_valid() {
-            if let Err(e) = self.iter.next() {
-                self.has_errored = true;
-                return Err(e);
-            }
+        if self.iter.is_valid()
+            && let Err(e) = self.iter.next()
+        {
+            return Err(self.record_error(e));
         }
         Ok(())
     }
@@ -154,3 +261,203 @@ impl<I: StorageIterator> StorageIterator for FusedIterator<I> {
         self.iter.num_active_iterators()
     }
 }
+
+impl FusedIterator<LsmIterator> {
+    /// Reposition the scan, see [`LsmIterator::seek`]. Unlike `next`, this also works after the
+    /// iterator is exhausted.
+    pub fn seek(&mut self, key: &[u8]) -> Result<()> {
+        if self.has_errored {
+            bail!(tainted_error());
+        }
+        if let Err(e) = self.iter.seek(key) {
+            return Err(self.record_error(e));
+        }
+        Ok(())
+    }
+}
+
+/// The error of using an iterator after it returned an error.
+fn tainted_error() -> LsmError {
+    LsmError::Other("the iterator is tainted".to_string())
+}
+
+impl IntoIterator for FusedIterator<LsmIterator> {
+    type Item = Result<(Bytes, Bytes), LsmError>;
+    type IntoIter = ScanIter;
+
+    fn into_iter(self) -> ScanIter {
+        ScanIter {
+            iter: self,
+            started: false,
+            finished: false,
+        }
+    }
+}
+
+/// Yields the key-value pairs of a scan as owned copies, for use with `for` loops and iterator
+/// adapters. Stops after the scan is exhausted or after yielding the first error.
+pub struct ScanIter {
+    iter: FusedIterator<LsmIterator>,
+    /// Whether the current entry of `iter` is already yielded.
+    started: bool,
+    finished: bool,
+}
+
+impl Iterator for ScanIter {
+    type Item = Result<(Bytes, Bytes), LsmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.started {
+            if let Err(e) = self.iter.next() {
+                self.finished = true;
+                return Some(Err(e.into()));
+            }
+        } else {
+            self.started = true;
+            if self.iter.has_errored {
+                self.finished = true;
+                return Some(Err(self.iter.take_error().unwrap_or_else(tainted_error)));
+            }
+        }
+        if !self.iter.is_valid() {
+            self.finished = true;
+            return None;
+        }
+        Some(Ok((
+            Bytes::copy_from_slice(self.iter.key()),
+            Bytes::copy_from_slice(self.iter.value()),
+        )))
+    }
+}
+
+impl std::iter::FusedIterator for ScanIter {}
+
+/// Adapts a scan of one database to the key type expected by [`MergeIterator`].
+pub struct ShardIterator(FusedIterator<LsmIterator>);
+
+impl StorageIterator for ShardIterator {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn is_valid(&self) -> bool {
+        self.0.is_valid()
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        KeySlice::from_slice(self.0.key())
+    }
+
+    fn value(&self) -> &[u8] {
+        self.0.value()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.0.next()
+    }
+
+    fn num_active_iterators(&self) -> usize {
+        self.0.num_active_iterators()
+    }
+}
+
+/// Merges the scans of several databases, e.g. the shards of a key space, into one scan in key
+/// order. If the same key exists in multiple databases, the one from the scan with the smaller
+/// index wins.
+pub struct MergedScan {
+    inner: MergeIterator<ShardIterator>,
+}
+
+impl MergedScan {
+    pub fn new(iters: Vec<FusedIterator<LsmIterator>>) -> Self {
+        Self {
+            inner: MergeIterator::create(
+                iters
+                    .into_iter()
+                    .map(|iter| Box::new(ShardIterator(iter)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl StorageIterator for MergedScan {
+    type KeyType<'a> = &'a [u8];
+
+    fn is_valid(&self) -> bool {
+        self.inner.is_valid()
+    }
+
+    fn key(&self) -> &[u8] {
+        self.inner.key().raw_ref()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.inner.value()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.inner.next()
+    }
+
+    fn num_active_iterators(&self) -> usize {
+        self.inner.num_active_iterators()
+    }
+}
+
+/// Iterates over the entries of a single SST within a range, including deletes as empty values and
+/// expired entries. The values are decoded from the encoding of [`crate::ttl`], without checking
+/// their expiry.
+pub struct SstRangeIterator {
+    inner: SsTableIterator,
+    end_bound: Bound<Bytes>,
+    is_valid: bool,
+}
+
+impl SstRangeIterator {
+    pub(crate) fn new(inner: SsTableIterator, end_bound: Bound<Bytes>) -> Self {
+        let mut iter = Self {
+            inner,
+            end_bound,
+            is_valid: false,
+        };
+        iter.update_valid();
+        iter
+    }
+
+    fn update_valid(&mut self) {
+        self.is_valid = self.inner.is_valid()
+            && match self.end_bound.as_ref() {
+                Bound::Unbounded => true,
+                Bound::Included(key) => self.inner.key().raw_ref() <= key.as_ref(),
+                Bound::Excluded(key) => self.inner.key().raw_ref() < key.as_ref(),
+            };
+    }
+}
+
+impl StorageIterator for SstRangeIterator {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    fn key(&self) -> KeySlice<'_> {
+        self.inner.key()
+    }
+
+    fn value(&self) -> &[u8] {
+        decode_value(self.inner.value()).0
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.inner.next()?;
+        self.update_valid();
+        Ok(())
+    }
+
+    fn epoch(&self) -> usize {
+        self.inner.epoch()
+    }
+}
@@ -12,34 +12,51 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
 use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
-use std::ops::Bound;
+use std::ops::{Bound, RangeBounds};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use bytes::Bytes;
-use parking_lot::{Mutex, MutexGuard, RwLock};
+use parking_lot::{Condvar, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
 
-use crate::block::Block;
+use crate::block::{Block, DEFAULT_RESTART_INTERVAL, MAX_ENTRY_FIELD_LEN};
 use crate::compact::{
-    CompactionController, CompactionOptions, LeveledCompactionController, LeveledCompactionOptions,
+    CompactionController, CompactionOptions, CompactionPlan, CompactionTask,
+    LeveledCompactionController, LeveledCompactionOptions, RunningCompactions,
     SimpleLeveledCompactionController, SimpleLeveledCompactionOptions, TieredCompactionController,
+    panic_message,
 };
+use crate::error::LsmError;
+use crate::event_listener::LsmEventListener;
 use crate::iterators::StorageIterator;
 use crate::iterators::concat_iterator::SstConcatIterator;
 use crate::iterators::merge_iterator::MergeIterator;
 use crate::iterators::two_merge_iterator::TwoMergeIterator;
 use crate::key::KeySlice;
-use crate::lsm_iterator::{FusedIterator, LsmIterator};
-use crate::manifest::{Manifest, ManifestRecord};
+use crate::lsm_iterator::{FusedIterator, LsmIterator, SstRangeIterator};
+use crate::manifest::{Manifest, ManifestRecord, ManifestSnapshot};
 use crate::mem_table::{MemTable, map_bound};
 use crate::mvcc::LsmMvccInner;
-use crate::table::{FileObject, SsTable, SsTableBuilder, SsTableIterator};
+use crate::pinned_ssts::{PinnedSsts, SstPinGuard};
+use crate::range_tombstone::{RangeTombstone, is_shadowed};
+use crate::rate_limiter::RateLimiter;
+use crate::stats::{LsmStats, LsmStatsSnapshot};
+use crate::table::{
+    BloomLoad, CompressionType, FileObject, SsTable, SsTableBuilder, SsTableIterator,
+};
+use crate::ttl::{
+    encode_value, encode_value_with_expiry, expire_at, live_value, live_value_bytes, now_millis,
+};
 
-pub type BlockCache = moka::sync::Cache<(usize, usize), Arc<Block>>;
+/// Keyed by `(cache namespace, SST id, block index)`. Every opened SST gets a unique namespace, so
+/// that an SST id recycled later or used by another instance sharing the cache never aliases.
+pub type BlockCache = moka::sync::Cache<(usize, usize, usize), Arc<Block>>;
 
 /// Represents the state of the storage engine.
 #[derive(Clone)]
@@ -57,6 +74,29 @@ pub struct LsmStorageState {
     pub sstables: HashMap<usize, Arc<SsTable>>,
 }
 
+/// Where the latest version of a key resides, see [`MiniLsm::locate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLocation {
+    ActiveMemtable,
+    /// Index into the immutable memtables, 0 being the latest one.
+    ImmMemtable(usize),
+    /// Id of the L0 SST.
+    L0(usize),
+    /// Level (or tier) id and SST id.
+    Level(usize, usize),
+}
+
+/// Statistics of memtable flushes since the engine was opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlushStats {
+    /// Number of memtables flushed.
+    pub flushes: u64,
+    /// Total size of the SSTs produced by flushes.
+    pub bytes_flushed: u64,
+    /// How long the latest flush took.
+    pub last_flush_duration: Duration,
+}
+
 pub enum WriteBatchRecord<T: AsRef<[u8]>> {
     Put(T, T),
     Del(T),
@@ -93,9 +133,105 @@ pub struct LsmStorageOptions {
     pub num_memtable_limit: usize,
     pub compaction_options: CompactionOptions,
     pub enable_wal: bool,
+    /// Unused by this engine, which has no transactions, see [`LsmStorageInner::new_txn`].
+    /// Serializable transactions are implemented by the `mini-lsm-mvcc` crate.
     pub serializable: bool,
+    /// Reject puts with [`LsmError::OutOfSpace`] once the SSTs in the bottom level reach this
+    /// size in bytes. Deletes are still accepted so that space can be reclaimed.
+    pub max_db_size_bytes: Option<u64>,
+    /// Directory for the WAL files, e.g. on a device with lower fsync latency. Defaults to the
+    /// database directory, where SSTs and the manifest are always stored.
+    pub wal_dir: Option<PathBuf>,
+    /// Block size of the SSTs compacted into L1, L2, ... in order, e.g. larger blocks for the
+    /// colder data in deeper levels. Levels not listed here use `block_size`.
+    pub level_block_sizes: Vec<usize>,
+    /// Once the flushed sorted runs waiting for compaction (L0 SSTs, or tiers in tiered
+    /// compaction) reach this number, the flush thread keeps up to twice `num_memtable_limit`
+    /// immutable memtables in memory before flushing, giving compaction time to catch up.
+    pub flush_slowdown_sorted_runs: Option<usize>,
+    /// Reject puts of keys that already exist with [`LsmError::AlreadyExists`], for append-only
+    /// workloads. Every put then goes through the read path first, and all writes are serialized
+    /// so that the check cannot race with another write, which makes writes much slower.
+    pub reject_overwrites: bool,
+    /// Maximum number of immutable memtables flushed, oldest first, each time the flush thread
+    /// finds at least `num_memtable_limit` of them.
+    pub flush_batch_size: usize,
+    /// Block writes while this many immutable memtables are waiting to be flushed, until the
+    /// flush thread makes room, so that a write storm cannot grow memory without bound. Should be
+    /// above `num_memtable_limit`, e.g. twice of it. Only enforced while the flush thread of
+    /// [`MiniLsm`] runs.
+    pub max_imm_memtables: Option<usize>,
+    /// Name of the key ordering, persisted when the database is created and checked by every
+    /// `open` afterwards, so that a database is never read with an ordering other than the one its
+    /// SSTs were written with. Only [`DEFAULT_COMPARATOR`] is implemented for now; other names
+    /// are rejected.
+    pub comparator_name: String,
+    /// Bytes of WAL writes buffered in memory before they are written to the file. Larger buffers
+    /// mean fewer writes between syncs.
+    pub wal_buffer_size: usize,
+    /// Flush a memtable directly to the lowest level where neither that level nor anything above
+    /// it overlaps with the memtable, instead of L0, saving the compactions of sorted inserts.
+    /// Only takes effect with leveled compaction.
+    pub flush_to_lowest_disjoint_level: bool,
+    /// Flush all memtables on `close`, also with WAL enabled, directly into the lowest levels
+    /// they are disjoint with, so that the next open neither replays WALs nor starts with L0 SSTs
+    /// to compact. Ignored with tiered compaction.
+    pub flush_to_level_on_close: bool,
+    /// Append a checksum of the whole file to every SST written, which is checked by
+    /// `verify_sst_files`.
+    pub sst_file_checksum: bool,
+    /// Never compact in the background, compaction is then only done by explicit calls to
+    /// `run_one_compaction`, e.g. to make tests deterministic.
+    pub disable_background_compaction: bool,
+    /// Whether the bloom filters of the SSTs found by `open` are read right away or by the first
+    /// lookup, see [`BloomLoad`]. SSTs written afterwards always keep their bloom filters in memory.
+    pub bloom_load: BloomLoad,
+    /// Rewrite the manifest as a single snapshot of the current state once it has this many
+    /// records, bounding the time `open` spends replaying it. `None` only compacts it on `close`.
+    pub manifest_compaction_threshold: Option<usize>,
+    /// How the data blocks of the SSTs written are compressed. SSTs keep the compression they
+    /// were written with, so changing it only affects new SSTs.
+    pub compression: CompressionType,
+    /// Memory-map the SSTs for reads instead of reading each block with `pread` into a new buffer.
+    pub mmap_reads: bool,
+    /// Every this many entries, a data block stores the full key instead of the part not shared
+    /// with the previous key. Smaller intervals make seeks within a block faster, larger ones make
+    /// blocks smaller.
+    pub block_restart_interval: usize,
+    /// Maximum number of blocks kept in the block cache. 0 disables the block cache, so that every
+    /// block is read from the SST files.
+    pub block_cache_capacity: usize,
+    /// Evict a block from the block cache once it has not been read for this long.
+    pub block_cache_idle_ttl: Option<Duration>,
+    /// Limit the bytes of SSTs written by compactions per second, so that compactions leave
+    /// enough disk bandwidth for flushes. All compactions share the budget.
+    pub compaction_rate_limit_bytes_per_sec: Option<u64>,
+    /// Number of background threads running compactions. Compactions only run at the same time
+    /// on disjoint levels, so more than 1 only helps leveled and simple leveled compaction.
+    pub max_background_compactions: usize,
+    /// Number of threads building the output SSTs of a compaction, each over a disjoint part of
+    /// the key space. 1 builds them in the compaction thread.
+    pub compaction_parallelism: usize,
+    /// Store values larger than this many bytes out of line in the SSTs instead of in the data
+    /// blocks, which lifts the limit of [`MAX_ENTRY_FIELD_LEN`] bytes per value to `u32::MAX`.
+    /// Must not exceed [`MAX_ENTRY_FIELD_LEN`]. `None` keeps every value in the data blocks.
+    pub large_value_threshold: Option<usize>,
+    /// Notified of every flush, compaction and memtable freeze, see [`LsmEventListener`].
+    pub event_listener: Option<Arc<dyn LsmEventListener>>,
+    /// Move the SST and WAL files that `open` finds unreferenced by the manifest to a
+    /// [`LOST_FOUND_DIR`] subdirectory for inspection, instead of deleting them.
+    pub move_orphan_files_to_lost_found: bool,
 }
 
+/// The comparator ordering keys bytewise.
+pub const DEFAULT_COMPARATOR: &str = "bytewise";
+
+/// Number of blocks in the block cache by default, i.e., 4GB of 4KB blocks.
+pub const DEFAULT_BLOCK_CACHE_CAPACITY: usize = 1 << 20;
+
+/// Number of locks the keys of conditional writes are spread over.
+const NUM_KEY_LOCKS: usize = 64;
+
 impl LsmStorageOptions {
     pub fn default_for_week1_test() -> Self {
         Self {
@@ -105,33 +241,255 @@ impl LsmStorageOptions {
             enable_wal: false,
             num_memtable_limit: 50,
             serializable: false,
+            max_db_size_bytes: None,
+            wal_dir: None,
+            level_block_sizes: Vec::new(),
+            flush_slowdown_sorted_runs: None,
+            reject_overwrites: false,
+            flush_batch_size: 1,
+            max_imm_memtables: None,
+            comparator_name: DEFAULT_COMPARATOR.to_string(),
+            wal_buffer_size: 8 << 10,
+            flush_to_lowest_disjoint_level: false,
+            flush_to_level_on_close: false,
+            sst_file_checksum: false,
+            disable_background_compaction: false,
+            bloom_load: BloomLoad::Eager,
+            compression: CompressionType::None,
+            manifest_compaction_threshold: Some(1000),
+            mmap_reads: false,
+            block_restart_interval: DEFAULT_RESTART_INTERVAL,
+            block_cache_capacity: DEFAULT_BLOCK_CACHE_CAPACITY,
+            block_cache_idle_ttl: None,
+            compaction_rate_limit_bytes_per_sec: None,
+            max_background_compactions: 1,
+            compaction_parallelism: 1,
+            large_value_threshold: None,
+            event_listener: None,
+            move_orphan_files_to_lost_found: false,
+        }
+    }
+
+    /// Block size of the SSTs written to `level`, where `None` stands for L0 and tiered compaction.
+    pub fn block_size_for_level(&self, level: Option<usize>) -> usize {
+        level
+            .and_then(|level| self.level_block_sizes.get(level.checked_sub(1)?))
+            .copied()
+            .unwrap_or(self.block_size)
+    }
+
+    /// Reject options that are almost always a misconfiguration.
+    pub fn validate(&self) -> Result<()> {
+        if self
+            .max_imm_memtables
+            .is_some_and(|max| max < self.num_memtable_limit)
+        {
+            return Err(LsmError::InvalidOptions(
+                "max_imm_memtables must be at least num_memtable_limit".to_string(),
+            )
+            .into());
+        }
+        if self.comparator_name != DEFAULT_COMPARATOR {
+            return Err(LsmError::InvalidOptions(format!(
+                "unsupported comparator {:?}",
+                self.comparator_name
+            ))
+            .into());
         }
+        if self.flush_batch_size == 0 {
+            return Err(
+                LsmError::InvalidOptions("flush_batch_size must be positive".to_string()).into(),
+            );
+        }
+        if self.max_background_compactions == 0 {
+            return Err(LsmError::InvalidOptions(
+                "max_background_compactions must be positive".to_string(),
+            )
+            .into());
+        }
+        if self.compaction_parallelism == 0 {
+            return Err(LsmError::InvalidOptions(
+                "compaction_parallelism must be positive".to_string(),
+            )
+            .into());
+        }
+        if self.compaction_rate_limit_bytes_per_sec == Some(0) {
+            return Err(LsmError::InvalidOptions(
+                "compaction_rate_limit_bytes_per_sec must be positive".to_string(),
+            )
+            .into());
+        }
+        if self
+            .large_value_threshold
+            .is_some_and(|threshold| threshold > MAX_ENTRY_FIELD_LEN)
+        {
+            return Err(LsmError::InvalidOptions(format!(
+                "large_value_threshold must not exceed {}",
+                MAX_ENTRY_FIELD_LEN
+            ))
+            .into());
+        }
+        if self.block_restart_interval == 0 {
+            return Err(LsmError::InvalidOptions(
+                "block_restart_interval must be positive".to_string(),
+            )
+            .into());
+        }
+        // Without compaction, `target_sst_size` only limits the size of memtables. Otherwise
+        // compaction would cut a new SST after every block.
+        if !matches!(self.compaction_options, CompactionOptions::NoCompaction) {
+            let max_block_size = self
+                .level_block_sizes
+                .iter()
+                .copied()
+                .fold(self.block_size, usize::max);
+            if self.target_sst_size < max_block_size {
+                return Err(LsmError::InvalidOptions(format!(
+                    "target_sst_size ({}) is smaller than the block size ({})",
+                    self.target_sst_size, max_block_size
+                ))
+                .into());
+            }
+        }
+        Ok(())
     }
 
     pub fn default_for_week1_day6_test() -> Self {
         Self {
-            block_size: 4096,
-            target_sst_size: 2 << 20,
-            compaction_options: CompactionOptions::NoCompaction,
-            enable_wal: false,
             num_memtable_limit: 2,
-            serializable: false,
+            ..Self::default_for_week1_test()
         }
     }
 
     pub fn default_for_week2_test(compaction_options: CompactionOptions) -> Self {
         Self {
-            block_size: 4096,
             target_sst_size: 1 << 20, // 1MB
             compaction_options,
-            enable_wal: false,
             num_memtable_limit: 2,
-            serializable: false,
+            ..Self::default_for_week1_test()
+        }
+    }
+
+    /// Start building options from the defaults of [`LsmStorageOptionsBuilder`].
+    pub fn builder() -> LsmStorageOptionsBuilder {
+        LsmStorageOptionsBuilder::new()
+    }
+}
+
+/// Builds [`LsmStorageOptions`], filling every option not set with a default: 4KB blocks, 2MB
+/// SSTs, up to 3 memtables in memory, no compaction and WAL enabled. Options without a setter
+/// keep the defaults of [`LsmStorageOptions::default_for_week1_test`].
+///
+/// ```
+/// use mini_lsm::compact::{CompactionOptions, LeveledCompactionOptions};
+/// use mini_lsm::lsm_storage::{LsmStorageOptions, MiniLsm};
+///
+/// let options = LsmStorageOptions::builder()
+///     .block_size(4096)
+///     .target_sst_size(2 << 20)
+///     .compaction(CompactionOptions::Leveled(LeveledCompactionOptions {
+///         level_size_multiplier: 10,
+///         level0_file_num_compaction_trigger: 4,
+///         max_levels: 4,
+///         base_level_size_mb: 128,
+///     }))
+///     .build();
+/// let dir = tempfile::tempdir()?;
+/// let storage = MiniLsm::open(dir.path(), options)?;
+/// storage.put(b"key", b"value")?;
+/// assert_eq!(storage.get(b"key")?.as_deref(), Some(&b"value"[..]));
+/// storage.close()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct LsmStorageOptionsBuilder {
+    options: LsmStorageOptions,
+}
+
+impl LsmStorageOptionsBuilder {
+    pub fn new() -> Self {
+        Self {
+            options: LsmStorageOptions {
+                block_size: 4096,
+                target_sst_size: 2 << 20,
+                num_memtable_limit: 3,
+                compaction_options: CompactionOptions::NoCompaction,
+                enable_wal: true,
+                serializable: false,
+                ..LsmStorageOptions::default_for_week1_test()
+            },
         }
     }
+
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.options.block_size = block_size;
+        self
+    }
+
+    pub fn target_sst_size(mut self, target_sst_size: usize) -> Self {
+        self.options.target_sst_size = target_sst_size;
+        self
+    }
+
+    pub fn num_memtable_limit(mut self, num_memtable_limit: usize) -> Self {
+        self.options.num_memtable_limit = num_memtable_limit;
+        self
+    }
+
+    pub fn compaction(mut self, compaction_options: CompactionOptions) -> Self {
+        self.options.compaction_options = compaction_options;
+        self
+    }
+
+    pub fn enable_wal(mut self, enable_wal: bool) -> Self {
+        self.options.enable_wal = enable_wal;
+        self
+    }
+
+    pub fn serializable(mut self, serializable: bool) -> Self {
+        self.options.serializable = serializable;
+        self
+    }
+
+    pub fn block_cache_capacity(mut self, block_cache_capacity: usize) -> Self {
+        self.options.block_cache_capacity = block_cache_capacity;
+        self
+    }
+
+    pub fn block_cache_idle_ttl(mut self, block_cache_idle_ttl: Option<Duration>) -> Self {
+        self.options.block_cache_idle_ttl = block_cache_idle_ttl;
+        self
+    }
+
+    pub fn build(self) -> LsmStorageOptions {
+        self.options
+    }
+}
+
+impl Default for LsmStorageOptionsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Create the block cache configured by `options`.
+fn create_block_cache(options: &LsmStorageOptions) -> Arc<BlockCache> {
+    let mut builder = BlockCache::builder().max_capacity(options.block_cache_capacity as u64);
+    if let Some(ttl) = options.block_cache_idle_ttl {
+        builder = builder.time_to_idle(ttl);
+    }
+    Arc::new(builder.build())
 }
 
-fn range_overlap(
+/// Open an SST file, memory-mapped if `mmap_reads` is set.
+fn open_sst_file(path: &Path, options: &LsmStorageOptions) -> Result<FileObject> {
+    if options.mmap_reads {
+        FileObject::open_mmap(path)
+    } else {
+        FileObject::open(path)
+    }
+}
+
+pub(crate) fn range_overlap(
     user_begin: Bound<&[u8]>,
     user_end: Bound<&[u8]>,
     table_begin: KeySlice,
@@ -162,9 +520,244 @@ fn key_within(user_key: &[u8], table_begin: KeySlice, table_end: KeySlice) -> bo
     table_begin.raw_ref() <= user_key && user_key <= table_end.raw_ref()
 }
 
+/// Pick up to `n - 1` first keys of the blocks splitting them into `n` runs of roughly equal size,
+/// skipping keys outside of the range. The blocks are given as their first keys and sizes.
+pub(crate) fn split_blocks(
+    mut blocks: Vec<(&[u8], usize)>,
+    lower: Bound<&[u8]>,
+    upper: Bound<&[u8]>,
+    n: usize,
+) -> Vec<Bytes> {
+    blocks.sort();
+
+    let after_lower = |key: &[u8]| match lower {
+        Bound::Included(lower) | Bound::Excluded(lower) => key > lower,
+        Bound::Unbounded => true,
+    };
+    let before_upper = |key: &[u8]| match upper {
+        Bound::Included(upper) => key <= upper,
+        Bound::Excluded(upper) => key < upper,
+        Bound::Unbounded => true,
+    };
+    let total_size: usize = blocks.iter().map(|(_, size)| size).sum();
+    let mut split_keys: Vec<Bytes> = Vec::new();
+    let mut size_before = 0;
+    for (first_key, size) in blocks {
+        // Split before this block once the blocks before it fill the next sub-range.
+        let next_split = (split_keys.len() + 1) * total_size;
+        if split_keys.len() + 1 < n
+            && size_before * n >= next_split
+            && after_lower(first_key)
+            && before_upper(first_key)
+            && split_keys
+                .last()
+                .is_none_or(|last| last.as_ref() < first_key)
+        {
+            split_keys.push(Bytes::copy_from_slice(first_key));
+        }
+        size_before += size;
+    }
+    split_keys
+}
+
+/// Create an iterator over the SST positioned at the first key within `lower`.
+fn seek_sst_to_lower_bound(table: Arc<SsTable>, lower: Bound<&[u8]>) -> Result<SsTableIterator> {
+    let iter = match lower {
+        Bound::Included(key) => {
+            SsTableIterator::create_and_seek_to_key(table, KeySlice::from_slice(key))?
+        }
+        Bound::Excluded(key) => {
+            let mut iter =
+                SsTableIterator::create_and_seek_to_key(table, KeySlice::from_slice(key))?;
+            if iter.is_valid() && iter.key().raw_ref() == key {
+                iter.next()?;
+            }
+            iter
+        }
+        Bound::Unbounded => SsTableIterator::create_and_seek_to_first(table)?,
+    };
+    Ok(iter)
+}
+
+/// Name of the directory orphan files are moved to with
+/// [`LsmStorageOptions::move_orphan_files_to_lost_found`], created in the directory they are found in.
+pub const LOST_FOUND_DIR: &str = "lost+found";
+
+/// Delete the files with extension `ext` in `dir` whose ids are not in `live`, e.g., the inputs of
+/// a compaction that was recorded in the manifest right before a crash, or an SST or WAL written
+/// right before a crash but never recorded. They are moved to [`LOST_FOUND_DIR`] instead if
+/// `lost_found` is set.
+fn remove_orphan_files(
+    dir: &Path,
+    ext: &str,
+    live: impl Fn(usize) -> bool,
+    lost_found: bool,
+) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != ext) {
+            continue;
+        }
+        let Some(id) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if live(id) {
+            continue;
+        }
+        if lost_found {
+            log::warn!("moving orphan {}.{} to {}", id, ext, LOST_FOUND_DIR);
+            let lost_found_dir = dir.join(LOST_FOUND_DIR);
+            std::fs::create_dir_all(&lost_found_dir)?;
+            std::fs::rename(&path, lost_found_dir.join(path.file_name().unwrap()))?;
+        } else {
+            log::info!("removing orphan {}.{}", id, ext);
+            std::fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Create an iterator over `table` in descending key order, starting at the last key within
+/// `upper`.
+fn seek_sst_to_upper_bound(table: Arc<SsTable>, upper: Bound<&[u8]>) -> Result<SsTableIterator> {
+    let iter = match upper {
+        Bound::Included(key) => {
+            SsTableIterator::create_and_seek_to_key_rev(table, KeySlice::from_slice(key))?
+        }
+        Bound::Excluded(key) => {
+            let mut iter =
+                SsTableIterator::create_and_seek_to_key_rev(table, KeySlice::from_slice(key))?;
+            if iter.is_valid() && iter.key().raw_ref() == key {
+                iter.next()?;
+            }
+            iter
+        }
+        Bound::Unbounded => SsTableIterator::create_and_seek_to_last(table)?.reversed(),
+    };
+    Ok(iter)
+}
+
+/// Find the lowest level down to `max_level` that `sst` can be placed into, where neither the
+/// level nor anything above it overlaps with the SST, stopping at the first level a running
+/// compaction uses.
+fn lowest_disjoint_level(
+    state: &LsmStorageState,
+    running_compactions: &RunningCompactions,
+    sst: &SsTable,
+    max_level: usize,
+) -> Option<usize> {
+    let overlaps = |sst_id: &usize| {
+        let table = &state.sstables[sst_id];
+        range_overlap(
+            Bound::Included(sst.first_key().raw_ref()),
+            Bound::Included(sst.last_key().raw_ref()),
+            table.first_key().as_key_slice(),
+            table.last_key().as_key_slice(),
+        )
+    };
+    if state.l0_sstables.iter().any(overlaps) {
+        return None;
+    }
+    state
+        .levels
+        .iter()
+        .take_while(|(level, level_ssts)| {
+            *level <= max_level
+                && !running_compactions.is_busy(*level)
+                && !level_ssts.iter().any(overlaps)
+        })
+        .last()
+        .map(|(level, _)| *level)
+}
+
+/// Wait for a background thread to stop, turning a panic it did not catch into an error.
+fn join_background_thread(thread: std::thread::JoinHandle<Result<()>>, name: &str) -> Result<()> {
+    thread
+        .join()
+        .map_err(|e| anyhow::anyhow!("{} thread: {}", name, panic_message(e.as_ref())))?
+        .with_context(|| format!("{} thread stopped", name))
+}
+
+/// The smallest key greater than every key starting with `prefix`, i.e. the prefix without its
+/// trailing 0xff bytes and with the last byte left incremented. `None` if there is no such key,
+/// i.e. the prefix is empty or all 0xff.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let len = prefix.iter().rposition(|byte| *byte != 0xff)? + 1;
+    let mut successor = prefix[..len].to_vec();
+    successor[len - 1] += 1;
+    Some(successor)
+}
+
+/// Check whether the SST may contain `key` by its key range and bloom filter.
+fn may_contain_key(key: &[u8], table: &SsTable) -> bool {
+    key_within(
+        key,
+        table.first_key().as_key_slice(),
+        table.last_key().as_key_slice(),
+    ) && match table.bloom() {
+        Ok(Some(bloom)) => bloom.may_contain(farmhash::fingerprint32(key)),
+        // the bloom filter is only an optimization, a broken file fails when reading the blocks
+        Ok(None) | Err(_) => true,
+    }
+}
+
+/// Look up `key` in `table`, reusing the iterator `iters` keeps for the table. Returns the value,
+/// empty for a delete, and the epoch of the table if the key exists.
+fn get_from_sst(
+    iters: &mut HashMap<usize, SsTableIterator>,
+    table: &Arc<SsTable>,
+    key: &[u8],
+) -> Result<Option<(Bytes, usize)>> {
+    if !may_contain_key(key, table) {
+        return Ok(None);
+    }
+    let key = KeySlice::from_slice(key);
+    let iter = match iters.entry(table.sst_id()) {
+        std::collections::hash_map::Entry::Occupied(entry) => {
+            let iter = entry.into_mut();
+            iter.seek_to_key(key)?;
+            iter
+        }
+        std::collections::hash_map::Entry::Vacant(entry) => {
+            entry.insert(SsTableIterator::create_and_seek_to_key(table.clone(), key)?)
+        }
+    };
+    if iter.is_valid() && iter.key() == key {
+        return Ok(Some((Bytes::copy_from_slice(iter.value()), iter.epoch())));
+    }
+    Ok(None)
+}
+
 #[derive(Clone, Debug)]
 pub enum CompactionFilter {
+    /// Drop every key starting with the prefix when compacting to the bottom level.
     Prefix(Bytes),
+    /// Drop every key within the range when compacting to the bottom level, e.g. to expire old
+    /// partitions of time-series data without writing tombstones.
+    KeyRange {
+        lower: Bound<Bytes>,
+        upper: Bound<Bytes>,
+    },
+}
+
+impl CompactionFilter {
+    /// Check whether a key compacted to the bottom level should be dropped.
+    pub(crate) fn drops_at_bottom_level(&self, key: &[u8]) -> bool {
+        match self {
+            CompactionFilter::Prefix(prefix) => key.starts_with(prefix),
+            CompactionFilter::KeyRange { lower, upper } => RangeBounds::<[u8]>::contains(
+                &(
+                    lower.as_ref().map(|x| x.as_ref()),
+                    upper.as_ref().map(|x| x.as_ref()),
+                ),
+                key,
+            ),
+        }
+    }
 }
 
 /// The storage interface of the LSM tree.
@@ -172,6 +765,7 @@ pub(crate) struct LsmStorageInner {
     pub(crate) state: Arc<RwLock<Arc<LsmStorageState>>>,
     pub(crate) state_lock: Mutex<()>,
     path: PathBuf,
+    wal_dir: PathBuf,
     pub(crate) block_cache: Arc<BlockCache>,
     next_sst_id: AtomicUsize,
     pub(crate) options: Arc<LsmStorageOptions>,
@@ -179,158 +773,701 @@ pub(crate) struct LsmStorageInner {
     pub(crate) manifest: Option<Manifest>,
     #[allow(dead_code)]
     pub(crate) mvcc: Option<LsmMvccInner>,
-    #[allow(dead_code)]
     pub(crate) compaction_filters: Arc<Mutex<Vec<CompactionFilter>>>,
+    /// Range tombstones that may still cover some data. Only modified while holding the write lock
+    /// of `state`, so that readers get a consistent view by reading both under the read lock.
+    pub(crate) range_tombstones: RwLock<Arc<Vec<RangeTombstone>>>,
+    flush_stats: Mutex<FlushStats>,
+    pub(crate) stats: Arc<LsmStats>,
+    /// The levels used by the running compactions, see [`RunningCompactions`].
+    pub(crate) running_compactions: Mutex<RunningCompactions>,
+    /// Notified whenever a compaction finishes, to wake up compactions waiting for its levels.
+    pub(crate) compaction_finished: Condvar,
+    pub(crate) compaction_rate_limiter: Option<RateLimiter>,
+    /// Serializes writes when `reject_overwrites` is enabled.
+    overwrite_check_lock: Mutex<()>,
+    /// Striped locks serializing the conditional writes of the same key, such as
+    /// `put_if_absent`, picked by the hash of the key.
+    key_locks: Vec<Mutex<()>>,
+    /// The SSTs read by running scans, whose files are only removed once the scans are dropped.
+    pub(crate) pinned_ssts: Arc<PinnedSsts>,
+    /// The total size of the SSTs in the bottom level, updated whenever the SSTs change so that
+    /// `max_db_size_bytes` is checked without walking the SSTs on every write.
+    bottom_level_size: AtomicU64,
+    /// The latest error of the flush or the compaction thread.
+    pub(crate) background_error: Mutex<Option<String>>,
+    /// Whether a flush thread drains the immutable memtables. Writers are only stalled by
+    /// `max_imm_memtables` while it runs, as nothing else would make room.
+    pub(crate) flush_thread_running: AtomicBool,
+    /// Notified whenever an immutable memtable is flushed or the flush thread stops, to wake up
+    /// stalled writers.
+    imm_memtables_flushed: Condvar,
+    imm_memtables_flushed_lock: Mutex<()>,
+    /// Opened with `open_read_only`, in which case there is no manifest.
+    read_only: bool,
+    /// See [`ManifestRecord::EncodedValuesSince`]. The WALs of earlier memtables are encoded when
+    /// recovered, and are only kept until the memtables are flushed.
+    encoded_values_since: usize,
 }
 
-/// A thin wrapper for `LsmStorageInner` and the user interface for MiniLSM.
-pub struct MiniLsm {
-    pub(crate) inner: Arc<LsmStorageInner>,
-    /// Notifies the L0 flush thread to stop working. (In week 1 day 6)
-    flush_notifier: crossbeam_channel::Sender<()>,
-    /// The handle for the flush thread. (In week 1 day 6)
-    flush_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
-    /// Notifies the compaction thread to stop working. (In week 2)
-    compaction_notifier: crossbeam_channel::Sender<()>,
-    /// The handle for the compaction thread. (In week 2)
-    compaction_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
-}
-
-impl Drop for MiniLsm {
-    fn drop(&mut self) {
-        self.compaction_notifier.send(()).ok();
-        self.flush_notifier.send(()).ok();
+impl LsmStorageState {
+    /// The total size of the SSTs in the bottom level.
+    fn bottom_level_size(&self) -> u64 {
+        self.levels.last().map_or(0, |(_, ssts)| {
+            ssts.iter().map(|id| self.sstables[id].table_size()).sum()
+        })
     }
-}
-
-impl MiniLsm {
-    pub fn close(&self) -> Result<()> {
-        self.inner.sync_dir()?;
-        self.compaction_notifier.send(()).ok();
-        self.flush_notifier.send(()).ok();
 
-        let mut compaction_thread = self.compaction_thread.lock();
-        if let Some(compaction_thread) = compaction_thread.take() {
-            compaction_thread
-                .join()
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-        }
-        let mut flush_thread = self.flush_thread.lock();
-        if let Some(flush_thread) = flush_thread.take() {
-            flush_thread
-                .join()
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    /// Search the memtables from the latest to the earliest, reading the active one as of the
+    /// write with sequence number `memtable_seq`. Returns the value, empty for a delete, and the id
+    /// of the memtable if the key exists.
+    fn get_from_memtables(&self, key: &[u8], memtable_seq: u64) -> Option<(Bytes, usize)> {
+        if let Some(value) = self.memtable.get_at(key, memtable_seq) {
+            return Some((value, self.memtable.id()));
         }
+        self.imm_memtables
+            .iter()
+            .find_map(|memtable| memtable.get(key).map(|value| (value, memtable.id())))
+    }
 
-        if self.inner.options.enable_wal {
-            self.inner.sync()?;
-            self.inner.sync_dir()?;
-            return Ok(());
+    /// The range tombstones of the memtables as they apply to the earlier memtables and SSTs,
+    /// reading the active memtable as of `memtable_seq`.
+    fn memtable_range_tombstones(&self, memtable_seq: u64) -> Vec<RangeTombstone> {
+        let mut range_tombstones = self
+            .memtable
+            .range_tombstones_for_earlier_data(memtable_seq);
+        for memtable in &self.imm_memtables {
+            range_tombstones
+                .extend(memtable.range_tombstones_for_earlier_data(memtable.visible_seq()));
         }
+        range_tombstones
+    }
 
-        // create memtable and skip updating manifest
-        if !self.inner.state.read().memtable.is_empty() {
-            self.inner
-                .freeze_memtable_with_memtable(Arc::new(MemTable::create(
-                    self.inner.next_sst_id(),
-                )))?;
+    /// Search the SSTs from the latest to the earliest, stopping at the first one holding the
+    /// key: the L0 SSTs one by one, then the single SST of each level that may hold the key.
+    /// Returns the value, empty for a delete, and the epoch of the SST if the key exists.
+    fn get_from_ssts(
+        &self,
+        iters: &mut HashMap<usize, SsTableIterator>,
+        key: &[u8],
+    ) -> Result<Option<(Bytes, usize)>> {
+        for table_id in self.l0_sstables.iter() {
+            if let Some(value) = get_from_sst(iters, &self.sstables[table_id], key)? {
+                return Ok(Some(value));
+            }
         }
-
-        while {
-            let snapshot = self.inner.state.read();
-            !snapshot.imm_memtables.is_empty()
-        } {
-            self.inner.force_flush_next_imm_memtable()?;
+        for (_, level_sst_ids) in &self.levels {
+            let Some(table_id) = self.level_sst_for_key(level_sst_ids, key) else {
+                continue;
+            };
+            if let Some(value) = get_from_sst(iters, &self.sstables[&table_id], key)? {
+                return Ok(Some(value));
+            }
         }
-        self.inner.sync_dir()?;
-
-        Ok(())
-    }
-
-    /// Start the storage engine by either loading an existing directory or creating a new one if the directory does
-    /// not exist.
-    pub fn open(path: impl AsRef<Path>, options: LsmStorageOptions) -> Result<Arc<Self>> {
-        let inner = Arc::new(LsmStorageInner::open(path, options)?);
-        let (tx1, rx) = crossbeam_channel::unbounded();
-        let compaction_thread = inner.spawn_compaction_thread(rx)?;
-        let (tx2, rx) = crossbeam_channel::unbounded();
-        let flush_thread = inner.spawn_flush_thread(rx)?;
-        Ok(Arc::new(Self {
-            inner,
-            flush_notifier: tx2,
-            flush_thread: Mutex::new(flush_thread),
-            compaction_notifier: tx1,
-            compaction_thread: Mutex::new(compaction_thread),
-        }))
+        Ok(None)
     }
 
-    pub fn add_compaction_filter(&self, compaction_filter: CompactionFilter) {
-        self.inner.add_compaction_filter(compaction_filter)
+    /// The only SST of a level, or a tier, whose key range may contain `key`: the last one that
+    /// starts at or before it, as the SSTs of a level are sorted and do not overlap.
+    fn level_sst_for_key(&self, level_sst_ids: &[usize], key: &[u8]) -> Option<usize> {
+        let idx = level_sst_ids
+            .partition_point(|table_id| self.sstables[table_id].first_key().raw_ref() <= key);
+        idx.checked_sub(1).map(|idx| level_sst_ids[idx])
     }
 
-    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
-        self.inner.get(key)
+    /// Ids of the SSTs a scan over the range reads.
+    pub(crate) fn sst_ids_in_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Vec<usize> {
+        let mut ids = self
+            .l0_sstables
+            .iter()
+            .filter(|id| {
+                let table = &self.sstables[*id];
+                range_overlap(
+                    lower,
+                    upper,
+                    table.first_key().as_key_slice(),
+                    table.last_key().as_key_slice(),
+                )
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        for (_, level_sst_ids) in &self.levels {
+            ids.extend(
+                self.level_ssts_in_range(level_sst_ids, lower, upper)
+                    .iter()
+                    .map(|table| table.sst_id()),
+            );
+        }
+        ids
     }
 
-    pub fn write_batch<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<()> {
-        self.inner.write_batch(batch)
+    /// The SSTs of a level, or a tier, which overlap with the range. The SSTs of a level are
+    /// sorted and disjoint, so they are found by binary search instead of checking every SST.
+    pub(crate) fn level_ssts_in_range(
+        &self,
+        level_sst_ids: &[usize],
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Vec<Arc<SsTable>> {
+        let table = |sst_id: &usize| &self.sstables[sst_id];
+        // the first SST not ending before the lower bound
+        let begin = level_sst_ids.partition_point(|sst_id| {
+            let last_key = table(sst_id).last_key().raw_ref();
+            match lower {
+                Bound::Included(key) => last_key < key,
+                Bound::Excluded(key) => last_key <= key,
+                Bound::Unbounded => false,
+            }
+        });
+        // the first SST starting after the upper bound
+        let end = level_sst_ids.partition_point(|sst_id| {
+            let first_key = table(sst_id).first_key().raw_ref();
+            match upper {
+                Bound::Included(key) => first_key <= key,
+                Bound::Excluded(key) => first_key < key,
+                Bound::Unbounded => true,
+            }
+        });
+        level_sst_ids
+            .get(begin..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|sst_id| table(sst_id).clone())
+            .collect()
     }
+}
 
-    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
-        self.inner.put(key, value)
-    }
+/// A read-only view of the storage at the time `snapshot` was called, so that a sequence of reads
+/// is not affected by writes, flushes and compactions in between.
+///
+/// The snapshot pins the SSTs it references, so that their files are kept until it is dropped
+/// even if a compaction replaces them. The disk space of such files is only released once all
+/// snapshots and scans referencing them are dropped, so do not keep a snapshot around for long.
+///
+/// Later writes to the memtable that was active when the snapshot was taken are hidden from the
+/// snapshot by their sequence numbers.
+pub struct Snapshot {
+    state: Arc<LsmStorageState>,
+    range_tombstones: Arc<Vec<RangeTombstone>>,
+    /// The sequence number of the latest write to the active memtable the snapshot sees.
+    memtable_seq: u64,
+    /// The pinned SSTs, which are kept pinned by the iterators created from the snapshot.
+    pins: Option<Arc<SstPinGuard>>,
+}
 
-    pub fn delete(&self, key: &[u8]) -> Result<()> {
-        self.inner.delete(key)
+impl Snapshot {
+    /// Get a key from the snapshot.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let memtable_value = self.state.get_from_memtables(key, self.memtable_seq);
+        self.get_with_memtable_value(key, memtable_value)
     }
 
-    pub fn sync(&self) -> Result<()> {
-        self.inner.sync()
-    }
+    /// Get a key whose lookup in the memtables of the snapshot returned `memtable_value`.
+    fn get_with_memtable_value(
+        &self,
+        key: &[u8],
+        memtable_value: Option<(Bytes, usize)>,
+    ) -> Result<Option<Bytes>> {
+        let (snapshot, range_tombstones) = (&self.state, &self.range_tombstones);
+        let now = now_millis();
+        if let Some((value, epoch)) = memtable_value {
+            if is_shadowed(range_tombstones, key, epoch) {
+                return Ok(None);
+            }
+            // a tombstone or an expired value means the key does not exist
+            return Ok(live_value_bytes(value, now));
+        }
 
-    pub fn new_txn(&self) -> Result<()> {
-        self.inner.new_txn()
+        // the newest version of the key decides, deeper levels are not searched after it
+        match snapshot.get_from_ssts(&mut HashMap::new(), key)? {
+            Some((value, epoch)) if !is_shadowed(range_tombstones, key, epoch) => {
+                Ok(live_value_bytes(value, now))
+            }
+            _ => Ok(None),
+        }
     }
 
+    /// Create an iterator over a range of keys in the snapshot.
     pub fn scan(
         &self,
         lower: Bound<&[u8]>,
         upper: Bound<&[u8]>,
     ) -> Result<FusedIterator<LsmIterator>> {
-        self.inner.scan(lower, upper)
-    }
+        let (snapshot, range_tombstones) = (&self.state, self.range_tombstones.clone());
+        // The SSTs are positioned at an excluded lower bound itself, and the bound is skipped once
+        // all the iterators are merged, so that every version of the key is skipped together.
+        let seek_lower = match lower {
+            Bound::Excluded(key) => Bound::Included(key),
+            bound => bound,
+        };
 
-    /// Only call this in test cases due to race conditions
-    pub fn force_flush(&self) -> Result<()> {
-        if !self.inner.state.read().memtable.is_empty() {
-            self.inner
-                .force_freeze_memtable(&self.inner.state_lock.lock())?;
+        let mut memtable_iters = Vec::with_capacity(snapshot.imm_memtables.len() + 1);
+        memtable_iters.push(Box::new(snapshot.memtable.scan_at(
+            lower,
+            upper,
+            self.memtable_seq,
+            false,
+        )));
+        for memtable in snapshot.imm_memtables.iter() {
+            memtable_iters.push(Box::new(memtable.scan(lower, upper)));
         }
-        if !self.inner.state.read().imm_memtables.is_empty() {
-            self.inner.force_flush_next_imm_memtable()?;
+        let memtable_iter = MergeIterator::create(memtable_iters);
+
+        let mut table_iters = Vec::with_capacity(snapshot.l0_sstables.len());
+        for table_id in snapshot.l0_sstables.iter() {
+            let table = snapshot.sstables[table_id].clone();
+            if range_overlap(
+                lower,
+                upper,
+                table.first_key().as_key_slice(),
+                table.last_key().as_key_slice(),
+            ) {
+                table_iters.push(Box::new(seek_sst_to_lower_bound(table, seek_lower)?));
+            }
         }
-        Ok(())
-    }
 
-    pub fn force_full_compaction(&self) -> Result<()> {
-        self.inner.force_full_compaction()
-    }
-}
+        let l0_iter = MergeIterator::create(table_iters);
+        let mut level_iters = Vec::with_capacity(snapshot.levels.len());
+        for (_, level_sst_ids) in &snapshot.levels {
+            let level_ssts = snapshot.level_ssts_in_range(level_sst_ids, lower, upper);
+            let level_iter = match seek_lower {
+                Bound::Included(key) | Bound::Excluded(key) => {
+                    SstConcatIterator::create_and_seek_to_key(
+                        level_ssts,
+                        KeySlice::from_slice(key),
+                    )?
+                }
+                Bound::Unbounded => SstConcatIterator::create_and_seek_to_first(level_ssts)?,
+            };
+            level_iters.push(Box::new(level_iter));
+        }
 
-impl LsmStorageInner {
-    pub(crate) fn next_sst_id(&self) -> usize {
-        self.next_sst_id
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+        let iter = TwoMergeIterator::create(memtable_iter, l0_iter)?;
+        let mut iter = TwoMergeIterator::create(iter, MergeIterator::create(level_iters))?;
+        if let Bound::Excluded(key) = lower
+            && iter.is_valid()
+            && iter.key().raw_ref() == key
+        {
+            iter.next()?;
+        }
+
+        Ok(FusedIterator::new(
+            LsmIterator::new(iter, map_bound(lower), map_bound(upper), range_tombstones)?
+                .with_pins(self.pins.clone()),
+        ))
     }
 
-    /// Start the storage engine by either loading an existing directory or creating a new one if the directory does
-    /// not exist.
-    pub(crate) fn open(path: impl AsRef<Path>, options: LsmStorageOptions) -> Result<Self> {
+    /// Create an iterator over a range of keys in the snapshot in descending order.
+    pub fn scan_rev(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>> {
+        let (snapshot, range_tombstones) = (&self.state, self.range_tombstones.clone());
+
+        let mut memtable_iters = Vec::with_capacity(snapshot.imm_memtables.len() + 1);
+        memtable_iters.push(Box::new(snapshot.memtable.scan_at(
+            lower,
+            upper,
+            self.memtable_seq,
+            true,
+        )));
+        for memtable in snapshot.imm_memtables.iter() {
+            memtable_iters.push(Box::new(memtable.scan_rev(lower, upper)));
+        }
+        let memtable_iter = MergeIterator::create_rev(memtable_iters);
+
+        let mut table_iters = Vec::with_capacity(snapshot.l0_sstables.len());
+        for table_id in snapshot.l0_sstables.iter() {
+            let table = snapshot.sstables[table_id].clone();
+            if range_overlap(
+                lower,
+                upper,
+                table.first_key().as_key_slice(),
+                table.last_key().as_key_slice(),
+            ) {
+                table_iters.push(Box::new(seek_sst_to_upper_bound(table, upper)?));
+            }
+        }
+
+        let l0_iter = MergeIterator::create_rev(table_iters);
+        let mut level_iters = Vec::with_capacity(snapshot.levels.len());
+        for (_, level_sst_ids) in &snapshot.levels {
+            let level_ssts = snapshot.level_ssts_in_range(level_sst_ids, lower, upper);
+            let level_iter = match upper {
+                Bound::Included(key) => SstConcatIterator::create_and_seek_to_key_rev(
+                    level_ssts,
+                    KeySlice::from_slice(key),
+                )?,
+                Bound::Excluded(key) => {
+                    let mut iter = SstConcatIterator::create_and_seek_to_key_rev(
+                        level_ssts,
+                        KeySlice::from_slice(key),
+                    )?;
+                    if iter.is_valid() && iter.key().raw_ref() == key {
+                        iter.next()?;
+                    }
+                    iter
+                }
+                Bound::Unbounded => SstConcatIterator::create_and_seek_to_last(level_ssts)?,
+            };
+            level_iters.push(Box::new(level_iter));
+        }
+
+        let iter = TwoMergeIterator::create_rev(memtable_iter, l0_iter)?;
+        let iter = TwoMergeIterator::create_rev(iter, MergeIterator::create_rev(level_iters))?;
+
+        Ok(FusedIterator::new(
+            LsmIterator::new_rev(iter, map_bound(lower), range_tombstones)?
+                .with_pins(self.pins.clone()),
+        ))
+    }
+}
+
+/// A thin wrapper for `LsmStorageInner` and the user interface for MiniLSM.
+pub struct MiniLsm {
+    pub(crate) inner: Arc<LsmStorageInner>,
+    /// Notifies the L0 flush thread to stop working. (In week 1 day 6)
+    flush_notifier: crossbeam_channel::Sender<()>,
+    /// The handle for the flush thread. (In week 1 day 6)
+    flush_thread: Mutex<Option<std::thread::JoinHandle<Result<()>>>>,
+    /// Notifies the compaction threads to stop working, one message per thread. (In week 2)
+    compaction_notifier: crossbeam_channel::Sender<()>,
+    /// The handles for the compaction threads. (In week 2)
+    compaction_threads: Mutex<Vec<std::thread::JoinHandle<Result<()>>>>,
+}
+
+impl Drop for MiniLsm {
+    fn drop(&mut self) {
+        self.stop_compaction_threads();
+        self.flush_notifier.send(()).ok();
+    }
+}
+
+impl MiniLsm {
+    pub fn close(&self) -> Result<(), LsmError> {
+        if self.inner.read_only {
+            return Ok(());
+        }
+        self.inner.sync_dir()?;
+        self.stop_compaction_threads();
+        self.flush_notifier.send(()).ok();
+
+        // A thread stopped by a panic returns the panic as an error, which is only returned once
+        // the rest of the shutdown is done, so that the memtables are still persisted.
+        let compaction_result = std::mem::take(&mut *self.compaction_threads.lock())
+            .into_iter()
+            .map(|compaction_thread| join_background_thread(compaction_thread, "compaction"))
+            .fold(Ok(()), Result::and);
+        let flush_result = match self.flush_thread.lock().take() {
+            Some(flush_thread) => join_background_thread(flush_thread, "flush"),
+            None => Ok(()),
+        };
+        let result = self.persist_on_close();
+        compaction_result?;
+        flush_result?;
+        Ok(result?)
+    }
+
+    fn stop_compaction_threads(&self) {
+        for _ in 0..self.compaction_threads.lock().len() {
+            self.compaction_notifier.send(()).ok();
+        }
+    }
+
+    /// Flush or sync the memtables and compact the manifest once the background threads stopped.
+    fn persist_on_close(&self) -> Result<()> {
+        if self.inner.options.flush_to_level_on_close
+            && self.inner.compaction_controller.flush_to_l0()
+        {
+            let state_lock = self.inner.state_lock.lock();
+            if !self.inner.state.read().memtable.is_empty() {
+                let frozen_memtable_id = self.inner.force_freeze_memtable(&state_lock)?;
+                drop(state_lock);
+                self.inner.notify_memtable_freeze(frozen_memtable_id);
+            }
+            let bottom_level = self.inner.state.read().levels.len();
+            while !self.inner.state.read().imm_memtables.is_empty() {
+                self.inner.force_flush_to_level(bottom_level)?;
+            }
+        }
+
+        if self.inner.options.enable_wal {
+            self.inner.sync()?;
+            self.inner.sync_dir()?;
+            return self.inner.compact_manifest();
+        }
+
+        // create memtable and skip updating manifest
+        if !self.inner.state.read().memtable.is_empty() {
+            self.inner
+                .freeze_memtable_with_memtable(Arc::new(MemTable::create(
+                    self.inner.next_sst_id(),
+                )))?;
+        }
+
+        while {
+            let snapshot = self.inner.state.read();
+            !snapshot.imm_memtables.is_empty()
+        } {
+            self.inner.force_flush_next_imm_memtable()?;
+        }
+        self.inner.sync_dir()?;
+
+        self.inner.compact_manifest()
+    }
+
+    /// Start the storage engine by either loading an existing directory or creating a new one if the directory does
+    /// not exist.
+    pub fn open(path: impl AsRef<Path>, options: LsmStorageOptions) -> Result<Arc<Self>, LsmError> {
+        let inner = Arc::new(LsmStorageInner::open(path, options)?);
+        let (tx1, rx) = crossbeam_channel::unbounded();
+        let compaction_threads = inner.spawn_compaction_threads(rx)?;
+        let (tx2, rx) = crossbeam_channel::unbounded();
+        let flush_thread = inner.spawn_flush_thread(rx)?;
+        Ok(Arc::new(Self {
+            inner,
+            flush_notifier: tx2,
+            flush_thread: Mutex::new(flush_thread),
+            compaction_notifier: tx1,
+            compaction_threads: Mutex::new(compaction_threads),
+        }))
+    }
+
+    /// Open an existing database without modifying it and without the flush and compaction
+    /// threads. Reads work as usual, writes fail with [`LsmError::ReadOnly`].
+    pub fn open_read_only(
+        path: impl AsRef<Path>,
+        options: LsmStorageOptions,
+    ) -> Result<Arc<Self>, LsmError> {
+        let inner = Arc::new(LsmStorageInner::open_read_only(path, options)?);
+        Ok(Arc::new(Self {
+            inner,
+            flush_notifier: crossbeam_channel::unbounded().0,
+            flush_thread: Mutex::new(None),
+            compaction_notifier: crossbeam_channel::unbounded().0,
+            compaction_threads: Mutex::new(Vec::new()),
+        }))
+    }
+
+    pub fn add_compaction_filter(&self, compaction_filter: CompactionFilter) {
+        self.inner.add_compaction_filter(compaction_filter)
+    }
+
+    /// The latest error of the background flush and compaction, if any. A thread stops after a
+    /// panic, which `close` then reports.
+    pub fn last_background_error(&self) -> Option<String> {
+        self.inner.background_error.lock().clone()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>, LsmError> {
+        Ok(self.inner.get(key)?)
+    }
+
+    pub fn locate(&self, key: &[u8]) -> Result<Option<KeyLocation>, LsmError> {
+        Ok(self.inner.locate(key)?)
+    }
+
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Result<Vec<Option<Bytes>>, LsmError> {
+        Ok(self.inner.get_batch(keys)?)
+    }
+
+    pub fn write_batch<T: AsRef<[u8]>>(
+        &self,
+        batch: &[WriteBatchRecord<T>],
+    ) -> Result<(), LsmError> {
+        Ok(self.inner.write_batch(batch)?)
+    }
+
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<(), LsmError> {
+        Ok(self.inner.put(key, value)?)
+    }
+
+    pub fn put_with_ttl(&self, key: &[u8], value: &[u8], ttl: Duration) -> Result<(), LsmError> {
+        Ok(self.inner.put_with_ttl(key, value, ttl)?)
+    }
+
+    pub fn delete(&self, key: &[u8]) -> Result<(), LsmError> {
+        Ok(self.inner.delete(key)?)
+    }
+
+    pub fn put_if_absent(&self, key: &[u8], value: &[u8]) -> Result<bool, LsmError> {
+        Ok(self.inner.put_if_absent(key, value)?)
+    }
+
+    pub fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: &[u8],
+    ) -> Result<bool, LsmError> {
+        Ok(self.inner.compare_and_swap(key, expected, new)?)
+    }
+
+    pub fn delete_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<(), LsmError> {
+        Ok(self.inner.delete_range(lower, upper)?)
+    }
+
+    pub fn flush_stats(&self) -> FlushStats {
+        self.inner.flush_stats()
+    }
+
+    /// A copy of the operation counters since the storage was opened.
+    pub fn stats(&self) -> LsmStatsSnapshot {
+        self.inner.stats()
+    }
+
+    pub fn sync(&self) -> Result<(), LsmError> {
+        Ok(self.inner.sync()?)
+    }
+
+    pub fn new_txn(&self) -> Result<(), LsmError> {
+        Ok(self.inner.new_txn()?)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+
+    pub fn ingest_sst(&self, path: &Path) -> Result<(), LsmError> {
+        Ok(self.inner.ingest_sst(path)?)
+    }
+
+    pub fn checkpoint(&self, dest: &Path) -> Result<(), LsmError> {
+        Ok(self.inner.checkpoint(dest)?)
+    }
+
+    pub fn scan(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>, LsmError> {
+        Ok(self.inner.scan(lower, upper)?)
+    }
+
+    pub fn scan_rev(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>, LsmError> {
+        Ok(self.inner.scan_rev(lower, upper)?)
+    }
+
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<FusedIterator<LsmIterator>, LsmError> {
+        Ok(self.inner.scan_prefix(prefix)?)
+    }
+
+    pub fn last_key_le(&self, key: &[u8]) -> Result<Option<Bytes>, LsmError> {
+        Ok(self.inner.last_key_le(key)?)
+    }
+
+    /// Only call this in test cases due to race conditions
+    pub fn force_flush(&self) -> Result<(), LsmError> {
+        if !self.inner.state.read().memtable.is_empty() {
+            let frozen_memtable_id = self
+                .inner
+                .force_freeze_memtable(&self.inner.state_lock.lock())?;
+            self.inner.notify_memtable_freeze(frozen_memtable_id);
+        }
+        if !self.inner.state.read().imm_memtables.is_empty() {
+            self.inner.force_flush_next_imm_memtable()?;
+        }
+        Ok(())
+    }
+
+    pub fn force_full_compaction(&self) -> Result<(), LsmError> {
+        Ok(self.inner.force_full_compaction()?)
+    }
+
+    pub fn set_max_size_amplification_percent(&self, percent: usize) -> Result<(), LsmError> {
+        Ok(self.inner.set_max_size_amplification_percent(percent)?)
+    }
+
+    pub fn dry_run_compaction(&self, task: &CompactionTask) -> Result<CompactionPlan, LsmError> {
+        Ok(self.inner.dry_run_compaction(task)?)
+    }
+
+    /// Run the next compaction, if any is needed. Returns whether a compaction was done.
+    pub fn run_one_compaction(&self) -> Result<bool, LsmError> {
+        Ok(self.inner.run_one_compaction()?)
+    }
+
+    pub fn all_sst_ids(&self) -> Vec<usize> {
+        self.inner.all_sst_ids()
+    }
+
+    pub fn key_range(&self) -> Option<(Bytes, Bytes)> {
+        self.inner.key_range()
+    }
+
+    pub fn verify_sst_files(&self) -> Result<(), LsmError> {
+        Ok(self.inner.verify_sst_files()?)
+    }
+
+    pub fn split_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>, n: usize) -> Vec<Bytes> {
+        self.inner.split_range(lower, upper, n)
+    }
+
+    pub fn approximate_size(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> u64 {
+        self.inner.approximate_size(lower, upper)
+    }
+
+    pub fn compact_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<(), LsmError> {
+        Ok(self.inner.compact_range(lower, upper)?)
+    }
+
+    pub fn scan_sst(
+        &self,
+        sst_id: usize,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<SstRangeIterator, LsmError> {
+        Ok(self.inner.scan_sst(sst_id, lower, upper)?)
+    }
+
+    /// Flush everything written so far to SSTs before returning.
+    pub fn flush_and_wait(&self) -> Result<(), LsmError> {
+        Ok(self.inner.flush_and_wait()?)
+    }
+}
+
+impl LsmStorageInner {
+    pub(crate) fn next_sst_id(&self) -> usize {
+        self.next_sst_id
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// The id `next_sst_id` would return next, without taking it.
+    pub(crate) fn peek_next_sst_id(&self) -> usize {
+        self.next_sst_id.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Start the storage engine by either loading an existing directory or creating a new one if the directory does
+    /// not exist.
+    pub(crate) fn open(path: impl AsRef<Path>, options: LsmStorageOptions) -> Result<Self> {
+        Self::open_inner(path.as_ref(), options, false)
+    }
+
+    /// Open an existing database without modifying any of its files, e.g. to inspect a database
+    /// owned by another process or a checkpoint. The recovered memtables are kept in memory, and
+    /// all writes, flushes and compactions fail with [`LsmError::ReadOnly`].
+    pub(crate) fn open_read_only(
+        path: impl AsRef<Path>,
+        options: LsmStorageOptions,
+    ) -> Result<Self> {
+        Self::open_inner(path.as_ref(), options, true)
+    }
+
+    fn open_inner(path: &Path, options: LsmStorageOptions, read_only: bool) -> Result<Self> {
+        options.validate()?;
         let mut state = LsmStorageState::create(&options);
-        let path = path.as_ref();
         let mut next_sst_id = 1;
-        let block_cache = Arc::new(BlockCache::new(1 << 20)); // 4GB block cache,
+        let block_cache = create_block_cache(&options);
+        let sst_block_cache = (options.block_cache_capacity > 0).then(|| block_cache.clone());
+        let stats = Arc::new(LsmStats::default());
         let manifest;
+        let mut range_tombstones = Vec::new();
+        let encoded_values_since;
 
         let compaction_controller = match &options.compaction_options {
             CompactionOptions::Leveled(options) => {
@@ -345,24 +1482,59 @@ impl LsmStorageInner {
             CompactionOptions::NoCompaction => CompactionController::NoCompaction,
         };
 
-        if !path.exists() {
-            std::fs::create_dir_all(path).context("failed to create DB dir")?;
-        }
+        let wal_dir = options.wal_dir.as_deref().unwrap_or(path).to_path_buf();
         let manifest_path = path.join("MANIFEST");
+        if read_only {
+            if !manifest_path.exists() {
+                bail!("no database to open read-only at {}", path.display());
+            }
+        } else {
+            if !path.exists() {
+                std::fs::create_dir_all(path).context("failed to create DB dir")?;
+            }
+            if !wal_dir.exists() {
+                std::fs::create_dir_all(&wal_dir).context("failed to create WAL dir")?;
+            }
+            // A crash while compacting the manifest leaves the new manifest in a temporary file,
+            // while the old one is still complete.
+            let manifest_tmp_path = Manifest::tmp_path(&manifest_path);
+            if manifest_tmp_path.exists() {
+                std::fs::remove_file(&manifest_tmp_path)?;
+            }
+        }
         if !manifest_path.exists() {
             if options.enable_wal {
                 state.memtable = Arc::new(MemTable::create_with_wal(
                     state.memtable.id(),
-                    Self::path_of_wal_static(path, state.memtable.id()),
+                    Self::path_of_wal_static(&wal_dir, state.memtable.id()),
+                    options.wal_buffer_size,
                 )?);
             }
-            manifest = Manifest::create(&manifest_path).context("failed to create manifest")?;
-            manifest.add_record_when_init(ManifestRecord::NewMemtable(state.memtable.id()))?;
+            let m = Manifest::create(&manifest_path).context("failed to create manifest")?;
+            m.add_record_when_init(ManifestRecord::Comparator(options.comparator_name.clone()))?;
+            m.add_record_when_init(ManifestRecord::EncodedValuesSince(state.memtable.id()))?;
+            m.add_record_when_init(ManifestRecord::NewMemtable(state.memtable.id()))?;
+            encoded_values_since = state.memtable.id();
+            manifest = Some(m);
         } else {
-            let (m, records) = Manifest::recover(&manifest_path)?;
+            let (m, records) = if read_only {
+                (None, Manifest::read_records(&manifest_path)?)
+            } else {
+                let (m, records) = Manifest::recover(&manifest_path)?;
+                (Some(m), records)
+            };
             let mut memtables = BTreeSet::new();
+            // Databases created before the comparator was recorded always use the default one.
+            let mut comparator = DEFAULT_COMPARATOR.to_string();
+            let mut recorded_encoded_values_since = None;
             for record in records {
                 match record {
+                    ManifestRecord::Comparator(name) => {
+                        comparator = name;
+                    }
+                    ManifestRecord::EncodedValuesSince(id) => {
+                        recorded_encoded_values_since = Some(id);
+                    }
                     ManifestRecord::Flush(sst_id) => {
                         let res = memtables.remove(&sst_id);
                         assert!(res, "memtable not exist?");
@@ -373,10 +1545,28 @@ impl LsmStorageInner {
                         }
                         next_sst_id = next_sst_id.max(sst_id);
                     }
+                    ManifestRecord::FlushToLevel(level, sst_id) => {
+                        let res = memtables.remove(&sst_id);
+                        assert!(res, "memtable not exist?");
+                        // the level is sorted after all SSTs are opened
+                        state.levels[level - 1].1.push(sst_id);
+                        next_sst_id = next_sst_id.max(sst_id);
+                    }
                     ManifestRecord::NewMemtable(x) => {
                         next_sst_id = next_sst_id.max(x);
                         memtables.insert(x);
                     }
+                    ManifestRecord::Ingest(level, sst_id) => {
+                        match level {
+                            // the level is sorted after all SSTs are opened
+                            Some(level) => state.levels[level - 1].1.push(sst_id),
+                            None if compaction_controller.flush_to_l0() => {
+                                state.l0_sstables.insert(0, sst_id)
+                            }
+                            None => state.levels.insert(0, (sst_id, vec![sst_id])),
+                        }
+                        next_sst_id = next_sst_id.max(sst_id);
+                    }
                     ManifestRecord::Compaction(task, output) => {
                         let (new_state, _) = compaction_controller
                             .apply_compaction_result(&state, &task, &output, true);
@@ -385,7 +1575,29 @@ impl LsmStorageInner {
                         next_sst_id =
                             next_sst_id.max(output.iter().max().copied().unwrap_or_default());
                     }
+                    ManifestRecord::DeleteRange(tombstone) => {
+                        range_tombstones.push(tombstone);
+                    }
+                    ManifestRecord::DropRangeTombstones(dropped) => {
+                        range_tombstones.retain(|tombstone| !dropped.contains(tombstone));
+                    }
+                    ManifestRecord::Snapshot(snapshot) => {
+                        state.l0_sstables = snapshot.l0_sstables;
+                        state.levels = snapshot.levels;
+                        memtables = snapshot.memtables.into_iter().collect();
+                        next_sst_id = next_sst_id.max(snapshot.last_sst_id);
+                        range_tombstones = snapshot.range_tombstones;
+                        comparator = snapshot.comparator;
+                        recorded_encoded_values_since = snapshot.encoded_values_since;
+                    }
+                }
+            }
+            if comparator != options.comparator_name {
+                return Err(LsmError::ComparatorMismatch {
+                    stored: comparator,
+                    configured: options.comparator_name.clone(),
                 }
+                .into());
             }
 
             let mut sst_cnt = 0;
@@ -396,70 +1608,129 @@ impl LsmStorageInner {
                 .chain(state.levels.iter().flat_map(|(_, files)| files))
             {
                 let table_id = *table_id;
-                let sst = SsTable::open(
-                    table_id,
-                    Some(block_cache.clone()),
-                    FileObject::open(&Self::path_of_sst_static(path, table_id))
-                        .with_context(|| format!("failed to open SST: {}", table_id))?,
-                )?;
+                let mut sst = open_sst_file(&Self::path_of_sst_static(path, table_id), &options)
+                    .and_then(|file| {
+                        SsTable::open_with_bloom_load(
+                            table_id,
+                            sst_block_cache.clone(),
+                            file,
+                            options.bloom_load,
+                        )
+                    })
+                    .with_context(|| format!("failed to open SST: {}", table_id))?;
+                sst.set_stats(stats.clone());
                 state.sstables.insert(table_id, Arc::new(sst));
                 sst_cnt += 1;
             }
-            println!("{} SSTs opened", sst_cnt);
+            log::info!("{} SSTs opened", sst_cnt);
+            if !read_only {
+                remove_orphan_files(
+                    path,
+                    "sst",
+                    |id| state.sstables.contains_key(&id),
+                    options.move_orphan_files_to_lost_found,
+                )?;
+                remove_orphan_files(
+                    &wal_dir,
+                    "wal",
+                    |id| memtables.contains(&id),
+                    options.move_orphan_files_to_lost_found,
+                )?;
+            }
 
             next_sst_id += 1;
 
-            // Sort SSTs on each level (only for leveled compaction)
-            if let CompactionController::Leveled(_) = &compaction_controller {
-                for (_id, ssts) in &mut state.levels {
-                    ssts.sort_by(|x, y| {
-                        state
-                            .sstables
-                            .get(x)
-                            .unwrap()
-                            .first_key()
-                            .cmp(state.sstables.get(y).unwrap().first_key())
-                    })
-                }
+            // Sort SSTs on each level, which some compactions do not keep in order in recovery.
+            // The SSTs of a tier are in key order as well.
+            for (_id, ssts) in &mut state.levels {
+                ssts.sort_by(|x, y| {
+                    state
+                        .sstables
+                        .get(x)
+                        .unwrap()
+                        .first_key()
+                        .cmp(state.sstables.get(y).unwrap().first_key())
+                })
             }
 
             // recover memtables
             if options.enable_wal {
                 let mut wal_cnt = 0;
                 for id in memtables.iter() {
-                    let memtable =
-                        MemTable::recover_from_wal(*id, Self::path_of_wal_static(path, *id))?;
+                    let wal_path = Self::path_of_wal_static(&wal_dir, *id);
+                    let memtable = if recorded_encoded_values_since.is_none_or(|since| *id < since)
+                    {
+                        MemTable::read_from_raw_wal(*id, wal_path)?
+                    } else if read_only {
+                        MemTable::read_from_wal(*id, wal_path)?
+                    } else {
+                        MemTable::recover_from_wal(*id, wal_path, options.wal_buffer_size)?
+                    };
                     if !memtable.is_empty() {
                         state.imm_memtables.insert(0, Arc::new(memtable));
                         wal_cnt += 1;
                     }
                 }
-                println!("{} WALs recovered", wal_cnt);
-                state.memtable = Arc::new(MemTable::create_with_wal(
+                log::info!("{} WALs recovered", wal_cnt);
+            }
+            state.memtable = Arc::new(if options.enable_wal && !read_only {
+                MemTable::create_with_wal(
                     next_sst_id,
-                    Self::path_of_wal_static(path, next_sst_id),
-                )?);
+                    Self::path_of_wal_static(&wal_dir, next_sst_id),
+                    options.wal_buffer_size,
+                )?
             } else {
-                state.memtable = Arc::new(MemTable::create(next_sst_id));
+                MemTable::create(next_sst_id)
+            });
+            if let Some(m) = &m {
+                if recorded_encoded_values_since.is_none() {
+                    m.add_record_when_init(ManifestRecord::EncodedValuesSince(
+                        state.memtable.id(),
+                    ))?;
+                }
+                m.add_record_when_init(ManifestRecord::NewMemtable(state.memtable.id()))?;
             }
-            m.add_record_when_init(ManifestRecord::NewMemtable(state.memtable.id()))?;
+            encoded_values_since = recorded_encoded_values_since.unwrap_or(state.memtable.id());
             next_sst_id += 1;
             manifest = m;
         };
 
+        let compaction_rate_limiter = options
+            .compaction_rate_limit_bytes_per_sec
+            .map(RateLimiter::new);
+        let bottom_level_size = AtomicU64::new(state.bottom_level_size());
         let storage = Self {
             state: Arc::new(RwLock::new(Arc::new(state))),
             state_lock: Mutex::new(()),
             path: path.to_path_buf(),
+            wal_dir,
             block_cache,
             next_sst_id: AtomicUsize::new(next_sst_id),
             compaction_controller,
-            manifest: Some(manifest),
+            manifest,
             options: options.into(),
             mvcc: None,
             compaction_filters: Arc::new(Mutex::new(Vec::new())),
+            range_tombstones: RwLock::new(Arc::new(range_tombstones)),
+            flush_stats: Mutex::new(FlushStats::default()),
+            stats,
+            running_compactions: Mutex::new(RunningCompactions::default()),
+            compaction_finished: Condvar::new(),
+            compaction_rate_limiter,
+            overwrite_check_lock: Mutex::new(()),
+            key_locks: (0..NUM_KEY_LOCKS).map(|_| Mutex::new(())).collect(),
+            pinned_ssts: Arc::default(),
+            bottom_level_size,
+            background_error: Mutex::new(None),
+            flush_thread_running: AtomicBool::new(false),
+            imm_memtables_flushed: Condvar::new(),
+            imm_memtables_flushed_lock: Mutex::new(()),
+            read_only,
+            encoded_values_since,
         };
-        storage.sync_dir()?;
+        if !read_only {
+            storage.sync_dir()?;
+        }
 
         Ok(storage)
     }
@@ -468,6 +1739,40 @@ impl LsmStorageInner {
         self.state.read().memtable.sync_wal()
     }
 
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(LsmError::ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    pub fn flush_stats(&self) -> FlushStats {
+        self.flush_stats.lock().clone()
+    }
+
+    pub fn stats(&self) -> LsmStatsSnapshot {
+        self.stats.snapshot()
+    }
+
+    /// The block cache for the SSTs to read through, `None` if the block cache is disabled.
+    pub(crate) fn sst_block_cache(&self) -> Option<Arc<BlockCache>> {
+        (self.options.block_cache_capacity > 0).then(|| self.block_cache.clone())
+    }
+
+    /// Create a builder for an SST of the database, with data blocks of `block_size` bytes and the
+    /// rest of the format as configured in the options.
+    pub(crate) fn new_sst_builder(&self, block_size: usize) -> SsTableBuilder {
+        let mut builder = SsTableBuilder::new(block_size);
+        builder.set_file_checksum(self.options.sst_file_checksum);
+        builder.set_compression(self.options.compression);
+        builder.set_mmap(self.options.mmap_reads);
+        builder.set_restart_interval(self.options.block_restart_interval);
+        if let Some(threshold) = self.options.large_value_threshold {
+            builder.set_large_value_threshold(threshold);
+        }
+        builder
+    }
+
     pub fn add_compaction_filter(&self, compaction_filter: CompactionFilter) {
         let mut compaction_filters = self.compaction_filters.lock();
         compaction_filters.push(compaction_filter);
@@ -475,108 +1780,339 @@ impl LsmStorageInner {
 
     /// Get a key from the storage. In day 7, this can be further optimized by using a bloom filter.
     pub fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.stats.record_gets(1);
         let snapshot = {
             let guard = self.state.read();
-            Arc::clone(&guard)
+            self.new_snapshot(&guard, None)
+        }; // drop global lock here
+        snapshot.get(key)
+    }
+
+    /// Take a snapshot of the storage, see [`Snapshot`].
+    pub fn snapshot(&self) -> Snapshot {
+        let guard = self.state.read();
+        let sst_ids = guard.sstables.keys().copied().collect();
+        self.new_snapshot(&guard, Some(sst_ids))
+    }
+
+    /// Take a snapshot for a scan over the range, pinning the SSTs the scan reads until its
+    /// iterator is dropped.
+    fn snapshot_for_scan(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Snapshot {
+        let guard = self.state.read();
+        let sst_ids = guard.sst_ids_in_range(lower, upper);
+        self.new_snapshot(&guard, Some(sst_ids))
+    }
+
+    /// Create a snapshot of `state`, which must be the current state read under the state lock,
+    /// pinning the SSTs `pinned_sst_ids` if set. Pinning under the lock makes sure that a
+    /// compaction replacing the SSTs afterwards finds them pinned when removing their files.
+    fn new_snapshot(
+        &self,
+        state: &Arc<LsmStorageState>,
+        pinned_sst_ids: Option<Vec<usize>>,
+    ) -> Snapshot {
+        let memtable_seq = state.memtable.visible_seq();
+        Snapshot {
+            state: Arc::clone(state),
+            range_tombstones: self.range_tombstones_for_read(state, memtable_seq),
+            memtable_seq,
+            pins: pinned_sst_ids.map(|ids| Arc::new(self.pinned_ssts.pin(ids))),
+        }
+    }
+
+    /// The range tombstones a read of `state` as of `memtable_seq` must apply: those recorded in
+    /// the manifest and those of the memtables.
+    fn range_tombstones_for_read(
+        &self,
+        state: &LsmStorageState,
+        memtable_seq: u64,
+    ) -> Arc<Vec<RangeTombstone>> {
+        let range_tombstones = self.range_tombstones.read().clone();
+        let memtable_range_tombstones = state.memtable_range_tombstones(memtable_seq);
+        if memtable_range_tombstones.is_empty() {
+            return range_tombstones;
+        }
+        Arc::new(
+            range_tombstones
+                .iter()
+                .cloned()
+                .chain(memtable_range_tombstones)
+                .collect(),
+        )
+    }
+
+    /// Get the values of multiple keys, in the order of `keys`, from a single snapshot of the
+    /// storage. Cheaper than a `get` per key, as each SST is only opened once for all keys, and
+    /// keys falling into the same block only read it once.
+    pub fn get_batch(&self, keys: &[&[u8]]) -> Result<Vec<Option<Bytes>>> {
+        self.stats.record_gets(keys.len() as u64);
+        let (snapshot, range_tombstones, memtable_values) = {
+            let guard = self.state.read();
+            let memtable_seq = guard.memtable.visible_seq();
+            let memtable_values = keys
+                .iter()
+                .map(|key| guard.get_from_memtables(key, memtable_seq))
+                .collect::<Vec<_>>();
+            (
+                Arc::clone(&guard),
+                self.range_tombstones.read().clone(),
+                memtable_values,
+            )
+        }; // drop global lock here
+
+        // Look up the keys not found in the memtables in ascending order, so that the iterator of
+        // each SST only moves forward.
+        let mut sst_keys = keys
+            .iter()
+            .zip(&memtable_values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        sst_keys.sort_unstable();
+        sst_keys.dedup();
+        let mut sst_iters = HashMap::new();
+        let mut sst_values = HashMap::with_capacity(sst_keys.len());
+        for key in sst_keys {
+            sst_values.insert(key, snapshot.get_from_ssts(&mut sst_iters, key)?);
+        }
+
+        let now = now_millis();
+        Ok(keys
+            .iter()
+            .zip(memtable_values)
+            .map(|(key, memtable_value)| {
+                let (value, epoch) = memtable_value.or_else(|| sst_values[key].clone())?;
+                if is_shadowed(&range_tombstones, key, epoch) {
+                    return None;
+                }
+                live_value_bytes(value, now)
+            })
+            .collect())
+    }
+
+    /// Find where the latest version of a live key resides, probing in the same order as `get`.
+    /// Returns `None` if the key does not exist or is deleted.
+    pub fn locate(&self, key: &[u8]) -> Result<Option<KeyLocation>> {
+        let (snapshot, range_tombstones) = {
+            let guard = self.state.read();
+            (Arc::clone(&guard), self.range_tombstones.read().clone())
         }; // drop global lock here
 
-        // Search on the current memtable.
+        let now = now_millis();
+        let is_live = |value: &[u8], epoch: usize| {
+            live_value(value, now).is_some() && !is_shadowed(&range_tombstones, key, epoch)
+        };
+
         if let Some(value) = snapshot.memtable.get(key) {
-            if value.is_empty() {
-                // found tomestone, return key not exists
-                return Ok(None);
+            return Ok(
+                is_live(&value, snapshot.memtable.id()).then_some(KeyLocation::ActiveMemtable)
+            );
+        }
+        for (idx, memtable) in snapshot.imm_memtables.iter().enumerate() {
+            if let Some(value) = memtable.get(key) {
+                return Ok(is_live(&value, memtable.id()).then_some(KeyLocation::ImmMemtable(idx)));
             }
-            return Ok(Some(value));
         }
 
-        // Search on immutable memtables.
-        for memtable in snapshot.imm_memtables.iter() {
-            if let Some(value) = memtable.get(key) {
-                if value.is_empty() {
-                    // found tomestone, return key not exists
-                    return Ok(None);
-                }
-                return Ok(Some(value));
+        let probe = |sst_id: usize| -> Result<Option<bool>> {
+            let table = snapshot.sstables[&sst_id].clone();
+            if !may_contain_key(key, &table) {
+                return Ok(None);
+            }
+            let iter = SsTableIterator::create_and_seek_to_key(table, KeySlice::from_slice(key))?;
+            if iter.is_valid() && iter.key().raw_ref() == key {
+                return Ok(Some(is_live(iter.value(), iter.epoch())));
+            }
+            Ok(None)
+        };
+        for &sst_id in &snapshot.l0_sstables {
+            if let Some(live) = probe(sst_id)? {
+                return Ok(live.then_some(KeyLocation::L0(sst_id)));
+            }
+        }
+        for (level, level_sst_ids) in &snapshot.levels {
+            let Some(sst_id) = snapshot.level_sst_for_key(level_sst_ids, key) else {
+                continue;
+            };
+            if let Some(live) = probe(sst_id)? {
+                return Ok(live.then_some(KeyLocation::Level(*level, sst_id)));
             }
         }
+        Ok(None)
+    }
 
-        let mut l0_iters = Vec::with_capacity(snapshot.l0_sstables.len());
+    /// Replace the state with `snapshot`, which changes the SSTs.
+    pub(crate) fn install_sst_state(
+        &self,
+        guard: &mut RwLockWriteGuard<'_, Arc<LsmStorageState>>,
+        snapshot: LsmStorageState,
+    ) {
+        self.bottom_level_size
+            .store(snapshot.bottom_level_size(), Ordering::Relaxed);
+        **guard = Arc::new(snapshot);
+    }
 
-        let keep_table = |key: &[u8], table: &SsTable| {
-            if key_within(
-                key,
-                table.first_key().as_key_slice(),
-                table.last_key().as_key_slice(),
-            ) {
-                if let Some(bloom) = &table.bloom {
-                    if bloom.may_contain(farmhash::fingerprint32(key)) {
-                        return true;
+    /// Change the space amplification ratio that triggers a full compaction of the tiers, which
+    /// is only supported with tiered compaction.
+    pub fn set_max_size_amplification_percent(&self, percent: usize) -> Result<()> {
+        let CompactionController::Tiered(ctrl) = &self.compaction_controller else {
+            return Err(LsmError::InvalidOptions(
+                "the space amplification ratio is only used by tiered compaction".to_string(),
+            )
+            .into());
+        };
+        ctrl.set_max_size_amplification_percent(percent);
+        Ok(())
+    }
+
+    /// Check whether the bottom level has reached `max_db_size_bytes`.
+    fn check_db_size(&self) -> Result<()> {
+        let Some(limit) = self.options.max_db_size_bytes else {
+            return Ok(());
+        };
+        let size = self.bottom_level_size.load(Ordering::Relaxed);
+        if size >= limit {
+            return Err(LsmError::OutOfSpace { size, limit }.into());
+        }
+        Ok(())
+    }
+
+    /// Apply a batch of puts and deletes to the current memtable as a single write: reads and
+    /// scans see either all records of the batch or none of them, and the memtable is frozen at
+    /// most once.
+    pub fn write_batch<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<()> {
+        self.write_batch_with_expiry(batch, None)
+    }
+
+    /// Apply a batch like `write_batch`, where the puts expire at `expire_at` milliseconds since
+    /// the Unix epoch, if set.
+    fn write_batch_with_expiry<T: AsRef<[u8]>>(
+        &self,
+        batch: &[WriteBatchRecord<T>],
+        expire_at: Option<u64>,
+    ) -> Result<()> {
+        self.check_writable()?;
+        self.wait_for_imm_memtables();
+        // an empty key cannot be told apart from an exhausted iterator
+        if batch.iter().any(|record| match record {
+            WriteBatchRecord::Put(key, _) | WriteBatchRecord::Del(key) => key.as_ref().is_empty(),
+        }) {
+            return Err(LsmError::EmptyKey.into());
+        }
+        if batch
+            .iter()
+            .any(|record| matches!(record, WriteBatchRecord::Put(_, _)))
+        {
+            self.check_db_size()?;
+        }
+        let _overwrite_check_guard = if self.options.reject_overwrites {
+            let guard = self.overwrite_check_lock.lock();
+            self.check_overwrites(batch)?;
+            Some(guard)
+        } else {
+            None
+        };
+        let values = batch
+            .iter()
+            .map(|record| match record {
+                WriteBatchRecord::Put(_, value) => {
+                    let value = value.as_ref();
+                    assert!(!value.is_empty(), "value cannot be empty");
+                    match expire_at {
+                        Some(expire_at) => Cow::Owned(encode_value_with_expiry(value, expire_at)),
+                        None => encode_value(value),
                     }
-                } else {
-                    return true;
                 }
-            }
-            false
+                WriteBatchRecord::Del(_) => Cow::Borrowed(&b""[..]),
+            })
+            .collect::<Vec<_>>();
+        // Reject what the block and WAL encodings cannot represent before anything is written.
+        let max_value_len = match self.options.large_value_threshold {
+            Some(_) => u32::MAX as usize,
+            None => MAX_ENTRY_FIELD_LEN,
         };
-
-        for table in snapshot.l0_sstables.iter() {
-            let table = snapshot.sstables[table].clone();
-            if keep_table(key, &table) {
-                l0_iters.push(Box::new(SsTableIterator::create_and_seek_to_key(
-                    table,
-                    KeySlice::from_slice(key),
-                )?));
+        for (record, value) in batch.iter().zip(&values) {
+            let (WriteBatchRecord::Put(key, _) | WriteBatchRecord::Del(key)) = record;
+            let key = key.as_ref();
+            if key.len() > MAX_ENTRY_FIELD_LEN {
+                return Err(LsmError::KeyTooLarge {
+                    len: key.len(),
+                    limit: MAX_ENTRY_FIELD_LEN,
+                }
+                .into());
             }
-        }
-        let l0_iter = MergeIterator::create(l0_iters);
-        let mut level_iters = Vec::with_capacity(snapshot.levels.len());
-        for (_, level_sst_ids) in &snapshot.levels {
-            let mut level_ssts = Vec::with_capacity(level_sst_ids.len());
-            for table in level_sst_ids {
-                let table = snapshot.sstables[table].clone();
-                if keep_table(key, &table) {
-                    level_ssts.push(table);
+            if let WriteBatchRecord::Put(_, user_value) = record
+                && value.len() > max_value_len
+            {
+                // the limit of the user value excludes the header of the encoding
+                let header_len = value.len() - user_value.as_ref().len();
+                return Err(LsmError::ValueTooLarge {
+                    len: user_value.as_ref().len(),
+                    limit: max_value_len - header_len,
                 }
+                .into());
             }
-            let level_iter =
-                SstConcatIterator::create_and_seek_to_key(level_ssts, KeySlice::from_slice(key))?;
-            level_iters.push(Box::new(level_iter));
         }
+        let data = batch
+            .iter()
+            .zip(&values)
+            .map(|(record, value)| match record {
+                WriteBatchRecord::Put(key, _) | WriteBatchRecord::Del(key) => {
+                    (KeySlice::from_slice(key.as_ref()), value.as_ref())
+                }
+            })
+            .collect::<Vec<_>>();
+        // The memtable makes the whole batch visible at once. It is written without holding the
+        // state lock, and rejects the batch if it is frozen in the meantime, in which case the
+        // batch goes to the new memtable.
+        let size = loop {
+            let memtable = self.state.read().memtable.clone();
+            if memtable.try_put_batch(&data)? {
+                break memtable.approximate_size();
+            }
+        };
+        let deletes = data.iter().filter(|(_, value)| value.is_empty()).count();
+        self.stats
+            .record_writes((data.len() - deletes) as u64, deletes as u64);
+        self.try_freeze(size)?;
+        Ok(())
+    }
 
-        let iter = TwoMergeIterator::create(l0_iter, MergeIterator::create(level_iters))?;
-
-        if iter.is_valid() && iter.key().raw_ref() == key && !iter.value().is_empty() {
-            return Ok(Some(Bytes::copy_from_slice(iter.value())));
+    /// Block while `max_imm_memtables` immutable memtables are waiting for the flush thread.
+    fn wait_for_imm_memtables(&self) {
+        let Some(max) = self.options.max_imm_memtables else {
+            return;
+        };
+        let mut guard = self.imm_memtables_flushed_lock.lock();
+        while self.flush_thread_running.load(Ordering::SeqCst)
+            && self.state.read().imm_memtables.len() >= max
+        {
+            // also wake up periodically in case the notification is missed
+            self.imm_memtables_flushed
+                .wait_for(&mut guard, Duration::from_millis(10));
         }
-        Ok(None)
     }
 
-    pub fn write_batch<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<()> {
+    /// Wake up the writers stalled by `max_imm_memtables`.
+    pub(crate) fn notify_imm_memtables_flushed(&self) {
+        let _guard = self.imm_memtables_flushed_lock.lock();
+        self.imm_memtables_flushed.notify_all();
+    }
+
+    /// Check that none of the keys put by the batch exists, either in the storage or earlier in
+    /// the batch. Must be called with `overwrite_check_lock` held.
+    fn check_overwrites<T: AsRef<[u8]>>(&self, batch: &[WriteBatchRecord<T>]) -> Result<()> {
+        let mut batch_keys = BTreeSet::new();
         for record in batch {
             match record {
-                WriteBatchRecord::Del(key) => {
+                WriteBatchRecord::Put(key, _) => {
                     let key = key.as_ref();
-                    assert!(!key.is_empty(), "key cannot be empty");
-                    let size;
-                    {
-                        let guard = self.state.read();
-                        guard.memtable.put(key, b"")?;
-                        size = guard.memtable.approximate_size();
+                    if !batch_keys.insert(key) || self.get(key)?.is_some() {
+                        return Err(LsmError::AlreadyExists(Bytes::copy_from_slice(key)).into());
                     }
-                    self.try_freeze(size)?;
                 }
-                WriteBatchRecord::Put(key, value) => {
-                    let key = key.as_ref();
-                    let value = value.as_ref();
-                    assert!(!key.is_empty(), "key cannot be empty");
-                    assert!(!value.is_empty(), "value cannot be empty");
-                    let size;
-                    {
-                        let guard = self.state.read();
-                        guard.memtable.put(key, value)?;
-                        size = guard.memtable.approximate_size();
-                    }
-                    self.try_freeze(size)?;
+                WriteBatchRecord::Del(key) => {
+                    batch_keys.remove(key.as_ref());
                 }
             }
         }
@@ -588,11 +2124,117 @@ impl LsmStorageInner {
         self.write_batch(&[WriteBatchRecord::Put(key, value)])
     }
 
+    /// Put a key-value pair which reads as absent once `ttl` has passed, and is dropped by the
+    /// compactions to the bottom level afterwards. A later `put` of the key without TTL replaces
+    /// it, clearing the expiry.
+    pub fn put_with_ttl(&self, key: &[u8], value: &[u8], ttl: Duration) -> Result<()> {
+        self.write_batch_with_expiry(&[WriteBatchRecord::Put(key, value)], Some(expire_at(ttl)))
+    }
+
     /// Remove a key from the storage by writing an empty value.
     pub fn delete(&self, key: &[u8]) -> Result<()> {
         self.write_batch(&[WriteBatchRecord::Del(key)])
     }
 
+    /// Lock the stripe of `key` for a read-then-write of it.
+    fn lock_key(&self, key: &[u8]) -> MutexGuard<'_, ()> {
+        self.key_locks[farmhash::fingerprint32(key) as usize % NUM_KEY_LOCKS].lock()
+    }
+
+    /// Put a key-value pair only if the key does not have a live value, returning whether it was
+    /// put. Atomic against other conditional writes of the key, but not against plain writes.
+    pub fn put_if_absent(&self, key: &[u8], value: &[u8]) -> Result<bool> {
+        self.compare_and_swap(key, None, value)
+    }
+
+    /// Put `new` only if the live value of the key is `expected`, or if the key does not have a
+    /// live value when `expected` is `None`, returning whether it was put. Atomic against other
+    /// conditional writes of the key, but not against plain writes.
+    pub fn compare_and_swap(
+        &self,
+        key: &[u8],
+        expected: Option<&[u8]>,
+        new: &[u8],
+    ) -> Result<bool> {
+        let _key_guard = self.lock_key(key);
+        if self.get(key)?.as_deref() != expected {
+            return Ok(false);
+        }
+        self.put(key, new)?;
+        Ok(true)
+    }
+
+    /// Delete all keys within the range by writing a range tombstone into the memtable and its
+    /// WAL, which shadows every key in the range written before this call. A write of a key in the
+    /// range after this call takes precedence over the tombstone.
+    ///
+    /// Once the memtable is flushed, the tombstone is recorded in the manifest, where it is kept
+    /// until compaction has dropped every key it shadows.
+    pub fn delete_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<()> {
+        self.check_writable()?;
+        for bound in [lower, upper] {
+            if let Bound::Included(key) | Bound::Excluded(key) = bound
+                && key.len() > MAX_ENTRY_FIELD_LEN
+            {
+                return Err(LsmError::KeyTooLarge {
+                    len: key.len(),
+                    limit: MAX_ENTRY_FIELD_LEN,
+                }
+                .into());
+            }
+        }
+        // The state lock keeps the memtable from being frozen meanwhile.
+        let state_lock = self.state_lock.lock();
+        let memtable = self.state.read().memtable.clone();
+        if !memtable.is_empty() {
+            if !memtable.try_delete_range(lower, upper)? {
+                bail!("memtable {} is frozen", memtable.id());
+            }
+            return Ok(());
+        }
+        // An empty memtable has nothing for the tombstone to delete, and a memtable holding
+        // nothing but tombstones could not be flushed into an SST, so the tombstone goes to the
+        // manifest right away, where it applies to the earlier memtables.
+        let Some(epoch) = memtable.id().checked_sub(1) else {
+            return Ok(());
+        };
+        let tombstone = RangeTombstone::new(epoch, lower, upper);
+        self.add_range_tombstones(&state_lock, vec![tombstone])?;
+        self.maybe_compact_manifest(&state_lock)
+    }
+
+    /// Record range tombstones in the manifest and add them to `range_tombstones`, skipping the
+    /// ones already there, e.g. those of a memtable flushed before a crash and replayed from its
+    /// WAL again.
+    fn add_range_tombstones(
+        &self,
+        state_lock: &MutexGuard<'_, ()>,
+        tombstones: Vec<RangeTombstone>,
+    ) -> Result<()> {
+        let existing = self.range_tombstones.read().clone();
+        let tombstones = tombstones
+            .into_iter()
+            .filter(|tombstone| !existing.contains(tombstone))
+            .collect::<Vec<_>>();
+        for tombstone in &tombstones {
+            self.manifest
+                .as_ref()
+                .unwrap()
+                .add_record(state_lock, ManifestRecord::DeleteRange(tombstone.clone()))?;
+        }
+        if tombstones.is_empty() {
+            return Ok(());
+        }
+        let state_guard = self.state.write();
+        let mut range_tombstones = self.range_tombstones.write();
+        let mut new_range_tombstones = range_tombstones.as_ref().clone();
+        new_range_tombstones.extend(tombstones);
+        *range_tombstones = Arc::new(new_range_tombstones);
+        drop(range_tombstones);
+        drop(state_guard);
+        Ok(())
+    }
+
     fn try_freeze(&self, estimated_size: usize) -> Result<()> {
         if estimated_size >= self.options.target_sst_size {
             let state_lock = self.state_lock.lock();
@@ -600,7 +2242,9 @@ impl LsmStorageInner {
             // the memtable could have already been frozen, check again to ensure we really need to freeze
             if guard.memtable.approximate_size() >= self.options.target_sst_size {
                 drop(guard);
-                self.force_freeze_memtable(&state_lock)?;
+                let frozen_memtable_id = self.force_freeze_memtable(&state_lock)?;
+                drop(state_lock);
+                self.notify_memtable_freeze(frozen_memtable_id);
             }
         }
         Ok(())
@@ -614,20 +2258,76 @@ impl LsmStorageInner {
         Self::path_of_sst_static(&self.path, id)
     }
 
+    /// Delete the files of SSTs that are no longer part of the state. A failure only leaks the
+    /// file until the next `open`, which removes SSTs unknown to the manifest.
+    pub(crate) fn remove_sst_files(&self, ids: impl IntoIterator<Item = usize>) {
+        for id in ids {
+            if let Err(e) = self.pinned_ssts.remove_file(id, self.path_of_sst(id)) {
+                log::warn!("failed to remove {}.sst: {}", id, e);
+            }
+        }
+    }
+
     pub(crate) fn path_of_wal_static(path: impl AsRef<Path>, id: usize) -> PathBuf {
         path.as_ref().join(format!("{:05}.wal", id))
     }
 
     pub(crate) fn path_of_wal(&self, id: usize) -> PathBuf {
-        Self::path_of_wal_static(&self.path, id)
+        Self::path_of_wal_static(&self.wal_dir, id)
     }
 
     pub(super) fn sync_dir(&self) -> Result<()> {
         File::open(&self.path)?.sync_all()?;
+        if self.wal_dir != self.path {
+            File::open(&self.wal_dir)?.sync_all()?;
+        }
         Ok(())
     }
 
-    fn freeze_memtable_with_memtable(&self, memtable: Arc<MemTable>) -> Result<()> {
+    /// Rewrite the manifest as a single snapshot of the current state, so that the next `open`
+    /// does not need to replay the whole history.
+    pub(crate) fn compact_manifest(&self) -> Result<()> {
+        self.check_writable()?;
+        self.compact_manifest_with_lock(&self.state_lock.lock())
+    }
+
+    /// Compact the manifest if it has reached `manifest_compaction_threshold` records. Must be
+    /// called after the state changes recorded in the manifest are applied.
+    pub(crate) fn maybe_compact_manifest(&self, state_lock: &MutexGuard<()>) -> Result<()> {
+        let manifest = self.manifest.as_ref().unwrap();
+        match self.options.manifest_compaction_threshold {
+            Some(threshold) if manifest.num_records() >= threshold => {
+                self.compact_manifest_with_lock(state_lock)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Describe `state` as a manifest snapshot. Must be called with `state_lock` held.
+    fn manifest_snapshot(&self, state: &LsmStorageState) -> ManifestSnapshot {
+        ManifestSnapshot {
+            l0_sstables: state.l0_sstables.clone(),
+            levels: state.levels.clone(),
+            memtables: std::iter::once(state.memtable.id())
+                .chain(state.imm_memtables.iter().map(|memtable| memtable.id()))
+                .collect(),
+            last_sst_id: self.next_sst_id.load(std::sync::atomic::Ordering::SeqCst) - 1,
+            range_tombstones: self.range_tombstones.read().to_vec(),
+            comparator: self.options.comparator_name.clone(),
+            encoded_values_since: Some(self.encoded_values_since),
+        }
+    }
+
+    fn compact_manifest_with_lock(&self, state_lock: &MutexGuard<()>) -> Result<()> {
+        let record = ManifestRecord::Snapshot(self.manifest_snapshot(&self.state.read()));
+        self.manifest
+            .as_ref()
+            .unwrap()
+            .rewrite(state_lock, self.path.join("MANIFEST"), &[record])
+    }
+
+    /// Replace the current memtable with `memtable`, returning the id of the frozen memtable.
+    fn freeze_memtable_with_memtable(&self, memtable: Arc<MemTable>) -> Result<usize> {
         let mut guard = self.state.write();
         // Swap the current memtable with a new one.
         let mut snapshot = guard.as_ref().clone();
@@ -638,188 +2338,527 @@ impl LsmStorageInner {
         *guard = Arc::new(snapshot);
 
         drop(guard);
+        // Wait for the writes that picked the memtable before it was swapped, so that they are
+        // all synced and flushed with it.
+        old_memtable.freeze();
         old_memtable.sync_wal()?;
 
-        Ok(())
+        Ok(old_memtable.id())
     }
 
-    /// Force freeze the current memtable to an immutable memtable
-    pub fn force_freeze_memtable(&self, state_lock_observer: &MutexGuard<'_, ()>) -> Result<()> {
+    /// Force freeze the current memtable to an immutable memtable, returning the id of the frozen
+    /// memtable. The event listener is not notified, as the state lock is held; callers pass the
+    /// id to `notify_memtable_freeze` once they release it.
+    pub fn force_freeze_memtable(&self, state_lock_observer: &MutexGuard<'_, ()>) -> Result<usize> {
+        self.check_writable()?;
         let memtable_id = self.next_sst_id();
         let memtable = if self.options.enable_wal {
             Arc::new(MemTable::create_with_wal(
                 memtable_id,
                 self.path_of_wal(memtable_id),
+                self.options.wal_buffer_size,
             )?)
         } else {
             Arc::new(MemTable::create(memtable_id))
         };
 
-        self.freeze_memtable_with_memtable(memtable)?;
+        let frozen_memtable_id = self.freeze_memtable_with_memtable(memtable)?;
 
         self.manifest.as_ref().unwrap().add_record(
             state_lock_observer,
             ManifestRecord::NewMemtable(memtable_id),
         )?;
+        self.maybe_compact_manifest(state_lock_observer)?;
         self.sync_dir()?;
 
-        Ok(())
+        Ok(frozen_memtable_id)
+    }
+
+    /// Tell the event listener that the memtable `id` was frozen. Must not be called with the
+    /// state lock held.
+    pub(crate) fn notify_memtable_freeze(&self, id: usize) {
+        if let Some(listener) = &self.options.event_listener {
+            listener.on_memtable_freeze(id);
+        }
     }
 
-    /// Force flush the earliest-created immutable memtable to disk
+    /// Force flush the earliest-created immutable memtable to disk. Does nothing if there are no
+    /// immutable memtables, e.g. when another thread flushed them first.
     pub fn force_flush_next_imm_memtable(&self) -> Result<()> {
+        let max_level = if self.options.flush_to_lowest_disjoint_level
+            && matches!(self.compaction_controller, CompactionController::Leveled(_))
+        {
+            self.state.read().levels.len()
+        } else {
+            0
+        };
+        self.flush_next_imm_memtable(max_level)
+    }
+
+    /// Flush the earliest-created immutable memtable like `force_flush_next_imm_memtable`, but
+    /// directly into the lowest level down to `level` where neither the level nor anything above
+    /// it overlaps with the memtable, or into L0 if there is no such level. Only supported with
+    /// compactions that flush to L0, i.e. not tiered compaction.
+    pub fn force_flush_to_level(&self, level: usize) -> Result<()> {
+        if !self.compaction_controller.flush_to_l0() {
+            bail!("cannot flush to a level with tiered compaction");
+        }
+        let num_levels = self.state.read().levels.len();
+        if !(1..=num_levels).contains(&level) {
+            bail!("level {} out of range 1..={}", level, num_levels);
+        }
+        self.flush_next_imm_memtable(level)
+    }
+
+    /// Flush the earliest-created immutable memtable into the lowest disjoint level down to
+    /// `max_level`, where 0 always flushes to L0 (or a new tier).
+    fn flush_next_imm_memtable(&self, max_level: usize) -> Result<()> {
+        self.check_writable()?;
         let state_lock = self.state_lock.lock();
+        let start = Instant::now();
 
         let flush_memtable;
 
         {
             let guard = self.state.read();
-            flush_memtable = guard
-                .imm_memtables
-                .last()
-                .expect("no imm memtables!")
-                .clone();
+            let Some(memtable) = guard.imm_memtables.last() else {
+                return Ok(());
+            };
+            flush_memtable = memtable.clone();
         }
 
-        let mut builder = SsTableBuilder::new(self.options.block_size);
+        let mut builder = self.new_sst_builder(self.options.block_size);
         flush_memtable.flush(&mut builder)?;
         let sst_id = flush_memtable.id();
-        let sst = Arc::new(builder.build(
-            sst_id,
-            Some(self.block_cache.clone()),
-            self.path_of_sst(sst_id),
-        )?);
+        let mut sst = builder.build(sst_id, self.sst_block_cache(), self.path_of_sst(sst_id))?;
+        sst.set_stats(self.stats.clone());
+        let sst = Arc::new(sst);
+        let sst_size = sst.table_size();
+        let (first_key, last_key) = (sst.first_key().clone(), sst.last_key().clone());
+
+        // The SST only holds the keys the range tombstones of the memtable delete in it, so the
+        // tombstones move to the manifest for the earlier data before the memtable is dropped.
+        let range_tombstones =
+            flush_memtable.range_tombstones_for_earlier_data(flush_memtable.visible_seq());
+        self.add_range_tombstones(&state_lock, range_tombstones)?;
 
         // Add the flushed L0 table to the list.
+        let flushed_to_level;
         {
+            // A compaction started from an earlier state may write overlapping SSTs into a level
+            // it uses, so the SST only goes to the levels no compaction uses. The compactions
+            // cannot start while the SST is placed.
+            let running_compactions = self.running_compactions.lock();
             let mut guard = self.state.write();
             let mut snapshot = guard.as_ref().clone();
             // Remove the memtable from the immutable memtables.
             let mem = snapshot.imm_memtables.pop().unwrap();
             assert_eq!(mem.id(), sst_id);
-            // Add L0 table
-            if self.compaction_controller.flush_to_l0() {
+            flushed_to_level = if max_level > 0 {
+                lowest_disjoint_level(&snapshot, &running_compactions, &sst, max_level)
+            } else {
+                None
+            };
+            if let Some(level) = flushed_to_level {
+                // Skip L0 as nothing newer than the memtable overlaps with it
+                let level_ssts = &mut snapshot.levels[level - 1].1;
+                let pos = level_ssts
+                    .partition_point(|id| snapshot.sstables[id].first_key() < sst.first_key());
+                level_ssts.insert(pos, sst_id);
+            } else if self.compaction_controller.flush_to_l0() {
                 // In leveled compaction or no compaction, simply flush to L0
                 snapshot.l0_sstables.insert(0, sst_id);
             } else {
                 // In tiered compaction, create a new tier
                 snapshot.levels.insert(0, (sst_id, vec![sst_id]));
             }
-            println!("flushed {}.sst with size={}", sst_id, sst.table_size());
+            log::info!("flushed {}.sst with size={}", sst_id, sst.table_size());
             snapshot.sstables.insert(sst_id, sst);
             // Update the snapshot.
-            *guard = Arc::new(snapshot);
+            self.install_sst_state(&mut guard, snapshot);
         }
+        self.notify_imm_memtables_flushed();
 
         if self.options.enable_wal {
             std::fs::remove_file(self.path_of_wal(sst_id))?;
         }
 
-        self.manifest
-            .as_ref()
-            .unwrap()
-            .add_record(&state_lock, ManifestRecord::Flush(sst_id))?;
+        self.manifest.as_ref().unwrap().add_record(
+            &state_lock,
+            match flushed_to_level {
+                Some(level) => ManifestRecord::FlushToLevel(level, sst_id),
+                None => ManifestRecord::Flush(sst_id),
+            },
+        )?;
+        self.maybe_compact_manifest(&state_lock)?;
 
         self.sync_dir()?;
+        drop(state_lock);
+
+        let mut flush_stats = self.flush_stats.lock();
+        flush_stats.flushes += 1;
+        flush_stats.bytes_flushed += sst_size;
+        flush_stats.last_flush_duration = start.elapsed();
+        drop(flush_stats);
+        self.stats.record_flush();
+
+        if let Some(listener) = &self.options.event_listener {
+            listener.on_flush(sst_id, first_key.raw_ref(), last_key.raw_ref());
+        }
 
         Ok(())
     }
 
-    pub fn new_txn(&self) -> Result<()> {
-        // no-op
+    /// Freeze the current memtable and flush it together with all immutable memtables, so that
+    /// everything written before this call is in SSTs when it returns. Memtables frozen by
+    /// concurrent writes after this call are left to the flush thread.
+    pub fn flush_and_wait(&self) -> Result<()> {
+        let newest_memtable_id = {
+            let state_lock = self.state_lock.lock();
+            if !self.state.read().memtable.is_empty() {
+                let frozen_memtable_id = self.force_freeze_memtable(&state_lock)?;
+                drop(state_lock);
+                self.notify_memtable_freeze(frozen_memtable_id);
+            }
+            let guard = self.state.read();
+            let Some(memtable) = guard.imm_memtables.first() else {
+                return Ok(());
+            };
+            memtable.id()
+        };
+        while self
+            .state
+            .read()
+            .imm_memtables
+            .last()
+            .is_some_and(|memtable| memtable.id() <= newest_memtable_id)
+        {
+            self.force_flush_next_imm_memtable()?;
+        }
         Ok(())
     }
 
-    /// Create an iterator over a range of keys.
-    pub fn scan(
-        &self,
-        lower: Bound<&[u8]>,
-        upper: Bound<&[u8]>,
-    ) -> Result<FusedIterator<LsmIterator>> {
-        let snapshot = {
+    /// Add an SST built outside of the storage, e.g. by [`SsTableBuilder`] from already sorted
+    /// data, without rewriting its data. The file is copied into the storage directory, and the
+    /// ingested keys take precedence over all data written before this call.
+    ///
+    /// The SST goes to the lowest level it does not overlap with in leveled compaction, and to L0
+    /// otherwise. It must be built with an id (its epoch, see [`RangeTombstone`]) smaller than the
+    /// active memtable, e.g. 0, and must not overlap with any range tombstone.
+    pub fn ingest_sst(&self, path: &Path) -> Result<()> {
+        self.check_writable()?;
+        let sst = SsTable::open(0, None, FileObject::open(path)?)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let (first_key, last_key) = (
+            sst.first_key().as_key_slice(),
+            sst.last_key().as_key_slice(),
+        );
+        let overlaps_memtable = {
             let guard = self.state.read();
-            Arc::clone(&guard)
-        }; // drop global lock here
+            std::iter::once(&guard.memtable)
+                .chain(guard.imm_memtables.iter())
+                .filter_map(|memtable| memtable.key_range())
+                .any(|(first, last)| {
+                    range_overlap(
+                        Bound::Included(&first),
+                        Bound::Included(&last),
+                        first_key,
+                        last_key,
+                    )
+                })
+        };
+        if overlaps_memtable {
+            // The memtables are newer than all SSTs, flush them so that they do not shadow the
+            // ingested keys.
+            self.flush_and_wait()?;
+        }
 
-        let mut memtable_iters = Vec::with_capacity(snapshot.imm_memtables.len() + 1);
-        memtable_iters.push(Box::new(snapshot.memtable.scan(lower, upper)));
-        for memtable in snapshot.imm_memtables.iter() {
-            memtable_iters.push(Box::new(memtable.scan(lower, upper)));
+        let state_lock = self.state_lock.lock();
+        let state = self.state.read().clone();
+        if sst.epoch() >= state.memtable.id() {
+            bail!(
+                "cannot ingest an SST with epoch {}, build it with a smaller id",
+                sst.epoch()
+            );
+        }
+        // Range deletes cannot be written into the memtables while holding the state lock.
+        if self
+            .range_tombstones_for_read(&state, state.memtable.visible_seq())
+            .iter()
+            .any(|tombstone| tombstone.overlaps(first_key, last_key))
+        {
+            bail!("cannot ingest an SST overlapping with a range tombstone");
         }
-        let memtable_iter = MergeIterator::create(memtable_iters);
 
-        let mut table_iters = Vec::with_capacity(snapshot.l0_sstables.len());
-        for table_id in snapshot.l0_sstables.iter() {
-            let table = snapshot.sstables[table_id].clone();
-            if range_overlap(
-                lower,
-                upper,
-                table.first_key().as_key_slice(),
-                table.last_key().as_key_slice(),
-            ) {
-                let iter = match lower {
-                    Bound::Included(key) => {
-                        SsTableIterator::create_and_seek_to_key(table, KeySlice::from_slice(key))?
-                    }
-                    Bound::Excluded(key) => {
-                        let mut iter = SsTableIterator::create_and_seek_to_key(
-                            table,
-                            KeySlice::from_slice(key),
-                        )?;
-                        if iter.is_valid() && iter.key().raw_ref() == key {
-                            iter.next()?;
-                        }
-                        iter
-                    }
-                    Bound::Unbounded => SsTableIterator::create_and_seek_to_first(table)?,
+        let sst_id = self.next_sst_id();
+        let sst_path = self.path_of_sst(sst_id);
+        std::fs::copy(path, &sst_path)?;
+        File::open(&sst_path)?.sync_all()?;
+        let mut sst = SsTable::open_with_bloom_load(
+            sst_id,
+            self.sst_block_cache(),
+            open_sst_file(&sst_path, &self.options)?,
+            self.options.bloom_load,
+        )?;
+        sst.set_stats(self.stats.clone());
+        let sst = Arc::new(sst);
+
+        let ingested_to_level;
+        {
+            // Like flushes, skip the levels running compactions use, see `flush_next_imm_memtable`.
+            let running_compactions = self.running_compactions.lock();
+            let mut guard = self.state.write();
+            let mut snapshot = guard.as_ref().clone();
+            ingested_to_level =
+                if matches!(self.compaction_controller, CompactionController::Leveled(_)) {
+                    lowest_disjoint_level(
+                        &snapshot,
+                        &running_compactions,
+                        &sst,
+                        snapshot.levels.len(),
+                    )
+                } else {
+                    None
                 };
+            if let Some(level) = ingested_to_level {
+                let level_ssts = &mut snapshot.levels[level - 1].1;
+                let pos = level_ssts
+                    .partition_point(|id| snapshot.sstables[id].first_key() < sst.first_key());
+                level_ssts.insert(pos, sst_id);
+            } else if self.compaction_controller.flush_to_l0() {
+                snapshot.l0_sstables.insert(0, sst_id);
+            } else {
+                snapshot.levels.insert(0, (sst_id, vec![sst_id]));
+            }
+            log::info!("ingested {}.sst to {:?}", sst_id, ingested_to_level);
+            snapshot.sstables.insert(sst_id, sst);
+            self.install_sst_state(&mut guard, snapshot);
+        }
 
-                table_iters.push(Box::new(iter));
+        self.manifest.as_ref().unwrap().add_record(
+            &state_lock,
+            ManifestRecord::Ingest(ingested_to_level, sst_id),
+        )?;
+        self.maybe_compact_manifest(&state_lock)?;
+        self.sync_dir()?;
+        Ok(())
+    }
+
+    /// Write a copy of the database into the directory `dest`, which can be opened like the
+    /// original one. The copy contains everything written before this call; writes concurrent
+    /// with it may or may not be included. Without the WAL, the memtables are flushed first.
+    ///
+    /// Only capturing the state and copying the WALs hold the state lock. SSTs are hard-linked
+    /// when possible, and copied through the open files otherwise, so that SSTs removed by a
+    /// compaction in the meantime are still copied. The WALs are copied into `dest` as well.
+    pub fn checkpoint(&self, dest: &Path) -> Result<()> {
+        if !self.options.enable_wal {
+            self.flush_and_wait()?;
+        }
+        std::fs::create_dir_all(dest)?;
+        let (snapshot, manifest_snapshot) = {
+            let _state_lock = self.state_lock.lock();
+            let snapshot = {
+                let guard = self.state.read();
+                Arc::clone(&guard)
+            };
+            let mut manifest_snapshot = self.manifest_snapshot(&snapshot);
+            if self.options.enable_wal {
+                // The immutable memtables are synced when frozen, and flushes remove their WALs
+                // while holding the state lock.
+                snapshot.memtable.sync_wal()?;
+                for id in &manifest_snapshot.memtables {
+                    std::fs::copy(self.path_of_wal(*id), Self::path_of_wal_static(dest, *id))?;
+                }
+            } else {
+                manifest_snapshot.memtables.clear();
+            }
+            (snapshot, manifest_snapshot)
+        };
+
+        for (id, sst) in &snapshot.sstables {
+            let sst_path = Self::path_of_sst_static(dest, *id);
+            if std::fs::hard_link(self.path_of_sst(*id), &sst_path).is_err() {
+                sst.file.copy_to(&sst_path)?;
             }
         }
+        // Only write the manifest once all the files it refers to exist.
+        Manifest::create(dest.join("MANIFEST"))?
+            .add_record_when_init(ManifestRecord::Snapshot(manifest_snapshot))?;
+        File::open(dest)?.sync_all()?;
+        Ok(())
+    }
 
-        let l0_iter = MergeIterator::create(table_iters);
-        let mut level_iters = Vec::with_capacity(snapshot.levels.len());
-        for (_, level_sst_ids) in &snapshot.levels {
-            let mut level_ssts = Vec::with_capacity(level_sst_ids.len());
-            for table in level_sst_ids {
-                let table = snapshot.sstables[table].clone();
-                if range_overlap(
+    /// A no-op: keys carry no timestamp in this engine, so there is a single version of each key
+    /// to read. Transactions over timestamped keys are implemented by the `mini-lsm-mvcc` crate.
+    /// Within this engine, [`Self::snapshot`] gives a consistent view of the storage.
+    pub fn new_txn(&self) -> Result<()> {
+        // no-op
+        Ok(())
+    }
+
+    /// Ids of all SSTs in the current state: L0 from latest to earliest, then the levels.
+    pub fn all_sst_ids(&self) -> Vec<usize> {
+        let snapshot = self.state.read();
+        snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flat_map(|(_, ssts)| ssts))
+            .copied()
+            .collect()
+    }
+
+    /// The smallest and the largest key in the database, or `None` if it is empty. This is cheap
+    /// as it only looks at the bounds of the memtables and the SSTs, so the bounds may be deleted
+    /// keys.
+    pub fn key_range(&self) -> Option<(Bytes, Bytes)> {
+        let snapshot = {
+            let guard = self.state.read();
+            Arc::clone(&guard)
+        };
+        let memtable_ranges = std::iter::once(&snapshot.memtable)
+            .chain(snapshot.imm_memtables.iter())
+            .filter_map(|memtable| memtable.key_range());
+        let sst_ranges = snapshot.sstables.values().map(|sst| {
+            (
+                Bytes::copy_from_slice(sst.first_key().raw_ref()),
+                Bytes::copy_from_slice(sst.last_key().raw_ref()),
+            )
+        });
+        memtable_ranges
+            .chain(sst_ranges)
+            .reduce(|(min, max), (first, last)| (min.min(first), max.max(last)))
+    }
+
+    /// Pick up to `n - 1` keys splitting the range into `n` sub-ranges of roughly equal size, e.g.
+    /// to scan them in parallel. The sizes are estimated from the block indexes of the SSTs
+    /// without reading any data block, and data still in memtables is not taken into account.
+    /// The split keys are in ascending order and within the range; sub-range `i` starts at split
+    /// key `i - 1` (inclusive) and ends at split key `i` (exclusive).
+    pub fn split_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>, n: usize) -> Vec<Bytes> {
+        let snapshot = {
+            let guard = self.state.read();
+            Arc::clone(&guard)
+        };
+        // The first key and the size in bytes of every block within the range.
+        let mut blocks = Vec::new();
+        for sst_id in snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flat_map(|(_, ssts)| ssts))
+        {
+            let table = &snapshot.sstables[sst_id];
+            for (first_key, upper_key, size) in table.block_ranges() {
+                if !range_overlap(
                     lower,
                     upper,
-                    table.first_key().as_key_slice(),
-                    table.last_key().as_key_slice(),
+                    first_key.as_key_slice(),
+                    upper_key.as_key_slice(),
                 ) {
-                    level_ssts.push(table);
+                    continue;
                 }
+                blocks.push((first_key.raw_ref(), size));
             }
-
-            let level_iter = match lower {
-                Bound::Included(key) => SstConcatIterator::create_and_seek_to_key(
-                    level_ssts,
-                    KeySlice::from_slice(key),
-                )?,
-                Bound::Excluded(key) => {
-                    let mut iter = SstConcatIterator::create_and_seek_to_key(
-                        level_ssts,
-                        KeySlice::from_slice(key),
-                    )?;
-                    if iter.is_valid() && iter.key().raw_ref() == key {
-                        iter.next()?;
-                    }
-                    iter
-                }
-                Bound::Unbounded => SstConcatIterator::create_and_seek_to_first(level_ssts)?,
-            };
-            level_iters.push(Box::new(level_iter));
         }
+        split_blocks(blocks, lower, upper, n)
+    }
 
-        let iter = TwoMergeIterator::create(memtable_iter, l0_iter)?;
-        let iter = TwoMergeIterator::create(iter, MergeIterator::create(level_iters))?;
+    /// Estimate the bytes of the SSTs within the range, without reading any data block. The
+    /// estimate has the granularity of blocks, i.e., every block that may hold a key within the
+    /// range is counted as a whole, and data still in memtables is not taken into account.
+    pub fn approximate_size(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> u64 {
+        let snapshot = {
+            let guard = self.state.read();
+            Arc::clone(&guard)
+        };
+        snapshot
+            .l0_sstables
+            .iter()
+            .chain(snapshot.levels.iter().flat_map(|(_, ssts)| ssts))
+            .map(|sst_id| &snapshot.sstables[sst_id])
+            .filter(|table| {
+                range_overlap(
+                    lower,
+                    upper,
+                    table.first_key().as_key_slice(),
+                    table.last_key().as_key_slice(),
+                )
+            })
+            .map(|table| table.approximate_size_in_range(lower, upper))
+            .sum()
+    }
+
+    /// Re-read every SST in the current state and check it against its file checksum. SSTs
+    /// written without `sst_file_checksum` are skipped.
+    pub fn verify_sst_files(&self) -> Result<()> {
+        let snapshot = {
+            let guard = self.state.read();
+            Arc::clone(&guard)
+        };
+        for (sst_id, table) in &snapshot.sstables {
+            table
+                .file
+                .verify()
+                .with_context(|| format!("failed to verify SST {}", sst_id))?;
+        }
+        Ok(())
+    }
 
-        Ok(FusedIterator::new(LsmIterator::new(
-            iter,
+    /// Create an iterator over the entries of a single SST within a range, without merging with
+    /// other sources. Deletes are returned as empty values, and expired entries are returned as
+    /// well.
+    pub fn scan_sst(
+        &self,
+        sst_id: usize,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<SstRangeIterator> {
+        let table = {
+            let snapshot = self.state.read();
+            let Some(table) = snapshot.sstables.get(&sst_id) else {
+                bail!("SST {} does not exist", sst_id);
+            };
+            table.clone()
+        };
+        Ok(SstRangeIterator::new(
+            seek_sst_to_lower_bound(table, lower)?,
             map_bound(upper),
-        )?))
+        ))
+    }
+
+    /// Create an iterator over a range of keys.
+    pub fn scan(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>> {
+        self.stats.record_scan();
+        self.snapshot_for_scan(lower, upper).scan(lower, upper)
+    }
+
+    /// Create an iterator over the keys starting with `prefix`. An empty prefix scans all keys.
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<FusedIterator<LsmIterator>> {
+        let upper = prefix_successor(prefix);
+        self.scan(
+            Bound::Included(prefix),
+            upper.as_deref().map_or(Bound::Unbounded, Bound::Excluded),
+        )
+    }
+
+    /// The greatest key which <= `key`, if any, e.g., to find the range containing `key` when
+    /// the ranges are stored by their start keys.
+    pub fn last_key_le(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let iter = self.scan_rev(Bound::Unbounded, Bound::Included(key))?;
+        Ok(iter.is_valid().then(|| Bytes::copy_from_slice(iter.key())))
+    }
+
+    /// Create an iterator over a range of keys in descending order.
+    pub fn scan_rev(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> Result<FusedIterator<LsmIterator>> {
+        self.stats.record_scan();
+        self.snapshot_for_scan(lower, upper).scan_rev(lower, upper)
     }
 }
@@ -14,25 +14,61 @@
 
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use bytes::{Buf, BufMut};
 use parking_lot::{Mutex, MutexGuard};
 use serde::{Deserialize, Serialize};
 
 use crate::compact::CompactionTask;
+use crate::error::LsmError;
+use crate::range_tombstone::RangeTombstone;
 
 pub struct Manifest {
     file: Arc<Mutex<File>>,
+    /// Number of records in the manifest file.
+    num_records: AtomicUsize,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum ManifestRecord {
     Flush(usize),
+    /// An SST flushed directly to a level, as `(level, sst_id)`.
+    FlushToLevel(usize, usize),
     NewMemtable(usize),
+    /// An external SST ingested as `(level, sst_id)`, into L0 (or a new tier) if the level is
+    /// `None`.
+    Ingest(Option<usize>, usize),
     Compaction(CompactionTask, Vec<usize>),
+    DeleteRange(RangeTombstone),
+    DropRangeTombstones(Vec<RangeTombstone>),
+    /// The full state of the storage, which replaces everything recorded before it.
+    Snapshot(ManifestSnapshot),
+    /// Name of the comparator the database is created with.
+    Comparator(String),
+    /// The WALs of the memtables from this id on store values encoded as described in
+    /// [`crate::ttl`]. Databases without this record were created before, and the WALs of their
+    /// earlier memtables store the user values as is.
+    EncodedValuesSince(usize),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSnapshot {
+    pub l0_sstables: Vec<usize>,
+    pub levels: Vec<(usize, Vec<usize>)>,
+    /// Ids of the memtables not flushed yet, whose WALs need to be replayed.
+    pub memtables: Vec<usize>,
+    /// The largest SST or memtable id allocated so far.
+    pub last_sst_id: usize,
+    pub range_tombstones: Vec<RangeTombstone>,
+    pub comparator: String,
+    /// See [`ManifestRecord::EncodedValuesSince`]. `None` in snapshots written before it was
+    /// recorded.
+    #[serde(default)]
+    pub encoded_values_since: Option<usize>,
 }
 
 impl Manifest {
@@ -46,6 +82,7 @@ impl Manifest {
                     .open(path)
                     .context("failed to create manifest")?,
             )),
+            num_records: AtomicUsize::new(0),
         })
     }
 
@@ -57,27 +94,84 @@ impl Manifest {
             .context("failed to recover manifest")?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
-        let mut buf_ptr = buf.as_slice();
-        let mut records = Vec::new();
-        while buf_ptr.has_remaining() {
-            let len = buf_ptr.get_u64();
-            let slice = &buf_ptr[..len as usize];
-            let json = serde_json::from_slice::<ManifestRecord>(slice)?;
-            buf_ptr.advance(len as usize);
-            let checksum = buf_ptr.get_u32();
-            if checksum != crc32fast::hash(slice) {
-                bail!("checksum mismatched!");
-            }
-            records.push(json);
+        let (records, valid_len) = Self::decode_records(&buf)?;
+        if valid_len < buf.len() {
+            // A crash in `add_record` leaves a partial record at the end. It was never
+            // acknowledged, so drop it and let new records be appended after the valid ones.
+            log::warn!(
+                "dropping {} bytes of incomplete or corrupted manifest records",
+                buf.len() - valid_len
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
         }
         Ok((
             Self {
                 file: Arc::new(Mutex::new(file)),
+                num_records: AtomicUsize::new(records.len()),
             },
             records,
         ))
     }
 
+    /// Read the records of the manifest at `path` like `recover`, without modifying the file.
+    pub fn read_records(path: impl AsRef<Path>) -> Result<Vec<ManifestRecord>> {
+        let buf = std::fs::read(path).context("failed to read manifest")?;
+        Ok(Self::decode_records(&buf)?.0)
+    }
+
+    /// Decode the records in `buf` up to the first incomplete or corrupted one. Returns the
+    /// records and the length of the valid part of `buf`.
+    fn decode_records(buf: &[u8]) -> Result<(Vec<ManifestRecord>, usize)> {
+        let mut buf_ptr = buf;
+        let mut records = Vec::new();
+        while buf_ptr.has_remaining() {
+            let Some(json) = Self::decode_record(buf_ptr) else {
+                break;
+            };
+            // the checksum matches, so the record was written like this
+            let record = serde_json::from_slice::<ManifestRecord>(json)
+                .map_err(|e| LsmError::ManifestCorrupt(e.to_string()))?;
+            records.push(record);
+            buf_ptr.advance(json.len() + 12);
+        }
+        Ok((records, buf.len() - buf_ptr.remaining()))
+    }
+
+    /// Replace the content of the manifest at `path` with `records`. The new manifest is written
+    /// to a temporary file first and then renamed, so that a crash leaves either the old or the new
+    /// manifest in place.
+    pub fn rewrite(
+        &self,
+        _state_lock_observer: &MutexGuard<()>,
+        path: impl AsRef<Path>,
+        records: &[ManifestRecord],
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = Self::tmp_path(path);
+        let mut buf = Vec::new();
+        for record in records {
+            buf.extend(Self::encode_record(record)?);
+        }
+        let mut file = self.file.lock();
+        {
+            let mut tmp_file = File::create(&tmp_path).context("failed to create manifest")?;
+            tmp_file.write_all(&buf)?;
+            tmp_file.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, path)?;
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        *file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(path)
+            .context("failed to reopen manifest")?;
+        self.num_records.store(records.len(), Ordering::SeqCst);
+        Ok(())
+    }
+
     pub fn add_record(
         &self,
         _state_lock_observer: &MutexGuard<()>,
@@ -88,12 +182,46 @@ impl Manifest {
 
     pub fn add_record_when_init(&self, record: ManifestRecord) -> Result<()> {
         let mut file = self.file.lock();
-        let mut buf = serde_json::to_vec(&record)?;
-        let hash = crc32fast::hash(&buf);
-        file.write_all(&(buf.len() as u64).to_be_bytes())?;
-        buf.put_u32(hash);
-        file.write_all(&buf)?;
+        file.write_all(&Self::encode_record(&record)?)?;
         file.sync_all()?;
+        self.num_records.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
+
+    /// Number of records in the manifest, i.e., the number of records the next `recover` replays.
+    pub fn num_records(&self) -> usize {
+        self.num_records.load(Ordering::SeqCst)
+    }
+
+    /// The temporary file `rewrite` writes the new manifest at `path` to.
+    pub(crate) fn tmp_path(path: &Path) -> PathBuf {
+        path.with_extension("tmp")
+    }
+
+    /// Decode the record at the start of `buf`, returning its JSON. Returns `None` if the record
+    /// is incomplete or fails the checksum.
+    fn decode_record(mut buf: &[u8]) -> Option<&[u8]> {
+        if buf.remaining() < 8 {
+            return None;
+        }
+        let len = buf.get_u64();
+        if (buf.remaining() as u64) < len.checked_add(4)? {
+            return None;
+        }
+        let (json, mut checksum) = buf.split_at(len as usize);
+        if checksum.get_u32() != crc32fast::hash(json) {
+            return None;
+        }
+        Some(json)
+    }
+
+    /// Encode a record as `len | json | checksum`.
+    fn encode_record(record: &ManifestRecord) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(record)?;
+        let mut buf = Vec::with_capacity(json.len() + 12);
+        buf.put_u64(json.len() as u64);
+        buf.extend_from_slice(&json);
+        buf.put_u32(crc32fast::hash(&json));
+        Ok(buf)
+    }
 }
@@ -12,31 +12,61 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::ops::Bound;
 use std::path::Path;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use bytes::Bytes;
 use crossbeam_skiplist::SkipMap;
-use crossbeam_skiplist::map::Entry;
 use ouroboros::self_referencing;
+use parking_lot::{Mutex, RwLock};
 
-use crate::iterators::StorageIterator;
+use crate::iterators::{SeekableIterator, StorageIterator};
 use crate::key::KeySlice;
+use crate::range_tombstone::RangeTombstone;
 use crate::table::SsTableBuilder;
-use crate::wal::Wal;
+use crate::ttl::encode_value;
+use crate::wal::{Wal, WalRecord};
+
+/// A key of the skipmap: the user key and the sequence number of the write that put it. The
+/// versions of a key are ordered from the latest to the earliest.
+type VersionedKey = (Bytes, Reverse<u64>);
 
 /// A basic mem-table based on crossbeam-skiplist.
 ///
 /// An initial implementation of memtable is part of week 1, day 1. It will be incrementally implemented in other
 /// chapters of week 1 and week 2.
+///
+/// Each write is assigned a sequence number and keeps the versions it overwrites, so that readers
+/// see a write batch either entirely or not at all, and a snapshot can keep reading the versions
+/// as of when it was taken. The versions are only dropped by the flush.
+///
+/// A range tombstone is a write of its own as well. It deletes the versions written before it in
+/// the mem-table and everything in earlier memtables and SSTs, while later writes of the keys in
+/// its range take precedence over it.
+///
+/// The skipmap itself is lock-free. Concurrent writers only contend on `frozen`, which orders
+/// their sequence numbers and WAL records, so spreading the keys over several skipmaps would not
+/// let them run any more concurrently.
 pub struct MemTable {
-    map: Arc<SkipMap<Bytes, Bytes>>,
+    map: Arc<SkipMap<VersionedKey, Bytes>>,
     wal: Option<Wal>,
     id: usize,
     approximate_size: Arc<AtomicUsize>,
+    /// Whether the mem-table is frozen, after which writes are rejected. Held by the writers, so
+    /// that sequence numbers are assigned in the order of the WAL records, and freezing waits for
+    /// the writes in progress.
+    frozen: Mutex<bool>,
+    /// The sequence number of the latest write whose records are all inserted. Newer versions are
+    /// ignored by readers.
+    visible_seq: AtomicU64,
+    /// The range tombstones written into the mem-table with the sequence numbers of their writes.
+    /// Their epoch is that of the previous memtable, as they apply to earlier data once flushed.
+    range_tombstones: RwLock<Arc<Vec<(u64, RangeTombstone)>>>,
 }
 
 /// Create a bound of `Bytes` from a bound of `&[u8]`.
@@ -48,36 +78,99 @@ pub(crate) fn map_bound(bound: Bound<&[u8]>) -> Bound<Bytes> {
     }
 }
 
+/// Create a bound of the skipmap from a lower bound of the user keys, covering all versions.
+fn map_lower_bound(bound: Bound<&[u8]>) -> Bound<VersionedKey> {
+    match bound {
+        Bound::Included(x) => Bound::Included((Bytes::copy_from_slice(x), Reverse(u64::MAX))),
+        Bound::Excluded(x) => Bound::Excluded((Bytes::copy_from_slice(x), Reverse(0))),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Create a bound of the skipmap from an upper bound of the user keys, covering all versions.
+fn map_upper_bound(bound: Bound<&[u8]>) -> Bound<VersionedKey> {
+    match bound {
+        Bound::Included(x) => Bound::Included((Bytes::copy_from_slice(x), Reverse(0))),
+        Bound::Excluded(x) => Bound::Excluded((Bytes::copy_from_slice(x), Reverse(u64::MAX))),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Whether the version `version` of `key` is deleted by one of the range tombstones, i.e. by one
+/// written after it.
+fn is_deleted(range_tombstones: &[(u64, RangeTombstone)], key: &[u8], version: u64) -> bool {
+    range_tombstones
+        .iter()
+        .any(|(seq, tombstone)| *seq > version && tombstone.covers(key))
+}
+
 impl MemTable {
-    /// Create a new mem-table.
-    pub fn create(id: usize) -> Self {
+    fn new(id: usize, replayed: Replayed, wal: Option<Wal>) -> Self {
         Self {
             id,
-            map: Arc::new(SkipMap::new()),
-            wal: None,
+            map: Arc::new(replayed.map),
+            wal,
             approximate_size: Arc::new(AtomicUsize::new(0)),
+            frozen: Mutex::new(false),
+            visible_seq: AtomicU64::new(replayed.seq),
+            range_tombstones: RwLock::new(Arc::new(replayed.range_tombstones)),
         }
     }
 
+    /// Create a new mem-table.
+    pub fn create(id: usize) -> Self {
+        Self::new(id, Replayed::default(), None)
+    }
+
     /// Create a new mem-table with WAL
-    pub fn create_with_wal(id: usize, path: impl AsRef<Path>) -> Result<Self> {
-        Ok(Self {
-            id,
-            map: Arc::new(SkipMap::new()),
-            wal: Some(Wal::create(path.as_ref())?),
-            approximate_size: Arc::new(AtomicUsize::new(0)),
-        })
+    pub fn create_with_wal(
+        id: usize,
+        path: impl AsRef<Path>,
+        wal_buffer_size: usize,
+    ) -> Result<Self> {
+        let wal = Wal::create(path.as_ref(), wal_buffer_size)?;
+        Ok(Self::new(id, Replayed::default(), Some(wal)))
     }
 
     /// Create a memtable from WAL
-    pub fn recover_from_wal(id: usize, path: impl AsRef<Path>) -> Result<Self> {
-        let map = Arc::new(SkipMap::new());
-        Ok(Self {
-            id,
-            wal: Some(Wal::recover(path.as_ref(), &map)?),
-            map,
-            approximate_size: Arc::new(AtomicUsize::new(0)),
-        })
+    pub fn recover_from_wal(
+        id: usize,
+        path: impl AsRef<Path>,
+        wal_buffer_size: usize,
+    ) -> Result<Self> {
+        let mut replayed = Replayed::default();
+        let wal = Wal::recover(
+            path.as_ref(),
+            |record| replayed.insert(id, record),
+            wal_buffer_size,
+        )?;
+        Ok(Self::new(id, replayed, Some(wal)))
+    }
+
+    /// Create a memtable from WAL without modifying the WAL, for opening the database read-only.
+    /// Writes to the memtable are not logged.
+    pub fn read_from_wal(id: usize, path: impl AsRef<Path>) -> Result<Self> {
+        let mut replayed = Replayed::default();
+        Wal::read(path.as_ref(), |record| replayed.insert(id, record))?;
+        Ok(Self::new(id, replayed, None))
+    }
+
+    /// Create a memtable from a WAL written before values were encoded as described in
+    /// [`crate::ttl`], encoding the values while replaying them. Like `read_from_wal`, the WAL is
+    /// not modified and writes are not logged, so the memtable must be frozen right away.
+    pub fn read_from_raw_wal(id: usize, path: impl AsRef<Path>) -> Result<Self> {
+        let mut replayed = Replayed::default();
+        Wal::read(path.as_ref(), |record| {
+            let record = match record {
+                WalRecord::Put(key, value) => match encode_value(&value) {
+                    Cow::Borrowed(_) => WalRecord::Put(key, value),
+                    Cow::Owned(encoded) => WalRecord::Put(key, encoded.into()),
+                },
+                record => record,
+            };
+            replayed.insert(id, record)
+        })?;
+        Ok(Self::new(id, replayed, None))
     }
 
     pub fn for_testing_put_slice(&self, key: &[u8], value: &[u8]) -> Result<()> {
@@ -99,9 +192,59 @@ impl MemTable {
         self.scan(lower, upper)
     }
 
+    /// The sequence number of the latest write visible to readers, to read the mem-table as of
+    /// now with [`MemTable::get_at`] and [`MemTable::scan_at`] later.
+    pub fn visible_seq(&self) -> u64 {
+        self.visible_seq.load(Ordering::Acquire)
+    }
+
     /// Get a value by key.
     pub fn get(&self, key: &[u8]) -> Option<Bytes> {
-        self.map.get(key).map(|e| e.value().clone())
+        self.get_at(key, self.visible_seq())
+    }
+
+    /// Get the value of a key as of the write with sequence number `seq`. A key deleted by a range
+    /// tombstone of the mem-table is returned as a delete, i.e. an empty value, so that earlier
+    /// data is not searched.
+    pub fn get_at(&self, key: &[u8], seq: u64) -> Option<Bytes> {
+        let lookup = (Bytes::copy_from_slice(key), Reverse(seq));
+        let latest = self
+            .map
+            .lower_bound(Bound::Included(&lookup))
+            .filter(|entry| entry.key().0 == key)
+            .map(|entry| (entry.key().1.0, entry.value().clone()));
+        let range_tombstones = self.range_tombstones_at(seq);
+        let version = latest.as_ref().map_or(0, |(version, _)| *version);
+        if is_deleted(&range_tombstones, key, version) {
+            return Some(Bytes::new());
+        }
+        latest.map(|(_, value)| value)
+    }
+
+    /// The range tombstones written up to the write with sequence number `seq`.
+    fn range_tombstones_at(&self, seq: u64) -> Vec<(u64, RangeTombstone)> {
+        let range_tombstones = self.range_tombstones.read();
+        if range_tombstones.is_empty() {
+            return Vec::new();
+        }
+        range_tombstones
+            .iter()
+            .filter(|(tombstone_seq, _)| *tombstone_seq <= seq)
+            .cloned()
+            .collect()
+    }
+
+    /// The range tombstones written up to the write with sequence number `seq`, as they apply to
+    /// the earlier memtables and SSTs.
+    pub fn range_tombstones_for_earlier_data(&self, seq: u64) -> Vec<RangeTombstone> {
+        // nothing is older than the first memtable
+        if self.id == 0 {
+            return Vec::new();
+        }
+        self.range_tombstones_at(seq)
+            .into_iter()
+            .map(|(_, tombstone)| tombstone)
+            .collect()
     }
 
     /// Put a key-value pair into the mem-table.
@@ -110,20 +253,67 @@ impl MemTable {
     /// In week 2, day 6, also flush the data to WAL.
     /// In week 3, day 5, modify the function to use the batch API.
     pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
-        let estimated_size = key.len() + value.len();
-        self.map
-            .insert(Bytes::copy_from_slice(key), Bytes::copy_from_slice(value));
+        self.put_batch(&[(KeySlice::from_slice(key), value)])
+    }
+
+    /// Put multiple key-value pairs into the mem-table. All records are written to the WAL before
+    /// any of them is inserted into the skipmap, and they become visible to readers at once.
+    pub fn put_batch(&self, data: &[(KeySlice, &[u8])]) -> Result<()> {
+        if !self.try_put_batch(data)? {
+            bail!("memtable {} is frozen", self.id);
+        }
+        Ok(())
+    }
+
+    /// Put multiple key-value pairs like `put_batch`, unless the mem-table is frozen, in which case
+    /// nothing is written and `false` is returned.
+    pub fn try_put_batch(&self, data: &[(KeySlice, &[u8])]) -> Result<bool> {
+        let frozen = self.frozen.lock();
+        if *frozen {
+            return Ok(false);
+        }
+        if let Some(ref wal) = self.wal {
+            wal.put_batch(data)?;
+        }
+        let seq = self.visible_seq.load(Ordering::Relaxed) + 1;
+        let mut estimated_size = 0;
+        for (key, value) in data {
+            estimated_size += key.len() + value.len();
+            self.map.insert(
+                (Bytes::copy_from_slice(key.raw_ref()), Reverse(seq)),
+                Bytes::copy_from_slice(value),
+            );
+        }
         self.approximate_size
-            .fetch_add(estimated_size, std::sync::atomic::Ordering::Relaxed);
+            .fetch_add(estimated_size, Ordering::Relaxed);
+        self.visible_seq.store(seq, Ordering::Release);
+        Ok(true)
+    }
+
+    /// Write a range tombstone deleting the keys within the bounds written so far, unless the
+    /// mem-table is frozen, in which case nothing is written and `false` is returned.
+    pub fn try_delete_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<bool> {
+        let frozen = self.frozen.lock();
+        if *frozen {
+            return Ok(false);
+        }
         if let Some(ref wal) = self.wal {
-            wal.put(key, value)?;
+            wal.delete_range(lower, upper)?;
         }
-        Ok(())
+        let seq = self.visible_seq.load(Ordering::Relaxed) + 1;
+        let tombstone = RangeTombstone::new(self.id.saturating_sub(1), lower, upper);
+        let mut range_tombstones = self.range_tombstones.write();
+        let mut new_range_tombstones = range_tombstones.as_ref().clone();
+        new_range_tombstones.push((seq, tombstone));
+        *range_tombstones = Arc::new(new_range_tombstones);
+        drop(range_tombstones);
+        self.visible_seq.store(seq, Ordering::Release);
+        Ok(true)
     }
 
-    /// Implement this in week 3, day 5; if you want to implement this earlier, use `&[u8]` as the key type.
-    pub fn put_batch(&self, _data: &[(KeySlice, &[u8])]) -> Result<()> {
-        unimplemented!()
+    /// Reject the writes from now on, waiting for the writes in progress.
+    pub fn freeze(&self) {
+        *self.frozen.lock() = true;
     }
 
     pub fn sync_wal(&self) -> Result<()> {
@@ -135,11 +325,32 @@ impl MemTable {
 
     /// Get an iterator over a range of keys.
     pub fn scan(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> MemTableIterator {
-        let (lower, upper) = (map_bound(lower), map_bound(upper));
+        self.scan_at(lower, upper, self.visible_seq(), false)
+    }
+
+    /// Get an iterator over a range of keys in descending order.
+    pub fn scan_rev(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> MemTableIterator {
+        self.scan_at(lower, upper, self.visible_seq(), true)
+    }
+
+    /// Get an iterator over a range of keys as of the write with sequence number `seq`, in
+    /// descending order if `reverse` is set.
+    pub fn scan_at(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+        seq: u64,
+        reverse: bool,
+    ) -> MemTableIterator {
+        let (lower, upper) = (map_lower_bound(lower), map_upper_bound(upper));
         let mut iter = MemTableIteratorBuilder {
             map: self.map.clone(),
+            bounds: (lower.clone(), upper.clone()),
             iter_builder: |map| map.range((lower, upper)),
             item: (Bytes::new(), Bytes::new()),
+            epoch: self.id,
+            visible: (seq, self.range_tombstones_at(seq)),
+            reverse,
         }
         .build();
         iter.next().unwrap();
@@ -147,9 +358,14 @@ impl MemTable {
     }
 
     /// Flush the mem-table to SSTable. Implement in week 1 day 6.
+    ///
+    /// Keys deleted by the range tombstones of the mem-table are written as deletes, while the
+    /// tombstones themselves are left to the caller, see `range_tombstones_for_earlier_data`.
     pub fn flush(&self, builder: &mut SsTableBuilder) -> Result<()> {
-        for entry in self.map.iter() {
-            builder.add(KeySlice::from_slice(&entry.key()[..]), &entry.value()[..]);
+        let mut iter = self.scan(Bound::Unbounded, Bound::Unbounded);
+        while iter.is_valid() {
+            builder.add(iter.key(), iter.value());
+            iter.next()?;
         }
         Ok(())
     }
@@ -159,18 +375,57 @@ impl MemTable {
     }
 
     pub fn approximate_size(&self) -> usize {
-        self.approximate_size
-            .load(std::sync::atomic::Ordering::Relaxed)
+        self.approximate_size.load(Ordering::Relaxed)
     }
 
     /// Only use this function when closing the database
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
     }
+
+    /// The smallest and the largest key in the memtable, including deletes.
+    pub fn key_range(&self) -> Option<(Bytes, Bytes)> {
+        let first = self.map.front()?.key().0.clone();
+        let last = self.map.back()?.key().0.clone();
+        Some((first, last))
+    }
+}
+
+/// The records replayed from a WAL, each as a write of its own.
+#[derive(Default)]
+struct Replayed {
+    map: SkipMap<VersionedKey, Bytes>,
+    range_tombstones: Vec<(u64, RangeTombstone)>,
+    /// The sequence number of the last record.
+    seq: u64,
+}
+
+impl Replayed {
+    fn insert(&mut self, id: usize, record: WalRecord) {
+        self.seq += 1;
+        match record {
+            WalRecord::Put(key, value) => {
+                self.map.insert((key, Reverse(self.seq)), value);
+            }
+            WalRecord::DeleteRange(lower, upper) => {
+                let tombstone = RangeTombstone {
+                    epoch: id.saturating_sub(1),
+                    lower,
+                    upper,
+                };
+                self.range_tombstones.push((self.seq, tombstone));
+            }
+        }
+    }
 }
 
-type SkipMapRangeIter<'a> =
-    crossbeam_skiplist::map::Range<'a, Bytes, (Bound<Bytes>, Bound<Bytes>), Bytes, Bytes>;
+type SkipMapRangeIter<'a> = crossbeam_skiplist::map::Range<
+    'a,
+    VersionedKey,
+    (Bound<VersionedKey>, Bound<VersionedKey>),
+    VersionedKey,
+    Bytes,
+>;
 
 /// An iterator over a range of `SkipMap`. This is a self-referential structure and please refer to week 1, day 2
 /// chapter for more information.
@@ -179,20 +434,71 @@ type SkipMapRangeIter<'a> =
 #[self_referencing]
 pub struct MemTableIterator {
     /// Stores a reference to the skipmap.
-    map: Arc<SkipMap<Bytes, Bytes>>,
+    map: Arc<SkipMap<VersionedKey, Bytes>>,
+    /// The bounds of the scan, for seeking and for skipping the versions of a key in descending
+    /// order.
+    bounds: (Bound<VersionedKey>, Bound<VersionedKey>),
     /// Stores a skipmap iterator that refers to the lifetime of `MemTableIterator` itself.
     #[borrows(map)]
     #[not_covariant]
     iter: SkipMapRangeIter<'this>,
     /// Stores the current key-value pair.
     item: (Bytes, Bytes),
+    /// The id of the memtable being scanned.
+    epoch: usize,
+    /// Versions written after the write with this sequence number are skipped, and the range
+    /// tombstones written up to it turn the versions written before them into deletes.
+    visible: (u64, Vec<(u64, RangeTombstone)>),
+    /// Whether the keys are iterated in descending order.
+    reverse: bool,
 }
 
 impl MemTableIterator {
-    fn entry_to_item(entry: Option<Entry<'_, Bytes, Bytes>>) -> (Bytes, Bytes) {
-        entry
-            .map(|x| (x.key().clone(), x.value().clone()))
-            .unwrap_or_else(|| (Bytes::from_static(&[]), Bytes::from_static(&[])))
+    /// Move to the latest version visible to the iterator of the next key in ascending order.
+    fn next_forward(&mut self) {
+        let seq = self.borrow_visible().0;
+        let item = self.with_mut(|fields| {
+            for entry in fields.iter.by_ref() {
+                let (key, Reverse(version)) = entry.key();
+                // the older versions of the current key follow it
+                if *version <= seq && *key != fields.item.0 {
+                    if is_deleted(&fields.visible.1, key, *version) {
+                        return (key.clone(), Bytes::new());
+                    }
+                    return (key.clone(), entry.value().clone());
+                }
+            }
+            (Bytes::new(), Bytes::new())
+        });
+        self.with_item_mut(|x| *x = item);
+    }
+
+    /// Move to the latest version visible to the iterator of the next key in descending order.
+    fn next_backward(&mut self) {
+        let seq = self.borrow_visible().0;
+        let item = self.with_mut(|fields| {
+            // The versions of a key are met from the earliest, so look up the latest visible one,
+            // and continue before all versions of the key.
+            while let Some(entry) = fields.iter.next_back() {
+                let key = entry.key().0.clone();
+                let lookup = (key.clone(), Reverse(seq));
+                let latest = fields
+                    .map
+                    .lower_bound(Bound::Included(&lookup))
+                    .filter(|entry| entry.key().0 == key)
+                    .map(|entry| (entry.key().1.0, entry.value().clone()));
+                let upper = Bound::Excluded((key.clone(), Reverse(u64::MAX)));
+                *fields.iter = fields.map.range((fields.bounds.0.clone(), upper));
+                if let Some((version, value)) = latest {
+                    if is_deleted(&fields.visible.1, &key, version) {
+                        return (key, Bytes::new());
+                    }
+                    return (key, value);
+                }
+            }
+            (Bytes::new(), Bytes::new())
+        });
+        self.with_item_mut(|x| *x = item);
     }
 }
 
@@ -203,7 +509,7 @@ impl StorageIterator for MemTableIterator {
         &self.borrow_item().1[..]
     }
 
-    fn key(&self) -> KeySlice {
+    fn key(&self) -> KeySlice<'_> {
         KeySlice::from_slice(&self.borrow_item().0[..])
     }
 
@@ -212,8 +518,27 @@ impl StorageIterator for MemTableIterator {
     }
 
     fn next(&mut self) -> Result<()> {
-        let entry = self.with_iter_mut(|iter| MemTableIterator::entry_to_item(iter.next()));
-        self.with_mut(|x| *x.item = entry);
+        if *self.borrow_reverse() {
+            self.next_backward();
+        } else {
+            self.next_forward();
+        }
         Ok(())
     }
+
+    fn epoch(&self) -> usize {
+        *self.borrow_epoch()
+    }
+}
+
+impl SeekableIterator for MemTableIterator {
+    fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        assert!(!*self.borrow_reverse(), "cannot seek a descending iterator");
+        let lower = map_lower_bound(Bound::Included(key.raw_ref()));
+        self.with_mut(|fields| {
+            *fields.iter = fields.map.range((lower, fields.bounds.1.clone()));
+            *fields.item = (Bytes::new(), Bytes::new());
+        });
+        self.next()
+    }
 }
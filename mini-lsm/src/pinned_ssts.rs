@@ -0,0 +1,93 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+/// The SSTs read by running scans and snapshots. Their files are only deleted once the last scan
+/// or snapshot reading them is dropped, instead of being unlinked under them when a compaction
+/// replaces them.
+#[derive(Default)]
+pub struct PinnedSsts {
+    inner: Mutex<PinnedSstsInner>,
+}
+
+#[derive(Default)]
+struct PinnedSstsInner {
+    /// Number of scans and snapshots reading each pinned SST.
+    pins: HashMap<usize, usize>,
+    /// Files of the pinned SSTs removed from the storage, deleted once they are unpinned.
+    pending_removals: HashMap<usize, PathBuf>,
+}
+
+impl PinnedSsts {
+    /// Pin the SSTs `ids` until the returned guard is dropped.
+    pub fn pin(self: &Arc<Self>, ids: Vec<usize>) -> SstPinGuard {
+        let mut inner = self.inner.lock();
+        for id in &ids {
+            *inner.pins.entry(*id).or_default() += 1;
+        }
+        SstPinGuard {
+            pinned: self.clone(),
+            ids,
+        }
+    }
+
+    pub fn is_pinned(&self, id: usize) -> bool {
+        self.inner.lock().pins.contains_key(&id)
+    }
+
+    /// Delete the file of the SST `id` at `path`, or once the SST is unpinned if a scan or a
+    /// snapshot still reads it.
+    pub fn remove_file(&self, id: usize, path: PathBuf) -> std::io::Result<()> {
+        let mut inner = self.inner.lock();
+        if inner.pins.contains_key(&id) {
+            inner.pending_removals.insert(id, path);
+            return Ok(());
+        }
+        std::fs::remove_file(path)
+    }
+
+    fn unpin(&self, ids: &[usize]) {
+        let mut inner = self.inner.lock();
+        for id in ids {
+            let pins = inner.pins.get_mut(id).unwrap();
+            *pins -= 1;
+            if *pins > 0 {
+                continue;
+            }
+            inner.pins.remove(id);
+            if let Some(path) = inner.pending_removals.remove(id)
+                && let Err(e) = std::fs::remove_file(&path)
+            {
+                log::warn!("failed to remove {}.sst: {}", id, e);
+            }
+        }
+    }
+}
+
+/// Keeps SSTs pinned in [`PinnedSsts`] while it is alive.
+pub struct SstPinGuard {
+    pinned: Arc<PinnedSsts>,
+    ids: Vec<usize>,
+}
+
+impl Drop for SstPinGuard {
+    fn drop(&mut self) {
+        self.pinned.unpin(&self.ids);
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::key::KeySlice;
+use crate::lsm_storage::LsmStorageState;
+use crate::table::SsTable;
+
+/// Deletes every key in `[lower, upper]` that was written before the tombstone.
+///
+/// The engine does not keep a timestamp for each key, so "before" is expressed with epochs. Every
+/// memtable, and every SST derived from it, carries an epoch which is the id of the memtable the
+/// data was written into. An SST produced by compaction inherits the largest epoch among its
+/// inputs. A range tombstone records the largest epoch that existed when it was issued, and shadows
+/// a key if the key lies in the range and comes from data of an epoch not larger than that.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeTombstone {
+    pub epoch: usize,
+    pub lower: Bound<Bytes>,
+    pub upper: Bound<Bytes>,
+}
+
+impl RangeTombstone {
+    pub fn new(epoch: usize, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Self {
+        Self {
+            epoch,
+            lower: crate::mem_table::map_bound(lower),
+            upper: crate::mem_table::map_bound(upper),
+        }
+    }
+
+    /// Check whether `key` is within the range of the tombstone.
+    pub fn covers(&self, key: &[u8]) -> bool {
+        let after_lower = match &self.lower {
+            Bound::Included(lower) => key >= lower.as_ref(),
+            Bound::Excluded(lower) => key > lower.as_ref(),
+            Bound::Unbounded => true,
+        };
+        let before_upper = match &self.upper {
+            Bound::Included(upper) => key <= upper.as_ref(),
+            Bound::Excluded(upper) => key < upper.as_ref(),
+            Bound::Unbounded => true,
+        };
+        after_lower && before_upper
+    }
+
+    /// Check whether a key coming from data of `epoch` is deleted by this tombstone.
+    pub fn shadows(&self, key: &[u8], epoch: usize) -> bool {
+        epoch <= self.epoch && self.covers(key)
+    }
+
+    /// Check whether the tombstone range overlaps with the key range `[first_key, last_key]`.
+    pub fn overlaps(&self, first_key: KeySlice, last_key: KeySlice) -> bool {
+        crate::lsm_storage::range_overlap(
+            self.lower.as_ref().map(|x| x.as_ref()),
+            self.upper.as_ref().map(|x| x.as_ref()),
+            first_key,
+            last_key,
+        )
+    }
+
+    /// Check whether the tombstone may shadow a key of `sst`.
+    pub fn may_shadow(&self, sst: &SsTable) -> bool {
+        sst.epoch() <= self.epoch
+            && self.overlaps(
+                sst.first_key().as_key_slice(),
+                sst.last_key().as_key_slice(),
+            )
+    }
+
+    /// Check whether no data of `state` is older than the tombstone within its range any more,
+    /// so that it can be dropped.
+    pub fn is_obsolete(&self, state: &LsmStorageState) -> bool {
+        state
+            .imm_memtables
+            .iter()
+            .all(|memtable| memtable.id() > self.epoch)
+            && state.sstables.values().all(|sst| !self.may_shadow(sst))
+    }
+}
+
+/// Check whether a key coming from data of `epoch` is deleted by any of the tombstones.
+pub fn is_shadowed(tombstones: &[RangeTombstone], key: &[u8], epoch: usize) -> bool {
+    tombstones
+        .iter()
+        .any(|tombstone| tombstone.shadows(key, epoch))
+}
+
+/// The tombstones that may shadow a key of the SSTs, i.e. the ones a compaction of them must apply.
+pub fn shadowing<'a>(
+    tombstones: &[RangeTombstone],
+    ssts: impl IntoIterator<Item = &'a SsTable>,
+) -> Vec<RangeTombstone> {
+    let ssts = ssts.into_iter().collect::<Vec<_>>();
+    tombstones
+        .iter()
+        .filter(|tombstone| ssts.iter().any(|sst| tombstone.may_shadow(sst)))
+        .cloned()
+        .collect()
+}
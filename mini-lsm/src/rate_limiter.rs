@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// A token bucket limiting the bytes written per second, shared by all the writers it throttles.
+/// The bucket holds up to 100ms worth of bytes, and a request larger than what is left goes into
+/// debt, which later requests wait for.
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    /// Bytes that can be written right away, negative when in debt.
+    available: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "rate limit must be positive");
+        Self {
+            bytes_per_sec,
+            state: Mutex::new(BucketState {
+                available: Self::capacity(bytes_per_sec),
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    fn capacity(bytes_per_sec: u64) -> f64 {
+        bytes_per_sec as f64 / 10.0
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Take `bytes` from the bucket, sleeping until the bucket is no longer in debt. Returns how
+    /// long the caller was blocked.
+    pub fn request(&self, bytes: u64) -> Duration {
+        let wait = {
+            let mut state = self.state.lock();
+            let now = Instant::now();
+            let refill =
+                now.duration_since(state.last_refill).as_secs_f64() * self.bytes_per_sec as f64;
+            state.available = (state.available + refill).min(Self::capacity(self.bytes_per_sec));
+            state.last_refill = now;
+            state.available -= bytes as f64;
+            if state.available >= 0.0 {
+                return Duration::ZERO;
+            }
+            Duration::from_secs_f64(-state.available / self.bytes_per_sec as f64)
+        };
+        std::thread::sleep(wait);
+        wait
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters of the operations done by the engine since it was opened. All counters are updated
+/// with relaxed atomics, so a [`LsmStats::snapshot`] taken during a workload may not be
+/// consistent across counters.
+#[derive(Debug, Default)]
+pub struct LsmStats {
+    gets: AtomicU64,
+    puts: AtomicU64,
+    deletes: AtomicU64,
+    scans: AtomicU64,
+    block_cache_hits: AtomicU64,
+    block_cache_misses: AtomicU64,
+    flushes: AtomicU64,
+    compactions: AtomicU64,
+    compaction_bytes_written: AtomicU64,
+    compaction_throttled_micros: AtomicU64,
+}
+
+/// A copy of the counters of [`LsmStats`] at some point.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LsmStatsSnapshot {
+    /// Number of keys looked up, by `get` or `get_batch`.
+    pub gets: u64,
+    /// Number of keys put, by `put` or write batches.
+    pub puts: u64,
+    /// Number of keys deleted, by `delete` or write batches.
+    pub deletes: u64,
+    /// Number of scans created, in either direction.
+    pub scans: u64,
+    /// Number of blocks read from the block cache.
+    pub block_cache_hits: u64,
+    /// Number of blocks read from the SST files.
+    pub block_cache_misses: u64,
+    /// Number of memtables flushed.
+    pub flushes: u64,
+    /// Number of compaction tasks finished.
+    pub compactions: u64,
+    /// Total size of the SSTs written by compactions.
+    pub compaction_bytes_written: u64,
+    /// How long compactions were blocked by `compaction_rate_limit_bytes_per_sec`.
+    pub compaction_throttled: Duration,
+}
+
+impl LsmStats {
+    pub(crate) fn record_gets(&self, n: u64) {
+        self.gets.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_writes(&self, puts: u64, deletes: u64) {
+        self.puts.fetch_add(puts, Ordering::Relaxed);
+        self.deletes.fetch_add(deletes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_scan(&self) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_block_read(&self, cache_hit: bool) {
+        if cache_hit {
+            self.block_cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.block_cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_flush(&self) {
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_compaction(&self) {
+        self.compactions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_compaction_write(&self, bytes: u64, throttled: Duration) {
+        self.compaction_bytes_written
+            .fetch_add(bytes, Ordering::Relaxed);
+        self.compaction_throttled_micros
+            .fetch_add(throttled.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LsmStatsSnapshot {
+        LsmStatsSnapshot {
+            gets: self.gets.load(Ordering::Relaxed),
+            puts: self.puts.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+            scans: self.scans.load(Ordering::Relaxed),
+            block_cache_hits: self.block_cache_hits.load(Ordering::Relaxed),
+            block_cache_misses: self.block_cache_misses.load(Ordering::Relaxed),
+            flushes: self.flushes.load(Ordering::Relaxed),
+            compactions: self.compactions.load(Ordering::Relaxed),
+            compaction_bytes_written: self.compaction_bytes_written.load(Ordering::Relaxed),
+            compaction_throttled: Duration::from_micros(
+                self.compaction_throttled_micros.load(Ordering::Relaxed),
+            ),
+        }
+    }
+}
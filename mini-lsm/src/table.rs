@@ -16,18 +16,25 @@ pub(crate) mod bloom;
 mod builder;
 mod iterator;
 
+use std::borrow::Cow;
 use std::fs::File;
+use std::ops::Bound;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Result, anyhow, bail};
+use anyhow::{Context, Result, anyhow, bail};
 pub use builder::SsTableBuilder;
-use bytes::{Buf, BufMut};
+use bytes::{Buf, BufMut, Bytes};
 pub use iterator::SsTableIterator;
+use memmap2::{Mmap, MmapOptions};
 
 use crate::block::Block;
+use crate::error::LsmError;
 use crate::key::{KeyBytes, KeySlice};
 use crate::lsm_storage::BlockCache;
+use crate::stats::LsmStats;
 
 use self::bloom::Bloom;
 
@@ -74,16 +81,22 @@ impl BlockMeta {
         assert_eq!(estimated_size, buf.len() - original_len);
     }
 
-    /// Decode block meta from a buffer.
+    /// Decode block meta from a buffer. Returns an error instead of panicking if the declared
+    /// lengths do not match the buffer.
     pub fn decode_block_meta(mut buf: &[u8]) -> Result<Vec<BlockMeta>> {
+        let ensure_remaining = |buf: &[u8], len| ensure_remaining(buf, len, "block meta");
+        ensure_remaining(buf, 8)?;
         let mut block_meta = Vec::new();
         let num = buf.get_u32() as usize;
         let checksum = crc32fast::hash(&buf[..buf.remaining() - 4]);
         for _ in 0..num {
+            ensure_remaining(buf, 6)?;
             let offset = buf.get_u32() as usize;
             let first_key_len = buf.get_u16() as usize;
+            ensure_remaining(buf, first_key_len + 2)?;
             let first_key = KeyBytes::from_bytes(buf.copy_to_bytes(first_key_len));
             let last_key_len: usize = buf.get_u16() as usize;
+            ensure_remaining(buf, last_key_len)?;
             let last_key = KeyBytes::from_bytes(buf.copy_to_bytes(last_key_len));
             block_meta.push(BlockMeta {
                 offset,
@@ -91,26 +104,307 @@ impl BlockMeta {
                 last_key,
             });
         }
+        if buf.remaining() != 4 {
+            bail!(LsmError::Corruption(format!(
+                "corrupted block meta: {} trailing bytes",
+                buf.remaining() as isize - 4
+            )));
+        }
         if buf.get_u32() != checksum {
-            bail!("meta checksum mismatched");
+            bail!(LsmError::Corruption("meta checksum mismatched".to_string()));
+        }
+        if block_meta.is_empty() {
+            bail!(LsmError::Corruption(
+                "corrupted block meta: no blocks".to_string()
+            ));
+        }
+        if block_meta.windows(2).any(|x| x[0].offset >= x[1].offset) {
+            bail!(LsmError::Corruption(
+                "corrupted block meta: block offsets are not increasing".to_string()
+            ));
         }
 
         Ok(block_meta)
     }
 }
 
-/// A file object.
-pub struct FileObject(Option<File>, u64);
+fn ensure_remaining(buf: &[u8], len: usize, section: &str) -> Result<()> {
+    if buf.remaining() < len {
+        bail!(LsmError::Corruption(format!(
+            "corrupted {}: need {} bytes, {} remaining",
+            section,
+            len,
+            buf.remaining()
+        )));
+    }
+    Ok(())
+}
+
+/// The meta of the data blocks of an SST, which may not be read from the file yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LazyBlockMeta {
+    num_of_blocks: usize,
+    block_meta: OnceLock<Vec<BlockMeta>>,
+}
+
+impl LazyBlockMeta {
+    pub(crate) fn loaded(block_meta: Vec<BlockMeta>) -> Self {
+        Self {
+            num_of_blocks: block_meta.len(),
+            block_meta: OnceLock::from(block_meta),
+        }
+    }
+
+    pub(crate) fn unloaded(num_of_blocks: usize) -> Self {
+        Self {
+            num_of_blocks,
+            block_meta: OnceLock::new(),
+        }
+    }
+
+    /// Number of data blocks, whether or not their meta is loaded.
+    pub(crate) fn len(&self) -> usize {
+        self.num_of_blocks
+    }
+}
+
+/// An entry of the sparse block index: where a data block starts and its first key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockIndexEntry {
+    pub offset: usize,
+    pub first_key: KeyBytes,
+}
+
+impl BlockIndexEntry {
+    /// Encode the index of the blocks described by `block_meta` as
+    /// `| num (u32) | (offset (u32) | key len (u16) | first key)* | last key len (u16) | last key | checksum (u32) |`,
+    /// where the last key is the last key of the SST.
+    pub fn encode_block_index(block_meta: &[BlockMeta], buf: &mut Vec<u8>) {
+        let original_len = buf.len();
+        buf.put_u32(block_meta.len() as u32);
+        for meta in block_meta {
+            buf.put_u32(meta.offset as u32);
+            buf.put_u16(meta.first_key.len() as u16);
+            buf.put_slice(meta.first_key.raw_ref());
+        }
+        let last_key = &block_meta.last().unwrap().last_key;
+        buf.put_u16(last_key.len() as u16);
+        buf.put_slice(last_key.raw_ref());
+        buf.put_u32(crc32fast::hash(&buf[original_len..]));
+    }
+
+    /// Decode the block index from a buffer, returning the entries and the last key of the SST.
+    pub fn decode_block_index(mut buf: &[u8]) -> Result<(Vec<BlockIndexEntry>, KeyBytes)> {
+        let ensure_remaining = |buf: &[u8], len| ensure_remaining(buf, len, "block index");
+        ensure_remaining(buf, 10)?;
+        let checksum = crc32fast::hash(&buf[..buf.remaining() - 4]);
+        let num = buf.get_u32() as usize;
+        let mut index = Vec::new();
+        for _ in 0..num {
+            ensure_remaining(buf, 6)?;
+            let offset = buf.get_u32() as usize;
+            let first_key_len = buf.get_u16() as usize;
+            ensure_remaining(buf, first_key_len)?;
+            let first_key = KeyBytes::from_bytes(buf.copy_to_bytes(first_key_len));
+            index.push(BlockIndexEntry { offset, first_key });
+        }
+        ensure_remaining(buf, 2)?;
+        let last_key_len = buf.get_u16() as usize;
+        ensure_remaining(buf, last_key_len)?;
+        let last_key = KeyBytes::from_bytes(buf.copy_to_bytes(last_key_len));
+        if buf.remaining() != 4 {
+            bail!(LsmError::Corruption(format!(
+                "corrupted block index: {} trailing bytes",
+                buf.remaining() as isize - 4
+            )));
+        }
+        if buf.get_u32() != checksum {
+            bail!(LsmError::Corruption(
+                "block index checksum mismatched".to_string()
+            ));
+        }
+        if index.is_empty() {
+            bail!(LsmError::Corruption(
+                "corrupted block index: no blocks".to_string()
+            ));
+        }
+        if index.windows(2).any(|x| x[0].offset >= x[1].offset) {
+            bail!(LsmError::Corruption(
+                "corrupted block index: block offsets are not increasing".to_string()
+            ));
+        }
+        Ok((index, last_key))
+    }
+}
+
+/// Marks an SST file with the properties footer. SSTs written before the footer was introduced
+/// end with the bloom filter offset instead, and are treated as having default properties.
+const SST_MAGIC: u32 = 0x4d4c_534d;
+
+/// The current version of the properties block. Version 1 only has the epoch, version 2 adds the
+/// creation time, version 3 adds the compression of the data blocks, version 4 adds the offset of
+/// the block index, version 5 adds the restart interval of the data blocks, version 6 adds the
+/// offset of the blob section, version 7 adds the offsets of the block meta and the bloom filter.
+/// Version 8 has the same layout as version 7, and marks SSTs whose values are encoded as described
+/// in [`crate::ttl`]. Earlier SSTs store the user values as is.
+const SST_PROPERTIES_VERSION: u32 = 8;
+
+/// Table-level properties, stored after the bloom filter as
+/// `| properties | properties offset (u32) | version (u32) | magic (u32) |`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SsTableProperties {
+    /// See [`crate::range_tombstone::RangeTombstone`].
+    pub(crate) epoch: usize,
+    /// Milliseconds since the UNIX epoch when the SST was built, 0 if unknown.
+    pub(crate) created_at: u64,
+    /// How the data blocks are compressed.
+    pub(crate) compression: CompressionType,
+    /// Offset of the block index, which ends where the properties begin, or 0 if the SST has
+    /// none.
+    pub(crate) index_offset: u64,
+    /// The restart interval of the data blocks, see [`Block`]. 0 for SSTs written before restart
+    /// points were introduced.
+    pub(crate) restart_interval: usize,
+    /// Offset of the blob section, which ends where the block meta begins, or 0 if the SST has
+    /// none.
+    pub(crate) blob_offset: u64,
+    /// Offsets of the block meta and the bloom filter (0 without one), which are also stored
+    /// unprotected after each of them. `open` checks them against these checksummed copies.
+    /// `None` for SSTs written before version 7.
+    pub(crate) meta_offsets: Option<(u64, u64)>,
+    /// Whether the values are encoded as described in [`crate::ttl`], which is implied by
+    /// version 8.
+    pub(crate) encoded_values: bool,
+}
+
+impl SsTableProperties {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let offset = buf.len();
+        buf.put_u64(self.epoch as u64);
+        buf.put_u64(self.created_at);
+        buf.put_u8(self.compression as u8);
+        buf.put_u64(self.index_offset);
+        buf.put_u32(self.restart_interval as u32);
+        buf.put_u64(self.blob_offset);
+        let (meta_offset, bloom_offset) = self.meta_offsets.unwrap_or_default();
+        buf.put_u64(meta_offset);
+        buf.put_u64(bloom_offset);
+        buf.put_u32(crc32fast::hash(&buf[offset..]));
+        buf.put_u32(offset as u32);
+        buf.put_u32(SST_PROPERTIES_VERSION);
+        buf.put_u32(SST_MAGIC);
+    }
+
+    fn decode(mut buf: &[u8], version: u32, id: usize) -> Result<Self> {
+        let expected_len = match version {
+            1 => 12,
+            2 => 20,
+            3 => 21,
+            4 => 29,
+            5 => 33,
+            6 => 41,
+            7 | 8 => 57,
+            _ => bail!("unsupported SST properties version {}", version),
+        };
+        if buf.len() != expected_len {
+            bail!(LsmError::Corruption("corrupted SST properties".to_string()));
+        }
+        let checksum = crc32fast::hash(&buf[..buf.len() - 4]);
+        let epoch = buf.get_u64() as usize;
+        let created_at = if version >= 2 { buf.get_u64() } else { 0 };
+        let compression = if version >= 3 {
+            CompressionType::from_u8(buf.get_u8())?
+        } else {
+            CompressionType::None
+        };
+        let index_offset = if version >= 4 { buf.get_u64() } else { 0 };
+        let restart_interval = if version >= 5 {
+            buf.get_u32() as usize
+        } else {
+            0
+        };
+        let blob_offset = if version >= 6 { buf.get_u64() } else { 0 };
+        let meta_offsets = if version >= 7 {
+            Some((buf.get_u64(), buf.get_u64()))
+        } else {
+            None
+        };
+        if buf.get_u32() != checksum {
+            bail!(LsmError::Corruption(format!(
+                "corrupted SST {}: properties checksum mismatched",
+                id
+            )));
+        }
+        Ok(Self {
+            epoch,
+            created_at,
+            compression,
+            index_offset,
+            restart_interval,
+            blob_offset,
+            meta_offsets,
+            encoded_values: version >= 8,
+        })
+    }
+
+    /// Read the properties footer of an SST. Returns the properties and the offset where the
+    /// footer begins, or `None` if the SST was written without properties.
+    fn read_from(file: &FileObject, id: usize) -> Result<Option<(Self, u64)>> {
+        let len = file.size();
+        if len < 12 {
+            return Ok(None);
+        }
+        let raw_footer = file.read(len - 12, 12)?;
+        let mut footer = &raw_footer[..];
+        let offset = footer.get_u32() as u64;
+        let version = footer.get_u32();
+        if footer.get_u32() != SST_MAGIC {
+            return Ok(None);
+        }
+        if offset > len - 12 {
+            bail!(LsmError::Corruption(
+                "corrupted SST properties offset".to_string()
+            ));
+        }
+        let raw_properties = file.read(offset, len - 12 - offset)?;
+        Ok(Some((Self::decode(&raw_properties, version, id)?, offset)))
+    }
+}
+
+/// Marks a file ending with `crc32 of the content | FILE_CHECKSUM_MAGIC`.
+const FILE_CHECKSUM_MAGIC: u32 = 0x4352_4331;
+
+/// A file object: the file, the size of its content, the checksum of its content if the file
+/// has one, and the mapping of the content if the file is memory-mapped. The checksum trailer is
+/// not part of the content.
+pub struct FileObject(Option<File>, u64, Option<u32>, Option<Mmap>);
 
 impl FileObject {
-    pub fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+    /// Read `len` bytes at `offset`. A memory-mapped file returns a view into the mapping instead
+    /// of copying the bytes.
+    pub fn read(&self, offset: u64, len: u64) -> Result<Cow<'_, [u8]>> {
         use std::os::unix::fs::FileExt;
+        if let Some(mmap) = &self.3 {
+            let data = &mmap[..];
+            let Some(end) = offset
+                .checked_add(len)
+                .filter(|end| *end <= data.len() as u64)
+            else {
+                bail!(
+                    "failed to read {} bytes at offset {}: the content has only {} bytes",
+                    len,
+                    offset,
+                    data.len()
+                );
+            };
+            return Ok(Cow::Borrowed(&data[offset as usize..end as usize]));
+        }
         let mut data = vec![0; len as usize];
         self.0
             .as_ref()
             .unwrap()
             .read_exact_at(&mut data[..], offset)?;
-        Ok(data)
+        Ok(Cow::Owned(data))
     }
 
     pub fn size(&self) -> u64 {
@@ -124,58 +418,392 @@ impl FileObject {
         Ok(FileObject(
             Some(File::options().read(true).write(false).open(path)?),
             data.len() as u64,
+            None,
+            None,
         ))
     }
 
+    /// Create a new file object and write the file with a checksum of the whole content.
+    pub fn create_with_checksum(path: &Path, mut data: Vec<u8>) -> Result<Self> {
+        let size = data.len() as u64;
+        let checksum = crc32fast::hash(&data);
+        data.put_u32(checksum);
+        data.put_u32(FILE_CHECKSUM_MAGIC);
+        let mut file = Self::create(path, data)?;
+        file.1 = size;
+        file.2 = Some(checksum);
+        Ok(file)
+    }
+
     pub fn open(path: &Path) -> Result<Self> {
         let file = File::options().read(true).write(false).open(path)?;
         let size = file.metadata()?.len();
-        Ok(FileObject(Some(file), size))
+        let mut file = FileObject(Some(file), size, None, None);
+        if size >= 8 {
+            let mut trailer = &file.read(size - 8, 8)?[..];
+            let checksum = trailer.get_u32();
+            if trailer.get_u32() == FILE_CHECKSUM_MAGIC {
+                file.1 = size - 8;
+                file.2 = Some(checksum);
+            }
+        }
+        Ok(file)
+    }
+
+    /// Open a file like `open` and memory-map it for reads.
+    pub fn open_mmap(path: &Path) -> Result<Self> {
+        let mut file = Self::open(path)?;
+        file.map()?;
+        Ok(file)
+    }
+
+    /// Memory-map the content of the file, so that reads go to the mapping instead of `pread`.
+    /// Empty files are not mapped.
+    pub fn map(&mut self) -> Result<()> {
+        let Some(file) = &self.0 else {
+            return Ok(());
+        };
+        if self.1 > 0 {
+            // SAFETY: the mapping only covers the content, which the file was checked to hold when
+            // it was opened, and reads are bounded by the mapping. SST files are never modified
+            // after they are written; removing the file keeps the mapping valid. Truncating the
+            // file while it is mapped (which only tools or tests tampering with it would do) makes
+            // reads of the truncated part crash the process.
+            let mmap = unsafe { MmapOptions::new().len(self.1 as usize).map(file)? };
+            self.3 = Some(mmap);
+        }
+        Ok(())
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        self.3.is_some()
+    }
+
+    /// Re-read the whole content and check it against the checksum of the file. Files written
+    /// without a checksum always pass.
+    pub fn verify(&self) -> Result<()> {
+        let Some(checksum) = self.2 else {
+            return Ok(());
+        };
+        if crc32fast::hash(&self.read(0, self.1)?) != checksum {
+            bail!(LsmError::Corruption("file checksum mismatched".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Write the whole file, including the checksum trailer, to `path`. Works even if the file
+    /// has been removed since it was opened.
+    pub fn copy_to(&self, path: &Path) -> Result<()> {
+        let len = self.1 + if self.2.is_some() { 8 } else { 0 };
+        std::fs::write(path, self.read(0, len)?)?;
+        File::open(path)?.sync_all()?;
+        Ok(())
+    }
+
+    pub fn has_checksum(&self) -> bool {
+        self.2.is_some()
     }
 }
 
-/// An SSTable.
+/// How the data blocks of an SST are compressed. A compressed block is stored as
+/// `| uncompressed length (u32) | compressed block |`, followed by the checksum of both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CompressionType {
+    #[default]
+    None = 0,
+    Lz4 = 1,
+    Zstd = 2,
+}
+
+impl CompressionType {
+    fn from_u8(x: u8) -> Result<Self> {
+        match x {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Lz4),
+            2 => Ok(Self::Zstd),
+            _ => bail!("unknown compression type {}", x),
+        }
+    }
+
+    /// Compress an encoded block into `buf`.
+    pub(crate) fn compress(self, block: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Self::None => buf.extend_from_slice(block),
+            Self::Lz4 => {
+                buf.put_u32(block.len() as u32);
+                buf.extend(lz4_flex::compress(block));
+            }
+            Self::Zstd => {
+                buf.put_u32(block.len() as u32);
+                buf.extend(
+                    zstd::bulk::compress(block, zstd::DEFAULT_COMPRESSION_LEVEL)
+                        .context("failed to compress block")?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Decompress a block written by `compress`.
+    pub(crate) fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        if self == Self::None {
+            return Ok(data.to_vec());
+        }
+        if data.len() < 4 {
+            bail!(LsmError::Corruption(
+                "corrupted compressed block".to_string()
+            ));
+        }
+        let uncompressed_len = (&data[..4]).get_u32() as usize;
+        let block = match self {
+            Self::None => unreachable!(),
+            Self::Lz4 => lz4_flex::decompress(&data[4..], uncompressed_len)?,
+            Self::Zstd => zstd::bulk::decompress(&data[4..], uncompressed_len)?,
+        };
+        if block.len() != uncompressed_len {
+            bail!(LsmError::Corruption(
+                "corrupted compressed block: unexpected length".to_string()
+            ));
+        }
+        Ok(block)
+    }
+}
+
+/// When the bloom filter of an SST opened from a file is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BloomLoad {
+    /// When the SST is opened.
+    #[default]
+    Eager,
+    /// By the first lookup that needs it, after which it is kept in memory. Saves the memory of
+    /// the blooms of SSTs that are never read, at the cost of a slower first lookup.
+    Lazy,
+}
+
+/// An SSTable, laid out as
+///
+/// ```text
+/// | data blocks | blobs | block meta | meta offset (u32) | bloom filter | bloom offset (u32) | block index | properties |
+/// ```
+///
+/// where each data block, compressed as described in [`CompressionType`], is followed by its
+/// checksum (u32), the blob section holds the values larger than the `large_value_threshold` the
+/// SST is built with, uncompressed and each followed by its checksum (u32), the bloom filter offset is 0 for SSTs without a bloom filter (with the bloom
+/// filter section left out), the block index is described in [`BlockIndexEntry`] and may be left
+/// out, and the properties footer is described in [`SsTableProperties`]. Files written with a
+/// checksum end with the trailer described in [`FILE_CHECKSUM_MAGIC`].
 pub struct SsTable {
     /// The actual storage unit of SsTable, the format is as above.
     pub(crate) file: FileObject,
-    /// The meta blocks that hold info for data blocks.
-    pub(crate) block_meta: Vec<BlockMeta>,
+    /// The first key and offset of every data block, which is all reads need.
+    block_index: Vec<BlockIndexEntry>,
+    /// The meta blocks that hold info for data blocks. SSTs opened with a block index read them
+    /// from `file` by the first call to [`SsTable::block_meta`].
+    pub(crate) block_meta: LazyBlockMeta,
     /// The offset that indicates the start point of meta blocks in `file`.
     pub(crate) block_meta_offset: usize,
+    /// The length of the meta blocks in `file`.
+    block_meta_len: u64,
+    /// The offset of the blob section in `file`, 0 if the SST has none.
+    blob_offset: usize,
+    /// Whether the values are encoded as described in [`crate::ttl`]. The values of older SSTs
+    /// are encoded while reading them.
+    pub(crate) encoded_values: bool,
     id: usize,
     block_cache: Option<Arc<BlockCache>>,
     first_key: KeyBytes,
     last_key: KeyBytes,
     pub(crate) bloom: Option<Bloom>,
+    /// Offset and length of the bloom filter in `file` if it is loaded lazily.
+    lazy_bloom_range: Option<(u64, u64)>,
+    lazy_bloom: OnceLock<Bloom>,
     max_ts: u64,
+    epoch: usize,
+    created_at: u64,
+    compression: CompressionType,
+    restart_interval: usize,
+    /// Distinguishes the blocks of this SST in the block cache from those of other SSTs that
+    /// have the same id.
+    cache_namespace: usize,
+    /// Where block cache hits and misses are counted, see [`SsTable::set_stats`].
+    stats: Option<Arc<LsmStats>>,
+}
+
+/// Allocate a process-wide unique namespace for an SST in the block cache.
+pub(crate) fn next_cache_namespace() -> usize {
+    static NEXT_CACHE_NAMESPACE: AtomicUsize = AtomicUsize::new(0);
+    NEXT_CACHE_NAMESPACE.fetch_add(1, Ordering::Relaxed)
 }
+
 impl SsTable {
     #[cfg(test)]
     pub(crate) fn open_for_test(file: FileObject) -> Result<Self> {
-        Self::open(0, None, file)
+        let sst = Self::open(0, None, file)?;
+        // tests compare the block meta of SSTs directly
+        sst.block_meta()?;
+        Ok(sst)
     }
 
     /// Open SSTable from a file.
     pub fn open(id: usize, block_cache: Option<Arc<BlockCache>>, file: FileObject) -> Result<Self> {
-        let len = file.size();
+        Self::open_with_bloom_load(id, block_cache, file, BloomLoad::Eager)
+    }
+
+    /// Open SSTable from a file, reading the bloom filter as specified by `bloom_load`.
+    pub fn open_with_bloom_load(
+        id: usize,
+        block_cache: Option<Arc<BlockCache>>,
+        file: FileObject,
+        bloom_load: BloomLoad,
+    ) -> Result<Self> {
+        let (properties, len) = match SsTableProperties::read_from(&file, id)? {
+            Some((properties, offset)) => (properties, offset),
+            None => (
+                SsTableProperties {
+                    epoch: id,
+                    created_at: 0,
+                    compression: CompressionType::None,
+                    index_offset: 0,
+                    restart_interval: 0,
+                    blob_offset: 0,
+                    meta_offsets: None,
+                    encoded_values: false,
+                },
+                file.size(),
+            ),
+        };
+        let (index_range, len) = if properties.index_offset == 0 {
+            (None, len)
+        } else {
+            if properties.index_offset > len {
+                bail!(LsmError::Corruption(format!(
+                    "corrupted SST: invalid block index offset {}",
+                    properties.index_offset
+                )));
+            }
+            (
+                Some((properties.index_offset, len - properties.index_offset)),
+                properties.index_offset,
+            )
+        };
+        if len < 4 {
+            bail!(LsmError::Corruption(
+                "corrupted SST: file too small".to_string()
+            ));
+        }
         let raw_bloom_offset = file.read(len - 4, 4)?;
         let bloom_offset = (&raw_bloom_offset[..]).get_u32() as u64;
-        let raw_bloom = file.read(bloom_offset, len - 4 - bloom_offset)?;
-        let bloom_filter = Bloom::decode(&raw_bloom)?;
-        let raw_meta_offset = file.read(bloom_offset - 4, 4)?;
+        let check_offset = |name: &str, offset: u64, expected: Option<u64>| match expected {
+            Some(expected) if expected != offset => Err(LsmError::Corruption(format!(
+                "corrupted SST {}: {} offset {} does not match {} in the properties",
+                id, name, offset, expected
+            ))),
+            _ => Ok(()),
+        };
+        check_offset(
+            "bloom filter",
+            bloom_offset,
+            properties.meta_offsets.map(|(_, bloom)| bloom),
+        )?;
+        // a zero bloom filter offset marks an SST written without a bloom filter
+        let (bloom_range, meta_end) = if bloom_offset == 0 {
+            if len < 8 {
+                bail!(LsmError::Corruption(
+                    "corrupted SST: file too small".to_string()
+                ));
+            }
+            (None, len - 4)
+        } else {
+            if bloom_offset < 4 || bloom_offset > len - 4 {
+                bail!(LsmError::Corruption(format!(
+                    "corrupted SST: invalid bloom filter offset {}",
+                    bloom_offset
+                )));
+            }
+            (Some((bloom_offset, len - 4 - bloom_offset)), bloom_offset)
+        };
+        let bloom_filter = match (bloom_load, bloom_range) {
+            (BloomLoad::Eager, Some((offset, len))) => {
+                Some(Bloom::decode(&file.read(offset, len)?)?)
+            }
+            _ => None,
+        };
+        let raw_meta_offset = file.read(meta_end - 4, 4)?;
         let block_meta_offset = (&raw_meta_offset[..]).get_u32() as u64;
-        let raw_meta = file.read(block_meta_offset, bloom_offset - 4 - block_meta_offset)?;
-        let block_meta = BlockMeta::decode_block_meta(&raw_meta[..])?;
+        check_offset(
+            "block meta",
+            block_meta_offset,
+            properties.meta_offsets.map(|(meta, _)| meta),
+        )?;
+        if block_meta_offset > meta_end - 4 {
+            bail!(LsmError::Corruption(format!(
+                "corrupted SST: invalid block meta offset {}",
+                block_meta_offset
+            )));
+        }
+        let block_meta_len = meta_end - 4 - block_meta_offset;
+        let (block_index, last_key, block_meta) = match index_range {
+            Some((offset, len)) => {
+                let (block_index, last_key) =
+                    BlockIndexEntry::decode_block_index(&file.read(offset, len)?)?;
+                let block_meta = LazyBlockMeta::unloaded(block_index.len());
+                (block_index, last_key, block_meta)
+            }
+            None => {
+                let raw_meta = file.read(block_meta_offset, block_meta_len)?;
+                let block_meta = BlockMeta::decode_block_meta(&raw_meta[..])?;
+                let block_index = block_meta
+                    .iter()
+                    .map(|meta| BlockIndexEntry {
+                        offset: meta.offset,
+                        first_key: meta.first_key.clone(),
+                    })
+                    .collect();
+                let last_key = block_meta.last().unwrap().last_key.clone();
+                (block_index, last_key, LazyBlockMeta::loaded(block_meta))
+            }
+        };
+        if properties.blob_offset > block_meta_offset {
+            bail!(LsmError::Corruption(format!(
+                "corrupted SST: invalid blob offset {}",
+                properties.blob_offset
+            )));
+        }
+        let data_end = match properties.blob_offset {
+            0 => block_meta_offset,
+            blob_offset => blob_offset,
+        };
+        if block_index.last().unwrap().offset as u64 + 4 > data_end {
+            bail!(LsmError::Corruption(
+                "corrupted SST: block offset exceeds data section".to_string()
+            ));
+        }
         Ok(Self {
             file,
-            first_key: block_meta.first().unwrap().first_key.clone(),
-            last_key: block_meta.last().unwrap().last_key.clone(),
+            first_key: block_index.first().unwrap().first_key.clone(),
+            last_key,
+            block_index,
             block_meta,
             block_meta_offset: block_meta_offset as usize,
+            block_meta_len,
+            blob_offset: properties.blob_offset as usize,
+            encoded_values: properties.encoded_values,
             id,
             block_cache,
-            bloom: Some(bloom_filter),
+            lazy_bloom_range: match bloom_load {
+                BloomLoad::Eager => None,
+                BloomLoad::Lazy => bloom_range,
+            },
+            bloom: bloom_filter,
+            lazy_bloom: OnceLock::new(),
             max_ts: 0,
+            epoch: properties.epoch,
+            created_at: properties.created_at,
+            compression: properties.compression,
+            restart_interval: properties.restart_interval,
+            cache_namespace: next_cache_namespace(),
+            stats: None,
         })
     }
 
@@ -187,53 +815,145 @@ impl SsTable {
         last_key: KeyBytes,
     ) -> Self {
         Self {
-            file: FileObject(None, file_size),
-            block_meta: vec![],
+            file: FileObject(None, file_size, None, None),
+            block_index: vec![],
+            block_meta: LazyBlockMeta::loaded(vec![]),
             block_meta_offset: 0,
+            block_meta_len: 0,
+            blob_offset: 0,
+            encoded_values: true,
             id,
             block_cache: None,
             first_key,
             last_key,
             bloom: None,
+            lazy_bloom_range: None,
+            lazy_bloom: OnceLock::new(),
             max_ts: 0,
+            epoch: id,
+            created_at: 0,
+            compression: CompressionType::None,
+            restart_interval: 0,
+            cache_namespace: next_cache_namespace(),
+            stats: None,
         }
     }
 
     /// Read a block from the disk.
     pub fn read_block(&self, block_idx: usize) -> Result<Arc<Block>> {
-        let offset = self.block_meta[block_idx].offset;
+        let offset = self.block_index[block_idx].offset;
         let offset_end = self
-            .block_meta
+            .block_index
             .get(block_idx + 1)
-            .map_or(self.block_meta_offset, |x| x.offset);
+            .map_or(self.data_end(), |x| x.offset);
         let block_len = offset_end - offset - 4;
-        let block_data_with_chksum: Vec<u8> = self
+        let block_data_with_chksum = self
             .file
             .read(offset as u64, (offset_end - offset) as u64)?;
         let block_data = &block_data_with_chksum[..block_len];
         let checksum = (&block_data_with_chksum[block_len..]).get_u32();
         if checksum != crc32fast::hash(block_data) {
-            bail!("block checksum mismatched");
+            bail!(LsmError::BlockChecksum {
+                sst_id: self.id,
+                block_idx,
+            });
+        }
+        if self.compression == CompressionType::None {
+            return Ok(Arc::new(Block::decode_with_restart_interval(
+                block_data,
+                self.restart_interval,
+            )));
+        }
+        Ok(Arc::new(Block::decode_with_restart_interval(
+            &self.compression.decompress(block_data)?,
+            self.restart_interval,
+        )))
+    }
+
+    /// Where the data blocks end: at the blob section if the SST has one, at the block meta
+    /// otherwise.
+    fn data_end(&self) -> usize {
+        if self.blob_offset == 0 {
+            self.block_meta_offset
+        } else {
+            self.blob_offset
         }
-        Ok(Arc::new(Block::decode(block_data)))
+    }
+
+    /// Whether the SST stores values out of line, see [`SsTableBuilder::set_large_value_threshold`].
+    pub fn has_blobs(&self) -> bool {
+        self.blob_offset != 0
+    }
+
+    /// Read the value of `len` bytes at `offset` of the blob section, which a data block refers
+    /// to instead of storing the value.
+    pub(crate) fn read_blob(&self, offset: u64, len: u64) -> Result<Bytes> {
+        let section_len = (self.block_meta_offset - self.blob_offset) as u64;
+        if !self.has_blobs()
+            || offset
+                .checked_add(len)
+                .and_then(|end| end.checked_add(4))
+                .is_none_or(|end| end > section_len)
+        {
+            bail!(LsmError::Corruption(format!(
+                "corrupted SST {}: invalid blob reference at offset {}",
+                self.id, offset
+            )));
+        }
+        let raw = self.file.read(self.blob_offset as u64 + offset, len + 4)?;
+        let (value, mut checksum) = raw.split_at(len as usize);
+        if checksum.get_u32() != crc32fast::hash(value) {
+            bail!(LsmError::Corruption(format!(
+                "corrupted SST {}: blob checksum mismatched at offset {}",
+                self.id, offset
+            )));
+        }
+        Ok(match raw {
+            Cow::Owned(mut raw) => {
+                raw.truncate(len as usize);
+                Bytes::from(raw)
+            }
+            Cow::Borrowed(raw) => Bytes::copy_from_slice(&raw[..len as usize]),
+        })
     }
 
     /// Read a block from disk, with block cache.
     pub fn read_block_cached(&self, block_idx: usize) -> Result<Arc<Block>> {
         if let Some(ref block_cache) = self.block_cache {
+            let mut cache_hit = true;
             let blk = block_cache
-                .try_get_with((self.id, block_idx), || self.read_block(block_idx))
-                .map_err(|e| anyhow!("{}", e))?;
+                .try_get_with((self.cache_namespace, self.id, block_idx), || {
+                    cache_hit = false;
+                    // typed, so that the shared error can be cloned below with its IO source
+                    self.read_block(block_idx)
+                        .map_err(|e| anyhow::Error::new(LsmError::from(e)))
+                })
+                // the cache shares the error between the waiting readers, keep its type
+                .map_err(|e| match e.downcast_ref::<LsmError>() {
+                    Some(err) => anyhow::Error::new(err.clone()),
+                    None => anyhow!("{}", e),
+                })?;
+            if let Some(stats) = &self.stats {
+                stats.record_block_read(cache_hit);
+            }
             Ok(blk)
         } else {
+            if let Some(stats) = &self.stats {
+                stats.record_block_read(false);
+            }
             self.read_block(block_idx)
         }
     }
 
+    /// Count the block cache hits and misses of this SST in `stats`.
+    pub(crate) fn set_stats(&mut self, stats: Arc<LsmStats>) {
+        self.stats = Some(stats);
+    }
+
     /// Find the block that may contain `key`.
     pub fn find_block_idx(&self, key: KeySlice) -> usize {
-        self.block_meta
-            .partition_point(|meta| meta.first_key.as_key_slice() <= key)
+        self.block_index
+            .partition_point(|entry| entry.first_key.as_key_slice() <= key)
             .saturating_sub(1)
     }
 
@@ -242,6 +962,104 @@ impl SsTable {
         self.block_meta.len()
     }
 
+    /// The meta of the data blocks, which is read from the file first if the SST is opened with
+    /// a block index.
+    pub fn block_meta(&self) -> Result<&[BlockMeta]> {
+        if let Some(block_meta) = self.block_meta.block_meta.get() {
+            return Ok(block_meta);
+        }
+        let raw_meta = self
+            .file
+            .read(self.block_meta_offset as u64, self.block_meta_len)?;
+        let block_meta = BlockMeta::decode_block_meta(&raw_meta)?;
+        if block_meta.len() != self.block_index.len() {
+            bail!(LsmError::Corruption(
+                "corrupted SST: block meta does not match block index".to_string()
+            ));
+        }
+        Ok(self.block_meta.block_meta.get_or_init(|| block_meta))
+    }
+
+    /// Whether the meta of the data blocks is in memory.
+    pub fn is_block_meta_loaded(&self) -> bool {
+        self.block_meta.block_meta.get().is_some()
+    }
+
+    /// The first key and the size in bytes of every data block, along with an upper bound of its
+    /// keys: the first key of the next block, or the last key of the SST for the last block. Only
+    /// needs the block index.
+    pub(crate) fn block_ranges(&self) -> impl Iterator<Item = (&KeyBytes, &KeyBytes, usize)> {
+        self.block_index.iter().enumerate().map(|(idx, entry)| {
+            let (upper, end) = match self.block_index.get(idx + 1) {
+                Some(next) => (&next.first_key, next.offset),
+                None => (&self.last_key, self.data_end()),
+            };
+            (&entry.first_key, upper, end - entry.offset)
+        })
+    }
+
+    /// Estimate the bytes of the SST within the range from the block index: the data blocks that
+    /// may hold keys within the range, scaled up by the share of the data blocks in the file.
+    pub(crate) fn approximate_size_in_range(
+        &self,
+        lower: Bound<&[u8]>,
+        upper: Bound<&[u8]>,
+    ) -> u64 {
+        if self.block_meta_offset == 0 {
+            return 0;
+        }
+        let first_block = match lower {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                self.find_block_idx(KeySlice::from_slice(key))
+            }
+            Bound::Unbounded => 0,
+        };
+        // The number of blocks starting within the upper bound.
+        let end_block = match upper {
+            Bound::Included(key) => self
+                .block_index
+                .partition_point(|entry| entry.first_key.raw_ref() <= key),
+            Bound::Excluded(key) => self
+                .block_index
+                .partition_point(|entry| entry.first_key.raw_ref() < key),
+            Bound::Unbounded => self.block_index.len(),
+        };
+        if first_block >= end_block {
+            return 0;
+        }
+        let end_offset = self
+            .block_index
+            .get(end_block)
+            .map_or(self.data_end(), |entry| entry.offset);
+        let data_size = (end_offset - self.block_index[first_block].offset) as u64;
+        data_size * self.table_size() / self.data_end() as u64
+    }
+
+    /// Read the data blocks in order, going through the block cache.
+    pub fn blocks(&self) -> impl Iterator<Item = Result<Arc<Block>>> + '_ {
+        (0..self.num_of_blocks()).map(|block_idx| self.read_block_cached(block_idx))
+    }
+
+    /// The bloom filter of the SST, which is read from the file first if it is loaded lazily.
+    pub fn bloom(&self) -> Result<Option<&Bloom>> {
+        if let Some(bloom) = &self.bloom {
+            return Ok(Some(bloom));
+        }
+        let Some((offset, len)) = self.lazy_bloom_range else {
+            return Ok(None);
+        };
+        if let Some(bloom) = self.lazy_bloom.get() {
+            return Ok(Some(bloom));
+        }
+        let bloom = Bloom::decode(&self.file.read(offset, len)?)?;
+        Ok(Some(self.lazy_bloom.get_or_init(|| bloom)))
+    }
+
+    /// Whether the bloom filter is in memory.
+    pub fn is_bloom_loaded(&self) -> bool {
+        self.bloom.is_some() || self.lazy_bloom.get().is_some()
+    }
+
     pub fn first_key(&self) -> &KeyBytes {
         &self.first_key
     }
@@ -258,7 +1076,20 @@ impl SsTable {
         self.id
     }
 
+    /// Always 0: keys carry no timestamp in this engine. Timestamped keys, and an SST format
+    /// recording their largest timestamp, are implemented by the `mini-lsm-mvcc` crate.
     pub fn max_ts(&self) -> u64 {
         self.max_ts
     }
+
+    /// The epoch of the data in this SST. See [`crate::range_tombstone::RangeTombstone`].
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// The time when the SST was built. SSTs written without a creation time report the UNIX
+    /// epoch.
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.created_at)
+    }
 }
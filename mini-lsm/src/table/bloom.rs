@@ -17,6 +17,8 @@
 use anyhow::{Result, bail};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+use crate::error::LsmError;
+
 /// Implements a bloom filter
 pub struct Bloom {
     /// data of filter in bits
@@ -61,9 +63,14 @@ impl<T: AsMut<[u8]>> BitSliceMut for T {
 impl Bloom {
     /// Decode a bloom filter
     pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 5 {
+            bail!(LsmError::Corruption("corrupted bloom filter".to_string()));
+        }
         let checksum = (&buf[buf.len() - 4..buf.len()]).get_u32();
         if checksum != crc32fast::hash(&buf[..buf.len() - 4]) {
-            bail!("checksum mismatched for bloom filters");
+            bail!(LsmError::Corruption(
+                "checksum mismatched for bloom filters".to_string()
+            ));
         }
         let filter = &buf[..buf.len() - 5];
         let k = buf[buf.len() - 5];
@@ -84,8 +91,7 @@ impl Bloom {
 
     /// Get bloom filter bits per key from entries count and FPR
     pub fn bloom_bits_per_key(entries: usize, false_positive_rate: f64) -> usize {
-        let size =
-            -1.0 * (entries as f64) * false_positive_rate.ln() / std::f64::consts::LN_2.powi(2);
+        let size = -(entries as f64) * false_positive_rate.ln() / std::f64::consts::LN_2.powi(2);
         let locs = (size / (entries as f64)).ceil();
         locs as usize
     }
@@ -95,7 +101,7 @@ impl Bloom {
         let k = (bits_per_key as f64 * 0.69) as u32;
         let k = k.clamp(1, 30);
         let nbits = (keys.len() * bits_per_key).max(64);
-        let nbytes = (nbits + 7) / 8;
+        let nbytes = nbits.div_ceil(8);
         let nbits = nbytes * 8;
         let mut filter = BytesMut::with_capacity(nbytes);
         filter.resize(nbytes, 0);
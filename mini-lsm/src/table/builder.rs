@@ -14,15 +14,21 @@
 
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
 
 use super::bloom::Bloom;
-use super::{BlockMeta, FileObject, SsTable};
-use crate::block::BlockBuilder;
+use super::{
+    BlockIndexEntry, BlockMeta, CompressionType, FileObject, LazyBlockMeta, SsTable,
+    SsTableProperties,
+};
+use crate::block::{BlockBuilder, DEFAULT_RESTART_INTERVAL};
+use crate::error::LsmError;
 use crate::key::{KeySlice, KeyVec};
 use crate::lsm_storage::BlockCache;
+use crate::ttl::encode_blob_ref;
 
 /// Builds an SSTable from key-value pairs.
 pub struct SsTableBuilder {
@@ -30,9 +36,22 @@ pub struct SsTableBuilder {
     first_key: KeyVec,
     last_key: KeyVec,
     data: Vec<u8>,
+    /// The values stored out of line, each followed by its checksum (u32).
+    blobs: Vec<u8>,
     pub(crate) meta: Vec<BlockMeta>,
     block_size: usize,
     key_hashes: Vec<u32>,
+    epoch: Option<usize>,
+    file_checksum: bool,
+    bloom_bits_per_key: Option<usize>,
+    compression: CompressionType,
+    mmap: bool,
+    block_index: bool,
+    restart_interval: usize,
+    large_value_threshold: Option<usize>,
+    /// The first violation of the key order, or the first key or value too large for a block,
+    /// reported by `build`.
+    error: Option<LsmError>,
 }
 
 impl SsTableBuilder {
@@ -40,23 +59,118 @@ impl SsTableBuilder {
     pub fn new(block_size: usize) -> Self {
         Self {
             data: Vec::new(),
+            blobs: Vec::new(),
             meta: Vec::new(),
             first_key: KeyVec::new(),
             last_key: KeyVec::new(),
             block_size,
             builder: BlockBuilder::new(block_size),
             key_hashes: Vec::new(),
+            epoch: None,
+            file_checksum: false,
+            bloom_bits_per_key: None,
+            compression: CompressionType::None,
+            mmap: false,
+            block_index: true,
+            restart_interval: DEFAULT_RESTART_INTERVAL,
+            large_value_threshold: None,
+            error: None,
         }
     }
 
-    /// Adds a key-value pair to SSTable
+    /// Append a checksum of the whole file, see [`FileObject::verify`].
+    pub fn set_file_checksum(&mut self, file_checksum: bool) {
+        self.file_checksum = file_checksum;
+    }
+
+    /// Set the bits per key of the bloom filter, trading memory and disk space for a lower false
+    /// positive rate. Defaults to the bits needed for a 1% false positive rate. 0 builds the SST
+    /// without a bloom filter.
+    pub fn set_bloom_bits_per_key(&mut self, bits_per_key: usize) {
+        self.bloom_bits_per_key = Some(bits_per_key);
+    }
+
+    /// Set how the data blocks are compressed. Defaults to no compression.
+    pub fn set_compression(&mut self, compression: CompressionType) {
+        self.compression = compression;
+    }
+
+    /// Memory-map the SST built for reads, see [`FileObject::map`].
+    pub fn set_mmap(&mut self, mmap: bool) {
+        self.mmap = mmap;
+    }
+
+    /// Write a block index, so that opening the SST does not read the whole block meta. Defaults
+    /// to true.
+    pub fn set_block_index(&mut self, block_index: bool) {
+        self.block_index = block_index;
+    }
+
+    /// Set the number of entries between the restart points of the data blocks, see
+    /// [`crate::block::Block`]. Defaults to [`DEFAULT_RESTART_INTERVAL`]. Must be called before
+    /// adding any key.
+    pub fn set_restart_interval(&mut self, restart_interval: usize) {
+        assert!(
+            self.meta.is_empty() && self.builder.is_empty(),
+            "keys are already added"
+        );
+        self.restart_interval = restart_interval;
+        self.builder = BlockBuilder::with_restart_interval(self.block_size, restart_interval);
+    }
+
+    /// Store the values larger than `threshold` bytes in the blob section of the SST instead of
+    /// the data blocks, so that values do not need to fit into a block. Must not exceed
+    /// [`crate::block::MAX_ENTRY_FIELD_LEN`].
+    pub fn set_large_value_threshold(&mut self, threshold: usize) {
+        self.large_value_threshold = Some(threshold);
+    }
+
+    /// Set the epoch of the SST. Defaults to the SST id.
+    pub fn set_epoch(&mut self, epoch: usize) {
+        self.epoch = Some(epoch);
+    }
+
+    /// Adds a key-value pair to SSTable. Keys must be added in strictly increasing order, which is
+    /// asserted in debug builds and makes `build` fail in release builds.
+    /// A key or value longer than [`MAX_ENTRY_FIELD_LEN`](crate::block::MAX_ENTRY_FIELD_LEN)
+    /// after moving large values out of the blocks makes `build` fail.
     pub fn add(&mut self, key: KeySlice, value: &[u8]) {
+        let prev_key = if self.last_key.is_empty() {
+            self.meta.last().map(|meta| meta.last_key.as_key_slice())
+        } else {
+            Some(self.last_key.as_key_slice())
+        };
+        if let Some(prev_key) = prev_key
+            && key <= prev_key
+        {
+            let msg = format!(
+                "SST keys out of order: {:?} added after {:?}",
+                Bytes::copy_from_slice(key.raw_ref()),
+                Bytes::copy_from_slice(prev_key.raw_ref())
+            );
+            debug_assert!(false, "{}", msg);
+            if self.error.is_none() {
+                self.error = Some(LsmError::Internal(msg));
+            }
+        }
+
         if self.first_key.is_empty() {
             self.first_key.set_from_slice(key);
         }
 
         self.key_hashes.push(farmhash::fingerprint32(key.raw_ref()));
 
+        let blob_ref;
+        let value = match self.large_value_threshold {
+            Some(threshold) if value.len() > threshold => {
+                blob_ref = encode_blob_ref(self.blobs.len() as u64, value.len() as u64);
+                self.blobs.put_slice(value);
+                self.blobs.put_u32(crc32fast::hash(value));
+                &blob_ref[..]
+            }
+            _ => value,
+        };
+
         if self.builder.add(key, value) {
             self.last_key.set_from_slice(key);
             return;
@@ -73,19 +187,32 @@ impl SsTableBuilder {
 
     /// Get the estimated size of the SSTable.
     pub fn estimated_size(&self) -> usize {
-        self.data.len()
+        self.data.len() + self.blobs.len()
     }
 
     fn finish_block(&mut self) {
-        let builder = std::mem::replace(&mut self.builder, BlockBuilder::new(self.block_size));
-        let encoded_block = builder.build().encode();
+        let builder = std::mem::replace(
+            &mut self.builder,
+            BlockBuilder::with_restart_interval(self.block_size, self.restart_interval),
+        );
+        let encoded_block = match builder.try_build() {
+            Ok(block) => block.encode(),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                return;
+            }
+        };
         self.meta.push(BlockMeta {
             offset: self.data.len(),
             first_key: std::mem::take(&mut self.first_key).into_key_bytes(),
             last_key: std::mem::take(&mut self.last_key).into_key_bytes(),
         });
-        let checksum = crc32fast::hash(&encoded_block);
-        self.data.extend(encoded_block);
+        let block_offset = self.data.len();
+        if let Err(error) = self.compression.compress(&encoded_block, &mut self.data) {
+            self.error.get_or_insert(error.into());
+            return;
+        }
+        let checksum = crc32fast::hash(&self.data[block_offset..]);
         self.data.put_u32(checksum);
     }
 
@@ -97,28 +224,91 @@ impl SsTableBuilder {
         path: impl AsRef<Path>,
     ) -> Result<SsTable> {
         self.finish_block();
+        if let Some(error) = self.error {
+            return Err(error.into());
+        }
         let mut buf = self.data;
+        let blob_offset = if self.blobs.is_empty() {
+            0
+        } else {
+            let blob_offset = buf.len();
+            buf.extend_from_slice(&self.blobs);
+            blob_offset
+        };
         let meta_offset = buf.len();
         BlockMeta::encode_block_meta(&self.meta, &mut buf);
+        let block_meta_len = (buf.len() - meta_offset) as u64;
         buf.put_u32(meta_offset as u32);
-        let bloom = Bloom::build_from_key_hashes(
-            &self.key_hashes,
-            Bloom::bloom_bits_per_key(self.key_hashes.len(), 0.01),
-        );
-        let bloom_offset = buf.len();
-        bloom.encode(&mut buf);
-        buf.put_u32(bloom_offset as u32);
-        let file = FileObject::create(path.as_ref(), buf)?;
+        let bits_per_key = self
+            .bloom_bits_per_key
+            .unwrap_or_else(|| Bloom::bloom_bits_per_key(self.key_hashes.len(), 0.01));
+        let (bloom, bloom_offset) = if bits_per_key == 0 {
+            buf.put_u32(0);
+            (None, 0)
+        } else {
+            let bloom = Bloom::build_from_key_hashes(&self.key_hashes, bits_per_key);
+            let bloom_offset = buf.len();
+            bloom.encode(&mut buf);
+            buf.put_u32(bloom_offset as u32);
+            (Some(bloom), bloom_offset)
+        };
+        let index_offset = if self.block_index {
+            let index_offset = buf.len();
+            BlockIndexEntry::encode_block_index(&self.meta, &mut buf);
+            index_offset as u64
+        } else {
+            0
+        };
+        let properties = SsTableProperties {
+            epoch: self.epoch.unwrap_or(id),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_millis() as u64),
+            compression: self.compression,
+            index_offset,
+            restart_interval: self.restart_interval,
+            blob_offset: blob_offset as u64,
+            meta_offsets: Some((meta_offset as u64, bloom_offset as u64)),
+            encoded_values: true,
+        };
+        properties.encode(&mut buf);
+        let mut file = if self.file_checksum {
+            FileObject::create_with_checksum(path.as_ref(), buf)?
+        } else {
+            FileObject::create(path.as_ref(), buf)?
+        };
+        if self.mmap {
+            file.map()?;
+        }
         Ok(SsTable {
             id,
             file,
             first_key: self.meta.first().unwrap().first_key.clone(),
             last_key: self.meta.last().unwrap().last_key.clone(),
-            block_meta: self.meta,
+            block_index: self
+                .meta
+                .iter()
+                .map(|meta| BlockIndexEntry {
+                    offset: meta.offset,
+                    first_key: meta.first_key.clone(),
+                })
+                .collect(),
+            block_meta: LazyBlockMeta::loaded(self.meta),
             block_meta_offset: meta_offset,
+            block_meta_len,
+            blob_offset,
+            encoded_values: true,
             block_cache,
-            bloom: Some(bloom),
+            bloom,
+            lazy_bloom_range: None,
+            lazy_bloom: Default::default(),
             max_ts: 0, // will be changed to latest ts in week 2
+            epoch: properties.epoch,
+            created_at: properties.created_at,
+            compression: properties.compression,
+            restart_interval: properties.restart_interval,
+            cache_namespace: super::next_cache_namespace(),
+            stats: None,
         })
     }
 
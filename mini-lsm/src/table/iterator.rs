@@ -12,23 +12,64 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
 use std::sync::Arc;
 
 use anyhow::Result;
+use bytes::Bytes;
 
 use super::SsTable;
 use crate::block::BlockIterator;
-use crate::iterators::StorageIterator;
+use crate::iterators::{SeekableIterator, StorageIterator};
 use crate::key::KeySlice;
+use crate::ttl::{decode_blob_ref, encode_value};
 
 /// An iterator over the contents of an SSTable.
 pub struct SsTableIterator {
     table: Arc<SsTable>,
     blk_iter: BlockIterator,
     blk_idx: usize,
+    /// Whether `next` moves to the previous key, see [`SsTableIterator::reversed`].
+    reverse: bool,
+    /// The current value if it differs from the one in the block: read from the blob section of
+    /// the SST if the block only refers to it, or encoded if the SST stores raw user values.
+    owned_value: Option<Bytes>,
 }
 
 impl SsTableIterator {
+    fn new(table: Arc<SsTable>, blk_idx: usize, blk_iter: BlockIterator) -> Result<Self> {
+        let mut iter = Self {
+            blk_iter,
+            table,
+            blk_idx,
+            reverse: false,
+            owned_value: None,
+        };
+        iter.resolve_value()?;
+        Ok(iter)
+    }
+
+    /// Read the current value from the blob section if the block only refers to it, and encode
+    /// it as described in [`crate::ttl`] if the SST was written before values were encoded. Must
+    /// be called whenever the iterator moves.
+    fn resolve_value(&mut self) -> Result<()> {
+        self.owned_value = None;
+        if !self.blk_iter.is_valid() {
+            return Ok(());
+        }
+        let value = self.blk_iter.value();
+        if !self.table.encoded_values {
+            if let Cow::Owned(encoded) = encode_value(value) {
+                self.owned_value = Some(encoded.into());
+            }
+        } else if self.table.has_blobs()
+            && let Some((offset, len)) = decode_blob_ref(value)
+        {
+            self.owned_value = Some(self.table.read_blob(offset, len)?);
+        }
+        Ok(())
+    }
+
     fn seek_to_first_inner(table: &Arc<SsTable>) -> Result<(usize, BlockIterator)> {
         Ok((
             0,
@@ -39,12 +80,7 @@ impl SsTableIterator {
     /// Create a new iterator and seek to the first key-value pair.
     pub fn create_and_seek_to_first(table: Arc<SsTable>) -> Result<Self> {
         let (blk_idx, blk_iter) = Self::seek_to_first_inner(&table)?;
-        let iter = Self {
-            blk_iter,
-            table,
-            blk_idx,
-        };
-        Ok(iter)
+        Self::new(table, blk_idx, blk_iter)
     }
 
     /// Seek to the first key-value pair.
@@ -52,7 +88,29 @@ impl SsTableIterator {
         let (blk_idx, blk_iter) = Self::seek_to_first_inner(&self.table)?;
         self.blk_idx = blk_idx;
         self.blk_iter = blk_iter;
-        Ok(())
+        self.resolve_value()
+    }
+
+    fn seek_to_last_inner(table: &Arc<SsTable>) -> Result<(usize, BlockIterator)> {
+        let blk_idx = table.num_of_blocks() - 1;
+        Ok((
+            blk_idx,
+            BlockIterator::create_and_seek_to_last(table.read_block_cached(blk_idx)?),
+        ))
+    }
+
+    /// Create a new iterator and seek to the last key-value pair.
+    pub fn create_and_seek_to_last(table: Arc<SsTable>) -> Result<Self> {
+        let (blk_idx, blk_iter) = Self::seek_to_last_inner(&table)?;
+        Self::new(table, blk_idx, blk_iter)
+    }
+
+    /// Seek to the last key-value pair.
+    pub fn seek_to_last(&mut self) -> Result<()> {
+        let (blk_idx, blk_iter) = Self::seek_to_last_inner(&self.table)?;
+        self.blk_idx = blk_idx;
+        self.blk_iter = blk_iter;
+        self.resolve_value()
     }
 
     fn seek_to_key_inner(table: &Arc<SsTable>, key: KeySlice) -> Result<(usize, BlockIterator)> {
@@ -72,20 +130,58 @@ impl SsTableIterator {
     /// Create a new iterator and seek to the first key-value pair which >= `key`.
     pub fn create_and_seek_to_key(table: Arc<SsTable>, key: KeySlice) -> Result<Self> {
         let (blk_idx, blk_iter) = Self::seek_to_key_inner(&table, key)?;
-        let iter = Self {
-            blk_iter,
-            table,
-            blk_idx,
-        };
-        Ok(iter)
+        Self::new(table, blk_idx, blk_iter)
     }
 
-    /// Seek to the first key-value pair which >= `key`.
+    /// Seek to the first key-value pair which >= `key`. The current block is reused if `key` falls
+    /// into it.
     pub fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        if self.blk_idx < self.table.num_of_blocks()
+            && self.table.find_block_idx(key) == self.blk_idx
+        {
+            self.blk_iter.seek_to_key(key);
+            if self.blk_iter.is_valid() {
+                return self.resolve_value();
+            }
+        }
         let (blk_idx, blk_iter) = Self::seek_to_key_inner(&self.table, key)?;
         self.blk_iter = blk_iter;
         self.blk_idx = blk_idx;
-        Ok(())
+        self.resolve_value()
+    }
+
+    /// Create a new iterator in descending order, see [`SsTableIterator::reversed`], and seek to
+    /// the last key-value pair which <= `key`.
+    pub fn create_and_seek_to_key_rev(table: Arc<SsTable>, key: KeySlice) -> Result<Self> {
+        let mut iter = Self::create_and_seek_to_key(table, key)?;
+        if !iter.is_valid() {
+            iter.seek_to_last()?;
+        } else if iter.key() > key {
+            iter.prev()?;
+        }
+        Ok(iter.reversed())
+    }
+
+    /// Make `next` move to the previous key-value pair, for merging with other iterators in
+    /// descending key order.
+    pub fn reversed(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// Move to the previous key-value pair, rolling back to the last entry of the previous block
+    /// at the start of a block. The iterator becomes invalid when moving before the first key.
+    pub fn prev(&mut self) -> Result<()> {
+        if !self.blk_iter.is_valid() {
+            return Ok(());
+        }
+        self.blk_iter.prev();
+        if !self.blk_iter.is_valid() && self.blk_idx > 0 {
+            self.blk_idx -= 1;
+            self.blk_iter =
+                BlockIterator::create_and_seek_to_last(self.table.read_block_cached(self.blk_idx)?);
+        }
+        self.resolve_value()
     }
 }
 
@@ -93,10 +189,13 @@ impl StorageIterator for SsTableIterator {
     type KeyType<'a> = KeySlice<'a>;
 
     fn value(&self) -> &[u8] {
-        self.blk_iter.value()
+        match &self.owned_value {
+            Some(value) => value,
+            None => self.blk_iter.value(),
+        }
     }
 
-    fn key(&self) -> KeySlice {
+    fn key(&self) -> KeySlice<'_> {
         self.blk_iter.key()
     }
 
@@ -105,6 +204,9 @@ impl StorageIterator for SsTableIterator {
     }
 
     fn next(&mut self) -> Result<()> {
+        if self.reverse {
+            return self.prev();
+        }
         self.blk_iter.next();
         if !self.blk_iter.is_valid() {
             self.blk_idx += 1;
@@ -114,6 +216,16 @@ impl StorageIterator for SsTableIterator {
                 );
             }
         }
-        Ok(())
+        self.resolve_value()
+    }
+
+    fn epoch(&self) -> usize {
+        self.table.epoch()
+    }
+}
+
+impl SeekableIterator for SsTableIterator {
+    fn seek_to_key(&mut self, key: KeySlice) -> Result<()> {
+        SsTableIterator::seek_to_key(self, key)
     }
 }
@@ -12,7 +12,88 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod active_iterators;
+mod approximate_size;
+mod background_error;
+mod block_cache;
+mod block_index;
+mod block_prefix;
+mod block_restart;
+mod block_seek;
+mod bloom_bits;
+mod checkpoint;
+mod compact_range;
+mod compact_snapshot;
+mod compaction_concurrency;
+mod compaction_parallel;
+mod compaction_rate_limit;
+mod comparator;
+mod compare_and_swap;
+mod compression;
+mod db_size_limit;
+mod describe;
+mod dry_run_compaction;
+mod empty_key;
+mod entry_size_limit;
+mod event_listener;
+mod excluded_bound;
+mod file_checksum;
+mod flush_and_wait;
+mod flush_batch;
+mod flush_slowdown;
+mod flush_stats;
+mod flush_to_level;
+mod get_batch;
+mod get_early_stop;
 mod harness;
+mod imm_backpressure;
+mod ingest_sst;
+mod iterator_error;
+mod key_range;
+mod key_range_filter;
+mod large_values;
+mod last_key_le;
+mod lazy_bloom;
+mod lazy_concat;
+mod level_scan_filter;
+mod locate;
+mod loser_tree;
+mod lsm_iter_seek;
+mod lsm_stats;
+mod manifest_compaction;
+mod manifest_recovery;
+mod merged_scan;
+mod mmap_reads;
+mod options_validation;
+mod orphan_files;
+mod pinned_ssts;
+mod prefix_filter;
+mod put_if_absent;
+mod range_tombstone;
+mod read_only;
+mod reject_overwrites;
+mod reverse_iter;
+mod run_one_compaction;
+mod scan_into_iter;
+mod scan_prefix;
+mod scan_rev;
+mod scan_sst;
+mod simple_compaction_overlap;
+mod simple_compaction_score;
+mod snapshot;
+mod split_range;
+mod sst_block_size;
+mod sst_blocks;
+mod sst_builder;
+mod sst_cleanup;
+mod sst_corruption;
+mod sst_properties;
+mod tiered_controller;
+mod ttl;
+mod typed_errors;
+mod wal_buffer;
+mod wal_dir;
+mod wal_truncation;
 mod week1_day1;
 mod week1_day2;
 mod week1_day3;
@@ -26,3 +107,4 @@ mod week2_day3;
 mod week2_day4;
 mod week2_day5;
 mod week2_day6;
+mod write_batch;
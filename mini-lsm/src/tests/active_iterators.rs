@@ -0,0 +1,65 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn flush(storage: &LsmStorageInner) {
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+}
+
+#[test]
+fn test_num_active_iterators_counts_ssts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    // SST `i` holds the keys `0` to `i`.
+    for i in 0..5 {
+        for key in 0..=i {
+            storage
+                .put(
+                    format!("key_{}", key).as_bytes(),
+                    format!("{}", i).as_bytes(),
+                )
+                .unwrap();
+        }
+        flush(&storage);
+    }
+    assert_eq!(storage.state.read().l0_sstables.len(), 5);
+
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.num_active_iterators(), 5);
+    for key in 0..5 {
+        assert_eq!(iter.key(), format!("key_{}", key).as_bytes());
+        // The SSTs ending before `key` are exhausted.
+        assert_eq!(iter.num_active_iterators(), 5 - key);
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    assert_eq!(iter.num_active_iterators(), 0);
+
+    // The SSTs of a level are iterated one at a time.
+    storage.force_full_compaction().unwrap();
+    storage.put(b"key_0", b"memtable").unwrap();
+    let iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    assert_eq!(iter.num_active_iterators(), 2);
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+fn key(i: usize) -> Vec<u8> {
+    format!("key_{:03}", i).into_bytes()
+}
+
+#[test]
+fn test_approximate_size() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            block_size: 64,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        storage.approximate_size(Bound::Unbounded, Bound::Unbounded),
+        0
+    );
+
+    // Two overlapping SSTs, with keys 0 to 199 and 100 to 299.
+    for range in [0..200, 100..300] {
+        for i in range {
+            storage.put(&key(i), b"value").unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    let total_size = storage
+        .state
+        .read()
+        .sstables
+        .values()
+        .map(|table| table.table_size())
+        .sum::<u64>();
+    assert_eq!(
+        storage.approximate_size(Bound::Unbounded, Bound::Unbounded),
+        total_size
+    );
+
+    // The estimate grows with the range, until it covers both SSTs as a whole.
+    let mut last_size = 0;
+    for i in (0..=300).step_by(10) {
+        let size = storage.approximate_size(Bound::Included(&key(0)), Bound::Excluded(&key(i)));
+        assert!(size >= last_size, "{} < {} at key {}", size, last_size, i);
+        last_size = size;
+    }
+    assert_eq!(last_size, total_size);
+    // The keys 100 to 199 are in both SSTs, so they take more space than the ones before.
+    let first_half = storage.approximate_size(Bound::Included(&key(0)), Bound::Excluded(&key(100)));
+    let second_half =
+        storage.approximate_size(Bound::Included(&key(100)), Bound::Excluded(&key(200)));
+    assert!(first_half > 0);
+    assert!(second_half > first_half);
+
+    assert_eq!(
+        storage.approximate_size(Bound::Unbounded, Bound::Excluded(&key(0))),
+        0
+    );
+    assert_eq!(
+        storage.approximate_size(Bound::Excluded(&key(299)), Bound::Unbounded),
+        0
+    );
+    assert_eq!(
+        storage.approximate_size(Bound::Included(b"a"), Bound::Included(b"b")),
+        0
+    );
+}
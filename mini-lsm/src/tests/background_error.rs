@@ -0,0 +1,71 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_background_compaction_panic() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 2,
+            },
+        )),
+    )
+    .unwrap();
+    assert_eq!(storage.last_background_error(), None);
+
+    storage.put(b"key", b"value").unwrap();
+
+    // L0 SSTs that do not exist make the next compaction panic
+    {
+        let _state_lock = storage.inner.state_lock.lock();
+        let mut state = storage.inner.state.write();
+        let mut snapshot = state.as_ref().clone();
+        snapshot.l0_sstables.extend([1000, 1001]);
+        *state = snapshot.into();
+    }
+    let mut error = None;
+    for _ in 0..100 {
+        error = storage.last_background_error();
+        if error.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let error = error.expect("compaction panic not reported");
+    assert!(error.starts_with("compaction panicked"), "{}", error);
+
+    let err = storage.close().unwrap_err();
+    assert!(
+        format!("{:#}", err).starts_with("compaction thread stopped: compaction panicked"),
+        "{:#}",
+        err
+    );
+    // the rest of the shutdown still ran
+    let state = storage.inner.state.read();
+    assert!(state.memtable.is_empty());
+    assert!(state.imm_memtables.is_empty());
+}
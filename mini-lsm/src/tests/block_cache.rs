@@ -0,0 +1,152 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use moka::sync::ConcurrentCacheExt;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    iterators::StorageIterator,
+    key::KeySlice,
+    lsm_storage::{BlockCache, LsmStorageInner, LsmStorageOptions, LsmStorageOptionsBuilder},
+    table::{SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn build_sst(
+    id: usize,
+    value: &[u8],
+    block_cache: &Arc<BlockCache>,
+    path: &std::path::Path,
+) -> SsTable {
+    let mut builder = SsTableBuilder::new(4096);
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"key"), value);
+    builder.build(id, Some(block_cache.clone()), path).unwrap()
+}
+
+#[test]
+fn test_block_cache_recycled_sst_id() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let block_cache = Arc::new(BlockCache::new(1 << 10));
+
+    let sst = build_sst(1, b"old", &block_cache, &path);
+    assert_eq!(
+        sst.read_block_cached(0).unwrap().data,
+        sst.read_block(0).unwrap().data
+    );
+    drop(sst);
+    std::fs::remove_file(&path).unwrap();
+
+    let sst = Arc::new(build_sst(1, b"new", &block_cache, &path));
+    let iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+    assert_eq!(iter.key().for_testing_key_ref(), b"key");
+    assert_eq!(iter.value(), b"new");
+}
+
+/// Open a storage with one SST of many small blocks.
+fn open_with_blocks(dir: &std::path::Path, options: LsmStorageOptions) -> Arc<LsmStorageInner> {
+    let storage = LsmStorageInner::open(
+        dir,
+        LsmStorageOptions {
+            block_size: 64,
+            ..options
+        },
+    )
+    .unwrap();
+    for i in 0..200 {
+        storage
+            .put(
+                format!("key_{:03}", i).as_bytes(),
+                format!("value_{:03}", i).as_bytes(),
+            )
+            .unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    Arc::new(storage)
+}
+
+fn check_contents(storage: &LsmStorageInner) {
+    for i in (0..200).rev() {
+        assert_eq!(
+            storage.get(format!("key_{:03}", i).as_bytes()).unwrap(),
+            Some(Bytes::from(format!("value_{:03}", i)))
+        );
+    }
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    check_lsm_iter_result_by_key(
+        &mut iter,
+        (0..200)
+            .map(|i| {
+                (
+                    Bytes::from(format!("key_{:03}", i)),
+                    Bytes::from(format!("value_{:03}", i)),
+                )
+            })
+            .collect(),
+    );
+}
+
+#[test]
+fn test_tiny_block_cache() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_blocks(
+        dir.path(),
+        LsmStorageOptionsBuilder::new()
+            .block_cache_capacity(2)
+            .block_cache_idle_ttl(Some(Duration::from_millis(1)))
+            .build(),
+    );
+    assert!(
+        storage
+            .state
+            .read()
+            .sstables
+            .values()
+            .next()
+            .unwrap()
+            .num_of_blocks()
+            > 2
+    );
+    check_contents(&storage);
+    check_contents(&storage);
+    storage.block_cache.sync();
+    assert!(storage.block_cache.entry_count() <= 2);
+}
+
+#[test]
+fn test_block_cache_disabled() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_blocks(
+        dir.path(),
+        LsmStorageOptions {
+            block_cache_capacity: 0,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    );
+    check_contents(&storage);
+    check_contents(&storage);
+    storage.block_cache.sync();
+    assert_eq!(storage.block_cache.entry_count(), 0);
+    let stats = storage.stats();
+    assert_eq!(stats.block_cache_hits, 0);
+    assert!(stats.block_cache_misses > 0);
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    key::KeySlice,
+    table::{FileObject, SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn build_and_open_sst(path: &Path, block_index: bool) -> Arc<SsTable> {
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_block_index(block_index);
+    for i in (0..1000).step_by(2) {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:04}", i).as_bytes()),
+            format!("value_{:04}", i).as_bytes(),
+        );
+    }
+    builder.build(1, None, path).unwrap();
+    Arc::new(SsTable::open(1, None, FileObject::open(path).unwrap()).unwrap())
+}
+
+fn seek(sst: &Arc<SsTable>, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let iter =
+        SsTableIterator::create_and_seek_to_key(sst.clone(), KeySlice::from_slice(key)).unwrap();
+    iter.is_valid()
+        .then(|| (iter.key().raw_ref().to_vec(), iter.value().to_vec()))
+}
+
+#[test]
+fn test_block_index_lookups_match_block_meta() {
+    let dir = tempdir().unwrap();
+    let with_index = build_and_open_sst(&dir.path().join("1.sst"), true);
+    let without_index = build_and_open_sst(&dir.path().join("2.sst"), false);
+
+    assert!(!with_index.is_block_meta_loaded());
+    assert!(without_index.is_block_meta_loaded());
+    assert!(with_index.num_of_blocks() > 10);
+    assert_eq!(with_index.num_of_blocks(), without_index.num_of_blocks());
+    assert_eq!(with_index.first_key(), without_index.first_key());
+    assert_eq!(with_index.last_key(), without_index.last_key());
+
+    for i in 0..1001 {
+        let key = format!("key_{:04}", i);
+        assert_eq!(
+            seek(&with_index, key.as_bytes()),
+            seek(&without_index, key.as_bytes()),
+            "mismatch when seeking to {}",
+            key
+        );
+    }
+    assert_eq!(seek(&with_index, b"a"), seek(&without_index, b"a"));
+    assert_eq!(seek(&with_index, b"z"), None);
+    assert!(!with_index.is_block_meta_loaded());
+
+    assert_eq!(
+        with_index.block_meta().unwrap(),
+        without_index.block_meta().unwrap()
+    );
+    assert!(with_index.is_block_meta_loaded());
+}
+
+#[test]
+fn test_block_index_corrupted() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let sst = build_and_open_sst(&path, true);
+    let file_size = sst.table_size() as usize;
+    drop(sst);
+    let mut data = std::fs::read(&path).unwrap();
+    // The block index is right before the properties footer, flip a byte of its last key.
+    data[file_size - 74] ^= 0xff;
+    std::fs::write(&path, &data).unwrap();
+    let Err(err) = SsTable::open(1, None, FileObject::open(&path).unwrap()) else {
+        panic!("expect opening a corrupted SST to fail");
+    };
+    assert_eq!(err.to_string(), "block index checksum mismatched");
+}
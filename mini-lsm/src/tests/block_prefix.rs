@@ -0,0 +1,100 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytes::BufMut;
+
+use crate::{
+    block::{Block, BlockBuilder, BlockIterator},
+    key::KeySlice,
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("metrics/cluster-01/host-0042/cpu/usage/{:06}", idx).into_bytes()
+}
+
+#[test]
+fn test_block_shared_prefix_shrinks_block() {
+    let mut builder = BlockBuilder::new(65536);
+    let mut full_size = 0;
+    for idx in 0..300 {
+        let key = key_of(idx);
+        assert!(builder.add(KeySlice::for_testing_from_slice_no_ts(&key), b"v"));
+        // shared prefix len, key len, key, value len, value and offset
+        full_size += 2 + 2 + key.len() + 2 + 1 + 2;
+    }
+    let block = Arc::new(builder.build());
+    let encoded = block.encode();
+    assert!(
+        encoded.len() * 3 < full_size,
+        "block has {} bytes, {} bytes without prefix compression",
+        encoded.len(),
+        full_size
+    );
+
+    let decoded = Arc::new(Block::decode(&encoded));
+    let mut iter = BlockIterator::create_and_seek_to_first(decoded.clone());
+    for idx in 0..300 {
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+        iter.next();
+    }
+    assert!(!iter.is_valid());
+
+    // Seek to every key, and to the keys right before them, in descending order so that the
+    // iterator never moves forward from its current position.
+    let mut iter = BlockIterator::create_and_seek_to_last(decoded);
+    for idx in (0..300).rev() {
+        iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&key_of(idx)));
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+        let mut before = key_of(idx);
+        before.pop();
+        iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&before));
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx / 10 * 10));
+    }
+}
+
+#[test]
+fn test_decode_block_without_restart_points() {
+    // Written before restart points: every key shares its prefix with the first key.
+    let keys: [&[u8]; 3] = [b"key_a", b"key_bb", b"key_c"];
+    let mut data = Vec::new();
+    let mut offsets = Vec::new();
+    for (idx, key) in keys.iter().enumerate() {
+        let overlap = if idx == 0 { 0 } else { 4 };
+        offsets.push(data.len() as u16);
+        data.put_u16(overlap as u16);
+        data.put_u16((key.len() - overlap) as u16);
+        data.put_slice(&key[overlap..]);
+        data.put_u16(1);
+        data.put_u8(b'0' + idx as u8);
+    }
+    for offset in &offsets {
+        data.put_u16(*offset);
+    }
+    data.put_u16(offsets.len() as u16);
+
+    let block = Arc::new(Block::decode_with_restart_interval(&data, 0));
+    let mut iter = BlockIterator::create_and_seek_to_last(block.clone());
+    for idx in (0..3).rev() {
+        assert_eq!(iter.key().for_testing_key_ref(), keys[idx]);
+        assert_eq!(iter.value(), [b'0' + idx as u8]);
+        iter.prev();
+    }
+    let iter = BlockIterator::create_and_seek_to_key(
+        block,
+        KeySlice::for_testing_from_slice_no_ts(b"key_b"),
+    );
+    assert_eq!(iter.key().for_testing_key_ref(), b"key_bb");
+}
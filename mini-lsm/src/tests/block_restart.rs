@@ -0,0 +1,111 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    block::{Block, BlockBuilder, BlockIterator},
+    key::KeySlice,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:04}0", idx).into_bytes()
+}
+
+#[test]
+fn test_seek_across_restart_points() {
+    for restart_interval in [1, 2, 3, 7, 16, 1000] {
+        let mut builder = BlockBuilder::with_restart_interval(65536, restart_interval);
+        for idx in 0..200 {
+            assert!(builder.add(
+                KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+                format!("value_{}", idx).as_bytes(),
+            ));
+        }
+        let encoded = builder.build().encode();
+        let block = Arc::new(Block::decode_with_restart_interval(
+            &encoded,
+            restart_interval,
+        ));
+        let mut iter = BlockIterator::create_and_seek_to_first(block.clone());
+        // Seek back and forth, so that seeks start both before and after their targets.
+        for idx in (0..200).map(|i| if i % 2 == 0 { i } else { 199 - i }) {
+            iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&key_of(idx)));
+            assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+            assert_eq!(iter.value(), format!("value_{}", idx).as_bytes());
+            let between = format!("key_{:04}5", idx).into_bytes();
+            iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&between));
+            if idx == 199 {
+                assert!(!iter.is_valid());
+            } else {
+                assert_eq!(iter.key().for_testing_key_ref(), key_of(idx + 1));
+            }
+        }
+
+        let mut iter = BlockIterator::create_and_seek_to_last(block);
+        for idx in (0..200).rev() {
+            assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+            iter.prev();
+        }
+        assert!(!iter.is_valid());
+    }
+}
+
+#[test]
+fn test_restart_interval_option() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        block_size: 1024,
+        block_restart_interval: 3,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for idx in 0..500 {
+        storage
+            .put(&key_of(idx), format!("value_{}", idx).as_bytes())
+            .unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    drop(storage);
+
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for idx in 0..500 {
+        assert_eq!(
+            storage.get(&key_of(idx)).unwrap(),
+            Some(Bytes::from(format!("value_{}", idx)))
+        );
+    }
+    drop(storage);
+
+    let Err(err) = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            block_restart_interval: 0,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    ) else {
+        panic!("expect open to fail");
+    };
+    assert_eq!(
+        err.to_string(),
+        "invalid options: block_restart_interval must be positive"
+    );
+}
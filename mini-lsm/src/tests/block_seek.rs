@@ -0,0 +1,54 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::{
+    block::{BlockBuilder, BlockIterator},
+    key::KeySlice,
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:04}0", idx).into_bytes()
+}
+
+#[test]
+fn test_block_seek_to_key() {
+    let mut builder = BlockBuilder::new(60000);
+    for idx in 0..1000 {
+        assert!(builder.add(
+            KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+            format!("value_{}", idx).as_bytes(),
+        ));
+    }
+    let block = Arc::new(builder.build());
+    let mut iter = BlockIterator::create_and_seek_to_first(block);
+    for idx in 0..1000 {
+        iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&key_of(idx)));
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+        assert_eq!(iter.value(), format!("value_{}", idx).as_bytes());
+
+        // a key between `idx - 1` and `idx` lands on `idx`
+        let between = match idx {
+            0 => b"key".to_vec(),
+            _ => format!("key_{:04}5", idx - 1).into_bytes(),
+        };
+        iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&between));
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+    }
+    iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(b"key_99995"));
+    assert!(!iter.is_valid());
+}
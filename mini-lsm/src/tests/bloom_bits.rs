@@ -0,0 +1,82 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    key::KeySlice,
+    table::{BloomLoad, FileObject, SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn build_sst_with_bits_per_key(dir: &tempfile::TempDir, bits_per_key: usize) -> SsTable {
+    let mut builder = SsTableBuilder::new(4096);
+    builder.set_bloom_bits_per_key(bits_per_key);
+    for i in 0..1000 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:04}", i).as_bytes()),
+            b"value",
+        );
+    }
+    let path = dir.path().join(format!("{}.sst", bits_per_key));
+    builder.build_for_test(&path).unwrap();
+    // check the bloom filter read back from the file
+    SsTable::open_for_test(FileObject::open(&path).unwrap()).unwrap()
+}
+
+fn false_positives(sst: &SsTable) -> usize {
+    let bloom = sst.bloom().unwrap().unwrap();
+    for i in 0..1000 {
+        let key = format!("key_{:04}", i);
+        assert!(bloom.may_contain(farmhash::fingerprint32(key.as_bytes())));
+    }
+    (0..1000)
+        .filter(|i| {
+            let key = format!("absent_{:04}", i);
+            bloom.may_contain(farmhash::fingerprint32(key.as_bytes()))
+        })
+        .count()
+}
+
+#[test]
+fn test_bloom_bits_per_key() {
+    let dir = tempdir().unwrap();
+    let sparse = build_sst_with_bits_per_key(&dir, 2);
+    let dense = build_sst_with_bits_per_key(&dir, 20);
+    assert!(
+        sparse.bloom().unwrap().unwrap().filter.len()
+            < dense.bloom().unwrap().unwrap().filter.len()
+    );
+    let sparse_false_positives = false_positives(&sparse);
+    let dense_false_positives = false_positives(&dense);
+    assert!(sparse_false_positives > 100, "{}", sparse_false_positives);
+    assert!(dense_false_positives < 10, "{}", dense_false_positives);
+}
+
+#[test]
+fn test_sst_without_bloom() {
+    let dir = tempdir().unwrap();
+    let sst = build_sst_with_bits_per_key(&dir, 0);
+    assert!(sst.bloom().unwrap().is_none());
+    let path = dir.path().join("0.sst");
+    let lazy_sst =
+        SsTable::open_with_bloom_load(0, None, FileObject::open(&path).unwrap(), BloomLoad::Lazy)
+            .unwrap();
+    assert!(lazy_sst.bloom().unwrap().is_none());
+    for sst in [sst, lazy_sst] {
+        let key = KeySlice::for_testing_from_slice_no_ts(b"key_0500");
+        let iter = SsTableIterator::create_and_seek_to_key(sst.into(), key).unwrap();
+        assert_eq!(iter.key(), key);
+    }
+}
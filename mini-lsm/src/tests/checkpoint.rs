@@ -0,0 +1,89 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:04}", idx).into_bytes()
+}
+
+fn check_checkpoint(enable_wal: bool) {
+    let dir = tempdir().unwrap();
+    let checkpoint_dir = tempdir().unwrap();
+    let dest = checkpoint_dir.path().join("checkpoint");
+    let options = LsmStorageOptions {
+        enable_wal,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for idx in 0..300 {
+        storage.put(&key_of(idx), b"flushed").unwrap();
+    }
+    storage.force_flush().unwrap();
+    for idx in (0..300).step_by(3) {
+        storage.delete(&key_of(idx)).unwrap();
+    }
+    for idx in 300..310 {
+        storage.put(&key_of(idx), b"memtable").unwrap();
+    }
+    let expected = (0..310)
+        .filter(|idx| idx % 3 != 0 || *idx >= 300)
+        .map(|idx| {
+            (
+                Bytes::from(key_of(idx)),
+                Bytes::from_static(if idx < 300 { b"flushed" } else { b"memtable" }),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    storage.checkpoint(&dest).unwrap();
+    // Changes after the checkpoint only affect the original database.
+    storage.put(&key_of(1), b"changed").unwrap();
+    storage.force_flush().unwrap();
+
+    let copy = MiniLsm::open(&dest, options).unwrap();
+    check_lsm_iter_result_by_key(
+        &mut copy.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected,
+    );
+    assert_eq!(
+        storage.get(&key_of(1)).unwrap(),
+        Some(Bytes::from_static(b"changed"))
+    );
+}
+
+#[test]
+fn test_checkpoint_with_wal() {
+    check_checkpoint(true);
+}
+
+#[test]
+fn test_checkpoint_without_wal() {
+    check_checkpoint(false);
+}
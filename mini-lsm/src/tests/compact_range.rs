@@ -0,0 +1,161 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    compact::{CompactionOptions, LeveledCompactionOptions},
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::SsTableIterator,
+};
+
+fn key(i: usize) -> Bytes {
+    Bytes::from(format!("key_{:03}", i))
+}
+
+fn value(i: usize) -> Bytes {
+    Bytes::from(format!("value_{:03}", i))
+}
+
+/// Flush all memtables, which writes may have frozen before.
+fn flush(storage: &LsmStorageInner) {
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    while !storage.state.read().imm_memtables.is_empty() {
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+}
+
+fn check_contents(storage: &LsmStorageInner, keys: impl Iterator<Item = usize>) {
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    check_lsm_iter_result_by_key(&mut iter, keys.map(|i| (key(i), value(i))).collect());
+}
+
+#[test]
+fn test_compact_range() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        block_size: 128,
+        target_sst_size: 512,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for i in 0..300 {
+        storage.put(&key(i), &value(i)).unwrap();
+    }
+    flush(&storage);
+    storage.force_full_compaction().unwrap();
+    for i in 100..200 {
+        storage.delete(&key(i)).unwrap();
+    }
+    flush(&storage);
+
+    // The L1 SSTs entirely outside of the range are not compacted.
+    let untouched = {
+        let snapshot = storage.state.read();
+        snapshot.levels[0]
+            .1
+            .iter()
+            .copied()
+            .filter(|id| {
+                let table = &snapshot.sstables[id];
+                table.last_key().raw_ref() < key(100).as_ref()
+                    || table.first_key().raw_ref() > key(199).as_ref()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert!(untouched.len() > 2);
+    storage
+        .compact_range(Bound::Included(&key(100)), Bound::Included(&key(199)))
+        .unwrap();
+    let levels = {
+        let snapshot = storage.state.read();
+        assert!(snapshot.l0_sstables.is_empty());
+        let l1 = &snapshot.levels[0].1;
+        for id in &untouched {
+            assert!(l1.contains(id), "{}.sst is compacted", id);
+        }
+        for pair in l1.windows(2) {
+            assert!(
+                snapshot.sstables[&pair[0]].last_key() < snapshot.sstables[&pair[1]].first_key()
+            );
+        }
+        // The deletes are dropped at the bottom level.
+        for id in l1 {
+            let mut iter =
+                SsTableIterator::create_and_seek_to_first(snapshot.sstables[id].clone()).unwrap();
+            while iter.is_valid() {
+                assert!(!iter.value().is_empty());
+                iter.next().unwrap();
+            }
+        }
+        snapshot.levels.clone()
+    };
+    check_contents(&storage, (0..100).chain(200..300));
+
+    // Nothing to compact
+    storage
+        .compact_range(Bound::Included(b"a"), Bound::Excluded(b"b"))
+        .unwrap();
+    assert_eq!(storage.state.read().levels, levels);
+
+    drop(storage);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    assert_eq!(storage.state.read().levels, levels);
+    check_contents(&storage, (0..100).chain(200..300));
+}
+
+#[test]
+fn test_compact_range_leveled() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        compaction_options: CompactionOptions::Leveled(LeveledCompactionOptions {
+            level_size_multiplier: 2,
+            level0_file_num_compaction_trigger: 2,
+            max_levels: 3,
+            base_level_size_mb: 1,
+        }),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for i in 0..100 {
+        storage.put(&key(i), &value(i)).unwrap();
+    }
+    flush(&storage);
+    for i in 50..100 {
+        storage.delete(&key(i)).unwrap();
+    }
+    flush(&storage);
+    storage
+        .compact_range(Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    {
+        let snapshot = storage.state.read();
+        assert!(snapshot.l0_sstables.is_empty());
+        assert!(snapshot.levels[0].1.is_empty());
+        assert!(snapshot.levels[1].1.is_empty());
+        assert_eq!(snapshot.levels[2].1.len(), 1);
+    }
+    check_contents(&storage, 0..50);
+
+    drop(storage);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    check_contents(&storage, 0..50);
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::SsTableIterator,
+};
+
+#[test]
+fn test_compact_obsolete_task() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 2,
+            },
+        )),
+    )
+    .unwrap();
+    for round in 0..2 {
+        for i in 0..10 {
+            storage
+                .put(
+                    format!("key_{}", i).as_bytes(),
+                    format!("value_{}_{}", round, i).as_bytes(),
+                )
+                .unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+
+    let snapshot = storage.state.read().clone();
+    let task = storage
+        .compaction_controller
+        .generate_compaction_task(&snapshot, &HashSet::new())
+        .unwrap();
+    // another compaction compacts the same SSTs away before the task runs
+    assert!(storage.run_one_compaction().unwrap());
+    assert!(storage.state.read().l0_sstables.is_empty());
+
+    let sstables = storage.compact(&task, &snapshot).unwrap();
+    assert_eq!(sstables.len(), 1);
+    let mut iter = SsTableIterator::create_and_seek_to_first(sstables[0].clone()).unwrap();
+    for i in 0..10 {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().raw_ref(), format!("key_{}", i).as_bytes());
+        assert_eq!(
+            Bytes::copy_from_slice(iter.value()),
+            Bytes::from(format!("value_1_{}", i))
+        );
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+}
@@ -0,0 +1,162 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_flush_while_compacting() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        disable_background_compaction: true,
+        // Writing the 80KB of output takes more than half a second.
+        compaction_rate_limit_bytes_per_sec: Some(100 << 10),
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 1,
+            },
+        ))
+    };
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    for round in 0..2 {
+        for i in 0..400 {
+            let key = format!("key_{}_{:03}", round, i);
+            storage.put(key.as_bytes(), &[b'x'; 200]).unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+
+    let compaction = {
+        let storage = storage.clone();
+        std::thread::spawn(move || storage.run_one_compaction().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(100));
+    storage.put(b"key_2_000", b"value").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    assert!(!compaction.is_finished());
+    assert!(compaction.join().unwrap());
+
+    let state = storage.state.read();
+    // The SST flushed while compacting stays in L0 while its inputs are compacted into L1.
+    assert_eq!(state.l0_sstables.len(), 1);
+    assert!(!state.levels[0].1.is_empty());
+    drop(state);
+    assert_eq!(
+        &storage.get(b"key_0_000").unwrap().unwrap()[..],
+        &[b'x'; 200]
+    );
+    assert_eq!(&storage.get(b"key_2_000").unwrap().unwrap()[..], b"value");
+}
+
+#[test]
+fn test_compactions_on_disjoint_levels() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        disable_background_compaction: true,
+        // Writing the 160KB of output of the L0 compaction takes more than a second. The rate
+        // limiter is asked after each SST, so small SSTs let the other compaction through.
+        compaction_rate_limit_bytes_per_sec: Some(100 << 10),
+        target_sst_size: 8 << 10,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    };
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    storage.put(b"a", b"value").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_to_level(2).unwrap();
+    // The memtables are frozen once they reach the SST size.
+    for i in 0..800 {
+        let key = format!("key_{:03}", i);
+        storage.put(key.as_bytes(), &[b'x'; 200]).unwrap();
+    }
+    while !storage.state.read().imm_memtables.is_empty() {
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+
+    let l0_compaction = {
+        let storage = storage.clone();
+        std::thread::spawn(move || storage.run_one_compaction().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(100));
+    // L0 and L1 are being compacted, which leaves L2 to L3.
+    assert!(storage.run_one_compaction().unwrap());
+    assert!(!l0_compaction.is_finished());
+    assert!(l0_compaction.join().unwrap());
+
+    let state = storage.state.read();
+    assert!(state.l0_sstables.is_empty());
+    assert!(!state.levels[0].1.is_empty());
+    assert!(state.levels[1].1.is_empty());
+    assert_eq!(state.levels[2].1.len(), 1);
+    drop(state);
+    assert_eq!(&storage.get(b"a").unwrap().unwrap()[..], b"value");
+    assert_eq!(&storage.get(b"key_799").unwrap().unwrap()[..], &[b'x'; 200]);
+}
+
+#[test]
+fn test_multiple_compaction_threads() {
+    let dir = tempdir().unwrap();
+    let options = || LsmStorageOptions {
+        max_background_compactions: 4,
+        target_sst_size: 8 << 10,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    };
+    let storage = MiniLsm::open(&dir, options()).unwrap();
+    for i in 0..2000 {
+        let key = format!("key_{:04}", i % 1000);
+        storage
+            .put(key.as_bytes(), format!("value_{}", i).as_bytes())
+            .unwrap();
+    }
+    storage.close().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, options()).unwrap();
+    for i in 0..1000 {
+        let key = format!("key_{:04}", i);
+        assert_eq!(
+            storage.get(key.as_bytes()).unwrap().unwrap(),
+            format!("value_{}", i + 1000).as_bytes()
+        );
+    }
+    storage.close().unwrap();
+}
@@ -0,0 +1,94 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::SsTableIterator,
+};
+
+fn key(i: usize) -> Bytes {
+    Bytes::from(format!("key_{:04}", i))
+}
+
+fn flush(storage: &LsmStorageInner) {
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    while !storage.state.read().imm_memtables.is_empty() {
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+}
+
+/// Fully compact the same data with `parallelism` threads, returning the entries of each L1 SST.
+fn compact_with_parallelism(parallelism: usize) -> Vec<Vec<(Bytes, Bytes)>> {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        block_size: 128,
+        compaction_parallelism: parallelism,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for i in 0..1000 {
+        storage.put(&key(i), b"v1").unwrap();
+    }
+    flush(&storage);
+    for i in (0..1000).step_by(3) {
+        storage.put(&key(i), b"v2").unwrap();
+    }
+    for i in (0..1000).step_by(7) {
+        storage.delete(&key(i)).unwrap();
+    }
+    flush(&storage);
+    storage.force_full_compaction().unwrap();
+
+    let snapshot = storage.state.read();
+    assert!(snapshot.l0_sstables.is_empty());
+    snapshot.levels[0]
+        .1
+        .iter()
+        .map(|id| {
+            let mut iter =
+                SsTableIterator::create_and_seek_to_first(snapshot.sstables[id].clone()).unwrap();
+            let mut entries = Vec::new();
+            while iter.is_valid() {
+                entries.push((
+                    Bytes::copy_from_slice(iter.key().raw_ref()),
+                    Bytes::copy_from_slice(iter.value()),
+                ));
+                iter.next().unwrap();
+            }
+            entries
+        })
+        .collect()
+}
+
+#[test]
+fn test_parallel_compaction_matches_sequential() {
+    let sequential = compact_with_parallelism(1);
+    let parallel = compact_with_parallelism(4);
+    // Every range is written to its own SSTs.
+    assert_eq!(sequential.len(), 1);
+    assert!(parallel.len() > 1);
+    for pair in parallel.windows(2) {
+        assert!(pair[0].last().unwrap().0 < pair[1].first().unwrap().0);
+    }
+    assert_eq!(
+        parallel.into_iter().flatten().collect::<Vec<_>>(),
+        sequential.into_iter().flatten().collect::<Vec<_>>()
+    );
+}
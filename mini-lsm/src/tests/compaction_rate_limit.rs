@@ -0,0 +1,81 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, Instant};
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+/// Write two overlapping L0 SSTs of about 40KB each and compact them.
+fn fill_and_compact(options: LsmStorageOptions) -> (LsmStorageInner, Duration) {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for round in 0..2 {
+        for i in 0..400 {
+            let value = format!("{:0100}", round);
+            storage
+                .put(format!("key_{:03}", i).as_bytes(), value.as_bytes())
+                .unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    let start = Instant::now();
+    storage.force_full_compaction().unwrap();
+    (storage, start.elapsed())
+}
+
+#[test]
+fn test_compaction_rate_limit() {
+    let (storage, _) = fill_and_compact(LsmStorageOptions::default_for_week1_test());
+    let stats = storage.stats();
+    let output_size = {
+        let snapshot = storage.state.read();
+        snapshot.levels[0]
+            .1
+            .iter()
+            .map(|id| snapshot.sstables[id].table_size())
+            .sum::<u64>()
+    };
+    assert_eq!(stats.compaction_bytes_written, output_size);
+    assert_eq!(stats.compaction_throttled, Duration::ZERO);
+
+    // Writing 40KB at 100KB/s with a 10KB burst takes at least 300ms.
+    let (storage, elapsed) = fill_and_compact(LsmStorageOptions {
+        compaction_rate_limit_bytes_per_sec: Some(100 << 10),
+        ..LsmStorageOptions::default_for_week1_test()
+    });
+    assert!(output_size > 40 << 10);
+    let stats = storage.stats();
+    assert_eq!(stats.compaction_bytes_written, output_size);
+    assert!(stats.compaction_throttled >= Duration::from_millis(250));
+    assert!(elapsed >= stats.compaction_throttled);
+}
+
+#[test]
+fn test_compaction_rate_limit_zero() {
+    let err = LsmStorageOptions {
+        compaction_rate_limit_bytes_per_sec: Some(0),
+        ..LsmStorageOptions::default_for_week1_test()
+    }
+    .validate()
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid options: compaction_rate_limit_bytes_per_sec must be positive"
+    );
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{
+    error::LsmError,
+    lsm_storage::{DEFAULT_COMPARATOR, LsmStorageOptions, MiniLsm},
+    manifest::{Manifest, ManifestRecord},
+};
+
+fn options(comparator_name: &str) -> LsmStorageOptions {
+    LsmStorageOptions {
+        comparator_name: comparator_name.to_string(),
+        ..LsmStorageOptions::default_for_week1_test()
+    }
+}
+
+#[test]
+fn test_comparator_mismatch() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, options(DEFAULT_COMPARATOR)).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    // a database created with another comparator, which this version cannot create
+    let (manifest, _) = Manifest::recover(dir.path().join("MANIFEST")).unwrap();
+    manifest
+        .add_record_when_init(ManifestRecord::Comparator("reverse".to_string()))
+        .unwrap();
+    drop(manifest);
+
+    let Err(err) = MiniLsm::open(&dir, options(DEFAULT_COMPARATOR)) else {
+        panic!("expect open to fail");
+    };
+    assert_eq!(
+        err,
+        LsmError::ComparatorMismatch {
+            stored: "reverse".to_string(),
+            configured: DEFAULT_COMPARATOR.to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_unsupported_comparator() {
+    let dir = tempdir().unwrap();
+    let Err(err) = MiniLsm::open(&dir, options("reverse")) else {
+        panic!("expect open to fail");
+    };
+    assert!(matches!(err, LsmError::InvalidOptions(_)));
+}
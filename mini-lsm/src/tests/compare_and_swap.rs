@@ -0,0 +1,79 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_compare_and_swap() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    // the key is absent
+    assert!(!storage.compare_and_swap(b"a", Some(b"1"), b"2").unwrap());
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert!(storage.compare_and_swap(b"a", None, b"1").unwrap());
+
+    // a mismatch leaves the value unchanged
+    assert!(!storage.compare_and_swap(b"a", None, b"2").unwrap());
+    assert!(!storage.compare_and_swap(b"a", Some(b"0"), b"2").unwrap());
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+
+    storage.force_flush().unwrap();
+    assert!(storage.compare_and_swap(b"a", Some(b"1"), b"2").unwrap());
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"2")));
+    storage.delete(b"a").unwrap();
+    assert!(!storage.compare_and_swap(b"a", Some(b"2"), b"3").unwrap());
+    assert!(storage.compare_and_swap(b"a", None, b"3").unwrap());
+}
+
+#[test]
+fn test_compare_and_swap_counter() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            let storage = &storage;
+            s.spawn(move || {
+                for _ in 0..100 {
+                    // increment by reading, then swapping in the next value if still unchanged
+                    loop {
+                        let current = storage.get(b"counter").unwrap();
+                        let next = match &current {
+                            Some(value) => {
+                                std::str::from_utf8(value).unwrap().parse::<u64>().unwrap() + 1
+                            }
+                            None => 1,
+                        };
+                        if storage
+                            .compare_and_swap(
+                                b"counter",
+                                current.as_deref(),
+                                next.to_string().as_bytes(),
+                            )
+                            .unwrap()
+                        {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(
+        storage.get(b"counter").unwrap(),
+        Some(Bytes::from_static(b"400"))
+    );
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::path::Path;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::CompressionType,
+};
+
+fn sst_size(path: &Path) -> u64 {
+    std::fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .map(|path| std::fs::metadata(path).unwrap().len())
+        .sum()
+}
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("some_long_key_prefix_{:05}", idx).into_bytes()
+}
+
+fn value_of(idx: usize) -> Vec<u8> {
+    format!("value_{:05}_", idx).repeat(8).into_bytes()
+}
+
+/// Write the same data with `compression`, returning the size of the SSTs.
+fn write_and_check(compression: CompressionType) -> u64 {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week1_test();
+    options.compression = compression;
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for idx in 0..1000 {
+        storage.put(&key_of(idx), &value_of(idx)).unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    drop(storage);
+
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for idx in (0..1000).step_by(37) {
+        assert_eq!(
+            storage.get(&key_of(idx)).unwrap(),
+            Some(Bytes::from(value_of(idx)))
+        );
+    }
+    check_lsm_iter_result_by_key(
+        &mut storage
+            .scan(Bound::Included(&key_of(500)), Bound::Excluded(&key_of(503)))
+            .unwrap(),
+        (500..503)
+            .map(|idx| (Bytes::from(key_of(idx)), Bytes::from(value_of(idx))))
+            .collect(),
+    );
+    sst_size(dir.path())
+}
+
+#[test]
+fn test_compression() {
+    let uncompressed = write_and_check(CompressionType::None);
+    let lz4 = write_and_check(CompressionType::Lz4);
+    let zstd = write_and_check(CompressionType::Zstd);
+    assert!(lz4 < uncompressed, "{} >= {}", lz4, uncompressed);
+    assert!(zstd < uncompressed, "{} >= {}", zstd, uncompressed);
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use super::harness::sync;
+use crate::{
+    error::LsmError,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+#[test]
+fn test_max_db_size_rejects_puts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            max_db_size_bytes: Some(4096),
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    for i in 0..1000 {
+        storage
+            .put(format!("key_{:04}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    sync(&storage);
+    // L0 does not count towards the limit
+    storage.put(b"key_1000", b"value").unwrap();
+    storage.force_full_compaction().unwrap();
+
+    let err = storage.put(b"key_1001", b"value").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<LsmError>(),
+        Some(LsmError::OutOfSpace { limit: 4096, .. })
+    ));
+    assert_eq!(storage.get(b"key_1001").unwrap(), None);
+
+    // deletes are accepted so that space can be reclaimed
+    for i in 0..1000 {
+        storage.delete(format!("key_{:04}", i).as_bytes()).unwrap();
+    }
+    sync(&storage);
+    storage.force_full_compaction().unwrap();
+    storage.put(b"key_1001", b"value").unwrap();
+    assert_eq!(&storage.get(b"key_1001").unwrap().unwrap()[..], b"value");
+}
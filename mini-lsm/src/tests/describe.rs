@@ -0,0 +1,73 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_describe() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for keys in [[b"a", b"c"], [b"b", b"d"]] {
+        for key in keys {
+            storage.put(key, b"value").unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    storage.put(b"e", b"value").unwrap();
+
+    let description = storage.describe();
+    assert_eq!(description.num_memtables, 1);
+    assert_eq!(description.memtables_size, 6);
+    assert!(description.imm_memtable_ids.is_empty());
+    let key_ranges = description
+        .l0_sstables
+        .iter()
+        .map(|sst| (sst.first_key.clone(), sst.last_key.clone()))
+        .collect::<Vec<_>>();
+    // the latest SST first
+    assert_eq!(
+        key_ranges,
+        vec![
+            (Bytes::from_static(b"b"), Bytes::from_static(b"d")),
+            (Bytes::from_static(b"a"), Bytes::from_static(b"c")),
+        ]
+    );
+    assert!(description.l0_sstables.iter().all(|sst| sst.size > 0));
+    assert_eq!(description.levels.len(), 1);
+    assert_eq!(description.levels[0].0, 1);
+    assert!(description.levels[0].1.is_empty());
+
+    storage.force_full_compaction().unwrap();
+    let description = storage.describe();
+    assert!(description.l0_sstables.is_empty());
+    let l1 = &description.levels[0].1;
+    assert_eq!(l1.len(), 1);
+    assert_eq!(&l1[0].first_key[..], b"a");
+    assert_eq!(&l1[0].last_key[..], b"d");
+    assert_eq!(
+        l1[0].size,
+        storage.inner.state.read().sstables[&l1[0].id].table_size()
+    );
+
+    let json = serde_json::to_value(&description).unwrap();
+    assert_eq!(json["num_memtables"], 1);
+    assert_eq!(json["levels"][0][1][0]["id"], l1[0].id);
+    assert_eq!(
+        json["levels"][0][1][0]["first_key"],
+        serde_json::json!([b'a'])
+    );
+}
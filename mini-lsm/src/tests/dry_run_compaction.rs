@@ -0,0 +1,149 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::{
+        CompactionOptions, CompactionTask, LeveledCompactionTask, TieredCompactionOptions,
+        TieredCompactionTask,
+    },
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_dry_run_full_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for prefix in ["a", "b", "c"] {
+        for i in 0..100 {
+            storage
+                .put(format!("{}_{:02}", prefix, i).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    // drops the whole SST of `b`
+    storage
+        .delete_range(Bound::Included(b"b"), Bound::Excluded(b"c"))
+        .unwrap();
+
+    let (l0_sstables, l1_sstables, sst_sizes) = {
+        let state = storage.inner.state.read();
+        let sst_sizes = state
+            .l0_sstables
+            .iter()
+            .map(|id| state.sstables[id].table_size())
+            .collect::<Vec<_>>();
+        (
+            state.l0_sstables.clone(),
+            state.levels[0].1.clone(),
+            sst_sizes,
+        )
+    };
+    assert_eq!(l0_sstables.len(), 3);
+    let task = CompactionTask::ForceFullCompaction {
+        l0_sstables: l0_sstables.clone(),
+        l1_sstables,
+    };
+    let plan = storage.dry_run_compaction(&task).unwrap();
+    assert_eq!(plan.input_sst_ids, l0_sstables);
+    assert_eq!(plan.input_bytes, sst_sizes.iter().sum::<u64>());
+    // L0 is ordered from the latest to the earliest, so `b` is in the middle
+    assert_eq!(plan.estimated_output_bytes, sst_sizes[0] + sst_sizes[2]);
+    // nothing changed yet
+    assert_eq!(storage.inner.state.read().l0_sstables, l0_sstables);
+
+    storage.force_full_compaction().unwrap();
+    {
+        let state = storage.inner.state.read();
+        assert_eq!(plan.l0_sstables, state.l0_sstables);
+        assert_eq!(plan.levels, state.levels);
+    }
+
+    // the input SSTs are gone
+    assert!(storage.dry_run_compaction(&task).is_err());
+}
+
+#[test]
+fn test_dry_run_invalid_task() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.force_flush().unwrap();
+    let l0_sstables = storage.inner.state.read().l0_sstables.clone();
+
+    // leveled tasks are not generated without leveled compaction
+    let task = CompactionTask::Leveled(LeveledCompactionTask {
+        upper_level: None,
+        upper_level_sst_ids: l0_sstables,
+        lower_level: 1,
+        lower_level_sst_ids: Vec::new(),
+        is_lower_level_bottom_level: true,
+    });
+    assert!(storage.dry_run_compaction(&task).is_err());
+
+    // L1 does not hold the SST
+    let task = CompactionTask::ForceFullCompaction {
+        l0_sstables: Vec::new(),
+        l1_sstables: vec![100],
+    };
+    assert!(storage.dry_run_compaction(&task).is_err());
+}
+
+#[test]
+fn test_dry_run_tiered_task_matches_tiers_by_id() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Tiered(
+            TieredCompactionOptions {
+                num_tiers: 100,
+                max_size_amplification_percent: 10000,
+                size_ratio: 10000,
+                min_merge_width: 100,
+                max_merge_width: None,
+            },
+        )),
+    )
+    .unwrap();
+    for key in [b"a", b"b"] {
+        storage.put(key, b"1").unwrap();
+        storage.force_flush().unwrap();
+    }
+    let tiers = storage.inner.state.read().levels.clone();
+    assert_eq!(tiers.len(), 2);
+    let task = CompactionTask::Tiered(TieredCompactionTask {
+        tiers: tiers.clone(),
+        bottom_tier_included: true,
+    });
+
+    // a tier flushed after the task was generated does not invalidate it
+    storage.put(b"c", b"1").unwrap();
+    storage.force_flush().unwrap();
+    let plan = storage.dry_run_compaction(&task).unwrap();
+    assert_eq!(plan.levels.len(), 2);
+
+    // a tier with other SSTs than the task expects does
+    let (tier_id, mut ssts) = tiers[0].clone();
+    ssts.push(100);
+    let task = CompactionTask::Tiered(TieredCompactionTask {
+        tiers: vec![(tier_id, ssts)],
+        bottom_tier_included: false,
+    });
+    let err = storage.dry_run_compaction(&task).unwrap_err();
+    assert!(err.to_string().contains("changed"), "{}", err);
+}
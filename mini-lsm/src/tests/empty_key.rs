@@ -0,0 +1,48 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    error::LsmError,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, WriteBatchRecord},
+};
+
+#[test]
+fn test_empty_key_rejected() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let err = storage.put(b"", b"value").unwrap_err();
+    assert_eq!(err.downcast_ref::<LsmError>(), Some(&LsmError::EmptyKey));
+    let err = storage.delete(b"").unwrap_err();
+    assert_eq!(err.downcast_ref::<LsmError>(), Some(&LsmError::EmptyKey));
+
+    // the whole batch is rejected
+    let err = storage
+        .write_batch(&[
+            WriteBatchRecord::Put(&b"key"[..], &b"value"[..]),
+            WriteBatchRecord::Put(&b""[..], &b"value"[..]),
+        ])
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<LsmError>(), Some(&LsmError::EmptyKey));
+    assert_eq!(storage.get(b"key").unwrap(), None);
+    assert_eq!(storage.get(b"").unwrap(), None);
+
+    storage.put(b"key", b"value").unwrap();
+    assert_eq!(
+        storage.get(b"key").unwrap(),
+        Some(Bytes::from_static(b"value"))
+    );
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    block::{BlockBuilder, MAX_ENTRY_FIELD_LEN},
+    error::LsmError,
+    key::KeySlice,
+    lsm_storage::{LsmStorageOptions, MiniLsm, WriteBatchRecord},
+    table::SsTableBuilder,
+};
+
+#[test]
+fn test_reject_oversized_entries() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let value = vec![b'v'; 70 << 10];
+    assert_eq!(
+        storage.put(b"key", &value).unwrap_err(),
+        LsmError::ValueTooLarge {
+            len: 70 << 10,
+            limit: MAX_ENTRY_FIELD_LEN
+        }
+    );
+    let key = vec![b'k'; 70 << 10];
+    assert_eq!(
+        storage.put(&key, b"value").unwrap_err(),
+        LsmError::KeyTooLarge {
+            len: 70 << 10,
+            limit: MAX_ENTRY_FIELD_LEN
+        }
+    );
+    // Nothing in a rejected batch is written.
+    assert!(
+        storage
+            .write_batch(&[
+                WriteBatchRecord::Put(&b"a"[..], &b"1"[..]),
+                WriteBatchRecord::Put(&b"b"[..], &value[..]),
+            ])
+            .is_err()
+    );
+    // The escaped encoding of the value takes 2 more bytes.
+    let escaped = vec![0xff; MAX_ENTRY_FIELD_LEN];
+    assert_eq!(
+        storage.put(b"c", &escaped).unwrap_err(),
+        LsmError::ValueTooLarge {
+            len: MAX_ENTRY_FIELD_LEN,
+            limit: MAX_ENTRY_FIELD_LEN - 2
+        }
+    );
+
+    let largest = vec![b'v'; MAX_ENTRY_FIELD_LEN];
+    storage.put(b"d", &largest).unwrap();
+    storage.force_flush().unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert_eq!(storage.get(b"b").unwrap(), None);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    assert_eq!(storage.get(b"d").unwrap(), Some(Bytes::from(largest)));
+    assert_eq!(storage.get(b"key").unwrap(), None);
+}
+
+#[test]
+fn test_builders_reject_oversized_entries() {
+    let mut builder = BlockBuilder::new(4096);
+    assert!(builder.add(KeySlice::for_testing_from_slice_no_ts(b"a"), b"value"));
+    assert!(builder.add(
+        KeySlice::for_testing_from_slice_no_ts(b"key"),
+        &vec![b'v'; 70 << 10],
+    ));
+    assert_eq!(
+        builder.try_build().err(),
+        Some(LsmError::ValueTooLarge {
+            len: 70 << 10,
+            limit: MAX_ENTRY_FIELD_LEN
+        })
+    );
+
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(4096);
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"a"), b"value");
+    builder.add(
+        KeySlice::for_testing_from_slice_no_ts(&vec![b'k'; 70 << 10]),
+        b"value",
+    );
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"l"), b"value");
+    let err = builder
+        .build_for_test(dir.path().join("1.sst"))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.downcast_ref::<LsmError>(),
+        Some(&LsmError::KeyTooLarge {
+            len: 70 << 10,
+            limit: MAX_ENTRY_FIELD_LEN
+        })
+    );
+}
@@ -0,0 +1,150 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, LeveledCompactionOptions},
+    event_listener::LsmEventListener,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    Flush(usize, Vec<u8>, Vec<u8>),
+    Compaction(Vec<usize>, Vec<usize>),
+    Freeze(usize),
+}
+
+#[derive(Default)]
+struct RecordingListener {
+    events: Mutex<Vec<Event>>,
+}
+
+impl LsmEventListener for RecordingListener {
+    fn on_flush(&self, sst_id: usize, first_key: &[u8], last_key: &[u8]) {
+        self.events
+            .lock()
+            .push(Event::Flush(sst_id, first_key.to_vec(), last_key.to_vec()));
+    }
+
+    fn on_compaction(&self, removed: &[usize], added: &[usize]) {
+        self.events
+            .lock()
+            .push(Event::Compaction(removed.to_vec(), added.to_vec()));
+    }
+
+    fn on_memtable_freeze(&self, id: usize) {
+        self.events.lock().push(Event::Freeze(id));
+    }
+}
+
+#[test]
+fn test_event_listener_matches_recovery() {
+    let dir = tempdir().unwrap();
+    let listener = Arc::new(RecordingListener::default());
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        disable_background_compaction: true,
+        event_listener: Some(listener.clone()),
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Leveled(
+            LeveledCompactionOptions {
+                level_size_multiplier: 2,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+                base_level_size_mb: 1,
+            },
+        ))
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for round in 0..4 {
+        for i in 0..100 {
+            storage
+                .put(format!("key_{:02}_{:03}", round, i).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    while storage.run_one_compaction().unwrap() {}
+    // left in the memtable, recovered from the WAL
+    storage.put(b"key_99", b"value").unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    let events = listener.events.lock().clone();
+    let frozen = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Freeze(id) => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let flushed = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Flush(id, _, _) => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(frozen.len(), 4);
+    assert_eq!(frozen, flushed);
+    assert!(events.contains(&Event::Flush(
+        flushed[0],
+        b"key_00_000".to_vec(),
+        b"key_00_099".to_vec()
+    )));
+    // Every memtable is flushed only after it was frozen.
+    for id in &flushed {
+        let freeze = events.iter().position(|e| *e == Event::Freeze(*id));
+        let flush = events
+            .iter()
+            .position(|e| matches!(e, Event::Flush(x, _, _) if x == id));
+        assert!(freeze < flush);
+    }
+
+    // Replaying the events gives the SSTs the recovery finds.
+    let mut live = BTreeSet::new();
+    for event in &events {
+        match event {
+            Event::Flush(id, _, _) => assert!(live.insert(*id)),
+            Event::Compaction(removed, added) => {
+                assert!(!removed.is_empty());
+                for id in removed {
+                    assert!(live.remove(id));
+                }
+                live.extend(added.iter().copied());
+            }
+            Event::Freeze(_) => {}
+        }
+    }
+    assert!(
+        events
+            .iter()
+            .any(|event| matches!(event, Event::Compaction(..)))
+    );
+    let options = LsmStorageOptions {
+        event_listener: None,
+        ..options
+    };
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert_eq!(
+        storage.all_sst_ids().into_iter().collect::<BTreeSet<_>>(),
+        live
+    );
+    assert!(storage.get(b"key_99").unwrap().is_some());
+}
@@ -0,0 +1,77 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+fn flush(storage: &LsmStorageInner) {
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+}
+
+fn expected(keys: &[(&str, &str)]) -> Vec<(Bytes, Bytes)> {
+    keys.iter()
+        .map(|(k, v)| {
+            (
+                Bytes::copy_from_slice(k.as_bytes()),
+                Bytes::copy_from_slice(v.as_bytes()),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_excluded_lower_bound_at_sst_boundary() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    // `key_2` ends the first SST and starts the second one.
+    for key in ["key_0", "key_1", "key_2"] {
+        storage.put(key.as_bytes(), b"old").unwrap();
+    }
+    flush(&storage);
+    for key in ["key_2", "key_3"] {
+        storage.put(key.as_bytes(), b"new").unwrap();
+    }
+    flush(&storage);
+
+    let mut iter = storage
+        .scan(Bound::Excluded(b"key_2"), Bound::Unbounded)
+        .unwrap();
+    check_lsm_iter_result_by_key(&mut iter, expected(&[("key_3", "new")]));
+
+    // A compacted level followed by an L0 SST and the memtable, all containing the bound.
+    storage.force_full_compaction().unwrap();
+    storage.put(b"key_2", b"l0").unwrap();
+    flush(&storage);
+    storage.put(b"key_2", b"memtable").unwrap();
+    let mut iter = storage
+        .scan(Bound::Excluded(b"key_2"), Bound::Unbounded)
+        .unwrap();
+    check_lsm_iter_result_by_key(&mut iter, expected(&[("key_3", "new")]));
+    let mut iter = storage
+        .scan(Bound::Excluded(b"key_1"), Bound::Excluded(b"key_3"))
+        .unwrap();
+    check_lsm_iter_result_by_key(&mut iter, expected(&[("key_2", "memtable")]));
+    let mut iter = storage
+        .scan(Bound::Excluded(b"key_2"), Bound::Excluded(b"key_3"))
+        .unwrap();
+    check_lsm_iter_result_by_key(&mut iter, Vec::new());
+}
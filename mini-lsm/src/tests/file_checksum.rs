@@ -0,0 +1,88 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+    table::FileObject,
+};
+
+fn flip_byte(path: &Path, offset: usize) {
+    let mut data = std::fs::read(path).unwrap();
+    data[offset] ^= 0x01;
+    std::fs::write(path, data).unwrap();
+}
+
+#[test]
+fn test_file_object_verify() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let file = FileObject::create_with_checksum(&path, b"hello, world".to_vec()).unwrap();
+    assert!(file.has_checksum());
+    assert_eq!(file.size(), 12);
+    file.verify().unwrap();
+
+    let file = FileObject::open(&path).unwrap();
+    assert!(file.has_checksum());
+    assert_eq!(file.size(), 12);
+    file.verify().unwrap();
+
+    flip_byte(&path, 3);
+    assert!(FileObject::open(&path).unwrap().verify().is_err());
+
+    let path = dir.path().join("2.sst");
+    FileObject::create(&path, b"hello, world".to_vec()).unwrap();
+    let file = FileObject::open(&path).unwrap();
+    assert!(!file.has_checksum());
+    assert_eq!(file.size(), 12);
+    file.verify().unwrap();
+}
+
+#[test]
+fn test_verify_sst_files() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        sst_file_checksum: true,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    storage.verify_sst_files().unwrap();
+    let sst_id = storage.all_sst_ids()[0];
+    storage.close().unwrap();
+    drop(storage);
+
+    // The checksum trailer is transparent to reads.
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.verify_sst_files().unwrap();
+    assert_eq!(
+        storage.get(b"key_050").unwrap(),
+        Some(Bytes::from_static(b"value"))
+    );
+    storage.close().unwrap();
+    drop(storage);
+
+    flip_byte(&dir.path().join(format!("{:05}.sst", sst_id)), 10);
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert!(storage.verify_sst_files().is_err());
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_flush_and_wait() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    // nothing to flush
+    storage.flush_and_wait().unwrap();
+    assert!(storage.inner.state.read().l0_sstables.is_empty());
+
+    for round in 0..3 {
+        for i in 0..100 {
+            storage
+                .put(
+                    format!("key_{:03}", i).as_bytes(),
+                    format!("{}", round).as_bytes(),
+                )
+                .unwrap();
+        }
+        storage
+            .inner
+            .force_freeze_memtable(&storage.inner.state_lock.lock())
+            .unwrap();
+    }
+    storage.put(b"key_100", b"3").unwrap();
+    storage.flush_and_wait().unwrap();
+
+    {
+        let snapshot = storage.inner.state.read();
+        assert!(snapshot.memtable.is_empty());
+        assert!(snapshot.imm_memtables.is_empty());
+        assert_eq!(snapshot.l0_sstables.len(), 4);
+    }
+    for i in 0..100 {
+        assert_eq!(
+            &storage
+                .get(format!("key_{:03}", i).as_bytes())
+                .unwrap()
+                .unwrap()[..],
+            b"2"
+        );
+    }
+    assert_eq!(&storage.get(b"key_100").unwrap().unwrap()[..], b"3");
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+#[test]
+fn test_flush_batch() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            num_memtable_limit: 2,
+            flush_batch_size: 10,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    let mut memtable_ids = Vec::new();
+    for i in 0..8 {
+        storage
+            .put(b"key", format!("value_{i}").as_bytes())
+            .unwrap();
+        memtable_ids.push(storage.state.read().memtable.id());
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+    }
+    assert_eq!(storage.state.read().imm_memtables.len(), 8);
+
+    storage.trigger_flush().unwrap();
+    {
+        let state = storage.state.read();
+        assert!(state.imm_memtables.is_empty());
+        // L0 is ordered from latest to earliest, so the memtables were flushed oldest first.
+        memtable_ids.reverse();
+        assert_eq!(state.l0_sstables, memtable_ids);
+    }
+    assert_eq!(
+        storage.get(b"key").unwrap(),
+        Some(Bytes::from_static(b"value_7"))
+    );
+}
@@ -0,0 +1,66 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+#[test]
+fn test_flush_slowdown() {
+    let dir = tempdir().unwrap();
+    // No compaction, so the L0 SSTs are never compacted.
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            num_memtable_limit: 2,
+            flush_slowdown_sorted_runs: Some(2),
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    let mut history = Vec::new();
+    for i in 0..8 {
+        storage
+            .put(format!("key_{i}").as_bytes(), b"value")
+            .unwrap();
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.trigger_flush().unwrap();
+        let state = storage.state.read();
+        history.push((state.imm_memtables.len(), state.l0_sstables.len()));
+    }
+    // Flush whenever there are 2 immutable memtables until L0 has 2 SSTs, then wait for 4.
+    assert_eq!(
+        history,
+        vec![
+            (1, 0),
+            (1, 1),
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (3, 3),
+            (3, 4),
+            (3, 5)
+        ]
+    );
+    for i in 0..8 {
+        assert!(
+            storage
+                .get(format!("key_{i}").as_bytes())
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use super::harness::sync;
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+#[test]
+fn test_flush_stats() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert_eq!(storage.flush_stats().flushes, 0);
+    for round in 0..3 {
+        for i in 0..100 {
+            storage
+                .put(
+                    format!("key_{:03}", i).as_bytes(),
+                    format!("{}", round).as_bytes(),
+                )
+                .unwrap();
+        }
+        sync(&storage);
+    }
+    let stats = storage.flush_stats();
+    assert_eq!(stats.flushes, 3);
+    let snapshot = storage.state.read();
+    assert_eq!(snapshot.l0_sstables.len(), 3);
+    let total_size = snapshot
+        .l0_sstables
+        .iter()
+        .map(|id| snapshot.sstables[id].table_size())
+        .sum::<u64>();
+    assert_eq!(stats.bytes_flushed, total_size);
+}
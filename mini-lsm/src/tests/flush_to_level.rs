@@ -0,0 +1,233 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, LeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+};
+
+fn options() -> LsmStorageOptions {
+    LsmStorageOptions {
+        flush_to_lowest_disjoint_level: true,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Leveled(
+            LeveledCompactionOptions {
+                level_size_multiplier: 2,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+                base_level_size_mb: 1,
+            },
+        ))
+    }
+}
+
+fn put_and_flush(storage: &LsmStorageInner, keys: impl Iterator<Item = usize>) {
+    for i in keys {
+        storage
+            .put(
+                format!("key_{:04}", i).as_bytes(),
+                format!("value_{}", i).as_bytes(),
+            )
+            .unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+}
+
+#[test]
+fn test_flush_to_lowest_disjoint_level() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, options()).unwrap();
+    // Strictly increasing keys go to the bottom level directly.
+    for round in 0..3 {
+        put_and_flush(&storage, round * 100..(round + 1) * 100);
+    }
+    {
+        let state = storage.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert!(state.levels[0].1.is_empty());
+        assert!(state.levels[1].1.is_empty());
+        assert_eq!(state.levels[2].1.len(), 3);
+    }
+
+    // Overlapping keys stay above the SSTs they overlap with.
+    put_and_flush(&storage, (50..60).chain(500..510));
+    put_and_flush(&storage, 400..450);
+    put_and_flush(&storage, 420..430);
+    put_and_flush(&storage, 600..650);
+    {
+        let state = storage.state.read();
+        assert_eq!(state.l0_sstables.len(), 1);
+        assert_eq!(state.levels[0].1.len(), 1);
+        assert_eq!(state.levels[1].1.len(), 1);
+        assert_eq!(state.levels[2].1.len(), 4);
+        for level_ssts in state.levels.iter().map(|(_, ssts)| ssts) {
+            for pair in level_ssts.windows(2) {
+                assert!(state.sstables[&pair[0]].last_key() < state.sstables[&pair[1]].first_key());
+            }
+        }
+    }
+    let l0_sstables = storage.state.read().l0_sstables.clone();
+    let levels = storage.state.read().levels.clone();
+    drop(storage);
+
+    let storage = LsmStorageInner::open(&dir, options()).unwrap();
+    assert_eq!(storage.state.read().l0_sstables, l0_sstables);
+    assert_eq!(storage.state.read().levels, levels);
+    for i in (0..300).chain(400..450).chain(500..510).chain(600..650) {
+        assert_eq!(
+            storage.get(format!("key_{:04}", i).as_bytes()).unwrap(),
+            Some(Bytes::from(format!("value_{}", i)))
+        );
+    }
+}
+
+#[test]
+fn test_force_flush_to_level() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        flush_to_lowest_disjoint_level: false,
+        ..options()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    let flush_to_level = |keys: std::ops::Range<usize>, level: usize| {
+        for i in keys {
+            storage
+                .put(format!("key_{:04}", i).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_to_level(level).unwrap();
+    };
+    flush_to_level(0..100, 2);
+    // Overlaps with L2, so it stays above
+    flush_to_level(50..150, 3);
+    flush_to_level(200..300, 3);
+    {
+        let state = storage.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert_eq!(state.levels[0].1.len(), 1);
+        assert_eq!(state.levels[1].1.len(), 1);
+        assert_eq!(state.levels[2].1.len(), 1);
+    }
+    assert!(storage.force_flush_to_level(4).is_err());
+}
+
+#[test]
+fn test_flush_to_level_on_close() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        flush_to_lowest_disjoint_level: false,
+        flush_to_level_on_close: true,
+        disable_background_compaction: true,
+        ..options()
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:04}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage
+        .inner
+        .force_freeze_memtable(&storage.inner.state_lock.lock())
+        .unwrap();
+    for i in 50..150 {
+        storage
+            .put(format!("key_{:04}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.close().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    {
+        let state = storage.inner.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert!(state.imm_memtables.is_empty());
+        assert!(state.memtable.is_empty());
+        assert!(state.levels[0].1.is_empty());
+        assert_eq!(state.levels[1].1.len(), 1);
+        assert_eq!(state.levels[2].1.len(), 1);
+    }
+    for i in 0..150 {
+        assert_eq!(
+            storage.get(format!("key_{:04}", i).as_bytes()).unwrap(),
+            Some(Bytes::from("value"))
+        );
+    }
+}
+
+#[test]
+fn test_flush_to_level_while_compacting() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        disable_background_compaction: true,
+        // Writing the 22KB of output of the compaction takes about a second.
+        compaction_rate_limit_bytes_per_sec: Some(20 << 10),
+        flush_to_lowest_disjoint_level: false,
+        ..options()
+    };
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    let put_and_flush_to = |keys: &[usize], level: Option<usize>| {
+        for i in keys {
+            storage
+                .put(format!("key_{:04}", i).as_bytes(), &[b'x'; 1024])
+                .unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        match level {
+            Some(level) => storage.force_flush_to_level(level).unwrap(),
+            None => storage.force_flush_next_imm_memtable().unwrap(),
+        }
+    };
+    put_and_flush_to(&(0..10).collect::<Vec<_>>(), Some(3));
+    put_and_flush_to(&(90..100).collect::<Vec<_>>(), Some(3));
+    // The two L0 SSTs together overlap with both SSTs of L3, but not with key 50.
+    put_and_flush_to(&[5], None);
+    put_and_flush_to(&[95], None);
+
+    let compaction = {
+        let storage = storage.clone();
+        std::thread::spawn(move || storage.run_one_compaction().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(200));
+    // The output of the compaction into L3 covers key 50, so the SST stays above L3.
+    put_and_flush_to(&[50], Some(3));
+    assert!(!compaction.is_finished());
+    assert!(compaction.join().unwrap());
+
+    let state = storage.state.read();
+    assert!(state.l0_sstables.is_empty());
+    assert!(state.levels[0].1.is_empty());
+    assert_eq!(state.levels[1].1.len(), 1);
+    assert_eq!(state.levels[2].1.len(), 1);
+    drop(state);
+    for i in [0, 5, 50, 95, 99] {
+        let key = format!("key_{:04}", i);
+        assert!(storage.get(key.as_bytes()).unwrap().is_some());
+    }
+}
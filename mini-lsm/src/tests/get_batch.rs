@@ -0,0 +1,86 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::sync;
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:05}", idx).into_bytes()
+}
+
+#[test]
+fn test_get_batch() {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+        SimpleLeveledCompactionOptions {
+            size_ratio_percent: 200,
+            level0_file_num_compaction_trigger: 2,
+            max_levels: 2,
+        },
+    ));
+    options.block_size = 256;
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+
+    // Spread versions of the keys over the levels, L0, immutable memtables and the memtable.
+    for idx in 0..2000 {
+        storage.put(&key_of(idx), b"level").unwrap();
+    }
+    sync(&storage);
+    storage.trigger_compaction().unwrap();
+    for idx in (0..2000).step_by(3) {
+        storage.put(&key_of(idx), b"l0").unwrap();
+    }
+    for idx in (0..2000).step_by(7) {
+        storage.delete(&key_of(idx)).unwrap();
+    }
+    sync(&storage);
+    for idx in (0..2000).step_by(5) {
+        storage.put(&key_of(idx), b"imm").unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage
+        .delete_range(Bound::Included(&key_of(100)), Bound::Excluded(&key_of(200)))
+        .unwrap();
+    for idx in (0..2000).step_by(11) {
+        storage.put(&key_of(idx), b"mem").unwrap();
+    }
+
+    // Unsorted, with duplicates and keys that do not exist.
+    let keys = (0..2100)
+        .map(|idx| key_of(idx * 7919 % 2100))
+        .chain([key_of(5), key_of(5), b"a".to_vec(), b"z".to_vec()])
+        .collect::<Vec<_>>();
+    let keys = keys.iter().map(|key| &key[..]).collect::<Vec<_>>();
+
+    let expected = keys
+        .iter()
+        .map(|key| storage.get(key).unwrap())
+        .collect::<Vec<_>>();
+    let values = storage.get_batch(&keys).unwrap();
+    assert_eq!(values, expected);
+    assert_eq!(values[2100], Some(Bytes::from_static(b"imm")));
+    assert_eq!(values[2100], values[2101]);
+    assert_eq!(values[2102], None);
+    assert!(storage.get_batch(&[]).unwrap().is_empty());
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, LeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn flush_to_level(storage: &LsmStorageInner, level: usize) {
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_to_level(level).unwrap();
+}
+
+fn blocks_read(storage: &LsmStorageInner) -> u64 {
+    let stats = storage.stats();
+    stats.block_cache_hits + stats.block_cache_misses
+}
+
+#[test]
+fn test_get_stops_at_newest_level() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::Leveled(
+        LeveledCompactionOptions {
+            level_size_multiplier: 2,
+            level0_file_num_compaction_trigger: 2,
+            max_levels: 3,
+            base_level_size_mb: 1,
+        },
+    ));
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    storage.put(b"a", b"l3").unwrap();
+    storage.put(b"b", b"l3").unwrap();
+    storage.put(b"c", b"l3").unwrap();
+    flush_to_level(&storage, 3);
+    storage.delete(b"a").unwrap();
+    storage.put(b"b", b"l1").unwrap();
+    flush_to_level(&storage, 1);
+    storage.put(b"b", b"l0").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    {
+        let state = storage.state.read();
+        assert_eq!(state.l0_sstables.len(), 1);
+        assert_eq!(state.levels[0].1.len(), 1);
+        assert_eq!(state.levels[2].1.len(), 1);
+    }
+
+    // The tombstone in L1 shadows the value in L3, which is not read at all.
+    let before = blocks_read(&storage);
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert_eq!(blocks_read(&storage) - before, 1);
+    let before = blocks_read(&storage);
+    assert_eq!(storage.get(b"b").unwrap(), Some(Bytes::from_static(b"l0")));
+    assert_eq!(blocks_read(&storage) - before, 1);
+    assert_eq!(storage.get(b"c").unwrap(), Some(Bytes::from_static(b"l3")));
+    assert_eq!(storage.get(b"d").unwrap(), None);
+    assert_eq!(
+        storage.get_batch(&[b"a", b"b", b"c", b"d"]).unwrap(),
+        vec![
+            None,
+            Some(Bytes::from_static(b"l0")),
+            Some(Bytes::from_static(b"l3")),
+            None
+        ]
+    );
+}
@@ -0,0 +1,91 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+#[test]
+fn test_writes_stall_on_max_imm_memtables() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        target_sst_size: 1024,
+        num_memtable_limit: 2,
+        max_imm_memtables: Some(4),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    // Pretend a flush thread runs, which is paused until the test flushes.
+    storage.flush_thread_running.store(true, Ordering::SeqCst);
+
+    let writes = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let (storage, writes, stop) = (storage.clone(), writes.clone(), stop.clone());
+        std::thread::spawn(move || {
+            let value = [b'x'; 128];
+            while !stop.load(Ordering::SeqCst) {
+                let i = writes.load(Ordering::SeqCst);
+                storage
+                    .put(format!("key_{:06}", i).as_bytes(), &value)
+                    .unwrap();
+                writes.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+    };
+
+    // The writer stops making progress once 4 memtables wait for the flush.
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(storage.state.read().imm_memtables.len(), 4);
+    let stalled_at = writes.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(writes.load(Ordering::SeqCst), stalled_at);
+    assert_eq!(storage.state.read().imm_memtables.len(), 4);
+
+    // Making room lets the writer continue.
+    storage.force_flush_next_imm_memtable().unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(writes.load(Ordering::SeqCst) > stalled_at);
+    assert_eq!(storage.state.read().imm_memtables.len(), 4);
+
+    // Without a flush thread, nothing stalls writers anymore.
+    storage.flush_thread_running.store(false, Ordering::SeqCst);
+    let resumed_at = writes.load(Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(writes.load(Ordering::SeqCst) > resumed_at);
+    stop.store(true, Ordering::SeqCst);
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_max_imm_memtables_below_num_memtable_limit() {
+    let options = LsmStorageOptions {
+        num_memtable_limit: 4,
+        max_imm_memtables: Some(2),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    assert!(options.validate().is_err());
+    assert!(
+        LsmStorageOptions {
+            max_imm_memtables: Some(8),
+            ..options
+        }
+        .validate()
+        .is_ok()
+    );
+}
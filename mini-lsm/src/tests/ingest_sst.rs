@@ -0,0 +1,198 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, LeveledCompactionOptions},
+    key::KeySlice,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::SsTableBuilder,
+};
+
+fn options() -> LsmStorageOptions {
+    LsmStorageOptions {
+        // Start with the existing data in the bottom level.
+        flush_to_lowest_disjoint_level: true,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Leveled(
+            LeveledCompactionOptions {
+                level_size_multiplier: 2,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+                base_level_size_mb: 1,
+            },
+        ))
+    }
+}
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:04}", idx).into_bytes()
+}
+
+fn build_sst(path: &Path, id: usize, keys: impl Iterator<Item = usize>, value: &[u8]) {
+    let mut builder = SsTableBuilder::new(128);
+    for idx in keys {
+        builder.add(KeySlice::from_slice(&key_of(idx)), value);
+    }
+    builder.build(id, None, path).unwrap();
+}
+
+fn check_values(storage: &LsmStorageInner, keys: impl Iterator<Item = usize>, value: &[u8]) {
+    for idx in keys {
+        assert_eq!(
+            storage.get(&key_of(idx)).unwrap(),
+            Some(Bytes::copy_from_slice(value)),
+            "key {}",
+            idx
+        );
+    }
+}
+
+#[test]
+fn test_ingest_to_lowest_level() {
+    let dir = tempdir().unwrap();
+    let external = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, options()).unwrap();
+    for idx in 0..100 {
+        storage.put(&key_of(idx), b"old").unwrap();
+    }
+    storage.flush_and_wait().unwrap();
+
+    let sst_path = external.path().join("external.sst");
+    build_sst(&sst_path, 0, 200..300, b"ingested");
+    storage.ingest_sst(&sst_path).unwrap();
+    {
+        let state = storage.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert_eq!(state.levels[2].1.len(), 2);
+    }
+    check_values(&storage, 0..100, b"old");
+    check_values(&storage, 200..300, b"ingested");
+
+    // The ingested SST is a copy and is recovered from the manifest.
+    std::fs::remove_file(&sst_path).unwrap();
+    storage.flush_and_wait().unwrap();
+    drop(storage);
+    let storage = LsmStorageInner::open(&dir, options()).unwrap();
+    check_values(&storage, 0..100, b"old");
+    check_values(&storage, 200..300, b"ingested");
+}
+
+#[test]
+fn test_ingest_overlapping_to_l0() {
+    let dir = tempdir().unwrap();
+    let external = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        flush_to_lowest_disjoint_level: false,
+        ..options()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for idx in 0..100 {
+        storage.put(&key_of(idx), b"old").unwrap();
+    }
+    storage.flush_and_wait().unwrap();
+    // Newer data in the memtable is overwritten by the ingested SST as well.
+    storage.put(&key_of(60), b"memtable").unwrap();
+
+    let sst_path = external.path().join("external.sst");
+    build_sst(&sst_path, 0, 50..150, b"ingested");
+    storage.ingest_sst(&sst_path).unwrap();
+    {
+        let state = storage.state.read();
+        assert_eq!(state.l0_sstables.len(), 3);
+        assert!(state.memtable.is_empty());
+        assert!(state.imm_memtables.is_empty());
+    }
+    check_values(&storage, 0..50, b"old");
+    check_values(&storage, 50..150, b"ingested");
+
+    storage.put(&key_of(70), b"new").unwrap();
+    check_values(&storage, 70..71, b"new");
+
+    storage.flush_and_wait().unwrap();
+    drop(storage);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    check_values(&storage, 0..50, b"old");
+    check_values(&storage, 50..70, b"ingested");
+    check_values(&storage, 70..71, b"new");
+    check_values(&storage, 71..150, b"ingested");
+}
+
+#[test]
+fn test_ingest_rejects_newer_epoch() {
+    let dir = tempdir().unwrap();
+    let external = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, options()).unwrap();
+    let sst_path = external.path().join("external.sst");
+    build_sst(&sst_path, 1000, 0..10, b"ingested");
+    assert!(storage.ingest_sst(&sst_path).is_err());
+    assert_eq!(storage.get(&key_of(0)).unwrap(), None);
+}
+
+#[test]
+fn test_ingest_while_compacting() {
+    let dir = tempdir().unwrap();
+    let external = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        disable_background_compaction: true,
+        // Writing the 22KB of output of the compaction takes about a second.
+        compaction_rate_limit_bytes_per_sec: Some(20 << 10),
+        flush_to_lowest_disjoint_level: false,
+        ..options()
+    };
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    let put_and_flush_to = |keys: &[usize], level: Option<usize>| {
+        for idx in keys {
+            storage.put(&key_of(*idx), &[b'x'; 1024]).unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        match level {
+            Some(level) => storage.force_flush_to_level(level).unwrap(),
+            None => storage.force_flush_next_imm_memtable().unwrap(),
+        }
+    };
+    put_and_flush_to(&(0..10).collect::<Vec<_>>(), Some(3));
+    put_and_flush_to(&(90..100).collect::<Vec<_>>(), Some(3));
+    // The two L0 SSTs together overlap with both SSTs of L3, but not with key 50.
+    put_and_flush_to(&[5], None);
+    put_and_flush_to(&[95], None);
+
+    let compaction = {
+        let storage = storage.clone();
+        std::thread::spawn(move || storage.run_one_compaction().unwrap())
+    };
+    std::thread::sleep(Duration::from_millis(200));
+    // The output of the compaction into L3 covers key 50, so the SST stays above L3.
+    let sst_path = external.path().join("external.sst");
+    build_sst(&sst_path, 0, 50..51, b"ingested");
+    storage.ingest_sst(&sst_path).unwrap();
+    assert!(!compaction.is_finished());
+    assert!(compaction.join().unwrap());
+
+    let state = storage.state.read();
+    assert!(state.l0_sstables.is_empty());
+    assert!(state.levels[0].1.is_empty());
+    assert_eq!(state.levels[1].1.len(), 1);
+    assert_eq!(state.levels[2].1.len(), 1);
+    drop(state);
+    check_values(&storage, 50..51, b"ingested");
+    check_values(&storage, 90..100, &[b'x'; 1024]);
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::MockIterator;
+use crate::{
+    error::LsmError,
+    iterators::StorageIterator,
+    lsm_iterator::FusedIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_fused_iterator_take_error() {
+    let mut iter = FusedIterator::new(MockIterator::new_with_error(
+        vec![
+            (Bytes::from("a"), Bytes::from("1")),
+            (Bytes::from("b"), Bytes::from("2")),
+        ],
+        1,
+    ));
+    assert!(iter.take_error().is_none());
+    // The caller ignores the first error.
+    let _ = iter.next();
+    assert!(iter.next().is_err());
+    assert!(!iter.is_valid());
+    assert_eq!(iter.take_error().unwrap().to_string(), "fake error!");
+    assert!(iter.take_error().is_none());
+}
+
+#[test]
+fn test_scan_take_error() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions {
+            block_size: 64,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    let sst_id = storage.inner.state.read().l0_sstables[0];
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    // Only the first block has been read, make reading the following ones fail.
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(storage.inner.path_of_sst(sst_id))
+        .unwrap()
+        .set_len(0)
+        .unwrap();
+    let mut num_keys = 0;
+    while iter.is_valid() {
+        num_keys += 1;
+        if iter.next().is_err() {
+            break;
+        }
+    }
+    assert!(num_keys < 100);
+    assert!(iter.next().is_err());
+    let err = iter.take_error().unwrap();
+    assert!(
+        matches!(err, LsmError::Io(_)),
+        "unexpected error: {:?}",
+        err
+    );
+    assert!(
+        format!("{:#}", err).contains("failed to fill whole buffer"),
+        "unexpected error: {:#}",
+        err
+    );
+}
@@ -0,0 +1,49 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions};
+
+#[test]
+fn test_key_range() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert_eq!(storage.key_range(), None);
+
+    storage.put(b"key_5", b"5").unwrap();
+    storage.put(b"key_3", b"3").unwrap();
+    assert_eq!(
+        storage.key_range(),
+        Some((Bytes::from_static(b"key_3"), Bytes::from_static(b"key_5")))
+    );
+
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.put(b"key_4", b"4").unwrap();
+    storage.put(b"key_9", b"9").unwrap();
+    assert_eq!(
+        storage.key_range(),
+        Some((Bytes::from_static(b"key_3"), Bytes::from_static(b"key_9")))
+    );
+
+    storage.force_flush_next_imm_memtable().unwrap();
+    storage.put(b"key_1", b"1").unwrap();
+    assert_eq!(
+        storage.key_range(),
+        Some((Bytes::from_static(b"key_1"), Bytes::from_static(b"key_9")))
+    );
+}
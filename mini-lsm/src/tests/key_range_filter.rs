@@ -0,0 +1,71 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::{check_lsm_iter_result_by_key, sync};
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{CompactionFilter, LsmStorageInner, LsmStorageOptions},
+};
+
+#[test]
+fn test_key_range_compaction_filter() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 1,
+                max_levels: 2,
+            },
+        )),
+    )
+    .unwrap();
+    for day in 1..=5 {
+        storage
+            .put(format!("2024-01-0{day}").as_bytes(), b"event")
+            .unwrap();
+    }
+    sync(&storage);
+    // Expire everything before 2024-01-03.
+    storage.add_compaction_filter(CompactionFilter::KeyRange {
+        lower: Bound::Unbounded,
+        upper: Bound::Excluded(Bytes::from_static(b"2024-01-03")),
+    });
+
+    // L0 -> L1 is not the bottom level, nothing is dropped.
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().l0_sstables.is_empty());
+    assert!(storage.get(b"2024-01-01").unwrap().is_some());
+
+    // L1 -> L2
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().levels[0].1.is_empty());
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        (3..=5)
+            .map(|day| {
+                (
+                    Bytes::from(format!("2024-01-0{day}")),
+                    Bytes::from_static(b"event"),
+                )
+            })
+            .collect(),
+    );
+}
@@ -0,0 +1,230 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::hash::Hasher;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bytes::{BufMut, Bytes};
+use tempfile::tempdir;
+
+use crate::{
+    block::MAX_ENTRY_FIELD_LEN,
+    error::LsmError,
+    iterators::StorageIterator,
+    key::KeySlice,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+    mem_table::MemTable,
+    table::{FileObject, SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn large_value(seed: u8) -> Vec<u8> {
+    (0..1 << 20).map(|i| (i % 251) as u8 ^ seed).collect()
+}
+
+#[test]
+fn test_large_value_flush_and_compaction() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        large_value_threshold: Some(1024),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    let large = large_value(0);
+    storage.put(b"a", b"small").unwrap();
+    storage.put(b"b", &large).unwrap();
+    storage.put(b"c", b"small").unwrap();
+    // A value starting with the header byte of the value encoding is escaped, also out of line.
+    let escaped = [&[0xff][..], &large_value(1)].concat();
+    storage.put(b"d", &escaped).unwrap();
+    storage.force_flush().unwrap();
+
+    let check = |storage: &MiniLsm| {
+        assert_eq!(
+            storage.get(b"a").unwrap(),
+            Some(Bytes::from_static(b"small"))
+        );
+        assert_eq!(storage.get(b"b").unwrap(), Some(Bytes::from(large.clone())));
+        assert_eq!(
+            storage.get(b"d").unwrap(),
+            Some(Bytes::from(escaped.clone()))
+        );
+        let mut iter = storage
+            .scan(Bound::Unbounded, Bound::Excluded(b"e"))
+            .unwrap();
+        let mut entries = Vec::new();
+        while iter.is_valid() {
+            entries.push((iter.key().to_vec(), iter.value().to_vec()));
+            iter.next().unwrap();
+        }
+        // not `assert_eq`, which would print megabytes of values
+        assert!(
+            entries
+                == vec![
+                    (b"a".to_vec(), b"small".to_vec()),
+                    (b"b".to_vec(), large.clone()),
+                    (b"c".to_vec(), b"small".to_vec()),
+                    (b"d".to_vec(), escaped.clone()),
+                ]
+        );
+    };
+    check(&storage);
+
+    storage.put(b"e", &large_value(2)).unwrap();
+    storage.force_flush().unwrap();
+    storage.force_full_compaction().unwrap();
+    check(&storage);
+    assert_eq!(
+        storage.get(b"e").unwrap(),
+        Some(Bytes::from(large_value(2)))
+    );
+    {
+        let snapshot = storage.inner.state.read();
+        assert!(snapshot.sstables.values().all(|sst| sst.has_blobs()));
+    }
+
+    storage.close().unwrap();
+    drop(storage);
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    // SSTs with blobs stay readable without the option.
+    check(&storage);
+}
+
+#[test]
+fn test_large_value_wal_recovery() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        large_value_threshold: Some(1024),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.put(b"a", &large_value(0)).unwrap();
+    // The largest value whose length fits the short WAL encoding, and the smallest that does not.
+    let boundary = [
+        vec![b'x'; MAX_ENTRY_FIELD_LEN - 1],
+        vec![b'y'; MAX_ENTRY_FIELD_LEN],
+    ];
+    storage.put(b"b", &boundary[0]).unwrap();
+    storage.put(b"c", &boundary[1]).unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert_eq!(
+        storage.get(b"a").unwrap(),
+        Some(Bytes::from(large_value(0)))
+    );
+    assert_eq!(
+        storage.get(b"b").unwrap(),
+        Some(Bytes::from(boundary[0].clone()))
+    );
+    assert_eq!(
+        storage.get(b"c").unwrap(),
+        Some(Bytes::from(boundary[1].clone()))
+    );
+}
+
+#[test]
+fn test_replay_old_format_wal() {
+    // Before large values, a value of `MAX_ENTRY_FIELD_LEN` bytes was stored with its length
+    // inline, which is the marker of a large value now.
+    fn encode_old_record(buf: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.write_u16(key.len() as u16);
+        buf.put_u16(key.len() as u16);
+        hasher.write(key);
+        buf.put_slice(key);
+        hasher.write_u16(value.len() as u16);
+        buf.put_u16(value.len() as u16);
+        buf.put_slice(value);
+        hasher.write(value);
+        buf.put_u32(hasher.finalize());
+    }
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.wal");
+    let value = vec![b'v'; MAX_ENTRY_FIELD_LEN];
+    let mut buf = Vec::new();
+    encode_old_record(&mut buf, b"a", &value);
+    encode_old_record(&mut buf, b"b", b"1");
+    std::fs::write(&path, &buf).unwrap();
+
+    let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+    assert_eq!(
+        memtable.for_testing_get_slice(b"a"),
+        Some(Bytes::from(value.clone()))
+    );
+    assert_eq!(
+        memtable.for_testing_get_slice(b"b"),
+        Some(Bytes::from_static(b"1"))
+    );
+    // new records are appended after the old ones
+    memtable.for_testing_put_slice(b"c", &value).unwrap();
+    memtable.sync_wal().unwrap();
+    drop(memtable);
+    let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+    assert_eq!(
+        memtable.for_testing_get_slice(b"a"),
+        Some(Bytes::from(value.clone()))
+    );
+    assert_eq!(
+        memtable.for_testing_get_slice(b"c"),
+        Some(Bytes::from(value))
+    );
+    assert_eq!(std::fs::read(&path).unwrap()[..buf.len()], buf[..]);
+}
+
+#[test]
+fn test_large_value_threshold_validation() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        large_value_threshold: Some(MAX_ENTRY_FIELD_LEN + 1),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    assert!(matches!(
+        MiniLsm::open(&dir, options),
+        Err(LsmError::InvalidOptions(_))
+    ));
+}
+
+#[test]
+fn test_sst_blob_checksum() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let mut builder = SsTableBuilder::new(4096);
+    builder.set_large_value_threshold(16);
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"a"), b"short");
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"b"), &[b'v'; 4096]);
+    let sst = builder.build_for_test(&path).unwrap();
+    assert!(sst.has_blobs());
+    drop(sst);
+
+    // The value is stored as is, so flip a bit in the middle of it.
+    let mut data = std::fs::read(&path).unwrap();
+    let blob_offset = data.windows(4096).position(|x| x == [b'v'; 4096]).unwrap();
+    data[blob_offset + 100] ^= 1;
+    std::fs::write(&path, data).unwrap();
+    let sst = Arc::new(SsTable::open_for_test(FileObject::open(&path).unwrap()).unwrap());
+    let iter = SsTableIterator::create_and_seek_to_key(
+        sst.clone(),
+        KeySlice::for_testing_from_slice_no_ts(b"a"),
+    )
+    .unwrap();
+    assert_eq!(iter.value(), b"short");
+    let err =
+        SsTableIterator::create_and_seek_to_key(sst, KeySlice::for_testing_from_slice_no_ts(b"b"))
+            .err()
+            .unwrap();
+    assert!(LsmError::from(err).is_corruption());
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_last_key_le() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert_eq!(storage.last_key_le(b"anything").unwrap(), None);
+
+    // ranges stored by their start keys
+    storage.put(b"b", b"range b").unwrap();
+    storage.put(b"d", b"range d").unwrap();
+    storage.put(b"f", b"range f").unwrap();
+    storage.force_flush().unwrap();
+    storage.put(b"h", b"range h").unwrap();
+    storage.delete(b"f").unwrap();
+
+    assert_eq!(storage.last_key_le(b"a").unwrap(), None);
+    assert_eq!(
+        storage.last_key_le(b"b").unwrap(),
+        Some(Bytes::from_static(b"b"))
+    );
+    assert_eq!(
+        storage.last_key_le(b"c").unwrap(),
+        Some(Bytes::from_static(b"b"))
+    );
+    // the deleted key is skipped
+    assert_eq!(
+        storage.last_key_le(b"g").unwrap(),
+        Some(Bytes::from_static(b"d"))
+    );
+    assert_eq!(
+        storage.last_key_le(b"zzz").unwrap(),
+        Some(Bytes::from_static(b"h"))
+    );
+}
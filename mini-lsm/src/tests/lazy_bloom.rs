@@ -0,0 +1,65 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+    table::BloomLoad,
+};
+
+#[test]
+fn test_lazy_bloom_load() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for prefix in ["a", "b"] {
+        for i in 0..100 {
+            storage
+                .put(format!("{}_{:02}", prefix, i).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    let sst_ids = storage.all_sst_ids();
+    storage.close().unwrap();
+    drop(storage);
+
+    let options = LsmStorageOptions {
+        bloom_load: BloomLoad::Lazy,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    // L0 SSTs are ordered from the latest to the earliest
+    let (sst_b, sst_a) = {
+        let state = storage.state.read();
+        (
+            state.sstables[&sst_ids[0]].clone(),
+            state.sstables[&sst_ids[1]].clone(),
+        )
+    };
+    assert!(!sst_a.is_bloom_loaded());
+    assert!(!sst_b.is_bloom_loaded());
+
+    assert_eq!(
+        storage.get(b"b_50").unwrap(),
+        Some(Bytes::from_static(b"value"))
+    );
+    assert!(!sst_a.is_bloom_loaded());
+    assert!(sst_b.is_bloom_loaded());
+    let bloom = sst_b.bloom().unwrap().unwrap() as *const _;
+    assert_eq!(storage.get(b"b_51_missing").unwrap(), None);
+    assert_eq!(sst_b.bloom().unwrap().unwrap() as *const _, bloom);
+    assert!(!sst_a.is_bloom_loaded());
+}
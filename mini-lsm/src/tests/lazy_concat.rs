@@ -0,0 +1,105 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::generate_sst;
+use crate::{
+    iterators::{StorageIterator, concat_iterator::LazySstConcatIterator},
+    key::KeySlice,
+    table::{FileObject, SsTable},
+};
+
+fn key_of(idx: usize) -> Bytes {
+    Bytes::from(format!("key_{:04}", idx))
+}
+
+/// Write 10 SSTs with 20 keys each, and return their paths.
+fn generate_level(dir: &std::path::Path) -> Vec<PathBuf> {
+    (0..10)
+        .map(|sst_idx| {
+            let path = dir.join(format!("{}.sst", sst_idx));
+            let data = (sst_idx * 20..(sst_idx + 1) * 20)
+                .map(|idx| (key_of(idx), Bytes::from("value")))
+                .collect();
+            generate_sst(sst_idx, &path, data, None);
+            path
+        })
+        .collect()
+}
+
+/// Opens the SSTs one by one and keeps a weak reference to each of them.
+fn provider(
+    paths: Vec<PathBuf>,
+    opened: Arc<Mutex<Vec<Weak<SsTable>>>>,
+) -> impl FnMut() -> anyhow::Result<Option<Arc<SsTable>>> {
+    let mut paths = paths.into_iter().enumerate();
+    move || {
+        let Some((idx, path)) = paths.next() else {
+            return Ok(None);
+        };
+        let table = Arc::new(SsTable::open(idx, None, FileObject::open(&path)?)?);
+        opened.lock().unwrap().push(Arc::downgrade(&table));
+        Ok(Some(table))
+    }
+}
+
+fn num_alive(opened: &Mutex<Vec<Weak<SsTable>>>) -> usize {
+    opened
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|table| table.strong_count() > 0)
+        .count()
+}
+
+#[test]
+fn test_lazy_concat_iterator() {
+    let dir = tempdir().unwrap();
+    let paths = generate_level(dir.path());
+    let opened = Arc::new(Mutex::new(Vec::new()));
+    let mut iter =
+        LazySstConcatIterator::create_and_seek_to_first(provider(paths, opened.clone())).unwrap();
+    for idx in 0..200 {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().raw_ref(), key_of(idx).as_ref());
+        assert!(num_alive(&opened) <= 2);
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+    assert_eq!(opened.lock().unwrap().len(), 10);
+    assert_eq!(num_alive(&opened), 0);
+}
+
+#[test]
+fn test_lazy_concat_iterator_seek() {
+    let dir = tempdir().unwrap();
+    let paths = generate_level(dir.path());
+    let opened = Arc::new(Mutex::new(Vec::new()));
+    let mut iter = LazySstConcatIterator::create_and_seek_to_key(
+        provider(paths, opened.clone()),
+        KeySlice::for_testing_from_slice_no_ts(b"key_0055a"),
+    )
+    .unwrap();
+    for idx in 56..200 {
+        assert_eq!(iter.key().raw_ref(), key_of(idx).as_ref());
+        assert!(num_alive(&opened) <= 2);
+        iter.next().unwrap();
+    }
+    assert!(!iter.is_valid());
+}
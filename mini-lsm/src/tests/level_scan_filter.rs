@@ -0,0 +1,138 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, range_overlap},
+};
+
+fn key(i: usize) -> String {
+    format!("key_{:04}", i)
+}
+
+#[test]
+fn test_narrow_scan_touches_overlapping_ssts() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        block_size: 128,
+        target_sst_size: 1024,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for i in 0..2000 {
+        storage.put(key(i).as_bytes(), &[b'v'; 32]).unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    while !storage.state.read().imm_memtables.is_empty() {
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    storage.force_full_compaction().unwrap();
+
+    let snapshot = storage.state.read().clone();
+    let level_sst_ids = &snapshot.levels[0].1;
+    assert!(level_sst_ids.len() > 50);
+
+    let (k1, k2) = (key(1000), key(1005));
+    let (first, last) = (
+        snapshot.sstables[&level_sst_ids[10]]
+            .first_key()
+            .raw_ref()
+            .to_vec(),
+        snapshot.sstables[&level_sst_ids[10]]
+            .last_key()
+            .raw_ref()
+            .to_vec(),
+    );
+    let bounds = [
+        (
+            Bound::Included(k1.as_bytes()),
+            Bound::Included(k2.as_bytes()),
+        ),
+        (
+            Bound::Excluded(k1.as_bytes()),
+            Bound::Excluded(k2.as_bytes()),
+        ),
+        (Bound::Included(&first[..]), Bound::Included(&last[..])),
+        (Bound::Excluded(&first[..]), Bound::Excluded(&last[..])),
+        (Bound::Excluded(&last[..]), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Excluded(&first[..])),
+        (Bound::Included(&b"z"[..]), Bound::Unbounded),
+        (
+            Bound::Included(k2.as_bytes()),
+            Bound::Included(k1.as_bytes()),
+        ),
+        (Bound::Unbounded, Bound::Unbounded),
+    ];
+    for (lower, upper) in bounds {
+        let expected = level_sst_ids
+            .iter()
+            .filter(|id| {
+                let table = &snapshot.sstables[*id];
+                range_overlap(
+                    lower,
+                    upper,
+                    table.first_key().as_key_slice(),
+                    table.last_key().as_key_slice(),
+                )
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let ssts = snapshot
+            .level_ssts_in_range(level_sst_ids, lower, upper)
+            .iter()
+            .map(|table| table.sst_id())
+            .collect::<Vec<_>>();
+        assert_eq!(ssts, expected, "bounds {:?} {:?}", lower, upper);
+    }
+    assert!(
+        snapshot
+            .level_ssts_in_range(
+                level_sst_ids,
+                Bound::Included(k1.as_bytes()),
+                Bound::Included(k2.as_bytes())
+            )
+            .len()
+            <= 2
+    );
+
+    // A narrow scan only reads the blocks of the SSTs holding the range.
+    let before = storage.stats();
+    let mut iter = storage
+        .scan(
+            Bound::Included(k1.as_bytes()),
+            Bound::Included(k2.as_bytes()),
+        )
+        .unwrap();
+    let mut keys = Vec::new();
+    while iter.is_valid() {
+        keys.push(String::from_utf8(iter.key().to_vec()).unwrap());
+        iter.next().unwrap();
+    }
+    assert_eq!(keys, (1000..=1005).map(key).collect::<Vec<_>>());
+    let after = storage.stats();
+    let blocks_read = after.block_cache_hits + after.block_cache_misses
+        - before.block_cache_hits
+        - before.block_cache_misses;
+    assert!(
+        (1..=4).contains(&blocks_read),
+        "read {} blocks",
+        blocks_read
+    );
+}
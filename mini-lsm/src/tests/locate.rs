@@ -0,0 +1,113 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    lsm_storage::{KeyLocation, LsmStorageOptions, MiniLsm, WriteBatchRecord},
+};
+
+#[test]
+fn test_locate() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    assert_eq!(
+        storage.locate(b"a").unwrap(),
+        Some(KeyLocation::ActiveMemtable)
+    );
+    assert_eq!(storage.locate(b"b").unwrap(), None);
+
+    storage
+        .inner
+        .force_freeze_memtable(&storage.inner.state_lock.lock())
+        .unwrap();
+    storage.put(b"b", b"1").unwrap();
+    assert_eq!(
+        storage.locate(b"a").unwrap(),
+        Some(KeyLocation::ImmMemtable(0))
+    );
+    assert_eq!(
+        storage.locate(b"b").unwrap(),
+        Some(KeyLocation::ActiveMemtable)
+    );
+
+    storage.force_flush().unwrap();
+    let l0_sst = storage.inner.state.read().l0_sstables[0];
+    assert_eq!(storage.locate(b"a").unwrap(), Some(KeyLocation::L0(l0_sst)));
+
+    storage.force_full_compaction().unwrap();
+    let (level, l1_sst) = {
+        let state = storage.inner.state.read();
+        (state.levels[0].0, state.levels[0].1[0])
+    };
+    assert_eq!(
+        storage.locate(b"a").unwrap(),
+        Some(KeyLocation::Level(level, l1_sst))
+    );
+
+    // A newer version in the memtable takes precedence, and a deleted key is not located.
+    storage.put(b"a", b"2").unwrap();
+    assert_eq!(
+        storage.locate(b"a").unwrap(),
+        Some(KeyLocation::ActiveMemtable)
+    );
+    storage.delete(b"b").unwrap();
+    assert_eq!(storage.locate(b"b").unwrap(), None);
+}
+
+#[test]
+fn test_locate_in_level_with_many_ssts() {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    options.block_size = 64;
+    options.target_sst_size = 256;
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    // in a single memtable, which the compaction splits into many SSTs
+    let keys = (0..500)
+        .map(|i| format!("key_{:04}", i))
+        .collect::<Vec<_>>();
+    storage
+        .write_batch(
+            &keys
+                .iter()
+                .map(|key| WriteBatchRecord::Put(key.as_bytes(), b"value"))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+    storage.force_flush().unwrap();
+    storage.force_full_compaction().unwrap();
+
+    let state = storage.inner.state.read().clone();
+    let (level, ssts) = &state.levels[0];
+    assert!(ssts.len() > 3);
+    for i in (0..500).step_by(7) {
+        let key = format!("key_{:04}", i);
+        let Some(KeyLocation::Level(located_level, sst_id)) =
+            storage.locate(key.as_bytes()).unwrap()
+        else {
+            panic!("{} is not located in a level", key);
+        };
+        assert_eq!(located_level, *level);
+        let sst = &state.sstables[&sst_id];
+        assert!(sst.first_key().raw_ref() <= key.as_bytes());
+        assert!(key.as_bytes() <= sst.last_key().raw_ref());
+    }
+    assert_eq!(storage.locate(b"key_0500").unwrap(), None);
+}
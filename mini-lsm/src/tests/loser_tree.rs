@@ -0,0 +1,160 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use super::harness::{MockIterator, check_iter_result_by_key, expect_iter_error};
+use crate::{
+    iterators::{
+        StorageIterator, loser_tree_iterator::LoserTreeIterator, merge_iterator::MergeIterator,
+    },
+    key::KeySlice,
+};
+
+/// Counts how many times the keys of the underlying iterator are accessed.
+struct CountingIterator {
+    iter: MockIterator,
+    key_accesses: Arc<AtomicUsize>,
+}
+
+impl StorageIterator for CountingIterator {
+    type KeyType<'a> = KeySlice<'a>;
+
+    fn key(&self) -> KeySlice<'_> {
+        self.key_accesses.fetch_add(1, Ordering::Relaxed);
+        self.iter.key()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.iter.value()
+    }
+
+    fn is_valid(&self) -> bool {
+        self.iter.is_valid()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.iter.next()
+    }
+}
+
+/// 64 iterators with interleaved keys, plus a few keys present in every iterator.
+fn generate_iters(key_accesses: &Arc<AtomicUsize>) -> Vec<CountingIterator> {
+    (0..64)
+        .map(|i| {
+            let mut data = Vec::new();
+            for j in 0..50 {
+                data.push((
+                    Bytes::from(format!("key_{:05}", j * 64 + i)),
+                    Bytes::from(format!("value_{}", i)),
+                ));
+                if j % 10 == 0 {
+                    data.push((
+                        Bytes::from(format!("key_{:05}_shared", j * 64)),
+                        Bytes::from(format!("value_{}", i)),
+                    ));
+                }
+            }
+            data.sort();
+            CountingIterator {
+                iter: MockIterator::new(data),
+                key_accesses: key_accesses.clone(),
+            }
+        })
+        .collect()
+}
+
+fn collect(
+    mut iter: impl for<'a> StorageIterator<KeyType<'a> = KeySlice<'a>>,
+) -> Vec<(Bytes, Bytes)> {
+    let mut result = Vec::new();
+    while iter.is_valid() {
+        result.push((
+            Bytes::copy_from_slice(iter.key().raw_ref()),
+            Bytes::copy_from_slice(iter.value()),
+        ));
+        iter.next().unwrap();
+    }
+    result
+}
+
+#[test]
+fn test_loser_tree_same_as_heap() {
+    let heap_accesses = Arc::new(AtomicUsize::new(0));
+    let heap_iters = generate_iters(&heap_accesses);
+    let heap_result = collect(MergeIterator::create(
+        heap_iters.into_iter().map(Box::new).collect(),
+    ));
+    let tree_accesses = Arc::new(AtomicUsize::new(0));
+    let tree_iters = generate_iters(&tree_accesses);
+    let tree_result = collect(LoserTreeIterator::create(
+        tree_iters.into_iter().map(Box::new).collect(),
+    ));
+
+    assert_eq!(heap_result.len(), 64 * 50 + 5);
+    assert_eq!(
+        heap_result
+            .iter()
+            .find(|(key, _)| key.ends_with(b"_shared")),
+        Some(&(
+            Bytes::from_static(b"key_00000_shared"),
+            Bytes::from_static(b"value_0")
+        ))
+    );
+    assert_eq!(heap_result, tree_result);
+
+    let heap_accesses = heap_accesses.load(Ordering::Relaxed);
+    let tree_accesses = tree_accesses.load(Ordering::Relaxed);
+    assert!(
+        tree_accesses < heap_accesses,
+        "loser tree accessed keys {} times, heap {} times",
+        tree_accesses,
+        heap_accesses
+    );
+}
+
+#[test]
+fn test_loser_tree_edge_cases() {
+    let iter = LoserTreeIterator::<MockIterator>::create(vec![]);
+    assert!(!iter.is_valid());
+
+    let i1 = MockIterator::new(vec![]);
+    let i2 = MockIterator::new(vec![(Bytes::from("a"), Bytes::from("1.2"))]);
+    let i3 = MockIterator::new(vec![
+        (Bytes::from("a"), Bytes::from("1.3")),
+        (Bytes::from("b"), Bytes::from("2.3")),
+    ]);
+    let mut iter = LoserTreeIterator::create(vec![Box::new(i1), Box::new(i2), Box::new(i3)]);
+    check_iter_result_by_key(
+        &mut iter,
+        vec![
+            (Bytes::from("a"), Bytes::from("1.2")),
+            (Bytes::from("b"), Bytes::from("2.3")),
+        ],
+    );
+
+    let i1 = MockIterator::new_with_error(
+        vec![
+            (Bytes::from("a"), Bytes::from("1.1")),
+            (Bytes::from("b"), Bytes::from("2.1")),
+        ],
+        1,
+    );
+    let i2 = MockIterator::new(vec![(Bytes::from("c"), Bytes::from("3.2"))]);
+    expect_iter_error(LoserTreeIterator::create(vec![Box::new(i1), Box::new(i2)]));
+}
@@ -0,0 +1,145 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::sync;
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    iterators::StorageIterator,
+    lsm_iterator::{FusedIterator, LsmIterator},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:03}", idx).into_bytes()
+}
+
+fn collect(iter: &mut FusedIterator<LsmIterator>) -> Vec<(Bytes, Bytes)> {
+    let mut result = Vec::new();
+    while iter.is_valid() {
+        result.push((
+            Bytes::copy_from_slice(iter.key()),
+            Bytes::copy_from_slice(iter.value()),
+        ));
+        iter.next().unwrap();
+    }
+    result
+}
+
+/// Spread the keys over the lower levels, L0, immutable memtables and the memtable.
+fn open_with_data(dir: &tempfile::TempDir) -> LsmStorageInner {
+    let mut options = LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+        SimpleLeveledCompactionOptions {
+            size_ratio_percent: 200,
+            level0_file_num_compaction_trigger: 2,
+            max_levels: 2,
+        },
+    ));
+    options.block_size = 64;
+    let storage = LsmStorageInner::open(dir, options).unwrap();
+    for idx in 0..60 {
+        storage.put(&key_of(idx), b"level").unwrap();
+    }
+    sync(&storage);
+    storage.trigger_compaction().unwrap();
+    for idx in (0..60).step_by(3) {
+        storage.put(&key_of(idx), b"l0").unwrap();
+    }
+    for idx in (1..60).step_by(7) {
+        storage.delete(&key_of(idx)).unwrap();
+    }
+    sync(&storage);
+    for idx in (0..70).step_by(5) {
+        storage.put(&key_of(idx), b"imm").unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    for idx in (2..70).step_by(11) {
+        storage.put(&key_of(idx), b"mem").unwrap();
+    }
+    storage.delete(&key_of(30)).unwrap();
+    storage
+}
+
+#[test]
+fn test_seek_matches_scan() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_data(&dir);
+
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    // Forward, backward, onto deleted keys, between keys and past the last key.
+    for idx in [40, 10, 10, 30, 1, 55, 0, 69, 99, 20] {
+        let key = key_of(idx);
+        iter.seek(&key).unwrap();
+        let expected = collect(
+            &mut storage
+                .scan(Bound::Included(&key), Bound::Unbounded)
+                .unwrap(),
+        );
+        assert_eq!(collect(&mut iter), expected, "seek to {}", idx);
+    }
+
+    iter.seek(b"key_04").unwrap();
+    assert_eq!(iter.key(), key_of(40));
+    iter.seek(b"").unwrap();
+    assert_eq!(iter.key(), key_of(0));
+}
+
+#[test]
+fn test_seek_within_bounds() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_data(&dir);
+    let (k10, k40) = (key_of(10), key_of(40));
+
+    let mut iter = storage
+        .scan(Bound::Excluded(&k10), Bound::Excluded(&k40))
+        .unwrap();
+    let all = collect(&mut iter);
+    assert_eq!(all.first().unwrap().0, key_of(11));
+
+    // Seeking before the lower bound moves to the start of the scan.
+    for key in [key_of(0), key_of(10)] {
+        iter.seek(&key).unwrap();
+        assert_eq!(collect(&mut iter), all);
+    }
+
+    // Seeking past the upper bound exhausts the scan.
+    iter.seek(&k40).unwrap();
+    assert!(!iter.is_valid());
+    iter.seek(&key_of(50)).unwrap();
+    assert!(!iter.is_valid());
+
+    iter.seek(&key_of(25)).unwrap();
+    let expected = all
+        .iter()
+        .filter(|(key, _)| key.as_ref() >= key_of(25).as_slice())
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(collect(&mut iter), expected);
+}
+
+#[test]
+fn test_seek_descending_scan_is_rejected() {
+    let dir = tempdir().unwrap();
+    let storage = open_with_data(&dir);
+    let mut iter = storage
+        .scan_rev(Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    assert!(iter.seek(&key_of(10)).is_err());
+}
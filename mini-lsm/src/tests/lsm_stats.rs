@@ -0,0 +1,68 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use tempfile::tempdir;
+
+use crate::{
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    stats::LsmStatsSnapshot,
+};
+
+#[test]
+fn test_lsm_stats() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert_eq!(storage.stats(), LsmStatsSnapshot::default());
+
+    storage.put(b"key_0", b"value_0").unwrap();
+    storage.put(b"key_1", b"value_1").unwrap();
+    storage.put(b"key_2", b"value_2").unwrap();
+    storage.delete(b"key_2").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    let stats = storage.stats();
+    assert_eq!(stats.puts, 3);
+    assert_eq!(stats.deletes, 1);
+    assert_eq!(stats.flushes, 1);
+
+    // The first read of the block misses the block cache, later reads hit it.
+    assert!(storage.get(b"key_0").unwrap().is_some());
+    let stats = storage.stats();
+    assert_eq!(stats.gets, 1);
+    assert_eq!(stats.block_cache_hits, 0);
+    assert_eq!(stats.block_cache_misses, 1);
+    assert!(storage.get(b"key_1").unwrap().is_some());
+    let stats = storage.stats();
+    assert_eq!(stats.gets, 2);
+    assert_eq!(stats.block_cache_hits, 1);
+    assert_eq!(stats.block_cache_misses, 1);
+
+    storage.get_batch(&[b"key_0", b"key_2", b"key_3"]).unwrap();
+    assert_eq!(storage.stats().gets, 5);
+
+    storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    storage
+        .scan_rev(Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    assert_eq!(storage.stats().scans, 2);
+
+    storage.force_full_compaction().unwrap();
+    let stats = storage.stats();
+    assert_eq!(stats.compactions, 1);
+    assert_eq!(stats.flushes, 1);
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+    manifest::{Manifest, ManifestRecord},
+};
+
+fn options(enable_wal: bool) -> LsmStorageOptions {
+    LsmStorageOptions {
+        enable_wal,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    }
+}
+
+fn test_manifest_compaction_on_close(enable_wal: bool) {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, options(enable_wal)).unwrap();
+    for round in 0..10 {
+        for i in 0..100 {
+            storage
+                .put(
+                    format!("key_{:03}", i * 10 + round).as_bytes(),
+                    format!("value_{}", round).as_bytes(),
+                )
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    storage.put(b"unflushed", b"1").unwrap();
+    storage.close().unwrap();
+    let (l0_sstables, levels) = {
+        let state = storage.inner.state.read();
+        (state.l0_sstables.clone(), state.levels.clone())
+    };
+    drop(storage);
+
+    let (_, records) = Manifest::recover(dir.path().join("MANIFEST")).unwrap();
+    assert_eq!(records.len(), 1);
+    let ManifestRecord::Snapshot(snapshot) = &records[0] else {
+        panic!("expect a snapshot record");
+    };
+    assert_eq!(snapshot.l0_sstables, l0_sstables);
+    assert_eq!(snapshot.levels, levels);
+
+    // Reopen without the background threads so that the state stays as recovered.
+    let storage = LsmStorageInner::open(&dir, options(enable_wal)).unwrap();
+    {
+        let state = storage.state.read();
+        assert_eq!(state.l0_sstables, l0_sstables);
+        assert_eq!(state.levels, levels);
+    }
+    for round in 0..10 {
+        for i in 0..100 {
+            assert_eq!(
+                storage
+                    .get(format!("key_{:03}", i * 10 + round).as_bytes())
+                    .unwrap(),
+                Some(Bytes::from(format!("value_{}", round)))
+            );
+        }
+    }
+    assert_eq!(
+        storage.get(b"unflushed").unwrap(),
+        Some(Bytes::from_static(b"1"))
+    );
+}
+
+#[test]
+fn test_manifest_compaction_on_close_with_wal() {
+    test_manifest_compaction_on_close(true);
+}
+
+#[test]
+fn test_manifest_compaction_on_close_without_wal() {
+    test_manifest_compaction_on_close(false);
+}
+
+#[test]
+fn test_manifest_compaction_threshold() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        manifest_compaction_threshold: Some(5),
+        ..options(true)
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for round in 0..20 {
+        storage
+            .put(format!("key_{:03}", round).as_bytes(), b"value")
+            .unwrap();
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+        if round % 4 == 0 {
+            storage.trigger_compaction().unwrap();
+        }
+        assert!(storage.manifest.as_ref().unwrap().num_records() < 5);
+    }
+    storage.put(b"unflushed", b"1").unwrap();
+    storage.sync().unwrap();
+    let (l0_sstables, levels) = {
+        let state = storage.state.read();
+        (state.l0_sstables.clone(), state.levels.clone())
+    };
+    drop(storage);
+
+    // A crash while compacting the manifest leaves a partially written temporary file.
+    std::fs::write(dir.path().join("MANIFEST.tmp"), b"garbage").unwrap();
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    assert!(!dir.path().join("MANIFEST.tmp").exists());
+    {
+        let state = storage.state.read();
+        assert_eq!(state.l0_sstables, l0_sstables);
+        assert_eq!(state.levels, levels);
+    }
+    for round in 0..20 {
+        assert_eq!(
+            storage.get(format!("key_{:03}", round).as_bytes()).unwrap(),
+            Some(Bytes::from_static(b"value"))
+        );
+    }
+    assert_eq!(
+        storage.get(b"unflushed").unwrap(),
+        Some(Bytes::from_static(b"1"))
+    );
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+
+use tempfile::tempdir;
+
+use crate::manifest::{Manifest, ManifestRecord};
+
+#[test]
+fn test_manifest_partial_record() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("MANIFEST");
+    let manifest = Manifest::create(&path).unwrap();
+    manifest
+        .add_record_when_init(ManifestRecord::NewMemtable(0))
+        .unwrap();
+    manifest
+        .add_record_when_init(ManifestRecord::Flush(0))
+        .unwrap();
+    drop(manifest);
+    let valid_len = std::fs::metadata(&path).unwrap().len();
+
+    // A torn write: a length prefix followed by only part of the record.
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(&[0, 0, 0, 0, 0, 0, 0, 20, b'{', b'"'])
+        .unwrap();
+    drop(file);
+
+    let (manifest, records) = Manifest::recover(&path).unwrap();
+    assert!(matches!(
+        records[..],
+        [ManifestRecord::NewMemtable(0), ManifestRecord::Flush(0)]
+    ));
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), valid_len);
+
+    // Records added after recovery are not hidden behind the garbage.
+    manifest
+        .add_record_when_init(ManifestRecord::NewMemtable(1))
+        .unwrap();
+    drop(manifest);
+    let (_, records) = Manifest::recover(&path).unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(matches!(records[2], ManifestRecord::NewMemtable(1)));
+}
+
+#[test]
+fn test_manifest_corrupted_record() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("MANIFEST");
+    let manifest = Manifest::create(&path).unwrap();
+    manifest
+        .add_record_when_init(ManifestRecord::NewMemtable(0))
+        .unwrap();
+    manifest
+        .add_record_when_init(ManifestRecord::NewMemtable(1))
+        .unwrap();
+    drop(manifest);
+
+    // Flip a bit in the last record, which then fails the checksum.
+    let mut data = std::fs::read(&path).unwrap();
+    let len = data.len();
+    data[len - 6] ^= 1;
+    std::fs::write(&path, &data).unwrap();
+    let (_, records) = Manifest::recover(&path).unwrap();
+    assert!(matches!(records[..], [ManifestRecord::NewMemtable(0)]));
+}
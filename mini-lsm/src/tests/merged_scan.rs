@@ -0,0 +1,70 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    lsm_iterator::MergedScan,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_merged_scan() {
+    let dir1 = tempdir().unwrap();
+    let dir2 = tempdir().unwrap();
+    let shard1 = MiniLsm::open(&dir1, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let shard2 = MiniLsm::open(&dir2, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let mut expected = Vec::new();
+    for i in 0..20 {
+        let key = format!("key_{:02}", i);
+        let value = format!("value_{}", i);
+        // interleave the shards to make sure the output is merged rather than concatenated
+        let shard = if i % 3 == 0 { &shard1 } else { &shard2 };
+        shard.put(key.as_bytes(), value.as_bytes()).unwrap();
+        expected.push((Bytes::from(key), Bytes::from(value)));
+    }
+    shard2.force_flush().unwrap();
+    shard1.delete(b"key_00").unwrap();
+    expected.remove(0);
+
+    let mut iter = MergedScan::new(vec![
+        shard1.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        shard2.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+    ]);
+    check_lsm_iter_result_by_key(&mut iter, expected);
+
+    let mut iter = MergedScan::new(vec![
+        shard1
+            .scan(Bound::Included(b"key_05"), Bound::Excluded(b"key_10"))
+            .unwrap(),
+        shard2
+            .scan(Bound::Included(b"key_05"), Bound::Excluded(b"key_10"))
+            .unwrap(),
+    ]);
+    check_lsm_iter_result_by_key(
+        &mut iter,
+        (5..10)
+            .map(|i| {
+                (
+                    Bytes::from(format!("key_{:02}", i)),
+                    Bytes::from(format!("value_{}", i)),
+                )
+            })
+            .collect(),
+    );
+}
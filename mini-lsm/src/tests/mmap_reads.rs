@@ -0,0 +1,103 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::FileObject,
+};
+
+fn mmap_options() -> LsmStorageOptions {
+    LsmStorageOptions {
+        mmap_reads: true,
+        ..LsmStorageOptions::default_for_week1_test()
+    }
+}
+
+fn assert_all_mapped(storage: &LsmStorageInner) {
+    let state = storage.state.read();
+    assert!(!state.sstables.is_empty());
+    for sst in state.sstables.values() {
+        assert!(sst.file.is_mapped());
+    }
+}
+
+#[test]
+fn test_mmap_reads() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, mmap_options()).unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value_1")
+            .unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value_2")
+            .unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    assert_all_mapped(&storage);
+
+    storage.force_full_compaction().unwrap();
+    assert_all_mapped(&storage);
+    assert_eq!(
+        storage.get(b"key_042").unwrap(),
+        Some(Bytes::from_static(b"value_2"))
+    );
+    drop(storage);
+
+    let storage = LsmStorageInner::open(&dir, mmap_options()).unwrap();
+    assert_all_mapped(&storage);
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    let mut num_keys = 0;
+    while iter.is_valid() {
+        assert_eq!(iter.value(), b"value_2");
+        num_keys += 1;
+        iter.next().unwrap();
+    }
+    assert_eq!(num_keys, 100);
+}
+
+#[test]
+fn test_mmap_read_out_of_range() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    std::fs::write(&path, b"0123456789").unwrap();
+    let file = FileObject::open_mmap(&path).unwrap();
+    assert!(file.is_mapped());
+    assert_eq!(&file.read(2, 3).unwrap()[..], b"234");
+    assert_eq!(&file.read(0, 10).unwrap()[..], b"0123456789");
+    assert!(file.read(8, 3).is_err());
+    assert!(file.read(11, 0).is_err());
+    assert!(file.read(1, u64::MAX).is_err());
+
+    let path = dir.path().join("2.sst");
+    std::fs::write(&path, b"").unwrap();
+    let file = FileObject::open_mmap(&path).unwrap();
+    assert!(!file.is_mapped());
+    assert!(file.read(0, 1).is_err());
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    error::LsmError,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+fn options(target_sst_size: usize, block_size: usize) -> LsmStorageOptions {
+    LsmStorageOptions {
+        target_sst_size,
+        block_size,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    }
+}
+
+#[test]
+fn test_target_sst_size_smaller_than_block_size() {
+    let dir = tempdir().unwrap();
+    let Err(err) = MiniLsm::open(&dir, options(100, 4096)) else {
+        panic!("expect open to fail");
+    };
+    assert!(matches!(err, LsmError::InvalidOptions(_)));
+    assert_eq!(
+        err.to_string(),
+        "invalid options: target_sst_size (100) is smaller than the block size (4096)"
+    );
+
+    assert!(options(8192, 4096).validate().is_ok());
+    // The block sizes of deeper levels are checked as well.
+    assert!(
+        LsmStorageOptions {
+            level_block_sizes: vec![16384],
+            ..options(8192, 4096)
+        }
+        .validate()
+        .is_err()
+    );
+
+    MiniLsm::open(&dir, options(4096, 4096))
+        .unwrap()
+        .close()
+        .unwrap();
+}
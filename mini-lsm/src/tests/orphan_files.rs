@@ -0,0 +1,98 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Path, PathBuf};
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LOST_FOUND_DIR, LsmStorageOptions, MiniLsm};
+
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|x| x == extension))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Write a database with an SST and a WAL, then leave a stray SST and WAL in its directory as a
+/// crash before recording them in the manifest would.
+fn create_db_with_orphans(dir: &Path, options: &LsmStorageOptions) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let storage = MiniLsm::open(dir, options.clone()).unwrap();
+    storage.put(b"flushed", b"1").unwrap();
+    storage.force_flush().unwrap();
+    storage.put(b"in_wal", b"2").unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    let live_ssts = files_with_extension(dir, "sst");
+    let live_wals = files_with_extension(dir, "wal");
+    assert_eq!(live_ssts.len(), 1);
+    assert!(!live_wals.is_empty());
+    std::fs::write(dir.join("99999.sst"), b"garbage").unwrap();
+    std::fs::write(dir.join("99998.wal"), b"garbage").unwrap();
+    (live_ssts, live_wals)
+}
+
+fn check_db(storage: &MiniLsm) {
+    assert_eq!(&storage.get(b"flushed").unwrap().unwrap()[..], b"1");
+    assert_eq!(&storage.get(b"in_wal").unwrap().unwrap()[..], b"2");
+}
+
+#[test]
+fn test_remove_orphan_files() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let (live_ssts, live_wals) = create_db_with_orphans(dir.path(), &options);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert!(!dir.path().join("99999.sst").exists());
+    assert!(!dir.path().join("99998.wal").exists());
+    assert!(!dir.path().join(LOST_FOUND_DIR).exists());
+    assert_eq!(files_with_extension(dir.path(), "sst"), live_ssts);
+    for wal in &live_wals {
+        assert!(wal.exists());
+    }
+    check_db(&storage);
+}
+
+#[test]
+fn test_move_orphan_files_to_lost_found() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        move_orphan_files_to_lost_found: true,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let (live_ssts, _) = create_db_with_orphans(dir.path(), &options);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert_eq!(files_with_extension(dir.path(), "sst"), live_ssts);
+    assert!(!dir.path().join("99998.wal").exists());
+    let lost_found = dir.path().join(LOST_FOUND_DIR);
+    assert_eq!(
+        std::fs::read(lost_found.join("99999.sst")).unwrap(),
+        b"garbage"
+    );
+    assert_eq!(
+        std::fs::read(lost_found.join("99998.wal")).unwrap(),
+        b"garbage"
+    );
+    check_db(&storage);
+}
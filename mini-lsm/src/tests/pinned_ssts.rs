@@ -0,0 +1,120 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::path::Path;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn count_ssts(path: &Path) -> usize {
+    std::fs::read_dir(path)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "sst")
+        })
+        .count()
+}
+
+/// Flush 4 L0 SSTs of 10 keys each.
+fn fill(storage: &LsmStorageInner) {
+    for round in 0..4 {
+        for i in 0..10 {
+            storage
+                .put(format!("key_{}_{}", round, i).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+}
+
+#[test]
+fn test_scan_pins_ssts() {
+    for reverse in [false, true] {
+        let dir = tempdir().unwrap();
+        let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+        let storage = LsmStorageInner::open(&dir, options).unwrap();
+        fill(&storage);
+        let l0_sstables = storage.state.read().l0_sstables.clone();
+        assert_eq!(count_ssts(dir.path()), 4);
+
+        // the scan only reads the SSTs of rounds 1 and 2
+        let (lower, upper) = (
+            Bound::Included(&b"key_1"[..]),
+            Bound::Excluded(&b"key_3"[..]),
+        );
+        let mut iter = if reverse {
+            storage.scan_rev(lower, upper).unwrap()
+        } else {
+            storage.scan(lower, upper).unwrap()
+        };
+        let pinned = l0_sstables
+            .iter()
+            .filter(|id| storage.pinned_ssts.is_pinned(**id))
+            .count();
+        assert_eq!(pinned, 2);
+        iter.next().unwrap();
+
+        storage.force_full_compaction().unwrap();
+        // the files of the 2 SSTs read by the scan are kept next to the output
+        assert_eq!(count_ssts(dir.path()), 3);
+        let mut num_keys = 1;
+        while iter.is_valid() {
+            assert_eq!(iter.value(), b"value");
+            num_keys += 1;
+            iter.next().unwrap();
+        }
+        assert_eq!(num_keys, 20);
+        assert_eq!(count_ssts(dir.path()), 3);
+
+        drop(iter);
+        assert_eq!(count_ssts(dir.path()), 1);
+        assert!(
+            l0_sstables
+                .iter()
+                .all(|id| !storage.pinned_ssts.is_pinned(*id))
+        );
+    }
+}
+
+#[test]
+fn test_concurrent_scans_pin_ssts() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    fill(&storage);
+
+    let iter1 = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    let iter2 = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    storage.force_full_compaction().unwrap();
+    assert_eq!(count_ssts(dir.path()), 5);
+    drop(iter1);
+    // still pinned by the other scan
+    assert_eq!(count_ssts(dir.path()), 5);
+    drop(iter2);
+    assert_eq!(count_ssts(dir.path()), 1);
+}
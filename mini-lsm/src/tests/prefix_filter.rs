@@ -0,0 +1,88 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::{check_lsm_iter_result_by_key, sync};
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{CompactionFilter, LsmStorageInner, LsmStorageOptions},
+};
+
+#[test]
+fn test_prefix_compaction_filter() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 1,
+                max_levels: 2,
+            },
+        )),
+    )
+    .unwrap();
+    for key in ["data_1", "tmp_1", "tmp_2", "data_2", "tm"] {
+        storage.put(key.as_bytes(), b"1").unwrap();
+    }
+    sync(&storage);
+    storage.add_compaction_filter(CompactionFilter::Prefix(Bytes::from("tmp_")));
+
+    // L0 -> L1 is not the bottom level, the filtered keys survive.
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().l0_sstables.is_empty());
+    assert_eq!(
+        storage.get(b"tmp_1").unwrap(),
+        Some(Bytes::from_static(b"1"))
+    );
+
+    // L1 -> L2
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().levels[0].1.is_empty());
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from("data_1"), Bytes::from_static(b"1")),
+            (Bytes::from("data_2"), Bytes::from_static(b"1")),
+            (Bytes::from("tm"), Bytes::from_static(b"1")),
+        ],
+    );
+}
+
+#[test]
+fn test_prefix_compaction_filter_full_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    for i in 0..10 {
+        storage.put(format!("tmp_{i}").as_bytes(), b"1").unwrap();
+        storage.put(format!("user_{i}").as_bytes(), b"1").unwrap();
+    }
+    sync(&storage);
+    storage.add_compaction_filter(CompactionFilter::Prefix(Bytes::from("tmp_")));
+    storage.force_full_compaction().unwrap();
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        (0..10)
+            .map(|i| (Bytes::from(format!("user_{i}")), Bytes::from_static(b"1")))
+            .collect(),
+    );
+}
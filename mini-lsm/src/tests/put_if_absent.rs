@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, Barrier};
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_put_if_absent() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert!(storage.put_if_absent(b"a", b"1").unwrap());
+    assert!(!storage.put_if_absent(b"a", b"2").unwrap());
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+
+    // also checked against the SSTs
+    storage.force_flush().unwrap();
+    assert!(!storage.put_if_absent(b"a", b"3").unwrap());
+
+    // a deleted key is absent
+    storage.delete(b"a").unwrap();
+    assert!(storage.put_if_absent(b"a", b"4").unwrap());
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"4")));
+}
+
+#[test]
+fn test_concurrent_put_if_absent() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for i in 0..100 {
+        let key = format!("key_{:03}", i);
+        let barrier = Arc::new(Barrier::new(2));
+        let winners = std::thread::scope(|s| {
+            let handles = (0..2)
+                .map(|t| {
+                    let (storage, key, barrier) = (&storage, &key, barrier.clone());
+                    s.spawn(move || {
+                        barrier.wait();
+                        let value = format!("value_{}", t);
+                        storage
+                            .put_if_absent(key.as_bytes(), value.as_bytes())
+                            .unwrap()
+                            .then_some(value)
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(winners.len(), 1);
+        assert_eq!(
+            storage.get(key.as_bytes()).unwrap(),
+            Some(Bytes::from(winners[0].clone()))
+        );
+    }
+}
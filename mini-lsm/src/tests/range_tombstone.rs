@@ -0,0 +1,233 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::{
+    check_iter_result_by_key, check_lsm_iter_result_by_key, construct_merge_iterator_over_storage,
+    sync,
+};
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_range_tombstone_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 1,
+                max_levels: 2,
+            },
+        )),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"c", b"1").unwrap();
+    storage.put(b"f", b"1").unwrap();
+    sync(&storage);
+    // L0 -> L1, then L1 -> L2
+    storage.trigger_compaction().unwrap();
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().l0_sstables.is_empty());
+    assert!(storage.state.read().levels[0].1.is_empty());
+    assert_eq!(storage.state.read().levels[1].1.len(), 1);
+
+    storage
+        .delete_range(Bound::Included(b"b"), Bound::Excluded(b"e"))
+        .unwrap();
+    // `e` overlaps the L2 SST, so the L1 -> L2 compaction rewrites it
+    storage.put(b"e", b"1").unwrap();
+    sync(&storage);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"e"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"f"), Bytes::from_static(b"1")),
+        ],
+    );
+
+    // L0 -> L1: `c` still lives in L2, so the tombstone must be kept.
+    storage.trigger_compaction().unwrap();
+    assert_eq!(storage.range_tombstones.read().len(), 1);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+
+    // L1 -> L2: `c` is dropped and the tombstone no longer covers anything.
+    storage.trigger_compaction().unwrap();
+    assert!(storage.state.read().levels[0].1.is_empty());
+    check_iter_result_by_key(
+        &mut construct_merge_iterator_over_storage(&storage.state.read()),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"e"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"f"), Bytes::from_static(b"1")),
+        ],
+    );
+    assert!(storage.range_tombstones.read().is_empty());
+}
+
+#[test]
+fn test_range_tombstone_recovery() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"c", b"1").unwrap();
+    storage
+        .delete_range(Bound::Included(b"b"), Bound::Unbounded)
+        .unwrap();
+    storage.put(b"d", b"2").unwrap();
+    storage.close().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"d"), Bytes::from_static(b"2")),
+        ],
+    );
+}
+
+#[test]
+fn test_range_tombstone_across_freeze() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for key in ["a", "b", "c", "d"] {
+        storage.put(key.as_bytes(), b"old").unwrap();
+    }
+    sync(&storage);
+    storage.put(b"c", b"imm").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.put(b"b", b"mem").unwrap();
+
+    // The tombstone is written into the memtable holding `b`, and deletes the earlier memtables
+    // and SSTs from there.
+    storage
+        .delete_range(Bound::Included(b"b"), Bound::Included(b"c"))
+        .unwrap();
+    assert_eq!(storage.state.read().imm_memtables.len(), 1);
+    assert!(storage.range_tombstones.read().is_empty());
+    let expected = vec![
+        (Bytes::from_static(b"a"), Bytes::from_static(b"old")),
+        (Bytes::from_static(b"d"), Bytes::from_static(b"old")),
+    ];
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected.clone(),
+    );
+    check_lsm_iter_result_by_key(
+        &mut storage
+            .scan(Bound::Included(b"b"), Bound::Unbounded)
+            .unwrap(),
+        expected[1..].to_vec(),
+    );
+
+    // A put after the tombstone takes precedence over it, also once it is frozen and flushed.
+    storage.put(b"c", b"new").unwrap();
+    let expected = vec![
+        (Bytes::from_static(b"a"), Bytes::from_static(b"old")),
+        (Bytes::from_static(b"c"), Bytes::from_static(b"new")),
+        (Bytes::from_static(b"d"), Bytes::from_static(b"old")),
+    ];
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected.clone(),
+    );
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected.clone(),
+    );
+    sync(&storage);
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected,
+    );
+    assert_eq!(storage.get(b"b").unwrap(), None);
+    assert_eq!(storage.get(b"c").unwrap(), Some(Bytes::from_static(b"new")));
+}
+
+#[test]
+fn test_range_tombstone_wal_recovery() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"c", b"1").unwrap();
+    storage
+        .delete_range(Bound::Included(b"b"), Bound::Excluded(b"d"))
+        .unwrap();
+    storage.put(b"d", b"2").unwrap();
+    storage.sync().unwrap();
+    // The tombstone only lives in the WAL of the memtable.
+    assert!(storage.range_tombstones.read().is_empty());
+    drop(storage);
+
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    let expected = vec![
+        (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+        (Bytes::from_static(b"d"), Bytes::from_static(b"2")),
+    ];
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected.clone(),
+    );
+    sync(&storage);
+    assert_eq!(storage.get(b"c").unwrap(), None);
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected,
+    );
+}
+
+#[test]
+fn test_range_tombstone_snapshot() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    let snapshot = storage.snapshot();
+    storage
+        .delete_range(Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert_eq!(storage.get_batch(&[b"a", b"b"]).unwrap(), vec![None, None]);
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    check_lsm_iter_result_by_key(
+        &mut snapshot.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"b"), Bytes::from_static(b"1")),
+        ],
+    );
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    error::LsmError,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+fn dir_contents(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let content = std::fs::read(&path).unwrap();
+            (path, content)
+        })
+        .collect()
+}
+
+fn assert_read_only(result: Result<(), LsmError>) {
+    assert_eq!(result.unwrap_err(), LsmError::ReadOnly);
+}
+
+#[test]
+fn test_open_read_only() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 3,
+            },
+        ))
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.put(b"a", b"flushed").unwrap();
+    storage.put(b"b", b"flushed").unwrap();
+    storage.force_flush().unwrap();
+    storage.put(b"b", b"wal").unwrap();
+    storage.put(b"c", b"wal").unwrap();
+    storage.sync().unwrap();
+    // Leave the WAL to be replayed by not closing the database.
+    drop(storage);
+
+    let contents = dir_contents(dir.path());
+    let storage = MiniLsm::open_read_only(&dir, options).unwrap();
+    assert_eq!(
+        storage.get(b"a").unwrap(),
+        Some(Bytes::from_static(b"flushed"))
+    );
+    assert_eq!(storage.get(b"b").unwrap(), Some(Bytes::from_static(b"wal")));
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"flushed")),
+            (Bytes::from_static(b"b"), Bytes::from_static(b"wal")),
+            (Bytes::from_static(b"c"), Bytes::from_static(b"wal")),
+        ],
+    );
+
+    assert_read_only(storage.put(b"d", b"1"));
+    assert_read_only(storage.delete(b"a"));
+    assert_read_only(storage.delete_range(Bound::Unbounded, Bound::Unbounded));
+    assert_read_only(storage.force_flush());
+    assert_eq!(storage.get(b"d").unwrap(), None);
+    storage.close().unwrap();
+    drop(storage);
+
+    assert_eq!(dir_contents(dir.path()), contents);
+}
+
+#[test]
+fn test_open_read_only_missing_database() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("missing");
+    assert!(MiniLsm::open_read_only(&path, LsmStorageOptions::default_for_week1_test()).is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,57 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    error::LsmError,
+    lsm_storage::{LsmStorageOptions, MiniLsm, WriteBatchRecord},
+};
+
+#[test]
+fn test_reject_overwrites() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions {
+            reject_overwrites: true,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    let err = storage.put(b"a", b"2").unwrap_err();
+    assert_eq!(err, LsmError::AlreadyExists(Bytes::from_static(b"a")));
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+
+    // The key is also checked on disk, and nothing in a rejected batch is written.
+    storage.force_flush().unwrap();
+    assert!(storage.put(b"b", b"2").is_err());
+    assert!(
+        storage
+            .write_batch(&[
+                WriteBatchRecord::Put(b"c", b"1"),
+                WriteBatchRecord::Put(b"c", b"2")
+            ])
+            .is_err()
+    );
+    assert_eq!(storage.get(b"c").unwrap(), None);
+
+    // A deleted key can be written again.
+    storage.delete(b"a").unwrap();
+    storage.put(b"a", b"3").unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"3")));
+}
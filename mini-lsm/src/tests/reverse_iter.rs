@@ -0,0 +1,132 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::{
+    block::{Block, BlockBuilder, BlockIterator},
+    iterators::StorageIterator,
+    key::KeySlice,
+    table::{SsTableBuilder, SsTableIterator},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:03}", idx).into_bytes()
+}
+
+#[test]
+fn test_block_prev() {
+    let mut builder = BlockBuilder::new(10000);
+    for idx in 0..100 {
+        assert!(builder.add(
+            KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+            b"value"
+        ));
+    }
+    let block = Arc::new(builder.build());
+    let mut iter = BlockIterator::create_and_seek_to_last(block.clone());
+    for idx in (0..100).rev() {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+        iter.prev();
+    }
+    assert!(!iter.is_valid());
+    iter.prev();
+    assert!(!iter.is_valid());
+
+    // forward and backward again
+    iter.seek_to_key(KeySlice::for_testing_from_slice_no_ts(&key_of(50)));
+    iter.next();
+    iter.prev();
+    assert_eq!(iter.key().for_testing_key_ref(), key_of(50));
+
+    let mut iter = BlockIterator::create_and_seek_to_key(
+        block,
+        KeySlice::for_testing_from_slice_no_ts(&key_of(0)),
+    );
+    iter.prev();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_sst_prev() {
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(128);
+    for idx in 0..100 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+            b"value",
+        );
+    }
+    let sst = Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap());
+    assert!(sst.num_of_blocks() > 2);
+
+    let mut iter = SsTableIterator::create_and_seek_to_last(sst.clone()).unwrap();
+    for idx in (0..100).rev() {
+        assert!(iter.is_valid());
+        assert_eq!(iter.key().for_testing_key_ref(), key_of(idx));
+        assert_eq!(iter.value(), b"value");
+        iter.prev().unwrap();
+    }
+    assert!(!iter.is_valid());
+
+    let mut iter = SsTableIterator::create_and_seek_to_key(
+        sst,
+        KeySlice::for_testing_from_slice_no_ts(&key_of(0)),
+    )
+    .unwrap();
+    iter.prev().unwrap();
+    assert!(!iter.is_valid());
+}
+
+#[test]
+fn test_sst_seek_to_last() {
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(128);
+    for idx in 0..100 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+            b"value",
+        );
+    }
+    let sst = Arc::new(builder.build_for_test(dir.path().join("1.sst")).unwrap());
+    assert!(sst.num_of_blocks() > 2);
+
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+    iter.seek_to_last().unwrap();
+    assert!(iter.is_valid());
+    assert_eq!(iter.key().for_testing_key_ref(), key_of(99));
+    iter.next().unwrap();
+    assert!(!iter.is_valid());
+    // seek back after the iterator is exhausted
+    iter.seek_to_last().unwrap();
+    assert_eq!(iter.key().for_testing_key_ref(), key_of(99));
+    iter.prev().unwrap();
+    assert_eq!(iter.key().for_testing_key_ref(), key_of(98));
+}
+
+#[test]
+fn test_empty_block_seek_to_last() {
+    let block = Arc::new(Block {
+        data: Vec::new(),
+        offsets: Vec::new(),
+        restart_interval: 0,
+    });
+    let mut iter = BlockIterator::create_and_seek_to_last(block);
+    assert!(!iter.is_valid());
+    iter.prev();
+    assert!(!iter.is_valid());
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_run_one_compaction() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        disable_background_compaction: true,
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 2,
+                max_levels: 2,
+            },
+        ))
+    };
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    for round in 0..4 {
+        for i in 0..100 {
+            storage
+                .put(
+                    format!("key_{:03}", i).as_bytes(),
+                    format!("value_{}_{}", round, i).as_bytes(),
+                )
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+    // nothing is compacted until asked to
+    assert_eq!(storage.inner.state.read().l0_sstables.len(), 4);
+
+    let mut num_compactions = 0;
+    while storage.run_one_compaction().unwrap() {
+        num_compactions += 1;
+    }
+    // L0 -> L1, then L1 -> L2
+    assert_eq!(num_compactions, 2);
+    assert!(!storage.run_one_compaction().unwrap());
+    {
+        let state = storage.inner.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert!(state.levels[0].1.is_empty());
+        assert_eq!(state.levels[1].1.len(), 1);
+    }
+    for i in 0..100 {
+        assert_eq!(
+            storage.get(format!("key_{:03}", i).as_bytes()).unwrap(),
+            Some(Bytes::from(format!("value_3_{}", i)))
+        );
+    }
+    storage.close().unwrap();
+}
@@ -0,0 +1,124 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use anyhow::Result;
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::error::LsmError;
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+#[test]
+fn test_scan_into_iter() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for i in 0..10 {
+        storage
+            .put(
+                format!("key_{}", i).as_bytes(),
+                format!("value_{}", i).as_bytes(),
+            )
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    storage.delete(b"key_1").unwrap();
+    storage.delete(b"key_3").unwrap();
+
+    let first = storage
+        .scan(Bound::Unbounded, Bound::Unbounded)
+        .unwrap()
+        .into_iter()
+        .take(5)
+        .collect::<Result<Vec<_>, LsmError>>()
+        .unwrap();
+    let expected = [0, 2, 4, 5, 6]
+        .into_iter()
+        .map(|i| {
+            (
+                Bytes::from(format!("key_{}", i)),
+                Bytes::from(format!("value_{}", i)),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(first, expected);
+
+    let mut keys = Vec::new();
+    for entry in storage
+        .scan(Bound::Included(b"key_5"), Bound::Unbounded)
+        .unwrap()
+    {
+        keys.push(entry.unwrap().0);
+    }
+    assert_eq!(keys, vec!["key_5", "key_6", "key_7", "key_8", "key_9"]);
+
+    let mut iter = storage
+        .scan(Bound::Excluded(b"key_9"), Bound::Unbounded)
+        .unwrap()
+        .into_iter();
+    assert!(iter.next().is_none());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_scan_into_iter_stops_on_error() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions {
+            block_size: 64,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    let sst_id = storage.inner.state.read().l0_sstables[0];
+    let mut iter = storage
+        .scan(Bound::Unbounded, Bound::Unbounded)
+        .unwrap()
+        .into_iter();
+    // Only the first block has been read, make reading the following ones fail.
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(storage.inner.path_of_sst(sst_id))
+        .unwrap()
+        .set_len(0)
+        .unwrap();
+    let mut num_keys = 0;
+    let err = loop {
+        match iter.next() {
+            Some(Ok(_)) => num_keys += 1,
+            Some(Err(e)) => break e,
+            None => panic!("expect the scan to fail"),
+        }
+    };
+    assert!(num_keys < 100);
+    assert!(
+        matches!(err, LsmError::Io(_)),
+        "unexpected error: {:?}",
+        err
+    );
+    assert!(
+        format!("{:#}", err).contains("failed to fill whole buffer"),
+        "unexpected error: {:#}",
+        err
+    );
+    assert!(iter.next().is_none());
+}
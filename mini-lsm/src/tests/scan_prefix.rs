@@ -0,0 +1,77 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_scan_prefix() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let keys: [&[u8]; 9] = [
+        b"user:41:a",
+        b"user:42:",
+        b"user:42:a",
+        b"user:42:b",
+        b"user:43",
+        b"\x01\xff",
+        b"\x01\xff\x00",
+        b"\x02",
+        b"\xff\xff\x01",
+    ];
+    for key in keys {
+        storage.put(key, b"1").unwrap();
+    }
+    storage.force_flush().unwrap();
+    storage.put(b"user:42:c", b"2").unwrap();
+    storage.delete(b"user:42:a").unwrap();
+
+    check_lsm_iter_result_by_key(
+        &mut storage.scan_prefix(b"user:42:").unwrap(),
+        vec![
+            (Bytes::from("user:42:"), Bytes::from("1")),
+            (Bytes::from("user:42:b"), Bytes::from("1")),
+            (Bytes::from("user:42:c"), Bytes::from("2")),
+        ],
+    );
+    // The upper bound of a prefix ending in 0xff is \x02, not \x01\x00.
+    check_lsm_iter_result_by_key(
+        &mut storage.scan_prefix(b"\x01\xff").unwrap(),
+        vec![
+            (Bytes::from_static(b"\x01\xff"), Bytes::from("1")),
+            (Bytes::from_static(b"\x01\xff\x00"), Bytes::from("1")),
+        ],
+    );
+    // No key is greater than all keys starting with 0xff 0xff, so the scan is unbounded.
+    check_lsm_iter_result_by_key(
+        &mut storage.scan_prefix(b"\xff\xff").unwrap(),
+        vec![(Bytes::from_static(b"\xff\xff\x01"), Bytes::from("1"))],
+    );
+    check_lsm_iter_result_by_key(&mut storage.scan_prefix(b"user:44").unwrap(), vec![]);
+
+    // An empty prefix scans all keys.
+    let mut iter = storage.scan_prefix(b"").unwrap();
+    let mut num_keys = 0;
+    while iter.is_valid() {
+        num_keys += 1;
+        iter.next().unwrap();
+    }
+    assert_eq!(num_keys, keys.len());
+}
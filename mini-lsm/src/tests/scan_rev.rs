@@ -0,0 +1,129 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::sync;
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:03}", idx).into_bytes()
+}
+
+fn collect(mut iter: impl for<'a> StorageIterator<KeyType<'a> = &'a [u8]>) -> Vec<(Bytes, Bytes)> {
+    let mut result = Vec::new();
+    while iter.is_valid() {
+        result.push((
+            Bytes::copy_from_slice(iter.key()),
+            Bytes::copy_from_slice(iter.value()),
+        ));
+        iter.next().unwrap();
+    }
+    result
+}
+
+#[test]
+fn test_scan_rev_descending() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for idx in 1..=100 {
+        storage.put(&key_of(idx), b"value").unwrap();
+    }
+    let keys = collect(
+        storage
+            .scan_rev(Bound::Unbounded, Bound::Unbounded)
+            .unwrap(),
+    )
+    .into_iter()
+    .map(|(key, _)| key)
+    .collect::<Vec<_>>();
+    let expected = (1..=100)
+        .rev()
+        .map(|idx| Bytes::from(key_of(idx)))
+        .collect::<Vec<_>>();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_scan_rev_matches_scan() {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+        SimpleLeveledCompactionOptions {
+            size_ratio_percent: 200,
+            level0_file_num_compaction_trigger: 2,
+            max_levels: 2,
+        },
+    ));
+    options.block_size = 64;
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+
+    // Spread the keys over the lower levels, L0, immutable memtables and the memtable, with
+    // newer versions and deletes shadowing older data.
+    for idx in 0..60 {
+        storage.put(&key_of(idx), b"level").unwrap();
+    }
+    sync(&storage);
+    storage.trigger_compaction().unwrap();
+    for idx in (0..60).step_by(3) {
+        storage.put(&key_of(idx), b"l0").unwrap();
+    }
+    for idx in (1..60).step_by(7) {
+        storage.delete(&key_of(idx)).unwrap();
+    }
+    sync(&storage);
+    for idx in (0..70).step_by(5) {
+        storage.put(&key_of(idx), b"imm").unwrap();
+    }
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage
+        .delete_range(Bound::Included(&key_of(20)), Bound::Excluded(&key_of(25)))
+        .unwrap();
+    for idx in (2..70).step_by(11) {
+        storage.put(&key_of(idx), b"mem").unwrap();
+    }
+    storage.delete(&key_of(30)).unwrap();
+
+    let (k10, k33, k40) = (key_of(10), key_of(33), key_of(40));
+    let bounds = [
+        (Bound::Unbounded, Bound::Unbounded),
+        (Bound::Included(&k10[..]), Bound::Included(&k40[..])),
+        (Bound::Excluded(&k10[..]), Bound::Excluded(&k40[..])),
+        (Bound::Included(&k33[..]), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Excluded(&k33[..])),
+        (
+            Bound::Excluded(b"key".as_slice()),
+            Bound::Included(b"key_999".as_slice()),
+        ),
+    ];
+    for (lower, upper) in bounds {
+        let mut expected = collect(storage.scan(lower, upper).unwrap());
+        expected.reverse();
+        assert_eq!(
+            collect(storage.scan_rev(lower, upper).unwrap()),
+            expected,
+            "bounds {:?} {:?}",
+            lower,
+            upper
+        );
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_iter_result_by_key;
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_scan_sst() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    for i in 0..10 {
+        storage
+            .put(
+                format!("key_{i}").as_bytes(),
+                format!("value_{i}").as_bytes(),
+            )
+            .unwrap();
+    }
+    storage.delete(b"key_4").unwrap();
+    storage.force_flush().unwrap();
+    // Newer data in the memtable is not visible to the SST scan.
+    storage.put(b"key_5", b"new").unwrap();
+
+    let sst_ids = storage.all_sst_ids();
+    assert_eq!(sst_ids.len(), 1);
+    let mut iter = storage
+        .scan_sst(
+            sst_ids[0],
+            Bound::Excluded(b"key_2"),
+            Bound::Included(b"key_6"),
+        )
+        .unwrap();
+    check_iter_result_by_key(
+        &mut iter,
+        vec![
+            (Bytes::from("key_3"), Bytes::from("value_3")),
+            (Bytes::from("key_4"), Bytes::new()),
+            (Bytes::from("key_5"), Bytes::from("value_5")),
+            (Bytes::from("key_6"), Bytes::from("value_6")),
+        ],
+    );
+
+    let iter = storage
+        .scan_sst(sst_ids[0], Bound::Included(b"key_a"), Bound::Unbounded)
+        .unwrap();
+    assert!(!iter.is_valid());
+    assert!(
+        storage
+            .scan_sst(sst_ids[0] + 100, Bound::Unbounded, Bound::Unbounded)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_scan_sst_decodes_values() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage.put(b"a", b"\xff\x01").unwrap();
+    storage
+        .put_with_ttl(b"b", b"expired", Duration::from_millis(1))
+        .unwrap();
+    storage
+        .put_with_ttl(b"c", b"live", Duration::from_secs(3600))
+        .unwrap();
+    storage.force_flush().unwrap();
+    std::thread::sleep(Duration::from_millis(10));
+
+    let sst_ids = storage.all_sst_ids();
+    let mut iter = storage
+        .scan_sst(sst_ids[0], Bound::Unbounded, Bound::Unbounded)
+        .unwrap();
+    check_iter_result_by_key(
+        &mut iter,
+        vec![
+            (Bytes::from("a"), Bytes::from_static(b"\xff\x01")),
+            (Bytes::from("b"), Bytes::from("expired")),
+            (Bytes::from("c"), Bytes::from("live")),
+        ],
+    );
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::{
+    compact::{SimpleLeveledCompactionController, SimpleLeveledCompactionOptions},
+    key::KeyBytes,
+    lsm_storage::LsmStorageState,
+    mem_table::MemTable,
+    table::SsTable,
+};
+
+fn key_of(idx: usize) -> KeyBytes {
+    KeyBytes::for_testing_from_bytes_no_ts(format!("key_{:04}", idx).into())
+}
+
+fn state_with_ssts(ssts: &[(usize, usize, usize)]) -> LsmStorageState {
+    let mut state = LsmStorageState {
+        memtable: Arc::new(MemTable::create(0)),
+        imm_memtables: Vec::new(),
+        l0_sstables: Vec::new(),
+        levels: vec![(1, Vec::new()), (2, Vec::new())],
+        sstables: Default::default(),
+    };
+    for &(id, first, last) in ssts {
+        state.sstables.insert(
+            id,
+            Arc::new(SsTable::create_meta_only(
+                id,
+                1,
+                key_of(first),
+                key_of(last),
+            )),
+        );
+    }
+    state
+}
+
+#[test]
+fn test_simple_compaction_keeps_non_overlapping_ssts() {
+    let controller = SimpleLeveledCompactionController::new(SimpleLeveledCompactionOptions {
+        size_ratio_percent: 500,
+        level0_file_num_compaction_trigger: 2,
+        max_levels: 2,
+    });
+    // L1 touches the range of SST 13 and 14 in L2
+    let mut state = state_with_ssts(&[
+        (1, 350, 380),
+        (2, 390, 420),
+        (10, 0, 99),
+        (11, 100, 199),
+        (12, 200, 299),
+        (13, 300, 399),
+        (14, 400, 499),
+        (15, 500, 599),
+        (16, 600, 699),
+    ]);
+    state.levels[0].1 = vec![1, 2];
+    state.levels[1].1 = vec![10, 11, 12, 13, 14, 15, 16];
+
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.upper_level, Some(1));
+    assert_eq!(task.upper_level_sst_ids, vec![1, 2]);
+    assert_eq!(task.lower_level_sst_ids, vec![13, 14]);
+    assert!(task.is_lower_level_bottom_level);
+
+    for (id, first, last) in [(20, 300, 419), (21, 420, 499)] {
+        state.sstables.insert(
+            id,
+            Arc::new(SsTable::create_meta_only(
+                id,
+                1,
+                key_of(first),
+                key_of(last),
+            )),
+        );
+    }
+    let (new_state, mut removed) = controller.apply_compaction_result(&state, &task, &[20, 21]);
+    removed.sort();
+    assert_eq!(removed, vec![1, 2, 13, 14]);
+    assert!(new_state.levels[0].1.is_empty());
+    assert_eq!(new_state.levels[1].1, vec![10, 11, 12, 20, 21, 15, 16]);
+}
+
+#[test]
+fn test_simple_compaction_no_overlapping_ssts() {
+    let controller = SimpleLeveledCompactionController::new(SimpleLeveledCompactionOptions {
+        size_ratio_percent: 500,
+        level0_file_num_compaction_trigger: 2,
+        max_levels: 2,
+    });
+    let mut state = state_with_ssts(&[(1, 150, 160), (10, 0, 99), (11, 200, 299), (12, 300, 399)]);
+    state.levels[0].1 = vec![1];
+    state.levels[1].1 = vec![10, 11, 12];
+
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert!(task.lower_level_sst_ids.is_empty());
+    state.sstables.insert(
+        20,
+        Arc::new(SsTable::create_meta_only(20, 1, key_of(150), key_of(160))),
+    );
+    let (new_state, removed) = controller.apply_compaction_result(&state, &task, &[20]);
+    assert_eq!(removed, vec![1]);
+    assert_eq!(new_state.levels[1].1, vec![10, 20, 11, 12]);
+
+    // without the SSTs loaded, e.g. during recovery, the output is appended
+    state.sstables.clear();
+    let (new_state, _) = controller.apply_compaction_result(&state, &task, &[20]);
+    assert_eq!(new_state.levels[1].1, vec![10, 11, 12, 20]);
+}
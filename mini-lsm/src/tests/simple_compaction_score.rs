@@ -0,0 +1,83 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::{
+    compact::{SimpleLeveledCompactionController, SimpleLeveledCompactionOptions},
+    key::KeyBytes,
+    lsm_storage::LsmStorageState,
+    mem_table::MemTable,
+    table::SsTable,
+};
+
+/// A state with one SST per id in each of L0 and the levels, all covering the same keys.
+fn state_with_levels(l0: &[usize], levels: &[&[usize]]) -> LsmStorageState {
+    let mut state = LsmStorageState {
+        memtable: Arc::new(MemTable::create(0)),
+        imm_memtables: Vec::new(),
+        l0_sstables: l0.to_vec(),
+        levels: levels
+            .iter()
+            .enumerate()
+            .map(|(idx, ssts)| (idx + 1, ssts.to_vec()))
+            .collect(),
+        sstables: Default::default(),
+    };
+    for &id in l0.iter().chain(levels.iter().copied().flatten()) {
+        state.sstables.insert(
+            id,
+            Arc::new(SsTable::create_meta_only(
+                id,
+                1,
+                KeyBytes::for_testing_from_bytes_no_ts("a".into()),
+                KeyBytes::for_testing_from_bytes_no_ts("z".into()),
+            )),
+        );
+    }
+    state
+}
+
+#[test]
+fn test_simple_compaction_picks_highest_score() {
+    let controller = SimpleLeveledCompactionController::new(SimpleLeveledCompactionOptions {
+        size_ratio_percent: 200,
+        level0_file_num_compaction_trigger: 2,
+        max_levels: 3,
+    });
+    // L2 / L1 = 1.5 is barely below the ratio of 2, while L3 / L2 = 0.25 is far below it.
+    let state = state_with_levels(&[], &[&[1, 2], &[3, 4, 5], &[6]]);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.upper_level, Some(2));
+    assert_eq!(task.upper_level_sst_ids, vec![3, 4, 5]);
+    assert_eq!(task.lower_level, 3);
+    assert_eq!(task.lower_level_sst_ids, vec![6]);
+    assert!(task.is_lower_level_bottom_level);
+
+    // An empty lower level is the most urgent.
+    let state = state_with_levels(&[], &[&[1, 2], &[], &[3]]);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.upper_level, Some(1));
+    assert_eq!(task.lower_level, 2);
+
+    // L0 reaching its trigger takes precedence over all levels.
+    let state = state_with_levels(&[7, 8], &[&[1, 2], &[3, 4, 5], &[6]]);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.upper_level, None);
+    assert_eq!(task.upper_level_sst_ids, vec![7, 8]);
+
+    // No level below the ratio.
+    let state = state_with_levels(&[7], &[&[1], &[2, 3], &[4, 5, 6, 8]]);
+    assert!(controller.generate_compaction_task(&state).is_none());
+}
@@ -0,0 +1,155 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::{check_lsm_iter_result_by_key, sync};
+use crate::{
+    compact::CompactionOptions,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+#[test]
+fn test_snapshot_ignores_flush_and_compaction() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    sync(&storage);
+    storage.put(b"c", b"1").unwrap();
+
+    let snapshot = storage.snapshot();
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.put(b"a", b"2").unwrap();
+    storage.delete(b"b").unwrap();
+    storage.put(b"d", b"2").unwrap();
+    sync(&storage);
+    sync(&storage);
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"2")));
+
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(snapshot.get(b"b").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(snapshot.get(b"c").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(snapshot.get(b"d").unwrap(), None);
+}
+
+#[test]
+fn test_snapshot_reads_removed_ssts() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    sync(&storage);
+    storage.put(b"c", b"1").unwrap();
+    let snapshot = storage.snapshot();
+    let sst_ids = snapshot_sst_ids(&storage);
+
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.put(b"a", b"2").unwrap();
+    sync(&storage);
+    storage.force_flush_next_imm_memtable().unwrap();
+    storage.force_full_compaction().unwrap();
+    // the snapshot keeps the files of the compacted SSTs
+    for id in &sst_ids {
+        assert!(storage.path_of_sst(*id).exists());
+    }
+
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    check_lsm_iter_result_by_key(
+        &mut snapshot.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"b"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"c"), Bytes::from_static(b"1")),
+        ],
+    );
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        vec![
+            (Bytes::from_static(b"a"), Bytes::from_static(b"2")),
+            (Bytes::from_static(b"b"), Bytes::from_static(b"1")),
+            (Bytes::from_static(b"c"), Bytes::from_static(b"1")),
+        ],
+    );
+
+    drop(snapshot);
+    for id in sst_ids {
+        assert!(!storage.path_of_sst(id).exists());
+    }
+}
+
+#[test]
+fn test_snapshot_ignores_writes_to_active_memtable() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction),
+    )
+    .unwrap();
+    storage.put(b"a", b"1").unwrap();
+    storage.put(b"b", b"1").unwrap();
+    let snapshot = storage.snapshot();
+
+    storage.put(b"a", b"2").unwrap();
+    storage.delete(b"b").unwrap();
+    storage.put(b"c", b"2").unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from_static(b"2")));
+    assert_eq!(storage.get(b"b").unwrap(), None);
+
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(snapshot.get(b"b").unwrap(), Some(Bytes::from_static(b"1")));
+    assert_eq!(snapshot.get(b"c").unwrap(), None);
+    let expected = vec![
+        (Bytes::from_static(b"a"), Bytes::from_static(b"1")),
+        (Bytes::from_static(b"b"), Bytes::from_static(b"1")),
+    ];
+    check_lsm_iter_result_by_key(
+        &mut snapshot.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        expected.clone(),
+    );
+    check_lsm_iter_result_by_key(
+        &mut snapshot
+            .scan_rev(Bound::Unbounded, Bound::Unbounded)
+            .unwrap(),
+        expected.into_iter().rev().collect(),
+    );
+
+    // the snapshot keeps reading the memtable after it is flushed
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    assert_eq!(snapshot.get(b"a").unwrap(), Some(Bytes::from_static(b"1")));
+}
+
+fn snapshot_sst_ids(storage: &LsmStorageInner) -> Vec<usize> {
+    storage.state.read().sstables.keys().copied().collect()
+}
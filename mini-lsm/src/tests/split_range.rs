@@ -0,0 +1,92 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+fn count_keys(storage: &MiniLsm, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> usize {
+    let mut iter = storage.scan(lower, upper).unwrap();
+    let mut cnt = 0;
+    while iter.is_valid() {
+        cnt += 1;
+        iter.next().unwrap();
+    }
+    cnt
+}
+
+#[test]
+fn test_split_range() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(
+        &dir,
+        LsmStorageOptions {
+            block_size: 256,
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    for round in 0..4 {
+        for i in 0..2500 {
+            storage
+                .put(format!("key_{:05}", i * 4 + round).as_bytes(), b"value")
+                .unwrap();
+        }
+        storage.force_flush().unwrap();
+    }
+
+    let splits = storage.split_range(Bound::Unbounded, Bound::Unbounded, 4);
+    assert_eq!(splits.len(), 3);
+    let mut bounds = vec![Bound::Unbounded];
+    bounds.extend(splits.iter().map(|key| Bound::Included(key.as_ref())));
+    bounds.push(Bound::Unbounded);
+    let mut total = 0;
+    for (idx, window) in bounds.windows(2).enumerate() {
+        let upper = match window[1] {
+            Bound::Included(key) => Bound::Excluded(key),
+            bound => bound,
+        };
+        let cnt = count_keys(&storage, window[0], upper);
+        assert!(
+            (2300..=2700).contains(&cnt),
+            "sub-range {} has {} keys, splits: {:?}",
+            idx,
+            cnt,
+            splits
+        );
+        total += cnt;
+    }
+    assert_eq!(total, 10000);
+
+    // The split keys stay within the range.
+    let splits = storage.split_range(
+        Bound::Excluded(b"key_02000"),
+        Bound::Included(b"key_04000"),
+        2,
+    );
+    assert_eq!(splits.len(), 1);
+    assert!(splits[0] > Bytes::from_static(b"key_02000"));
+    assert!(splits[0] <= Bytes::from_static(b"key_04000"));
+    assert!(
+        storage
+            .split_range(Bound::Unbounded, Bound::Unbounded, 1)
+            .is_empty()
+    );
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::{check_lsm_iter_result_by_key, sync};
+use crate::{
+    compact::{CompactionOptions, SimpleLeveledCompactionOptions},
+    iterators::StorageIterator,
+    key::KeySlice,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::{FileObject, SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:04}", idx).into_bytes()
+}
+
+fn value_of(idx: usize) -> Vec<u8> {
+    format!("value_{:04}", idx).into_bytes()
+}
+
+#[test]
+fn test_sst_with_different_block_sizes() {
+    let dir = tempdir().unwrap();
+    let mut num_of_blocks = Vec::new();
+    for (id, block_size) in [128, 1024, 16384].into_iter().enumerate() {
+        let mut builder = SsTableBuilder::new(block_size);
+        for idx in 0..500 {
+            builder.add(
+                KeySlice::for_testing_from_slice_no_ts(&key_of(idx)),
+                &value_of(idx),
+            );
+        }
+        let path = dir.path().join(format!("{id}.sst"));
+        drop(builder.build(id, None, &path).unwrap());
+
+        // The reader does not need to know the block size.
+        let sst = Arc::new(SsTable::open(id, None, FileObject::open(&path).unwrap()).unwrap());
+        num_of_blocks.push(sst.num_of_blocks());
+        let mut iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+        for idx in 0..500 {
+            assert!(iter.is_valid());
+            assert_eq!(iter.key().for_testing_key_ref(), key_of(idx).as_slice());
+            assert_eq!(iter.value(), value_of(idx).as_slice());
+            iter.next().unwrap();
+        }
+        assert!(!iter.is_valid());
+    }
+    assert!(num_of_blocks[0] > num_of_blocks[1]);
+    assert!(num_of_blocks[1] > num_of_blocks[2]);
+    assert_eq!(num_of_blocks[2], 1);
+}
+
+#[test]
+fn test_compaction_level_block_size() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        block_size: 128,
+        level_block_sizes: vec![8192],
+        ..LsmStorageOptions::default_for_week2_test(CompactionOptions::Simple(
+            SimpleLeveledCompactionOptions {
+                size_ratio_percent: 200,
+                level0_file_num_compaction_trigger: 1,
+                max_levels: 2,
+            },
+        ))
+    };
+    assert_eq!(options.block_size_for_level(None), 128);
+    assert_eq!(options.block_size_for_level(Some(1)), 8192);
+    assert_eq!(options.block_size_for_level(Some(2)), 128);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    for idx in 0..100 {
+        storage.put(&key_of(idx), &value_of(idx)).unwrap();
+    }
+    sync(&storage);
+    let l0_blocks = {
+        let state = storage.state.read();
+        state.sstables[&state.l0_sstables[0]].num_of_blocks()
+    };
+
+    // L0 -> L1
+    storage.trigger_compaction().unwrap();
+    let l1_blocks = {
+        let state = storage.state.read();
+        assert!(state.l0_sstables.is_empty());
+        assert_eq!(state.levels[0].1.len(), 1);
+        state.sstables[&state.levels[0].1[0]].num_of_blocks()
+    };
+    assert!(l0_blocks > 1);
+    assert_eq!(l1_blocks, 1);
+    check_lsm_iter_result_by_key(
+        &mut storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap(),
+        (0..100)
+            .map(|idx| (Bytes::from(key_of(idx)), Bytes::from(value_of(idx))))
+            .collect(),
+    );
+}
@@ -0,0 +1,72 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::{
+    iterators::StorageIterator,
+    key::KeySlice,
+    lsm_storage::BlockCache,
+    table::{SsTableBuilder, SsTableIterator},
+};
+
+#[test]
+fn test_sst_blocks() {
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(128);
+    for i in 0..200 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:03}", i).as_bytes()),
+            format!("value_{:03}", i).as_bytes(),
+        );
+    }
+    let sst = Arc::new(
+        builder
+            .build(
+                1,
+                Some(Arc::new(BlockCache::new(128))),
+                dir.path().join("1.sst"),
+            )
+            .unwrap(),
+    );
+
+    let mut num_of_blocks = 0;
+    let mut num_of_entries = 0;
+    let mut keys = Vec::new();
+    for block in sst.blocks() {
+        let block = block.unwrap();
+        num_of_blocks += 1;
+        num_of_entries += block.num_of_entries();
+        let mut iter = block.iter();
+        while iter.is_valid() {
+            keys.push(iter.key().for_testing_key_ref().to_vec());
+            iter.next();
+        }
+    }
+    assert!(num_of_blocks > 1);
+    assert_eq!(num_of_blocks, sst.num_of_blocks());
+
+    let mut num_of_keys = 0;
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+    while iter.is_valid() {
+        assert_eq!(keys[num_of_keys], iter.key().for_testing_key_ref());
+        num_of_keys += 1;
+        iter.next().unwrap();
+    }
+    assert_eq!(num_of_keys, 200);
+    assert_eq!(num_of_entries, num_of_keys);
+    assert_eq!(keys.len(), num_of_keys);
+}
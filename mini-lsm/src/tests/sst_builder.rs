@@ -0,0 +1,67 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::{key::KeySlice, table::SsTableBuilder};
+
+fn add(builder: &mut SsTableBuilder, key: &str) {
+    builder.add(
+        KeySlice::for_testing_from_slice_no_ts(key.as_bytes()),
+        b"value",
+    );
+}
+
+#[test]
+fn test_sst_builder_increasing_keys() {
+    let dir = tempdir().unwrap();
+    // small blocks so that the order is also checked across blocks
+    let mut builder = SsTableBuilder::new(32);
+    for i in 0..100 {
+        add(&mut builder, &format!("key_{:03}", i));
+    }
+    let sst = builder.build_for_test(dir.path().join("1.sst")).unwrap();
+    assert!(sst.num_of_blocks() > 1);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "SST keys out of order")]
+fn test_sst_builder_duplicate_key() {
+    let mut builder = SsTableBuilder::new(32);
+    for i in 0..10 {
+        add(&mut builder, &format!("key_{:03}", i));
+    }
+    add(&mut builder, "key_009");
+}
+
+#[test]
+#[cfg(not(debug_assertions))]
+fn test_sst_builder_out_of_order_key() {
+    use crate::error::LsmError;
+
+    let dir = tempdir().unwrap();
+    let mut builder = SsTableBuilder::new(32);
+    for i in 0..10 {
+        add(&mut builder, &format!("key_{:03}", i));
+    }
+    add(&mut builder, "key_005");
+    let Err(err) = builder.build_for_test(dir.path().join("1.sst")) else {
+        panic!("expect an error");
+    };
+    assert!(matches!(
+        err.downcast_ref::<LsmError>(),
+        Some(LsmError::Internal(_))
+    ));
+}
@@ -0,0 +1,69 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::CompactionOptions,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+};
+
+fn count_ssts(path: &Path) -> usize {
+    std::fs::read_dir(path)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "sst")
+        })
+        .count()
+}
+
+#[test]
+fn test_full_compaction_removes_files() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week2_test(CompactionOptions::NoCompaction);
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    for i in 0..4 {
+        storage
+            .put(format!("key_{i}").as_bytes(), b"value")
+            .unwrap();
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    assert_eq!(count_ssts(dir.path()), 4);
+    storage.force_full_compaction().unwrap();
+    assert_eq!(count_ssts(dir.path()), 1);
+
+    // A file the manifest does not know about, e.g., left behind by a crash before it was
+    // deleted, is removed on open.
+    let live_sst = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "sst"))
+        .unwrap();
+    std::fs::copy(&live_sst, dir.path().join("00001.sst")).unwrap();
+    drop(storage);
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    assert_eq!(count_ssts(dir.path()), 1);
+    assert!(live_sst.exists());
+    assert_eq!(&storage.get(b"key_2").unwrap().unwrap()[..], b"value");
+}
@@ -0,0 +1,211 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::{
+    error::LsmError,
+    iterators::StorageIterator,
+    key::{KeyBytes, KeySlice},
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    table::{BlockMeta, FileObject, SsTable, SsTableBuilder, SsTableIterator},
+};
+
+fn encoded_block_meta() -> Vec<u8> {
+    let block_meta = vec![
+        BlockMeta {
+            offset: 0,
+            first_key: KeyBytes::for_testing_from_bytes_no_ts("aaa".into()),
+            last_key: KeyBytes::for_testing_from_bytes_no_ts("bbb".into()),
+        },
+        BlockMeta {
+            offset: 100,
+            first_key: KeyBytes::for_testing_from_bytes_no_ts("ccc".into()),
+            last_key: KeyBytes::for_testing_from_bytes_no_ts("ddd".into()),
+        },
+    ];
+    let mut buf = Vec::new();
+    BlockMeta::encode_block_meta(&block_meta, &mut buf);
+    assert_eq!(BlockMeta::decode_block_meta(&buf).unwrap(), block_meta);
+    buf
+}
+
+#[test]
+fn test_decode_block_meta_key_length_overflow() {
+    let mut buf = encoded_block_meta();
+    // the first key length of the second entry: num (4) + entry 1 (4 + 2 + 3 + 2 + 3) + offset (4)
+    let pos = 4 + 14 + 4;
+    assert_eq!(&buf[pos..pos + 2], &[0, 3]);
+    buf[pos..pos + 2].copy_from_slice(&u16::MAX.to_be_bytes());
+    assert!(BlockMeta::decode_block_meta(&buf).is_err());
+
+    // more entries declared than encoded
+    let mut buf = encoded_block_meta();
+    buf[..4].copy_from_slice(&3u32.to_be_bytes());
+    assert!(BlockMeta::decode_block_meta(&buf).is_err());
+}
+
+#[test]
+fn test_decode_block_meta_truncated() {
+    let buf = encoded_block_meta();
+    for len in 0..buf.len() {
+        assert!(BlockMeta::decode_block_meta(&buf[..len]).is_err());
+    }
+}
+
+#[test]
+fn test_open_corrupted_sst() {
+    let dir = tempdir().unwrap();
+    for (idx, data) in [
+        vec![],
+        vec![0, 0, 1],
+        vec![0xff; 64],
+        [vec![0; 60], 8u32.to_be_bytes().to_vec()].concat(),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let path = dir.path().join(format!("{}.sst", idx));
+        std::fs::write(&path, data).unwrap();
+        assert!(SsTable::open(idx, None, FileObject::open(&path).unwrap()).is_err());
+    }
+}
+
+#[test]
+fn test_open_truncated_sst() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    std::fs::write(&path, [0, 1]).unwrap();
+    let Err(err) = SsTable::open(1, None, FileObject::open(&path).unwrap()) else {
+        panic!("expect opening a 2-byte SST to fail");
+    };
+    assert_eq!(err.to_string(), "corrupted SST: file too small");
+}
+
+#[test]
+fn test_recover_truncated_sst() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage.put(b"key", b"value").unwrap();
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    let sst_id = storage.state.read().l0_sstables[0];
+    let sst_path = storage.path_of_sst(sst_id);
+    drop(storage);
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&sst_path)
+        .unwrap()
+        .set_len(2)
+        .unwrap();
+    let Err(err) = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()) else {
+        panic!("expect recovering a truncated SST to fail");
+    };
+    assert_eq!(
+        format!("{:#}", err),
+        format!(
+            "failed to open SST: {}: corrupted SST: file too small",
+            sst_id
+        )
+    );
+}
+
+#[test]
+fn test_corrupted_block_data() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.sst");
+    let mut builder = SsTableBuilder::new(128);
+    for idx in 0..100 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:03}", idx).as_bytes()),
+            format!("value_{:03}", idx).as_bytes(),
+        );
+    }
+    let sst = builder.build_for_test(&path).unwrap();
+    assert!(sst.num_of_blocks() > 2);
+    let corrupted_offset = sst.block_meta().unwrap()[1].offset + 3;
+    let corrupted_key = sst.block_meta().unwrap()[1].first_key.raw_ref().to_vec();
+    drop(sst);
+
+    let mut data = std::fs::read(&path).unwrap();
+    data[corrupted_offset] ^= 0x01;
+    std::fs::write(&path, data).unwrap();
+
+    let sst = Arc::new(SsTable::open(1, None, FileObject::open(&path).unwrap()).unwrap());
+    assert!(sst.read_block(0).is_ok());
+    assert!(sst.read_block(1).is_err());
+    assert!(
+        SsTableIterator::create_and_seek_to_key(
+            sst.clone(),
+            KeySlice::for_testing_from_slice_no_ts(&corrupted_key)
+        )
+        .is_err()
+    );
+    // Iterating stops with an error at the corrupted block instead of returning garbage.
+    let mut iter = SsTableIterator::create_and_seek_to_first(sst).unwrap();
+    let error = loop {
+        assert!(iter.key().raw_ref() < &corrupted_key[..]);
+        if let Err(e) = iter.next() {
+            break e;
+        }
+    };
+    assert!(error.to_string().contains("checksum"));
+}
+
+#[test]
+fn test_corrupted_sst_footer() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("7.sst");
+    let mut builder = SsTableBuilder::new(128);
+    builder.set_bloom_bits_per_key(0);
+    builder.set_block_index(false);
+    for idx in 0..100 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:03}", idx).as_bytes()),
+            b"value",
+        );
+    }
+    let sst = builder.build_for_test(&path).unwrap();
+    let meta_offset = sst.block_meta_offset as u32;
+    drop(sst);
+    let data = std::fs::read(&path).unwrap();
+    // | meta offset (u32) | bloom offset (u32, 0) | properties (57) | offset | version | magic |
+    let meta_offset_pos = data.len() - 12 - 57 - 8;
+    assert_eq!(
+        data[meta_offset_pos..meta_offset_pos + 4],
+        meta_offset.to_be_bytes()
+    );
+
+    let properties_pos = data.len() - 12 - 57;
+    for pos in [
+        meta_offset_pos + 3,
+        meta_offset_pos + 4,
+        properties_pos + 44,
+    ] {
+        let mut corrupted = data.clone();
+        corrupted[pos] ^= 0x01;
+        std::fs::write(&path, corrupted).unwrap();
+        let Err(err) = SsTable::open(7, None, FileObject::open(&path).unwrap()) else {
+            panic!("expect opening an SST with a corrupted footer to fail");
+        };
+        let err = LsmError::from(err);
+        assert!(err.is_corruption());
+        assert!(err.to_string().contains("SST 7"), "{}", err);
+    }
+}
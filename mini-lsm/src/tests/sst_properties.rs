@@ -0,0 +1,74 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Buf;
+use tempfile::tempdir;
+
+use crate::{
+    key::KeySlice,
+    table::{FileObject, SsTable, SsTableBuilder},
+};
+
+fn build_sst(path: &std::path::Path) -> SsTable {
+    let mut builder = SsTableBuilder::new(128);
+    for i in 0..100 {
+        builder.add(
+            KeySlice::for_testing_from_slice_no_ts(format!("key_{:03}", i).as_bytes()),
+            format!("value_{:03}", i).as_bytes(),
+        );
+    }
+    builder.set_epoch(3);
+    builder.build(5, None, path).unwrap()
+}
+
+#[test]
+fn test_sst_created_at() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("5.sst");
+    let before = SystemTime::now();
+    let sst = build_sst(&path);
+    let after = SystemTime::now();
+    drop(sst);
+
+    let sst = SsTable::open(5, None, FileObject::open(&path).unwrap()).unwrap();
+    // the creation time is stored in milliseconds
+    assert!(sst.created_at() + Duration::from_millis(1) >= before);
+    assert!(sst.created_at() <= after);
+    assert_eq!(sst.epoch(), 3);
+}
+
+#[test]
+fn test_sst_open_without_properties() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("5.sst");
+    let sst = build_sst(&path);
+    drop(sst);
+
+    // Strip the block index and the properties footer to get the layout written by older
+    // versions.
+    let data = std::fs::read(&path).unwrap();
+    let properties_offset = (&data[data.len() - 12..]).get_u32() as usize;
+    let index_offset = (&data[properties_offset + 8 + 8 + 1..]).get_u64() as usize;
+    let legacy_path = dir.path().join("6.sst");
+    std::fs::write(&legacy_path, &data[..index_offset]).unwrap();
+
+    let sst = SsTable::open(6, None, FileObject::open(&legacy_path).unwrap()).unwrap();
+    assert_eq!(sst.created_at(), UNIX_EPOCH);
+    assert_eq!(sst.epoch(), 6);
+    assert!(sst.read_block(sst.num_of_blocks() - 1).is_ok());
+    assert_eq!(sst.first_key().raw_ref(), b"key_000");
+    assert_eq!(sst.last_key().raw_ref(), b"key_099");
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use tempfile::tempdir;
+
+use crate::{
+    compact::{CompactionOptions, TieredCompactionController, TieredCompactionOptions},
+    error::LsmError,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions, LsmStorageState},
+    mem_table::MemTable,
+};
+
+fn tiers_state(tiers: Vec<(usize, Vec<usize>)>) -> LsmStorageState {
+    LsmStorageState {
+        memtable: Arc::new(MemTable::create(0)),
+        imm_memtables: Vec::new(),
+        l0_sstables: Vec::new(),
+        levels: tiers,
+        sstables: Default::default(),
+    }
+}
+
+#[test]
+fn test_tiered_runtime_size_amplification() {
+    let controller = TieredCompactionController::new(TieredCompactionOptions {
+        num_tiers: 3,
+        max_size_amplification_percent: 200,
+        size_ratio: 1000,
+        min_merge_width: 2,
+        max_merge_width: Some(2),
+    });
+    let state = tiers_state(vec![(5, vec![5]), (4, vec![4]), (1, vec![1, 2, 3])]);
+
+    // space amplification is 2 / 3, so only the two upper tiers are merged
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.tiers.len(), 2);
+    assert!(!task.bottom_tier_included);
+
+    controller.set_max_size_amplification_percent(50);
+    assert_eq!(controller.max_size_amplification_percent(), 50);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.tiers, state.levels);
+    assert!(task.bottom_tier_included);
+}
+
+#[test]
+fn test_tiered_set_size_amplification_on_open_storage() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(
+        &dir,
+        LsmStorageOptions {
+            compaction_options: CompactionOptions::Tiered(TieredCompactionOptions {
+                num_tiers: 3,
+                max_size_amplification_percent: 1000,
+                size_ratio: 1000,
+                min_merge_width: 2,
+                max_merge_width: Some(2),
+            }),
+            ..LsmStorageOptions::default_for_week1_test()
+        },
+    )
+    .unwrap();
+    for i in 0..3 {
+        storage
+            .put(format!("key_{}", i).as_bytes(), b"value")
+            .unwrap();
+        storage
+            .force_freeze_memtable(&storage.state_lock.lock())
+            .unwrap();
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    assert_eq!(storage.state.read().levels.len(), 3);
+
+    // space amplification is 2 / 1, so all tiers are merged once the limit is lowered
+    storage.set_max_size_amplification_percent(200).unwrap();
+    assert!(storage.run_one_compaction().unwrap());
+    assert_eq!(storage.state.read().levels.len(), 1);
+    for i in 0..3 {
+        assert_eq!(
+            &storage
+                .get(format!("key_{}", i).as_bytes())
+                .unwrap()
+                .unwrap()[..],
+            b"value"
+        );
+    }
+
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let err = storage.set_max_size_amplification_percent(200).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<LsmError>(),
+        Some(LsmError::InvalidOptions(_))
+    ));
+}
+
+#[test]
+fn test_tiered_full_compaction_on_demand() {
+    let controller = TieredCompactionController::new(TieredCompactionOptions {
+        num_tiers: 10,
+        max_size_amplification_percent: 200,
+        size_ratio: 1,
+        min_merge_width: 2,
+        max_merge_width: None,
+    });
+    let state = tiers_state(vec![(4, vec![4]), (1, vec![1, 2, 3])]);
+    assert!(controller.generate_compaction_task(&state).is_none());
+    let task = controller.generate_full_compaction_task(&state).unwrap();
+    assert_eq!(task.tiers, state.levels);
+    assert!(task.bottom_tier_included);
+
+    let state = tiers_state(vec![(1, vec![1, 2, 3])]);
+    assert!(controller.generate_full_compaction_task(&state).is_none());
+}
+
+#[test]
+fn test_tiered_apply_after_flush() {
+    let controller = TieredCompactionController::new(TieredCompactionOptions {
+        num_tiers: 3,
+        max_size_amplification_percent: 200,
+        size_ratio: 1000,
+        min_merge_width: 2,
+        max_merge_width: Some(2),
+    });
+    let state = tiers_state(vec![(5, vec![5]), (4, vec![4]), (1, vec![1, 2, 3])]);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    assert_eq!(task.tiers, vec![(5, vec![5]), (4, vec![4])]);
+
+    // A tier is flushed while the task runs.
+    let mut state = state;
+    state.levels.insert(0, (6, vec![6]));
+    let (new_state, mut removed) = controller.apply_compaction_result(&state, &task, &[7, 8]);
+    removed.sort();
+    assert_eq!(removed, vec![4, 5]);
+    assert_eq!(
+        new_state.levels,
+        vec![(6, vec![6]), (7, vec![7, 8]), (1, vec![1, 2, 3])]
+    );
+
+    // The compacted tiers are dropped entirely, e.g. as they only held deletes.
+    let (new_state, _) = controller.apply_compaction_result(&state, &task, &[]);
+    assert_eq!(new_state.levels, vec![(6, vec![6]), (1, vec![1, 2, 3])]);
+}
+
+#[test]
+#[should_panic(expected = "tiers [4] of the compaction task not found")]
+fn test_tiered_apply_missing_tier() {
+    let controller = TieredCompactionController::new(TieredCompactionOptions {
+        num_tiers: 3,
+        max_size_amplification_percent: 200,
+        size_ratio: 1000,
+        min_merge_width: 2,
+        max_merge_width: Some(2),
+    });
+    let state = tiers_state(vec![(5, vec![5]), (4, vec![4]), (1, vec![1, 2, 3])]);
+    let task = controller.generate_compaction_task(&state).unwrap();
+    let state = tiers_state(vec![(5, vec![5]), (1, vec![1, 2, 3])]);
+    controller.apply_compaction_result(&state, &task, &[7]);
+}
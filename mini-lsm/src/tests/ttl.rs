@@ -0,0 +1,162 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tempfile::tempdir;
+
+use super::harness::check_lsm_iter_result_by_key;
+use crate::{
+    iterators::StorageIterator,
+    key::KeySlice,
+    lsm_storage::{LsmStorageInner, LsmStorageOptions},
+    manifest::{Manifest, ManifestRecord},
+    mem_table::MemTable,
+    table::{SsTableBuilder, SsTableIterator},
+};
+
+fn check_scan(storage: &LsmStorageInner, expected: Vec<(&'static str, &'static [u8])>) {
+    let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+    check_lsm_iter_result_by_key(
+        &mut iter,
+        expected
+            .into_iter()
+            .map(|(key, value)| (Bytes::from(key), Bytes::from(value)))
+            .collect(),
+    );
+}
+
+#[test]
+fn test_put_with_ttl() {
+    let dir = tempdir().unwrap();
+    let storage = LsmStorageInner::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    storage
+        .put_with_ttl(b"a", b"1", Duration::from_secs(3600))
+        .unwrap();
+    storage
+        .put_with_ttl(b"b", b"2", Duration::from_millis(100))
+        .unwrap();
+    // A put without TTL clears the expiry.
+    storage
+        .put_with_ttl(b"c", b"3", Duration::from_millis(100))
+        .unwrap();
+    storage.put(b"c", b"33").unwrap();
+    // A plain value looking like an encoded one is read back as is.
+    storage.put(b"d", b"\xff\x01").unwrap();
+    check_scan(
+        &storage,
+        vec![("a", b"1"), ("b", b"2"), ("c", b"33"), ("d", b"\xff\x01")],
+    );
+    assert_eq!(storage.get(b"b").unwrap(), Some(Bytes::from("2")));
+
+    std::thread::sleep(Duration::from_millis(200));
+    let expected = vec![("a", &b"1"[..]), ("c", b"33"), ("d", b"\xff\x01")];
+    assert_eq!(storage.get(b"a").unwrap(), Some(Bytes::from("1")));
+    assert_eq!(storage.get(b"b").unwrap(), None);
+    assert_eq!(
+        storage.get_batch(&[b"b", b"c"]).unwrap(),
+        vec![None, Some(Bytes::from("33"))]
+    );
+    check_scan(&storage, expected.clone());
+
+    storage
+        .force_freeze_memtable(&storage.state_lock.lock())
+        .unwrap();
+    storage.force_flush_next_imm_memtable().unwrap();
+    assert_eq!(storage.get(b"b").unwrap(), None);
+    check_scan(&storage, expected.clone());
+
+    // The expired key is physically dropped by the compaction to the bottom level.
+    storage.force_full_compaction().unwrap();
+    let snapshot = storage.state.read().clone();
+    assert_eq!(snapshot.levels[0].1.len(), 1);
+    let mut iter = SsTableIterator::create_and_seek_to_first(
+        snapshot.sstables[&snapshot.levels[0].1[0]].clone(),
+    )
+    .unwrap();
+    let mut keys = Vec::new();
+    while iter.is_valid() {
+        keys.push(Bytes::copy_from_slice(iter.key().raw_ref()));
+        iter.next().unwrap();
+    }
+    assert_eq!(
+        keys,
+        vec![Bytes::from("a"), Bytes::from("c"), Bytes::from("d")]
+    );
+    check_scan(&storage, expected);
+}
+
+#[test]
+fn test_read_values_of_old_format() {
+    let dir = tempdir().unwrap();
+    // A value that reads as expired if it is taken for an encoded one.
+    let raw_value = b"\xff\x01\0\0\0\0\0\0\0\x01";
+
+    // An SST written before values were encoded, marked by properties version 7.
+    let mut builder = SsTableBuilder::new(4096);
+    builder.add(KeySlice::for_testing_from_slice_no_ts(b"sst"), raw_value);
+    let sst_path = LsmStorageInner::path_of_sst_static(&dir, 1);
+    builder.build(1, None, &sst_path).unwrap();
+    let mut sst = std::fs::read(&sst_path).unwrap();
+    let version_offset = sst.len() - 8;
+    sst[version_offset..version_offset + 4].copy_from_slice(&7u32.to_be_bytes());
+    std::fs::write(&sst_path, sst).unwrap();
+
+    // A WAL and a manifest written before values were encoded.
+    let memtable =
+        MemTable::create_with_wal(2, LsmStorageInner::path_of_wal_static(&dir, 2), 0).unwrap();
+    memtable.put(b"wal", raw_value).unwrap();
+    memtable.sync_wal().unwrap();
+    let manifest = Manifest::create(dir.path().join("MANIFEST")).unwrap();
+    for record in [
+        ManifestRecord::NewMemtable(1),
+        ManifestRecord::Flush(1),
+        ManifestRecord::NewMemtable(2),
+    ] {
+        manifest.add_record_when_init(record).unwrap();
+    }
+    drop(manifest);
+
+    let mut options = LsmStorageOptions::default_for_week1_test();
+    options.enable_wal = true;
+    let expected = vec![
+        ("new", &raw_value[..]),
+        ("sst", raw_value),
+        ("wal", raw_value),
+    ];
+    let storage = LsmStorageInner::open(&dir, options.clone()).unwrap();
+    storage.put(b"new", raw_value).unwrap();
+    assert_eq!(
+        storage.get(b"sst").unwrap(),
+        Some(Bytes::from(&raw_value[..]))
+    );
+    assert_eq!(
+        storage.get(b"wal").unwrap(),
+        Some(Bytes::from(&raw_value[..]))
+    );
+    check_scan(&storage, expected.clone());
+    storage.sync().unwrap();
+    drop(storage);
+
+    // The WALs written since the first open are encoded, while the old one still is not.
+    let storage = LsmStorageInner::open(&dir, options).unwrap();
+    check_scan(&storage, expected.clone());
+    while !storage.state.read().imm_memtables.is_empty() {
+        storage.force_flush_next_imm_memtable().unwrap();
+    }
+    storage.force_full_compaction().unwrap();
+    check_scan(&storage, expected);
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error::Error;
+
+use tempfile::tempdir;
+
+use crate::{
+    error::LsmError,
+    lsm_storage::{LsmStorageOptions, MiniLsm},
+};
+
+#[test]
+fn test_block_checksum_error() {
+    let dir = tempdir().unwrap();
+    let options = LsmStorageOptions::default_for_week1_test();
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.put(b"key", b"value").unwrap();
+    storage.force_flush().unwrap();
+    let sst_id = storage.all_sst_ids()[0];
+    storage.close().unwrap();
+    drop(storage);
+
+    let path = dir.path().join(format!("{:05}.sst", sst_id));
+    let mut data = std::fs::read(&path).unwrap();
+    data[3] ^= 0x01;
+    std::fs::write(&path, data).unwrap();
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    let err = storage.get(b"key").unwrap_err();
+    match &err {
+        LsmError::BlockChecksum {
+            sst_id: id,
+            block_idx: 0,
+        } if *id == sst_id => {}
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert!(err.is_corruption());
+}
+
+#[test]
+fn test_io_error_source() {
+    let dir = tempdir().unwrap();
+    let storage = MiniLsm::open(&dir, LsmStorageOptions::default_for_week1_test()).unwrap();
+    let err = storage
+        .ingest_sst(&dir.path().join("missing.sst"))
+        .unwrap_err();
+    assert!(matches!(err, LsmError::Io(_)));
+    assert!(!err.is_corruption());
+    let source = err.source().unwrap().downcast_ref::<std::io::Error>();
+    assert_eq!(source.unwrap().kind(), std::io::ErrorKind::NotFound);
+}
@@ -0,0 +1,44 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::mem_table::MemTable;
+
+/// Put about 2 KiB of data into a memtable with WAL, and return the size of the WAL file before
+/// and after the sync.
+fn wal_size_before_and_after_sync(wal_buffer_size: usize) -> (u64, u64) {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.wal");
+    let memtable = MemTable::create_with_wal(1, &path, wal_buffer_size).unwrap();
+    for i in 0..100 {
+        memtable
+            .for_testing_put_slice(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    let before_sync = std::fs::metadata(&path).unwrap().len();
+    memtable.sync_wal().unwrap();
+    let after_sync = std::fs::metadata(&path).unwrap().len();
+    (before_sync, after_sync)
+}
+
+#[test]
+fn test_wal_buffer_size() {
+    let (small_before_sync, small_after_sync) = wal_size_before_and_after_sync(64);
+    let (large_before_sync, large_after_sync) = wal_size_before_and_after_sync(1 << 20);
+    // A small buffer writes to the file as the buffer fills up, a large one only on sync.
+    assert!(small_before_sync > 0);
+    assert_eq!(large_before_sync, 0);
+    assert_eq!(small_after_sync, large_after_sync);
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use tempfile::tempdir;
+
+use crate::lsm_storage::{LsmStorageOptions, MiniLsm};
+
+fn count_files_with_extension(dir: &Path, extension: &str) -> usize {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|x| x == extension)
+        })
+        .count()
+}
+
+#[test]
+fn test_separate_wal_dir() {
+    let dir = tempdir().unwrap();
+    let wal_dir = tempdir().unwrap();
+    let options = LsmStorageOptions {
+        enable_wal: true,
+        wal_dir: Some(wal_dir.path().join("wal")),
+        ..LsmStorageOptions::default_for_week1_test()
+    };
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    for i in 0..100 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.force_flush().unwrap();
+    for i in 100..200 {
+        storage
+            .put(format!("key_{:03}", i).as_bytes(), b"value")
+            .unwrap();
+    }
+    storage.close().unwrap();
+    drop(storage);
+
+    assert_eq!(count_files_with_extension(dir.path(), "wal"), 0);
+    assert_eq!(count_files_with_extension(dir.path(), "sst"), 1);
+    assert!(count_files_with_extension(&wal_dir.path().join("wal"), "wal") > 0);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    for i in 0..200 {
+        assert_eq!(
+            &storage
+                .get(format!("key_{:03}", i).as_bytes())
+                .unwrap()
+                .unwrap()[..],
+            b"value"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use tempfile::tempdir;
+
+use crate::mem_table::MemTable;
+
+fn key_of(idx: usize) -> Vec<u8> {
+    format!("key_{:03}", idx).into_bytes()
+}
+
+#[test]
+fn test_wal_truncated_record() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.wal");
+    let memtable = MemTable::create_with_wal(1, &path, 0).unwrap();
+    for idx in 0..10 {
+        memtable
+            .for_testing_put_slice(&key_of(idx), b"value")
+            .unwrap();
+    }
+    memtable.sync_wal().unwrap();
+    drop(memtable);
+
+    // Each record is key_len (2) + key (7) + value_len (2) + value (5) + checksum (4) bytes.
+    let record_len = 2 + 7 + 2 + 5 + 4;
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(data.len(), record_len * 10);
+    // Cut the last record at every possible position.
+    for len in record_len * 9..record_len * 10 {
+        std::fs::write(&path, &data[..len]).unwrap();
+        let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+        for idx in 0..9 {
+            assert_eq!(
+                &memtable.for_testing_get_slice(&key_of(idx)).unwrap()[..],
+                b"value"
+            );
+        }
+        assert_eq!(memtable.for_testing_get_slice(&key_of(9)), None);
+        // The torn record is removed, so that records written afterwards are recovered.
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            record_len as u64 * 9
+        );
+    }
+
+    let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+    memtable
+        .for_testing_put_slice(&key_of(9), b"value")
+        .unwrap();
+    memtable.sync_wal().unwrap();
+    drop(memtable);
+    let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+    assert_eq!(
+        &memtable.for_testing_get_slice(&key_of(9)).unwrap()[..],
+        b"value"
+    );
+}
+
+#[test]
+fn test_wal_corrupted_record() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("1.wal");
+    let memtable = MemTable::create_with_wal(1, &path, 0).unwrap();
+    for idx in 0..3 {
+        memtable
+            .for_testing_put_slice(&key_of(idx), b"value")
+            .unwrap();
+    }
+    memtable.sync_wal().unwrap();
+    drop(memtable);
+
+    // Flip a bit in the value of the last record.
+    let mut data = std::fs::read(&path).unwrap();
+    let len = data.len();
+    data[len - 6] ^= 1;
+    std::fs::write(&path, &data).unwrap();
+    let memtable = MemTable::recover_from_wal(1, &path, 0).unwrap();
+    assert!(memtable.for_testing_get_slice(&key_of(1)).is_some());
+    assert_eq!(memtable.for_testing_get_slice(&key_of(2)), None);
+}
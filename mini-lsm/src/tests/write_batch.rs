@@ -0,0 +1,94 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Bound;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tempfile::tempdir;
+
+use crate::iterators::StorageIterator;
+use crate::lsm_storage::{LsmStorageInner, LsmStorageOptions, MiniLsm, WriteBatchRecord};
+
+#[test]
+fn test_write_batch_atomic() {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week1_test();
+    options.target_sst_size = 1 << 30;
+    let storage = Arc::new(LsmStorageInner::open(&dir, options).unwrap());
+    storage.put(b"b", b"0").unwrap();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let storage = storage.clone();
+        let stop = stop.clone();
+        std::thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                // Exactly one of the two keys exists after every batch.
+                let memtable = storage.state.read().memtable.clone();
+                let seq = memtable.visible_seq();
+                let a = memtable.get_at(b"a", seq).filter(|value| !value.is_empty());
+                let b = memtable.get_at(b"b", seq).filter(|value| !value.is_empty());
+                assert!(a.is_some() != b.is_some(), "saw half a batch: {a:?} {b:?}");
+                let mut iter = storage.scan(Bound::Unbounded, Bound::Unbounded).unwrap();
+                let mut keys = Vec::new();
+                while iter.is_valid() {
+                    keys.push(iter.key().to_vec());
+                    iter.next().unwrap();
+                }
+                assert_eq!(keys.len(), 1, "scan saw half a batch: {keys:?}");
+            }
+        })
+    };
+    for i in 0..1000 {
+        let value = format!("{i}");
+        storage
+            .write_batch(&[
+                WriteBatchRecord::Put(b"a".as_slice(), value.as_bytes()),
+                WriteBatchRecord::Del(b"b".as_slice()),
+            ])
+            .unwrap();
+        storage
+            .write_batch(&[
+                WriteBatchRecord::Del(b"a".as_slice()),
+                WriteBatchRecord::Put(b"b".as_slice(), value.as_bytes()),
+            ])
+            .unwrap();
+    }
+    stop.store(true, Ordering::Relaxed);
+    reader.join().unwrap();
+    assert_eq!(storage.get(b"a").unwrap(), None);
+    assert_eq!(&storage.get(b"b").unwrap().unwrap()[..], b"999");
+}
+
+#[test]
+fn test_write_batch_wal_recovery() {
+    let dir = tempdir().unwrap();
+    let mut options = LsmStorageOptions::default_for_week1_test();
+    options.enable_wal = true;
+    let storage = MiniLsm::open(&dir, options.clone()).unwrap();
+    storage.put(b"b", b"0").unwrap();
+    storage
+        .write_batch(&[
+            WriteBatchRecord::Put(b"a".as_slice(), b"1".as_slice()),
+            WriteBatchRecord::Del(b"b".as_slice()),
+        ])
+        .unwrap();
+    storage.sync().unwrap();
+    drop(storage);
+
+    let storage = MiniLsm::open(&dir, options).unwrap();
+    assert_eq!(&storage.get(b"a").unwrap().unwrap()[..], b"1");
+    assert_eq!(storage.get(b"b").unwrap(), None);
+}
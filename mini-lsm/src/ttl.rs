@@ -0,0 +1,125 @@
+// Copyright (c) 2022-2025 Alex Chi Z
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encoding of the values stored in the memtables and SSTs, which may carry an expiry time.
+//!
+//! A value starting with [`VALUE_HEADER`] is followed by a tag: [`TAG_PLAIN`] escapes a user
+//! value which itself starts with the header byte, and [`TAG_EXPIRING`] is followed by the expiry
+//! time as milliseconds since the Unix epoch (u64) and then the user value. Any other value is
+//! stored as is, so that databases written before expiry support read the same, except for values
+//! starting with the header byte.
+//!
+//! Within the data blocks of an SST, [`TAG_BLOB`] replaces a stored value larger than
+//! `large_value_threshold` with the offset and length of the value in the blob section of the SST,
+//! which [`crate::table::SsTableIterator`] resolves before anything else sees the value.
+
+use std::borrow::Cow;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::{Buf, BufMut, Bytes};
+
+const VALUE_HEADER: u8 = 0xff;
+const TAG_PLAIN: u8 = 0;
+const TAG_EXPIRING: u8 = 1;
+const TAG_BLOB: u8 = 2;
+const EXPIRING_HEADER_LEN: usize = 2 + std::mem::size_of::<u64>();
+const BLOB_REF_LEN: usize = 2 + 2 * std::mem::size_of::<u64>();
+
+/// Milliseconds since the Unix epoch, the clock expiry times are compared against.
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis() as u64)
+}
+
+/// The expiry time of a value written now to live for `ttl`.
+pub(crate) fn expire_at(ttl: Duration) -> u64 {
+    now_millis().saturating_add(ttl.as_millis().min(u64::MAX as u128) as u64)
+}
+
+/// Encode a user value without expiry. A delete, i.e. an empty value, is stored as is.
+pub(crate) fn encode_value(value: &[u8]) -> Cow<'_, [u8]> {
+    if value.first() != Some(&VALUE_HEADER) {
+        return Cow::Borrowed(value);
+    }
+    let mut buf = Vec::with_capacity(value.len() + 2);
+    buf.put_u8(VALUE_HEADER);
+    buf.put_u8(TAG_PLAIN);
+    buf.put_slice(value);
+    Cow::Owned(buf)
+}
+
+/// Encode a user value which expires at `expire_at` milliseconds since the Unix epoch.
+pub(crate) fn encode_value_with_expiry(value: &[u8], expire_at: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(value.len() + EXPIRING_HEADER_LEN);
+    buf.put_u8(VALUE_HEADER);
+    buf.put_u8(TAG_EXPIRING);
+    buf.put_u64(expire_at);
+    buf.put_slice(value);
+    buf
+}
+
+/// Split a stored value into the user value and its expiry time, if any.
+pub(crate) fn decode_value(raw: &[u8]) -> (&[u8], Option<u64>) {
+    match raw {
+        [VALUE_HEADER, TAG_PLAIN, value @ ..] => (value, None),
+        [VALUE_HEADER, TAG_EXPIRING, rest @ ..] if rest.len() >= std::mem::size_of::<u64>() => {
+            let (mut expire_at, value) = rest.split_at(std::mem::size_of::<u64>());
+            (value, Some(expire_at.get_u64()))
+        }
+        _ => (raw, None),
+    }
+}
+
+/// Whether a stored value has expired at `now` milliseconds since the Unix epoch.
+pub(crate) fn is_expired(raw: &[u8], now: u64) -> bool {
+    decode_value(raw)
+        .1
+        .is_some_and(|expire_at| expire_at <= now)
+}
+
+/// The user value of a stored value as of `now`, or `None` if it is a delete or has expired.
+pub(crate) fn live_value(raw: &[u8], now: u64) -> Option<&[u8]> {
+    if raw.is_empty() || is_expired(raw, now) {
+        return None;
+    }
+    Some(decode_value(raw).0)
+}
+
+/// Like [`live_value`], but slices the user value out of `raw` without copying.
+pub(crate) fn live_value_bytes(raw: Bytes, now: u64) -> Option<Bytes> {
+    let len = live_value(&raw, now)?.len();
+    Some(raw.slice(raw.len() - len..))
+}
+
+/// Encode a reference to a stored value of `len` bytes at `offset` of the blob section of an SST.
+pub(crate) fn encode_blob_ref(offset: u64, len: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(BLOB_REF_LEN);
+    buf.put_u8(VALUE_HEADER);
+    buf.put_u8(TAG_BLOB);
+    buf.put_u64(offset);
+    buf.put_u64(len);
+    buf
+}
+
+/// The offset and length of the stored value `raw` refers to, if it is a blob reference.
+pub(crate) fn decode_blob_ref(raw: &[u8]) -> Option<(u64, u64)> {
+    match raw {
+        [VALUE_HEADER, TAG_BLOB, rest @ ..] if rest.len() == BLOB_REF_LEN - 2 => {
+            let mut rest = rest;
+            Some((rest.get_u64(), rest.get_u64()))
+        }
+        _ => None,
+    }
+}
@@ -15,12 +15,12 @@
 use std::fs::{File, OpenOptions};
 use std::hash::Hasher;
 use std::io::{BufWriter, Read, Write};
+use std::ops::Bound;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
 use bytes::{Buf, BufMut, Bytes};
-use crossbeam_skiplist::SkipMap;
 use parking_lot::Mutex;
 
 use crate::key::KeySlice;
@@ -29,10 +29,20 @@ pub struct Wal {
     file: Arc<Mutex<BufWriter<File>>>,
 }
 
+/// A record replayed from a WAL.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WalRecord {
+    Put(Bytes, Bytes),
+    /// A range tombstone, stored as a record with an empty key, which puts never have.
+    DeleteRange(Bound<Bytes>, Bound<Bytes>),
+}
+
 impl Wal {
-    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+    /// Create a WAL which buffers up to `buffer_size` bytes between writes to the file.
+    pub fn create(path: impl AsRef<Path>, buffer_size: usize) -> Result<Self> {
         Ok(Self {
-            file: Arc::new(Mutex::new(BufWriter::new(
+            file: Arc::new(Mutex::new(BufWriter::with_capacity(
+                buffer_size,
                 OpenOptions::new()
                     .read(true)
                     .create_new(true)
@@ -43,7 +53,12 @@ impl Wal {
         })
     }
 
-    pub fn recover(path: impl AsRef<Path>, skiplist: &SkipMap<Bytes, Bytes>) -> Result<Self> {
+    /// Open the WAL at `path` for appending, passing each of its records to `insert` first.
+    pub fn recover(
+        path: impl AsRef<Path>,
+        insert: impl FnMut(WalRecord),
+        buffer_size: usize,
+    ) -> Result<Self> {
         let path = path.as_ref();
         let mut file = OpenOptions::new()
             .read(true)
@@ -52,52 +67,58 @@ impl Wal {
             .context("failed to recover from WAL")?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
-        let mut rbuf: &[u8] = buf.as_slice();
-        while rbuf.has_remaining() {
-            let mut hasher = crc32fast::Hasher::new();
-            let key_len = rbuf.get_u16() as usize;
-            hasher.write_u16(key_len as u16);
-            let key = Bytes::copy_from_slice(&rbuf[..key_len]);
-            hasher.write(&key);
-            rbuf.advance(key_len);
-            let value_len = rbuf.get_u16() as usize;
-            hasher.write_u16(value_len as u16);
-            let value = Bytes::copy_from_slice(&rbuf[..value_len]);
-            hasher.write(&value);
-            rbuf.advance(value_len);
-            let checksum = rbuf.get_u32();
-            if hasher.finalize() != checksum {
-                bail!("checksum mismatch");
-            }
-            skiplist.insert(key, value);
+        let valid_len = replay(&buf, insert);
+        if valid_len < buf.len() {
+            // A crash while writing leaves a torn record at the end, drop it and everything
+            // after it so that new records are not appended after garbage.
+            log::warn!(
+                "dropping {} bytes of incomplete or corrupted WAL records in {}",
+                buf.len() - valid_len,
+                path.display()
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
         }
         Ok(Self {
-            file: Arc::new(Mutex::new(BufWriter::new(file))),
+            file: Arc::new(Mutex::new(BufWriter::with_capacity(buffer_size, file))),
         })
     }
 
+    /// Pass the records of the WAL at `path` to `insert` like `recover`, without modifying the
+    /// file.
+    pub fn read(path: impl AsRef<Path>, insert: impl FnMut(WalRecord)) -> Result<()> {
+        let buf = std::fs::read(path).context("failed to read WAL")?;
+        replay(&buf, insert);
+        Ok(())
+    }
+
     pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
-        let mut file = self.file.lock();
         let mut buf: Vec<u8> =
             Vec::with_capacity(key.len() + value.len() + std::mem::size_of::<u16>());
-        let mut hasher = crc32fast::Hasher::new();
-        hasher.write_u16(key.len() as u16);
-        buf.put_u16(key.len() as u16);
-        hasher.write(key);
-        buf.put_slice(key);
-        hasher.write_u16(value.len() as u16);
-        buf.put_u16(value.len() as u16);
-        buf.put_slice(value);
-        hasher.write(value);
-        // add checksum: week 2 day 7
-        buf.put_u32(hasher.finalize());
-        file.write_all(&buf)?;
+        encode_record(&mut buf, key, value);
+        self.file.lock().write_all(&buf)?;
         Ok(())
     }
 
-    /// Implement this in week 3, day 5; if you want to implement this earlier, use `&[u8]` as the key type.
-    pub fn put_batch(&self, _data: &[(KeySlice, &[u8])]) -> Result<()> {
-        unimplemented!()
+    /// Append all records with a single write, so that no other record ends up in between.
+    pub fn put_batch(&self, data: &[(KeySlice, &[u8])]) -> Result<()> {
+        let mut buf: Vec<u8> = Vec::new();
+        for (key, value) in data {
+            encode_record(&mut buf, key.raw_ref(), value);
+        }
+        self.file.lock().write_all(&buf)?;
+        Ok(())
+    }
+
+    /// Append a range tombstone deleting the keys within the bounds.
+    pub fn delete_range(&self, lower: Bound<&[u8]>, upper: Bound<&[u8]>) -> Result<()> {
+        let mut value = Vec::new();
+        encode_bound(&mut value, lower);
+        encode_bound(&mut value, upper);
+        let mut buf = Vec::new();
+        encode_record(&mut buf, &[], &value);
+        self.file.lock().write_all(&buf)?;
+        Ok(())
     }
 
     pub fn sync(&self) -> Result<()> {
@@ -107,3 +128,147 @@ impl Wal {
         Ok(())
     }
 }
+
+/// A value length (u16) of this marks a value of at least as many bytes, whose length follows as
+/// a u32. Large values only get there with `large_value_threshold` set. Values shorter than this
+/// are stored inline, so a value of exactly this many bytes is always written with the marker.
+/// WALs written before large values were supported store such a value inline with this length,
+/// which `decode_record` tells apart by the checksum.
+const LARGE_VALUE_LEN: u16 = u16::MAX;
+
+/// Decode the record at the start of `buf`, returning the key, the value and the length of the
+/// record. Returns `None` if the record is incomplete or its checksum does not match.
+fn decode_record(mut buf: &[u8]) -> Option<(Bytes, Bytes, usize)> {
+    let len = buf.len();
+    let mut hasher = crc32fast::Hasher::new();
+    if buf.remaining() < 2 {
+        return None;
+    }
+    let key_len = buf.get_u16() as usize;
+    hasher.write_u16(key_len as u16);
+    if buf.remaining() < key_len + 2 {
+        return None;
+    }
+    let key = Bytes::copy_from_slice(&buf[..key_len]);
+    hasher.write(&key);
+    buf.advance(key_len);
+    let value_len = buf.get_u16();
+    hasher.write_u16(value_len);
+    let decode_value = |mut buf: &[u8], value_len: usize, mut hasher: crc32fast::Hasher| {
+        if buf.remaining() < value_len + 4 {
+            return None;
+        }
+        let value = Bytes::copy_from_slice(&buf[..value_len]);
+        hasher.write(&value);
+        buf.advance(value_len);
+        if buf.get_u32() != hasher.finalize() {
+            return None;
+        }
+        Some((value, buf.remaining()))
+    };
+    let (value, remaining) = if value_len == LARGE_VALUE_LEN {
+        let large_value = (buf.remaining() >= 4).then(|| {
+            let mut hasher = hasher.clone();
+            let mut buf = buf;
+            let value_len = buf.get_u32();
+            hasher.write_u32(value_len);
+            decode_value(buf, value_len as usize, hasher)
+        });
+        // fall back to a value of exactly `LARGE_VALUE_LEN` bytes written by an older version
+        large_value
+            .flatten()
+            .or_else(|| decode_value(buf, value_len as usize, hasher))?
+    } else {
+        decode_value(buf, value_len as usize, hasher)?
+    };
+    Some((key, value, len - remaining))
+}
+
+/// Pass the records in `buf` to `insert` up to the first incomplete or corrupted one. Returns the
+/// length of the valid part of `buf`.
+fn replay(buf: &[u8], mut insert: impl FnMut(WalRecord)) -> usize {
+    let mut rbuf = buf;
+    while rbuf.has_remaining() {
+        let Some((key, value, record_len)) = decode_record(rbuf) else {
+            break;
+        };
+        let record = if key.is_empty() {
+            let Some(record) = decode_range_tombstone(&value) else {
+                break;
+            };
+            record
+        } else {
+            WalRecord::Put(key, value)
+        };
+        insert(record);
+        rbuf.advance(record_len);
+    }
+    buf.len() - rbuf.remaining()
+}
+
+/// Encode a bound as a tag byte, 0 for unbounded, 1 for included and 2 for excluded, followed by
+/// the length (u16) and the bytes of the key, if any.
+fn encode_bound(buf: &mut Vec<u8>, bound: Bound<&[u8]>) {
+    let (tag, key) = match bound {
+        Bound::Unbounded => (0, None),
+        Bound::Included(key) => (1, Some(key)),
+        Bound::Excluded(key) => (2, Some(key)),
+    };
+    buf.put_u8(tag);
+    if let Some(key) = key {
+        buf.put_u16(key.len() as u16);
+        buf.put_slice(key);
+    }
+}
+
+fn decode_bound(buf: &mut &[u8]) -> Option<Bound<Bytes>> {
+    if !buf.has_remaining() {
+        return None;
+    }
+    let tag = buf.get_u8();
+    if tag == 0 {
+        return Some(Bound::Unbounded);
+    }
+    if buf.remaining() < 2 {
+        return None;
+    }
+    let key_len = buf.get_u16() as usize;
+    if buf.remaining() < key_len {
+        return None;
+    }
+    let key = Bytes::copy_from_slice(&buf[..key_len]);
+    buf.advance(key_len);
+    match tag {
+        1 => Some(Bound::Included(key)),
+        2 => Some(Bound::Excluded(key)),
+        _ => None,
+    }
+}
+
+/// Decode the value of a range tombstone record written by `Wal::delete_range`.
+fn decode_range_tombstone(mut buf: &[u8]) -> Option<WalRecord> {
+    let lower = decode_bound(&mut buf)?;
+    let upper = decode_bound(&mut buf)?;
+    (!buf.has_remaining()).then_some(WalRecord::DeleteRange(lower, upper))
+}
+
+fn encode_record(buf: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.write_u16(key.len() as u16);
+    buf.put_u16(key.len() as u16);
+    hasher.write(key);
+    buf.put_slice(key);
+    if value.len() < LARGE_VALUE_LEN as usize {
+        hasher.write_u16(value.len() as u16);
+        buf.put_u16(value.len() as u16);
+    } else {
+        hasher.write_u16(LARGE_VALUE_LEN);
+        buf.put_u16(LARGE_VALUE_LEN);
+        hasher.write_u32(value.len() as u32);
+        buf.put_u32(value.len() as u32);
+    }
+    buf.put_slice(value);
+    hasher.write(value);
+    // add checksum: week 2 day 7
+    buf.put_u32(hasher.finalize());
+}